@@ -9,6 +9,7 @@ import android.provider.DocumentsContract.Document
 import android.util.Log
 import android.view.WindowManager
 import android.view.KeyEvent
+import android.webkit.MimeTypeMap
 import androidx.activity.result.ActivityResultLauncher
 import androidx.activity.result.contract.ActivityResultContracts
 import androidx.core.net.toUri
@@ -17,6 +18,7 @@ import androidx.core.view.WindowInsetsCompat
 import androidx.core.view.WindowInsetsControllerCompat
 import com.google.androidgamesdk.GameActivity
 import java.io.Closeable
+import java.io.IOException
 import java.util.LinkedList
 
 class MainActivity : GameActivity() {
@@ -47,7 +49,10 @@ class MainActivity : GameActivity() {
                 Log.i("main-activity", contentUri.toString())
                 if (contentUri != null) {
                     try {
-                        contentResolver.takePersistableUriPermission(contentUri, Intent.FLAG_GRANT_READ_URI_PERMISSION)
+                        contentResolver.takePersistableUriPermission(
+                            contentUri,
+                            Intent.FLAG_GRANT_READ_URI_PERMISSION or Intent.FLAG_GRANT_WRITE_URI_PERMISSION
+                        )
                     } catch (e: SecurityException) {
                         Log.e("main-activity", "Failed to get permission", e)
                     }
@@ -189,6 +194,71 @@ class MainActivity : GameActivity() {
         }
     }
 
+    @Suppress("unused")
+    private fun writeFileContent(ticketId: Long, rootUri: String, path: String, content: String) {
+        val rootUri = rootUri.toUri()
+
+        try {
+            val names = path.split('/').filter { it.isNotEmpty() }
+            if (names.isEmpty()) {
+                throw IOException("Empty path")
+            }
+
+            var parentId = DocumentsContract.getTreeDocumentId(rootUri)
+            for (name in names.dropLast(1)) {
+                parentId = findChildDocument(rootUri, parentId, name)
+                    ?: createChildDocument(rootUri, parentId, Document.MIME_TYPE_DIR, name)
+            }
+            val name = names.last()
+            val mime = MimeTypeMap.getSingleton()
+                .getMimeTypeFromExtension(name.substringAfterLast('.', ""))
+                ?: "application/octet-stream"
+            val docId = findChildDocument(rootUri, parentId, name)
+                ?: createChildDocument(rootUri, parentId, mime, name)
+
+            val uri = DocumentsContract.buildDocumentUriUsingTree(rootUri, docId)
+            val stream = contentResolver.openOutputStream(uri, "wt")
+                ?: throw IOException("Failed to open file, got null result")
+            stream.use { it.write(content.toByteArray()) }
+
+            wranglerWritten(ticketId, docId)
+        } catch (exception: Exception) {
+            Log.e("main-activity", "Failed to write file: $exception")
+            wranglerWriteFail(ticketId, exception.toString())
+        }
+    }
+
+    private fun findChildDocument(rootUri: Uri, parentId: String, name: String): String? {
+        val childrenUri = DocumentsContract.buildChildDocumentsUriUsingTree(rootUri, parentId)
+        val c = contentResolver.query(
+            childrenUri,
+            arrayOf(
+                Document.COLUMN_DOCUMENT_ID,
+                Document.COLUMN_DISPLAY_NAME,
+            ),
+            null,
+            null,
+            null
+        )
+        try {
+            while (c!!.moveToNext()) {
+                if (c.getString(1) == name) {
+                    return c.getString(0)
+                }
+            }
+        } finally {
+            closeQuietly(c)
+        }
+        return null
+    }
+
+    private fun createChildDocument(rootUri: Uri, parentId: String, mime: String, name: String): String {
+        val parentUri = DocumentsContract.buildDocumentUriUsingTree(rootUri, parentId)
+        val uri = DocumentsContract.createDocument(contentResolver, parentUri, mime, name)
+            ?: throw IOException("Failed to create $name")
+        return DocumentsContract.getDocumentId(uri)
+    }
+
     // Util method to close a closeable
     private fun closeQuietly(closeable: Closeable?) {
         if (closeable != null) {
@@ -214,6 +284,8 @@ class MainActivity : GameActivity() {
     external fun wranglerDiscoverEnd(ticketId: Long)
     external fun wranglerFile(ticketId: Long, docId: String, fd: Int, size: Long, lastModified: Long)
     external fun wranglerFail(ticketId: Long, reason: String)
+    external fun wranglerWritten(ticketId: Long, docId: String)
+    external fun wranglerWriteFail(ticketId: Long, reason: String)
 
     external fun inputKeyUp(): Boolean
     external fun inputKeyDown(): Boolean
@@ -55,6 +55,7 @@ jni::bind_java_type! {
 		fn discover_open_tree(),
 		fn discover_folder_content(ticket_id: i64, root_uri: JString),
 		fn open_file_content(ticket_id: i64, root_uri: JString, doc_id: JString),
+		fn write_file_content(ticket_id: i64, root_uri: JString, path: JString, content: JString),
 	}
 }
 
@@ -83,6 +84,14 @@ const _WRANGLER_NATIVE_METHODS: &[jni::NativeMethod] = &[
 		java_type = "org.lotrax.scribblereader.MainActivity",
 		extern fn wrangler_fail(ticket_id: jlong, reason: JString),
 	},
+	native_method! {
+		java_type = "org.lotrax.scribblereader.MainActivity",
+		extern fn wrangler_written(ticket_id: jlong, doc_id: JString),
+	},
+	native_method! {
+		java_type = "org.lotrax.scribblereader.MainActivity",
+		extern fn wrangler_write_fail(ticket_id: jlong, reason: JString),
+	},
 	native_method! {
 		java_type = "org.lotrax.scribblereader.MainActivity",
 		extern fn input_key_up() -> jboolean,
@@ -242,6 +251,32 @@ fn wrangler_fail<'local>(
 	Ok(())
 }
 
+fn wrangler_written<'local>(
+	env: &mut Env<'local>,
+	_this: JObject<'local>,
+	ticket_id: jlong,
+	doc_id: JString,
+) -> Result<(), jni::errors::Error> {
+	let ticket = Ticket::new(ticket_id as u64);
+	let doc_id = doc_id.try_to_string(env)?;
+
+	send_written(ticket, Ok(DocumentId::new(doc_id)));
+	Ok(())
+}
+
+fn wrangler_write_fail<'local>(
+	env: &mut Env<'local>,
+	_this: JObject<'local>,
+	ticket_id: jlong,
+	reason: JString,
+) -> Result<(), jni::errors::Error> {
+	let ticket = Ticket::new(ticket_id as u64);
+	let reason = reason.try_to_string(env)?;
+
+	send_written(ticket, Err(io::Error::other(reason)));
+	Ok(())
+}
+
 fn input_key_up<'local>(
 	_env: &mut Env<'local>,
 	_this: JObject<'local>,
@@ -276,6 +311,20 @@ fn send_document_fail(ticket: Ticket, err: impl ToString) {
 	}
 }
 
+fn send_written(ticket: Ticket, result: Result<DocumentId, io::Error>) {
+	let mut handled = false;
+	for w in &mut *WRANGLERS.wait().lock().unwrap() {
+		let result = w.written(ticket, &result);
+		if matches!(result, WranglerResult::Handled) {
+			handled = true;
+			break;
+		}
+	}
+	if !handled {
+		log::info!("wrangler_written unhandled {ticket:?}");
+	}
+}
+
 fn create_wrangler(app: &AndroidApp, config_path: &Path) -> (WranglerSystem, JoinHandle<()>) {
 	let (sender, receiver) = channel();
 	let wranglers = WRANGLERS
@@ -407,6 +456,32 @@ fn create_wrangler(app: &AndroidApp, config_path: &Path) -> (WranglerSystem, Joi
 						break;
 					}
 				}
+				WranglerCommand::Write(ticket, path, content) => {
+					if let Err(e) = vm.attach_current_thread(|env| -> jni::errors::Result<()> {
+						let activity =
+							unsafe { env.as_cast_raw::<Global<MainActivity>>(&activity)? };
+
+						let root_uri = JString::from_str(env, &document_tree)?;
+						let ticket_id = ticket.value() as jlong;
+						let path = JString::from_str(env, path.to_string_lossy())?;
+						let content = JString::from_str(env, &content)?;
+
+						MainActivity::write_file_content(
+							activity.as_ref(),
+							env,
+							ticket_id,
+							root_uri,
+							path,
+							content,
+						)?;
+
+						Ok(())
+					}) {
+						log::error!("Failed to interact with vm: {e}");
+						send_written(ticket, Err(io::Error::other(e.to_string())));
+						break;
+					}
+				}
 				WranglerCommand::Shutdown => {
 					log::info!("Wrangler shutdown");
 					return;
@@ -1,16 +1,111 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
 use fixed::types::I26F6;
+use fixed::types::U26F6;
 use pixelator::PixmapRef;
 use scribe::Location;
+use scribe::TextPosition;
 
+use crate::DisplayText;
 use crate::PageContent;
 use crate::PageFlags;
+use crate::Position;
+use crate::TextSelection;
+use crate::progress::visible_chars;
 
 const CACHE_CHAPTERS: usize = 5;
 
+/// Link targets of a chapter
+#[derive(Debug, Default)]
+pub(crate) struct ChapterAnchors {
+	/// Element by `id` attribute
+	pub(crate) elements: BTreeMap<String, U26F6>,
+	/// Text of footnotes by `id` attribute
+	pub(crate) notes: BTreeMap<String, Arc<String>>,
+	/// Text of blocks by element, in element order
+	pub(crate) blocks: Vec<(U26F6, String)>,
+	/// Text nodes by element, as block index and byte offset in block text
+	pub(crate) nodes: Vec<(u32, usize, usize)>,
+}
+
+impl ChapterAnchors {
+	/// Add block of text nodes, in element order
+	pub(crate) fn add_block<'a>(&mut self, nodes: impl IntoIterator<Item = (u32, &'a str)>) {
+		let block = self.blocks.len();
+		let mut element = None;
+		let mut text = String::new();
+		for (el, t) in nodes {
+			element.get_or_insert(el);
+			self.nodes.push((el, block, text.len()));
+			text.push_str(t);
+		}
+		if let Some(el) = element {
+			self.blocks.push((U26F6::from_num(el), text));
+		}
+	}
+
+	fn block_offset(&self, pos: TextPosition) -> Option<(usize, usize)> {
+		let index = self
+			.nodes
+			.binary_search_by_key(&pos.element, |(el, _, _)| *el)
+			.ok()?;
+		let (_, block, start) = self.nodes[index];
+		Some((block, start + pos.offset as usize))
+	}
+
+	/// Position after character at position
+	fn char_end(&self, pos: TextPosition) -> Option<TextPosition> {
+		let (block, offset) = self.block_offset(pos)?;
+		let (_, text) = self.blocks.get(block)?;
+		let c = text.get(offset..)?.chars().next()?;
+		Some(TextPosition {
+			element: pos.element,
+			offset: pos.offset + c.len_utf8() as u32,
+		})
+	}
+
+	/// Text in range, whitespace collapsed and blocks on separate lines
+	fn text(&self, range: Range<TextPosition>) -> Option<String> {
+		let (start_block, start) = self.block_offset(range.start)?;
+		let (end_block, end) = self.block_offset(range.end)?;
+		let mut lines = Vec::new();
+		for block in start_block..=end_block {
+			let (_, text) = self.blocks.get(block)?;
+			let from = if block == start_block { start } else { 0 };
+			let to = if block == end_block { end } else { text.len() };
+			let line = text
+				.get(from..to)?
+				.split_whitespace()
+				.collect::<Vec<_>>()
+				.join(" ");
+			if !line.is_empty() {
+				lines.push(line);
+			}
+		}
+		Some(lines.join("\n"))
+	}
+}
+
+/// Squared distance from point to area of text
+fn distance(text: &DisplayText, p: Position) -> f32 {
+	let dx = (text.pos.x - p.x)
+		.max(p.x - (text.pos.x + text.size.width))
+		.max(0.);
+	let dy = (text.pos.y - p.y)
+		.max(p.y - (text.pos.y + text.size.height))
+		.max(0.);
+	dx * dx + dy * dy
+}
+
 #[derive(Debug, Default)]
 struct PageCacheEntry {
 	spine: u32,
 	pages: Vec<PageContent>,
+	anchors: ChapterAnchors,
+	/// Atlas of chapter restored from layout cache, shared atlas if none
+	atlas_pixmap: Option<PixmapRef>,
 }
 
 #[derive(Debug)]
@@ -50,8 +145,14 @@ pub(crate) enum NavigateError {
 }
 
 impl PageContentCache {
-	pub fn pixmap(&self) -> Option<&PixmapRef> {
-		self.atlas_pixmap.as_ref()
+	/// Glyph atlas of page at location
+	pub fn pixmap(&self, loc: Location) -> Option<&PixmapRef> {
+		self.entries
+			.iter()
+			.flatten()
+			.find(|e| e.spine == loc.spine)
+			.and_then(|e| e.atlas_pixmap.as_ref())
+			.or(self.atlas_pixmap.as_ref())
 	}
 
 	pub(crate) fn pixmap_mut(&mut self) -> &mut Option<PixmapRef> {
@@ -137,11 +238,145 @@ impl PageContentCache {
 		}
 	}
 
+	/// Location of element with id in chapter, start of chapter without id
+	pub(crate) fn anchor(&self, spine: u32, id: Option<&str>) -> Option<Location> {
+		let entry = self.entries.iter().flatten().find(|e| e.spine == spine)?;
+		let element = match id {
+			Some(id) => *entry.anchors.elements.get(id)?,
+			None => U26F6::ZERO,
+		};
+		Some(Location { spine, element })
+	}
+
+	pub(crate) fn note(&self, spine: u32, id: &str) -> Option<Arc<String>> {
+		let entry = self.entries.iter().flatten().find(|e| e.spine == spine)?;
+		entry.anchors.notes.get(id).cloned()
+	}
+
+	/// Text from location on, cut after word reaching `max_chars`
+	pub fn excerpt(&self, loc: Location, max_chars: usize) -> Option<String> {
+		let entry = self
+			.entries
+			.iter()
+			.flatten()
+			.find(|e| e.spine == loc.spine)?;
+		let blocks = &entry.anchors.blocks;
+		let start = blocks
+			.partition_point(|(el, _)| *el <= loc.element)
+			.saturating_sub(1);
+
+		let mut excerpt = String::new();
+		let mut chars = 0;
+		for (el, text) in blocks.get(start..)? {
+			// Skip words of block before location, element fraction is part of block passed
+			let skip = if *el < loc.element {
+				let part = (loc.element - *el).min(U26F6::ONE);
+				let words = text.split_whitespace().count();
+				(part * U26F6::from_num(words)).to_num::<usize>()
+			} else {
+				0
+			};
+			for word in text.split_whitespace().skip(skip) {
+				if chars >= max_chars {
+					excerpt.push('…');
+					return Some(excerpt);
+				}
+				if !excerpt.is_empty() {
+					excerpt.push(' ');
+					chars += 1;
+				}
+				excerpt.push_str(word);
+				chars += word.chars().count();
+			}
+		}
+		(!excerpt.is_empty()).then_some(excerpt)
+	}
+
+	/// Visible characters of chapter through end of page at location, and if page is last
+	pub(crate) fn chars_read(&self, loc: Location) -> Option<(u64, bool)> {
+		let (entry, page, _) = self.entry(loc)?;
+		let end = page.elements.end;
+		let chars = entry
+			.anchors
+			.blocks
+			.iter()
+			.take_while(|(el, _)| *el < end)
+			.map(|(el, text)| {
+				let chars = visible_chars(text);
+				// Element fraction is part of block passed, as for excerpt
+				let part = (end - *el).min(U26F6::ONE);
+				(part * U26F6::from_num(chars)).to_num::<u64>()
+			})
+			.sum();
+		Some((chars, page.flags.contains(PageFlags::Last)))
+	}
+
+	/// Text on page of location between text nearest to points, in reading order
+	pub fn select(&self, loc: Location, from: Position, to: Position) -> Option<TextSelection> {
+		let (entry, page, _) = self.entry(loc)?;
+		let texts = &page.texts;
+		let nearest = |p: Position| {
+			texts
+				.iter()
+				.enumerate()
+				.min_by(|(_, a), (_, b)| distance(a, p).total_cmp(&distance(b, p)))
+				.map(|(i, _)| i)
+		};
+		let (a, b) = (nearest(from)?, nearest(to)?);
+		let (first, last) = (a.min(b), a.max(b));
+
+		let start = texts[first].text;
+		let last_text = texts[last].text;
+		// Next cluster in same element ends selection, covers ligatures
+		let end = texts
+			.get(last + 1)
+			.map(|t| t.text)
+			.filter(|t| t.element == last_text.element && t.offset > last_text.offset)
+			.or_else(|| entry.anchors.char_end(last_text))?;
+		let text = entry.anchors.text(start..end)?;
+
+		Some(TextSelection {
+			spine: loc.spine,
+			start,
+			end,
+			text,
+			areas: texts[first..=last]
+				.iter()
+				.map(|t| (t.pos, t.size))
+				.collect(),
+		})
+	}
+
+	/// Pages and anchors of chapter printed to shared atlas
+	pub(crate) fn chapter(&self, spine: u32) -> Option<(&[PageContent], &ChapterAnchors)> {
+		self.entries
+			.iter()
+			.flatten()
+			.find(|e| e.spine == spine && e.atlas_pixmap.is_none())
+			.map(|e| (e.pages.as_slice(), &e.anchors))
+	}
+
 	pub(crate) fn is_cached(&self, loc: Location) -> bool {
 		self.entries.iter().flatten().any(|e| e.spine == loc.spine)
 	}
 
-	pub(crate) fn insert(&mut self, spine_index: u32, pages: Vec<PageContent>) {
+	pub(crate) fn insert(
+		&mut self,
+		spine_index: u32,
+		pages: Vec<PageContent>,
+		anchors: ChapterAnchors,
+	) {
+		self.insert_with_atlas(spine_index, pages, anchors, None);
+	}
+
+	/// Insert chapter printed to its own glyph atlas
+	pub(crate) fn insert_with_atlas(
+		&mut self,
+		spine_index: u32,
+		pages: Vec<PageContent>,
+		anchors: ChapterAnchors,
+		atlas_pixmap: Option<PixmapRef>,
+	) {
 		#[cfg(debug_assertions)]
 		if !pages.iter().is_sorted_by_key(|p| p.elements.start) {
 			let starts = pages
@@ -154,6 +389,8 @@ impl PageContentCache {
 		self.entries[self.index % CACHE_CHAPTERS] = Some(PageCacheEntry {
 			spine: spine_index,
 			pages,
+			anchors,
+			atlas_pixmap,
 		});
 		self.index += 1;
 	}
@@ -191,3 +428,168 @@ impl PageContentCache {
 		Some((entry, page, meta))
 	}
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use std::sync::Arc;
+
+	use fixed::types::U26F6;
+	use scribe::Location;
+	use scribe::TextPosition;
+
+	use crate::DisplayText;
+	use crate::PageContent;
+	use crate::PageFlags;
+	use crate::Position;
+	use crate::Size;
+	use crate::cache::ChapterAnchors;
+	use crate::cache::PageContentCache;
+
+	pub(crate) fn page(flags: PageFlags, start: u32, end: u32) -> PageContent {
+		PageContent {
+			flags,
+			elements: U26F6::from_num(start)..U26F6::from_num(end),
+			items: Vec::new(),
+			links: Vec::new(),
+			highlights: Vec::new(),
+			texts: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn test_cache_anchor_location() {
+		let mut anchors = ChapterAnchors::default();
+		anchors
+			.elements
+			.insert("note".to_string(), U26F6::from_num(15));
+		anchors
+			.notes
+			.insert("note".to_string(), Arc::new("Note".to_string()));
+
+		let mut cache = PageContentCache::default();
+		cache.insert(
+			2,
+			vec![page(PageFlags::First, 0, 10), page(PageFlags::Last, 10, 20)],
+			anchors,
+		);
+
+		let loc = cache.anchor(2, Some("note")).expect("Missing anchor");
+		assert_eq!(loc.element, U26F6::from_num(15), "Element missmatch");
+		let (_, meta) = cache.page(loc).expect("Missing page");
+		assert_eq!(meta.page, 2, "Page missmatch");
+
+		assert_eq!(
+			cache.anchor(2, None).map(|l| l.element),
+			Some(U26F6::ZERO),
+			"Chapter start missmatch"
+		);
+		assert!(
+			cache.anchor(2, Some("missing")).is_none(),
+			"Unexpected anchor"
+		);
+		assert!(cache.anchor(3, None).is_none(), "Unexpected chapter");
+		assert_eq!(
+			cache.note(2, "note").as_deref().map(String::as_str),
+			Some("Note"),
+			"Note missmatch"
+		);
+	}
+
+	#[test]
+	fn test_cache_excerpt() {
+		let mut anchors = ChapterAnchors::default();
+		anchors.add_block([(2, "Chapter One")]);
+		anchors.add_block([(5, "It was a dark\n and stormy night.")]);
+		let mut cache = PageContentCache::default();
+		cache.insert(
+			1,
+			vec![page(PageFlags::First | PageFlags::Last, 0, 10)],
+			anchors,
+		);
+
+		assert_eq!(
+			cache.excerpt(Location::from_spine(1), 100).as_deref(),
+			Some("Chapter One It was a dark and stormy night."),
+			"Excerpt missmatch"
+		);
+		assert_eq!(
+			cache.excerpt(Location::from_spine(1), 14).as_deref(),
+			Some("Chapter One It…"),
+			"Cut excerpt missmatch"
+		);
+		let loc = Location {
+			spine: 1,
+			element: U26F6::from_num(5.5),
+		};
+		assert_eq!(
+			cache.excerpt(loc, 100).as_deref(),
+			Some("dark and stormy night."),
+			"Part excerpt missmatch"
+		);
+		assert!(
+			cache.excerpt(Location::from_spine(2), 100).is_none(),
+			"Unexpected excerpt"
+		);
+	}
+
+	#[test]
+	fn test_cache_select() {
+		let mut anchors = ChapterAnchors::default();
+		anchors.add_block([(3, "Café au "), (4, "lait  now")]);
+		let mut page = page(PageFlags::First | PageFlags::Last, 0, 10);
+		// One cluster per character, on a single line
+		let mut x = 0.;
+		for (element, text) in [(3, "Café au "), (4, "lait  now")] {
+			for (offset, _) in text.char_indices() {
+				page.texts.push(DisplayText {
+					pos: Position { x, y: 0. },
+					size: Size {
+						width: 10.,
+						height: 20.,
+					},
+					text: TextPosition {
+						element,
+						offset: offset as u32,
+					},
+				});
+				x += 10.;
+			}
+		}
+		let mut cache = PageContentCache::default();
+		cache.insert(1, vec![page], anchors);
+
+		let at = |x: f32| Position { x, y: 10. };
+		let selection = cache
+			.select(Location::from_spine(1), at(125.), at(35.))
+			.expect("Missing selection");
+		assert_eq!(selection.text, "é au lait", "Text missmatch");
+		assert_eq!(
+			selection.start,
+			TextPosition {
+				element: 3,
+				offset: 3
+			},
+			"Start missmatch"
+		);
+		assert_eq!(
+			selection.end,
+			TextPosition {
+				element: 4,
+				offset: 5
+			},
+			"End missmatch"
+		);
+		assert_eq!(selection.areas.len(), 10, "Areas missmatch");
+
+		let selection = cache
+			.select(Location::from_spine(1), at(500.), at(500.))
+			.expect("Missing selection");
+		assert_eq!(selection.text, "w", "Last text missmatch");
+		assert!(
+			cache
+				.select(Location::from_spine(2), at(0.), at(0.))
+				.is_none(),
+			"Unexpected selection"
+		);
+	}
+}
@@ -0,0 +1,939 @@
+//! Minimal css support for epub content.
+//!
+//! Supports selectors by tag, class, id, descendant and child (`>`, matched
+//! against direct parent only), everything else causes selector to be skipped.
+//! At-rules are skipped.
+
+/// Default font size in browsers, absolute author sizes are scaled against it
+/// so profile font size still applies.
+const CSS_DEFAULT_FONT_SIZE_PX: f32 = 16.0;
+const PX_PER_PT: f32 = 96.0 / 72.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Origin {
+	UserAgent,
+	User,
+	Author,
+}
+
+impl Origin {
+	fn rank(&self, important: bool) -> u8 {
+		match (self, important) {
+			(Origin::UserAgent, false) => 0,
+			(Origin::User, false) => 1,
+			(Origin::Author, false) => 2,
+			(Origin::Author, true) => 3,
+			(Origin::User, true) => 4,
+			(Origin::UserAgent, true) => 5,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Length {
+	Px(f32),
+	Em(f32),
+	Rem(f32),
+	Percent(f32),
+	Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Display {
+	#[default]
+	Block,
+	Inline,
+	None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum TextAlign {
+	#[default]
+	Left,
+	Center,
+	Right,
+	Justify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum FontStyle {
+	#[default]
+	Normal,
+	Italic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FontWeight {
+	Absolute(u16),
+	Bolder,
+	Lighter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FontSize {
+	Length(Length),
+	/// Keyword relative to root font size
+	Absolute(f32),
+	/// Keyword relative to parent font size
+	Relative(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum PageBreak {
+	#[default]
+	Auto,
+	Always,
+	Avoid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+	Top,
+	Right,
+	Bottom,
+	Left,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Property {
+	Margin(Side, Length),
+	Padding(Side, Length),
+	TextIndent(Length),
+	TextAlign(TextAlign),
+	FontStyle(FontStyle),
+	FontWeight(FontWeight),
+	FontSize(FontSize),
+	Display(Display),
+	PageBreakBefore(PageBreak),
+	PageBreakAfter(PageBreak),
+	PageBreakInside(PageBreak),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Declaration {
+	pub(crate) property: Property,
+	pub(crate) important: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Compound {
+	tag: Option<String>,
+	id: Option<String>,
+	classes: Vec<String>,
+}
+
+/// Relation of compound to the compound before it in selector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Combinator {
+	#[default]
+	Descendant,
+	Child,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Part {
+	combinator: Combinator,
+	compound: Compound,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Specificity(u32, u32, u32);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Selector {
+	/// Compound chain, subject last
+	parts: Vec<Part>,
+	specificity: Specificity,
+}
+
+#[derive(Debug)]
+struct Rule {
+	selectors: Vec<Selector>,
+	declarations: Vec<Declaration>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Stylesheet {
+	origin: Origin,
+	rules: Vec<Rule>,
+}
+
+/// Element as seen by selector matching.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ElementRef<'a> {
+	pub(crate) name: &'a str,
+	pub(crate) id: Option<&'a str>,
+	pub(crate) class: Option<&'a str>,
+}
+
+impl ElementRef<'_> {
+	fn matches(&self, compound: &Compound) -> bool {
+		compound
+			.tag
+			.as_deref()
+			.is_none_or(|tag| tag.eq_ignore_ascii_case(self.name))
+			&& compound.id.as_deref().is_none_or(|id| self.id == Some(id))
+			&& compound.classes.iter().all(|c| {
+				self.class
+					.is_some_and(|class| class.split_ascii_whitespace().any(|cl| cl == c))
+			})
+	}
+}
+
+impl Selector {
+	fn matches(&self, ancestors: &[ElementRef<'_>], element: &ElementRef<'_>) -> bool {
+		let Some((subject, rest)) = self.parts.split_last() else {
+			return false;
+		};
+		element.matches(&subject.compound) && matches_ancestors(rest, subject.combinator, ancestors)
+	}
+}
+
+/// Match remaining parts against ancestors, `combinator` relates last part to element matched before
+fn matches_ancestors(parts: &[Part], combinator: Combinator, ancestors: &[ElementRef<'_>]) -> bool {
+	let Some((part, rest)) = parts.split_last() else {
+		return true;
+	};
+	match combinator {
+		Combinator::Child => ancestors.split_last().is_some_and(|(parent, above)| {
+			parent.matches(&part.compound) && matches_ancestors(rest, part.combinator, above)
+		}),
+		Combinator::Descendant => (0..ancestors.len()).rev().any(|i| {
+			ancestors[i].matches(&part.compound)
+				&& matches_ancestors(rest, part.combinator, &ancestors[..i])
+		}),
+	}
+}
+
+impl Stylesheet {
+	pub(crate) fn parse(origin: Origin, source: &str) -> Self {
+		let source = strip_comments(source);
+		let mut rules = Vec::new();
+		let mut rest = source.as_str();
+		loop {
+			rest = rest.trim_start();
+			if rest.is_empty() {
+				break;
+			}
+			if rest.starts_with('@') {
+				rest = skip_at_rule(rest);
+				continue;
+			}
+			let Some(open) = rest.find('{') else {
+				log::trace!("Unterminated css rule: {rest}");
+				break;
+			};
+			let prelude = &rest[..open];
+			let (block, after) = split_block(&rest[open + 1..]);
+			rest = after;
+
+			let selectors = prelude
+				.split(',')
+				.filter_map(parse_selector)
+				.collect::<Vec<_>>();
+			if selectors.is_empty() {
+				continue;
+			}
+			let declarations = parse_declarations(block);
+			if declarations.is_empty() {
+				continue;
+			}
+			rules.push(Rule {
+				selectors,
+				declarations,
+			});
+		}
+		Self { origin, rules }
+	}
+}
+
+fn strip_comments(source: &str) -> String {
+	let mut out = String::with_capacity(source.len());
+	let mut rest = source;
+	while let Some(start) = rest.find("/*") {
+		out.push_str(&rest[..start]);
+		rest = match rest[start + 2..].find("*/") {
+			Some(end) => &rest[start + 2 + end + 2..],
+			None => "",
+		};
+	}
+	out.push_str(rest);
+	out
+}
+
+/// Split on matching close brace, returns block content and remainder.
+fn split_block(source: &str) -> (&str, &str) {
+	let mut depth = 0;
+	for (i, c) in source.char_indices() {
+		match c {
+			'{' => depth += 1,
+			'}' if depth == 0 => return (&source[..i], &source[i + 1..]),
+			'}' => depth -= 1,
+			_ => {}
+		}
+	}
+	(source, "")
+}
+
+fn skip_at_rule(source: &str) -> &str {
+	for (i, c) in source.char_indices() {
+		match c {
+			';' => return &source[i + 1..],
+			'{' => return split_block(&source[i + 1..]).1,
+			_ => {}
+		}
+	}
+	""
+}
+
+fn parse_selector(source: &str) -> Option<Selector> {
+	let source = source.trim();
+	if source.is_empty() || source.contains(['+', '~', '[', ':']) {
+		return None;
+	}
+	let mut parts = Vec::new();
+	let mut specificity = Specificity::default();
+	let mut combinator = None;
+	for token in source.replace('>', " > ").split_whitespace() {
+		if token == ">" {
+			// Combinator needs a compound on both sides
+			if parts.is_empty() || combinator.is_some() {
+				return None;
+			}
+			combinator = Some(Combinator::Child);
+			continue;
+		}
+		let compound = parse_compound(token)?;
+		specificity.0 += compound.id.is_some() as u32;
+		specificity.1 += compound.classes.len() as u32;
+		specificity.2 += compound.tag.is_some() as u32;
+		parts.push(Part {
+			combinator: combinator.take().unwrap_or_default(),
+			compound,
+		});
+	}
+	if parts.is_empty() || combinator.is_some() {
+		return None;
+	}
+	Some(Selector { parts, specificity })
+}
+
+fn parse_compound(source: &str) -> Option<Compound> {
+	let mut compound = Compound::default();
+	let start = source.find(['.', '#']).unwrap_or(source.len());
+	match &source[..start] {
+		"" | "*" => {}
+		tag if is_ident(tag) => compound.tag = Some(tag.to_ascii_lowercase()),
+		_ => return None,
+	}
+	let mut rest = &source[start..];
+	while let Some(marker) = rest.chars().next() {
+		let end = rest[1..].find(['.', '#']).map_or(rest.len(), |i| i + 1);
+		let name = &rest[1..end];
+		if !is_ident(name) {
+			return None;
+		}
+		match marker {
+			'.' => compound.classes.push(name.to_string()),
+			'#' => compound.id = Some(name.to_string()),
+			_ => return None,
+		}
+		rest = &rest[end..];
+	}
+	Some(compound)
+}
+
+fn is_ident(s: &str) -> bool {
+	!s.is_empty()
+		&& s.chars()
+			.all(|c| c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii())
+}
+
+/// Parse declaration block content, as found in rules and `style` attribute.
+pub(crate) fn parse_declarations(source: &str) -> Vec<Declaration> {
+	let mut declarations = Vec::new();
+	for declaration in source.split(';') {
+		let Some((name, value)) = declaration.split_once(':') else {
+			continue;
+		};
+		let name = name.trim().to_ascii_lowercase();
+		let value = value.trim();
+		let (value, important) = match value.strip_suffix("!important") {
+			Some(value) => (value.trim_end(), true),
+			None => (value, false),
+		};
+		let value = value.to_ascii_lowercase();
+		let len = declarations.len();
+		parse_property(&name, &value, &mut |property| {
+			declarations.push(Declaration {
+				property,
+				important,
+			})
+		});
+		if len == declarations.len() {
+			log::trace!("Unsupported css declaration: {name}: {value}");
+		}
+	}
+	declarations
+}
+
+fn parse_property(name: &str, value: &str, push: &mut impl FnMut(Property)) {
+	match name {
+		"margin" => parse_sides(value, Property::Margin, push),
+		"margin-top" => parse_side(value, Side::Top, Property::Margin, push),
+		"margin-right" => parse_side(value, Side::Right, Property::Margin, push),
+		"margin-bottom" => parse_side(value, Side::Bottom, Property::Margin, push),
+		"margin-left" => parse_side(value, Side::Left, Property::Margin, push),
+		"padding" => parse_sides(value, Property::Padding, push),
+		"padding-top" => parse_side(value, Side::Top, Property::Padding, push),
+		"padding-right" => parse_side(value, Side::Right, Property::Padding, push),
+		"padding-bottom" => parse_side(value, Side::Bottom, Property::Padding, push),
+		"padding-left" => parse_side(value, Side::Left, Property::Padding, push),
+		"text-indent" => {
+			if let Some(length) = parse_length(value) {
+				push(Property::TextIndent(length));
+			}
+		}
+		"text-align" => {
+			let align = match value {
+				"left" | "start" => Some(TextAlign::Left),
+				"center" => Some(TextAlign::Center),
+				"right" | "end" => Some(TextAlign::Right),
+				"justify" => Some(TextAlign::Justify),
+				_ => None,
+			};
+			if let Some(align) = align {
+				push(Property::TextAlign(align));
+			}
+		}
+		"font-style" => {
+			let style = match value {
+				"normal" => Some(FontStyle::Normal),
+				"italic" | "oblique" => Some(FontStyle::Italic),
+				_ => None,
+			};
+			if let Some(style) = style {
+				push(Property::FontStyle(style));
+			}
+		}
+		"font-weight" => {
+			let weight = match value {
+				"normal" => Some(FontWeight::Absolute(400)),
+				"bold" => Some(FontWeight::Absolute(700)),
+				"bolder" => Some(FontWeight::Bolder),
+				"lighter" => Some(FontWeight::Lighter),
+				value => value
+					.parse::<u16>()
+					.ok()
+					.filter(|w| (1..=1000).contains(w))
+					.map(FontWeight::Absolute),
+			};
+			if let Some(weight) = weight {
+				push(Property::FontWeight(weight));
+			}
+		}
+		"font-size" => {
+			let size = match value {
+				"xx-small" => Some(FontSize::Absolute(0.6)),
+				"x-small" => Some(FontSize::Absolute(0.75)),
+				"small" => Some(FontSize::Absolute(0.89)),
+				"medium" => Some(FontSize::Absolute(1.0)),
+				"large" => Some(FontSize::Absolute(1.2)),
+				"x-large" => Some(FontSize::Absolute(1.5)),
+				"xx-large" => Some(FontSize::Absolute(2.0)),
+				"smaller" => Some(FontSize::Relative(1.0 / 1.2)),
+				"larger" => Some(FontSize::Relative(1.2)),
+				value => parse_length(value)
+					.filter(|l| !matches!(l, Length::Auto))
+					.map(FontSize::Length),
+			};
+			if let Some(size) = size {
+				push(Property::FontSize(size));
+			}
+		}
+		"display" => {
+			let display = match value {
+				"none" => Some(Display::None),
+				"inline" | "inline-block" => Some(Display::Inline),
+				"block" | "list-item" | "flex" | "table" => Some(Display::Block),
+				_ => None,
+			};
+			if let Some(display) = display {
+				push(Property::Display(display));
+			}
+		}
+		"page-break-before" | "break-before" => {
+			if let Some(br) = parse_page_break(value) {
+				push(Property::PageBreakBefore(br));
+			}
+		}
+		"page-break-after" | "break-after" => {
+			if let Some(br) = parse_page_break(value) {
+				push(Property::PageBreakAfter(br));
+			}
+		}
+		"page-break-inside" | "break-inside" => {
+			if let Some(br) = parse_page_break(value) {
+				push(Property::PageBreakInside(br));
+			}
+		}
+		_ => {}
+	}
+}
+
+fn parse_page_break(value: &str) -> Option<PageBreak> {
+	match value {
+		"auto" => Some(PageBreak::Auto),
+		"always" | "page" | "left" | "right" | "recto" | "verso" => Some(PageBreak::Always),
+		"avoid" | "avoid-page" => Some(PageBreak::Avoid),
+		_ => None,
+	}
+}
+
+fn parse_side(
+	value: &str,
+	side: Side,
+	property: fn(Side, Length) -> Property,
+	push: &mut impl FnMut(Property),
+) {
+	if let Some(length) = parse_length(value) {
+		push(property(side, length));
+	}
+}
+
+fn parse_sides(
+	value: &str,
+	property: fn(Side, Length) -> Property,
+	push: &mut impl FnMut(Property),
+) {
+	let values = value
+		.split_ascii_whitespace()
+		.map(parse_length)
+		.collect::<Option<Vec<_>>>();
+	let [top, right, bottom, left] = match values.as_deref() {
+		Some(&[all]) => [all; 4],
+		Some(&[vertical, horizontal]) => [vertical, horizontal, vertical, horizontal],
+		Some(&[top, horizontal, bottom]) => [top, horizontal, bottom, horizontal],
+		Some(&[top, right, bottom, left]) => [top, right, bottom, left],
+		_ => return,
+	};
+	push(property(Side::Top, top));
+	push(property(Side::Right, right));
+	push(property(Side::Bottom, bottom));
+	push(property(Side::Left, left));
+}
+
+fn parse_length(value: &str) -> Option<Length> {
+	if value == "auto" {
+		return Some(Length::Auto);
+	}
+	let split = value
+		.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+		.unwrap_or(value.len());
+	let (number, unit) = value.split_at(split);
+	let n = number.parse::<f32>().ok()?;
+	match unit {
+		"" if n == 0.0 => Some(Length::Px(0.0)),
+		"px" => Some(Length::Px(n)),
+		"pt" => Some(Length::Px(n * PX_PER_PT)),
+		"pc" => Some(Length::Px(n * 12. * PX_PER_PT)),
+		"in" => Some(Length::Px(n * 96.)),
+		"cm" => Some(Length::Px(n * 96. / 2.54)),
+		"mm" => Some(Length::Px(n * 96. / 25.4)),
+		"em" => Some(Length::Em(n)),
+		"ex" | "ch" => Some(Length::Em(n * 0.5)),
+		"rem" => Some(Length::Rem(n)),
+		"%" => Some(Length::Percent(n)),
+		_ => None,
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Sides<T> {
+	pub(crate) top: T,
+	pub(crate) right: T,
+	pub(crate) bottom: T,
+	pub(crate) left: T,
+}
+
+impl<T: Copy> Sides<T> {
+	fn all(value: T) -> Self {
+		Self {
+			top: value,
+			right: value,
+			bottom: value,
+			left: value,
+		}
+	}
+
+	fn set(&mut self, side: Side, value: T) {
+		match side {
+			Side::Top => self.top = value,
+			Side::Right => self.right = value,
+			Side::Bottom => self.bottom = value,
+			Side::Left => self.left = value,
+		}
+	}
+}
+
+/// Resolved length, relative units replaced by pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ComputedLength {
+	Px(f32),
+	Percent(f32),
+	Auto,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ComputedStyle {
+	pub(crate) display: Display,
+	pub(crate) margin: Sides<ComputedLength>,
+	pub(crate) padding: Sides<ComputedLength>,
+	pub(crate) text_indent: f32,
+	pub(crate) text_align: TextAlign,
+	pub(crate) font_style: FontStyle,
+	pub(crate) font_weight: u16,
+	pub(crate) font_size: f32,
+	pub(crate) page_break_before: PageBreak,
+	pub(crate) page_break_after: PageBreak,
+	pub(crate) page_break_inside: PageBreak,
+}
+
+impl ComputedStyle {
+	/// Initial style of root element.
+	pub(crate) fn root(font_size: f32) -> Self {
+		Self {
+			display: Display::Block,
+			margin: Sides::all(ComputedLength::Px(0.)),
+			padding: Sides::all(ComputedLength::Px(0.)),
+			text_indent: 0.,
+			text_align: TextAlign::Left,
+			font_style: FontStyle::Normal,
+			font_weight: 400,
+			font_size,
+			page_break_before: PageBreak::Auto,
+			page_break_after: PageBreak::Auto,
+			page_break_inside: PageBreak::Auto,
+		}
+	}
+
+	/// Style with inherited properties from parent, others initial.
+	fn inherit(parent: &ComputedStyle) -> Self {
+		Self {
+			text_indent: parent.text_indent,
+			text_align: parent.text_align,
+			font_style: parent.font_style,
+			font_weight: parent.font_weight,
+			font_size: parent.font_size,
+			..Self::root(parent.font_size)
+		}
+	}
+
+	pub(crate) fn is_bold(&self) -> bool {
+		self.font_weight >= 600
+	}
+
+	pub(crate) fn is_italic(&self) -> bool {
+		matches!(self.font_style, FontStyle::Italic)
+	}
+}
+
+/// Values needed to resolve relative lengths, in pixels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResolveContext {
+	pub(crate) root_font_size: f32,
+	pub(crate) scale: f32,
+	pub(crate) width: f32,
+}
+
+impl ResolveContext {
+	fn length(&self, length: Length, font_size: f32) -> ComputedLength {
+		match length {
+			Length::Px(n) => ComputedLength::Px(n * self.scale),
+			Length::Em(n) => ComputedLength::Px(n * font_size),
+			Length::Rem(n) => ComputedLength::Px(n * self.root_font_size),
+			Length::Percent(n) => ComputedLength::Percent(n / 100.),
+			Length::Auto => ComputedLength::Auto,
+		}
+	}
+
+	fn font_size(&self, size: FontSize, parent: f32) -> f32 {
+		match size {
+			// Absolute sizes relative to css default to respect profile font size
+			FontSize::Length(Length::Px(n)) => n / CSS_DEFAULT_FONT_SIZE_PX * self.root_font_size,
+			FontSize::Length(Length::Em(n)) => n * parent,
+			FontSize::Length(Length::Rem(n)) => n * self.root_font_size,
+			FontSize::Length(Length::Percent(n)) => n / 100. * parent,
+			FontSize::Length(Length::Auto) => parent,
+			FontSize::Absolute(n) => n * self.root_font_size,
+			FontSize::Relative(n) => n * parent,
+		}
+	}
+}
+
+/// Ordered set of stylesheets to cascade.
+///
+/// Sheets must be given in order of appearance.
+pub(crate) struct Cascade<'a> {
+	sheets: Vec<&'a Stylesheet>,
+	matched: Vec<(u8, Specificity, usize, Property)>,
+}
+
+impl<'a> Cascade<'a> {
+	pub(crate) fn new(sheets: impl IntoIterator<Item = &'a Stylesheet>) -> Self {
+		Self {
+			sheets: sheets.into_iter().collect(),
+			matched: Vec::new(),
+		}
+	}
+
+	pub(crate) fn compute(
+		&mut self,
+		ancestors: &[ElementRef<'_>],
+		element: &ElementRef<'_>,
+		inline: &[Declaration],
+		parent: &ComputedStyle,
+		ctx: &ResolveContext,
+	) -> ComputedStyle {
+		self.matched.clear();
+		let mut order = 0;
+		for sheet in &self.sheets {
+			for rule in &sheet.rules {
+				let specificity = rule
+					.selectors
+					.iter()
+					.filter(|s| s.matches(ancestors, element))
+					.map(|s| s.specificity)
+					.max();
+				if let Some(specificity) = specificity {
+					for decl in &rule.declarations {
+						let rank = sheet.origin.rank(decl.important);
+						self.matched.push((rank, specificity, order, decl.property));
+						order += 1;
+					}
+				}
+			}
+		}
+		let inline_specificity = Specificity(u32::MAX, 0, 0);
+		for decl in inline {
+			let rank = Origin::Author.rank(decl.important);
+			self.matched
+				.push((rank, inline_specificity, order, decl.property));
+			order += 1;
+		}
+		self.matched
+			.sort_by_key(|(rank, specificity, order, _)| (*rank, *specificity, *order));
+
+		let mut style = ComputedStyle::inherit(parent);
+
+		// Font size first, em lengths depend on it
+		for (.., property) in &self.matched {
+			if let Property::FontSize(size) = property {
+				style.font_size = ctx.font_size(*size, parent.font_size);
+			}
+		}
+
+		for (.., property) in &self.matched {
+			match *property {
+				Property::Margin(side, length) => {
+					style.margin.set(side, ctx.length(length, style.font_size));
+				}
+				Property::Padding(side, length) => {
+					style.padding.set(side, ctx.length(length, style.font_size));
+				}
+				Property::TextIndent(length) => {
+					style.text_indent = match ctx.length(length, style.font_size) {
+						ComputedLength::Px(n) => n,
+						ComputedLength::Percent(n) => n * ctx.width,
+						ComputedLength::Auto => 0.,
+					};
+				}
+				Property::TextAlign(align) => style.text_align = align,
+				Property::FontStyle(font_style) => style.font_style = font_style,
+				Property::FontWeight(FontWeight::Absolute(weight)) => style.font_weight = weight,
+				Property::FontWeight(FontWeight::Bolder) => {
+					style.font_weight = if parent.font_weight < 600 { 700 } else { 900 };
+				}
+				Property::FontWeight(FontWeight::Lighter) => {
+					style.font_weight = if parent.font_weight > 500 { 400 } else { 100 };
+				}
+				Property::FontSize(_) => {}
+				Property::Display(display) => style.display = display,
+				Property::PageBreakBefore(br) => style.page_break_before = br,
+				Property::PageBreakAfter(br) => style.page_break_after = br,
+				Property::PageBreakInside(br) => style.page_break_inside = br,
+			}
+		}
+
+		style
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::css::Cascade;
+	use crate::css::ComputedLength;
+	use crate::css::ComputedStyle;
+	use crate::css::Display;
+	use crate::css::ElementRef;
+	use crate::css::FontStyle;
+	use crate::css::Origin;
+	use crate::css::PageBreak;
+	use crate::css::ResolveContext;
+	use crate::css::Stylesheet;
+	use crate::css::TextAlign;
+	use crate::css::parse_declarations;
+
+	const CTX: ResolveContext = ResolveContext {
+		root_font_size: 20.,
+		scale: 1.,
+		width: 400.,
+	};
+
+	fn el<'a>(name: &'a str, id: Option<&'a str>, class: Option<&'a str>) -> ElementRef<'a> {
+		ElementRef { name, id, class }
+	}
+
+	#[test]
+	fn test_css_parse_skips_unsupported() {
+		let sheet = Stylesheet::parse(
+			Origin::Author,
+			r#"
+@charset "utf-8";
+@font-face { font-family: "X"; src: url(x.ttf); }
+/* comment { } */
+@media screen { p { display: none; } }
+p:first-child { display: none; }
+a[href] { display: none; }
+p, .note { text-align: center; color: red; }
+unknown { color: red; }
+"#,
+		);
+		assert_eq!(sheet.rules.len(), 1, "Rule count missmatch");
+		assert_eq!(
+			sheet.rules[0].selectors.len(),
+			2,
+			"Selector count missmatch"
+		);
+		assert_eq!(
+			sheet.rules[0].declarations.len(),
+			1,
+			"Declaration count missmatch"
+		);
+	}
+
+	#[test]
+	fn test_css_cascade_specificity_and_order() {
+		let sheet = Stylesheet::parse(
+			Origin::Author,
+			r#"
+#intro { text-align: right; }
+#outro { text-align: justify; }
+p.note { text-align: center; }
+p { text-align: left; font-style: italic; }
+p { font-style: normal; }
+"#,
+		);
+		let mut cascade = Cascade::new([&sheet]);
+		let root = ComputedStyle::root(CTX.root_font_size);
+
+		let style = cascade.compute(&[], &el("p", None, Some("a note")), &[], &root, &CTX);
+		assert_eq!(style.text_align, TextAlign::Center, "Align missmatch");
+		assert_eq!(style.font_style, FontStyle::Normal, "Font style missmatch");
+
+		let style = cascade.compute(&[], &el("p", Some("intro"), Some("note")), &[], &root, &CTX);
+		assert_eq!(style.text_align, TextAlign::Right, "Align missmatch");
+
+		let style = cascade.compute(&[], &el("p", Some("outro"), None), &[], &root, &CTX);
+		assert_eq!(style.text_align, TextAlign::Justify, "Align missmatch");
+	}
+
+	#[test]
+	fn test_css_cascade_origins() {
+		let ua = Stylesheet::parse(Origin::UserAgent, "p { text-indent: 1em; display: block }");
+		let user = Stylesheet::parse(Origin::User, "p { text-align: right !important }");
+		let author = Stylesheet::parse(
+			Origin::Author,
+			"p { text-indent: 2em; text-align: center !important; display: none }",
+		);
+		let mut cascade = Cascade::new([&ua, &user, &author]);
+		let root = ComputedStyle::root(CTX.root_font_size);
+
+		let inline = parse_declarations("display: inline");
+		let style = cascade.compute(&[], &el("p", None, None), &inline, &root, &CTX);
+		assert_eq!(style.text_indent, 40., "Indent missmatch");
+		assert_eq!(style.text_align, TextAlign::Right, "Align missmatch");
+		assert_eq!(style.display, Display::Inline, "Display missmatch");
+	}
+
+	#[test]
+	fn test_css_cascade_descendant_and_inherit() {
+		let sheet = Stylesheet::parse(
+			Origin::Author,
+			r#"
+div.chapter p { font-size: 150%; margin: 1em 5% 0; }
+body > p { page-break-before: always; }
+"#,
+		);
+		let mut cascade = Cascade::new([&sheet]);
+		let root = ComputedStyle::root(CTX.root_font_size);
+		let body = el("body", None, None);
+		let div = el("div", None, Some("chapter"));
+		let p = el("p", None, None);
+
+		let div_style = cascade.compute(&[body], &div, &[], &root, &CTX);
+		assert_eq!(div_style.font_size, 20., "Font size missmatch");
+
+		let style = cascade.compute(&[body, div], &p, &[], &div_style, &CTX);
+		assert_eq!(style.font_size, 30., "Font size missmatch");
+		assert_eq!(
+			style.margin.top,
+			ComputedLength::Px(30.),
+			"Margin missmatch"
+		);
+		assert_eq!(
+			style.margin.right,
+			ComputedLength::Percent(0.05),
+			"Margin missmatch"
+		);
+		assert_eq!(
+			style.margin.bottom,
+			ComputedLength::Px(0.),
+			"Margin missmatch"
+		);
+		assert_eq!(
+			style.page_break_before,
+			PageBreak::Auto,
+			"Page break missmatch"
+		);
+
+		let style = cascade.compute(&[body], &p, &[], &root, &CTX);
+		assert_eq!(
+			style.page_break_before,
+			PageBreak::Always,
+			"Page break missmatch"
+		);
+
+		let style = cascade.compute(&[div], &p, &[], &div_style, &CTX);
+		assert_eq!(
+			style.page_break_before,
+			PageBreak::Auto,
+			"Page break missmatch"
+		);
+	}
+
+	#[test]
+	fn test_css_absolute_font_size_follows_profile() {
+		let sheet = Stylesheet::parse(
+			Origin::Author,
+			"h1 { font-size: 24px } h2 { font-size: 12pt }",
+		);
+		let mut cascade = Cascade::new([&sheet]);
+		let root = ComputedStyle::root(CTX.root_font_size);
+
+		let style = cascade.compute(&[], &el("h1", None, None), &[], &root, &CTX);
+		assert_eq!(style.font_size, 30., "Font size missmatch");
+		let style = cascade.compute(&[], &el("h2", None, None), &[], &root, &CTX);
+		assert_eq!(style.font_size, 20., "Font size missmatch");
+	}
+}
@@ -11,6 +11,7 @@ use html5ever::QualName;
 use html5ever::interface::NodeOrText;
 use html5ever::interface::TreeSink;
 use html5ever::local_name;
+use html5ever::ns;
 use html5ever::tendril::StrTendril;
 use html5ever::tendril::TendrilSink;
 use html5ever::tendril::stream::Utf8LossyDecoder;
@@ -27,6 +28,33 @@ pub struct Element {
 	pub attrs: BTreeMap<QualName, String>,
 }
 
+impl Element {
+	pub fn attr(&self, name: LocalName) -> Option<&str> {
+		self.attrs
+			.get(&QualName::new(None, ns!(), name))
+			.map(|v| v.as_str())
+	}
+
+	/// Language from `lang` or `xml:lang` attribute
+	pub fn lang(&self) -> Option<&str> {
+		self.attr(local_name!("lang"))
+			.or_else(|| self.attr(LocalName::from("xml:lang")))
+			.or_else(|| {
+				self.attrs
+					.get(&QualName::new(None, ns!(xml), local_name!("lang")))
+					.map(|v| v.as_str())
+			})
+			.filter(|lang| !lang.trim().is_empty())
+	}
+
+	/// Tokens of `epub:type` attribute
+	pub fn epub_types(&self) -> impl Iterator<Item = &str> {
+		self.attr(LocalName::from("epub:type"))
+			.unwrap_or_default()
+			.split_ascii_whitespace()
+	}
+}
+
 #[derive(Debug)]
 pub struct Text {
 	pub t: StrTendril,
@@ -244,6 +272,12 @@ impl<'a> Iterator for NodeTreeIter<'a> {
 	}
 }
 
+/// Stylesheet referenced by document, in document order
+pub(crate) enum StyleSource<'a> {
+	Link(&'a str),
+	Inline(String),
+}
+
 pub struct NodeTreeResult {
 	#[allow(dead_code)]
 	pub(crate) root: NodeId,
@@ -259,6 +293,70 @@ impl NodeTreeResult {
 		self.body.map(|id| NodeTreeIter::new(&self.tree, id))
 	}
 
+	/// Elements from document root down to and including body
+	pub(crate) fn body_ancestors<'a>(&'a self) -> Vec<ElementWrapper<'a>> {
+		let mut ancestors = Vec::new();
+		let mut node = self.body;
+		while let Some(id) = node {
+			if let Some(Leaf::Element(el)) = self.tree.get_context(id) {
+				ancestors.push(ElementWrapper { id, el });
+			}
+			node = self.tree.parent(id);
+		}
+		ancestors.reverse();
+		ancestors
+	}
+
+	pub(crate) fn style_sources<'a>(&'a self) -> Vec<StyleSource<'a>> {
+		let mut sources = Vec::new();
+		let mut node_iter = NodeTreeIter::new(&self.tree, self.root);
+		while let Some(edge) = node_iter.next() {
+			let EdgeRef::OpenElement(el) = edge else {
+				continue;
+			};
+			match *el.local_name() {
+				local_name!("style") => {
+					let mut css = String::new();
+					for edge in node_iter.by_ref() {
+						match edge {
+							EdgeRef::Text(TextWrapper { t, .. }) => css.push_str(&t.t),
+							EdgeRef::CloseElement(id, _) if id == el.id => break,
+							_ => {}
+						}
+					}
+					sources.push(StyleSource::Inline(css));
+				}
+				local_name!("link") => {
+					let is_stylesheet = el.el.attr(local_name!("rel")).is_some_and(|rel| {
+						rel.split_ascii_whitespace()
+							.any(|r| r.eq_ignore_ascii_case("stylesheet"))
+					});
+					if is_stylesheet && let Some(href) = el.el.attr(local_name!("href")) {
+						sources.push(StyleSource::Link(href));
+					}
+				}
+				_ => {}
+			}
+		}
+		sources
+	}
+
+	/// Text content of element with whitespace collapsed
+	pub(crate) fn text_content(&self, id: NodeId) -> String {
+		let mut text = String::new();
+		for edge in NodeTreeIter::new(&self.tree, id) {
+			if let EdgeRef::Text(TextWrapper { t, .. }) = edge {
+				for word in t.t.split_whitespace() {
+					if !text.is_empty() {
+						text.push(' ');
+					}
+					text.push_str(word);
+				}
+			}
+		}
+		text
+	}
+
 	pub(crate) fn into_builder(self) -> NodeTreeBuilder {
 		let NodeTreeResult {
 			mut tree,
@@ -495,6 +593,7 @@ impl TreeSink for NodeTreeBuilder {
 
 #[cfg(test)]
 mod tests {
+	use html5ever::local_name;
 	use html5ever::parse_document;
 	use html5ever::tendril::TendrilSink;
 
@@ -502,6 +601,7 @@ mod tests {
 	use crate::html_parser::Leaf;
 	use crate::html_parser::NodeTreeBuilder;
 	use crate::html_parser::NodeTreeIter;
+	use crate::html_parser::StyleSource;
 
 	#[test]
 	fn test_html_parser_text() {
@@ -593,4 +693,102 @@ mod tests {
 		assert!(has_head, "Missing head element");
 		assert!(has_body, "Missing body element");
 	}
+
+	#[test]
+	fn test_html_parser_style_sources() {
+		let _ = env_logger::try_init();
+		let input = r#"<html>
+  <head>
+    <link rel="stylesheet" href="../Styles/style.css"/>
+    <link rel="icon" href="icon.png"/>
+    <style>p { text-indent: 1em; }</style>
+  </head>
+  <body><p id="first" class="a b">text</p></body>
+</html>
+"#;
+
+		let parser = parse_document(NodeTreeBuilder::new(), Default::default());
+		let node_tree = parser.one(input);
+
+		let sources = node_tree.style_sources();
+		assert_eq!(sources.len(), 2, "Style source count missmatch");
+		assert!(
+			matches!(sources[0], StyleSource::Link("../Styles/style.css")),
+			"Link source missmatch"
+		);
+		assert!(
+			matches!(&sources[1], StyleSource::Inline(css) if css == "p { text-indent: 1em; }"),
+			"Inline source missmatch"
+		);
+
+		let ancestors = node_tree.body_ancestors();
+		let names = ancestors
+			.iter()
+			.map(|el| el.local_name().to_string())
+			.collect::<Vec<_>>();
+		assert_eq!(names, vec!["html", "body"], "Body ancestors missmatch");
+	}
+
+	#[test]
+	fn test_html_parser_lang() {
+		let _ = env_logger::try_init();
+		let input = r#"<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="sv">
+  <body lang="de-DE"><p>text</p></body>
+</html>
+"#;
+
+		let parser = parse_document(NodeTreeBuilder::new(), Default::default());
+		let node_tree = parser.one(input);
+
+		let langs = node_tree
+			.body_ancestors()
+			.iter()
+			.map(|el| el.el.lang())
+			.collect::<Vec<_>>();
+		assert_eq!(langs, vec![Some("sv"), Some("de-DE")], "Lang missmatch");
+	}
+
+	#[test]
+	fn test_html_parser_note_text() {
+		let _ = env_logger::try_init();
+		let input = r##"<html xmlns:epub="http://www.idpf.org/2007/ops">
+  <body>
+    <p>See <a epub:type="noteref" href="#n1">1</a></p>
+    <aside epub:type="footnote" id="n1">
+      <p>A  <i>short</i>
+      note.</p>
+    </aside>
+  </body>
+</html>
+"##;
+
+		let parser = parse_document(NodeTreeBuilder::new(), Default::default());
+		let node_tree = parser.one(input);
+
+		let elements = node_tree
+			.body_iter()
+			.expect("Missing body")
+			.filter_map(|edge| match edge {
+				EdgeRef::OpenElement(el) => Some(el),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		let link = elements
+			.iter()
+			.find(|el| *el.local_name() == local_name!("a"))
+			.expect("Missing link");
+		assert!(
+			link.el.epub_types().any(|t| t == "noteref"),
+			"Epub type missmatch"
+		);
+		let aside = elements
+			.iter()
+			.find(|el| *el.local_name() == local_name!("aside"))
+			.expect("Missing aside");
+		assert_eq!(
+			node_tree.text_content(aside.id),
+			"A short note.",
+			"Text content missmatch"
+		);
+	}
 }
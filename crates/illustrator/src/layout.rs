@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
 use std::io;
 use std::mem;
+use std::path::Component;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -13,10 +16,15 @@ use pixelator::PixelatorTextures;
 use pixelator::PixmapData;
 use resvg::tiny_skia;
 use resvg::usvg;
+use scribe::Highlight;
+use scribe::HighlightId;
+use scribe::TextPosition;
 use scribe::config::FontConfig;
 use scribe::config::IllustratorProfile;
+use scribe::config::TextAlignConfig;
 use sculpter::AtlasImage;
 use sculpter::Axis;
+use sculpter::BlockStyle;
 use sculpter::Family;
 use sculpter::Fixed;
 use sculpter::FontOptions;
@@ -25,21 +33,44 @@ use sculpter::Sculpter;
 use sculpter::SculpterHandle;
 use sculpter::SculpterInput;
 use sculpter::SculpterPrinterError;
+use sculpter::TextAlign;
+use sculpter::TextCluster;
+use sculpter::TextSpan;
 use sculpter::Variation;
 use taffy::prelude::*;
 use zip::ZipArchive;
 
 use crate::DisplayContent;
+use crate::DisplayHighlight;
 use crate::DisplayItem;
+use crate::DisplayLink;
 use crate::DisplayPixmap;
+use crate::DisplayText;
+use crate::Link;
+use crate::LinkTarget;
 use crate::PageContent;
 use crate::PageFlags;
 use crate::Params;
+use crate::cache::ChapterAnchors;
+use crate::css;
+use crate::css::Cascade;
+use crate::css::ComputedLength;
+use crate::css::ComputedStyle;
+use crate::css::Declaration;
+use crate::css::ElementRef;
+use crate::css::Origin;
+use crate::css::PageBreak;
+use crate::css::ResolveContext;
+use crate::css::Stylesheet;
+use crate::css::parse_declarations;
 use crate::html_parser::EdgeRef;
+use crate::html_parser::Element;
 use crate::html_parser::NodeTreeBuilder;
+use crate::html_parser::StyleSource;
 use crate::html_parser::Text;
 use crate::html_parser::TextWrapper;
 use crate::html_parser::TreeBuilderError;
+use crate::search::SearchQuery;
 use crate::svg::HORIZONTAL_RULER_SVG;
 use crate::svg::IllustratorSvgError;
 use crate::svg::read_svg;
@@ -75,36 +106,231 @@ pub enum IllustratorLayoutError {
 	MissingSvgContent(NodeId),
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-pub(crate) enum TextStyle {
-	#[default]
-	Body,
-	Bold,
-	Italic,
-	H1,
-	H2,
-	H3,
-	H4,
-	H5,
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextStyle<'a> {
+	bold: bool,
+	italic: bool,
+	font_size: f32,
+	lang: Option<&'a str>,
+	/// Index of enclosing link
+	link: Option<u32>,
+	/// Text is part of a search match
+	highlight: bool,
+	/// Text is part of stored highlight
+	user_highlight: Option<HighlightId>,
 }
 
-impl TextStyle {
-	fn try_from(name: &LocalName) -> Option<TextStyle> {
-		match *name {
-			local_name!("b") | local_name!("strong") => Some(TextStyle::Bold),
-			local_name!("i") | local_name!("em") => Some(TextStyle::Italic),
-			local_name!("h1") => Some(TextStyle::H1),
-			local_name!("h2") => Some(TextStyle::H2),
-			local_name!("h3") => Some(TextStyle::H3),
-			local_name!("h4") => Some(TextStyle::H4),
-			local_name!("h5") => Some(TextStyle::H5),
-			_ => None,
+impl<'a> TextStyle<'a> {
+	fn new(style: &ComputedStyle, lang: Option<&'a str>, link: Option<u32>) -> Self {
+		Self {
+			bold: style.is_bold(),
+			italic: style.is_italic(),
+			font_size: style.font_size,
+			lang,
+			link,
+			highlight: false,
+			user_highlight: None,
 		}
 	}
 }
 
+/// Text reported back as areas on page, indexed by sculpter span
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TextMark {
+	link: Option<u32>,
+	highlight: bool,
+	user_highlight: Option<HighlightId>,
+}
+
+/// Span of text style, marks are added as needed
+fn mark_span(marks: &mut Vec<TextMark>, style: &TextStyle<'_>) -> Option<u32> {
+	if style.link.is_none() && !style.highlight && style.user_highlight.is_none() {
+		return None;
+	}
+	let mark = TextMark {
+		link: style.link,
+		highlight: style.highlight,
+		user_highlight: style.user_highlight,
+	};
+	let index = marks.iter().position(|m| *m == mark).unwrap_or_else(|| {
+		marks.push(mark);
+		marks.len() - 1
+	});
+	Some(index as u32)
+}
+
+/// Text input of block, with byte offset into text node
+type TextInput<'t, 's> = (crate::html_parser::NodeId, u32, &'t str, TextStyle<'s>);
+
+/// Split text inputs of block at matches of highlight query
+fn highlight_inputs(inputs: &mut Vec<TextInput<'_, '_>>, highlight: Option<&SearchQuery>) {
+	let Some(query) = highlight else {
+		return;
+	};
+	let parts = inputs.iter().map(|(_, _, t, _)| *t).collect::<Vec<_>>();
+	let pieces = query
+		.split(&parts)
+		.into_iter()
+		.map(|(index, offset, t, highlight)| {
+			let (id, start, _, style) = inputs[index];
+			(
+				id,
+				start + offset as u32,
+				t,
+				TextStyle { highlight, ..style },
+			)
+		})
+		.collect();
+	*inputs = pieces;
+}
+
+/// Split text inputs of block at bounds of stored highlights
+fn mark_highlights(inputs: &mut Vec<TextInput<'_, '_>>, highlights: &[&Highlight]) {
+	if highlights.is_empty() {
+		return;
+	}
+	let mut pieces = Vec::with_capacity(inputs.len());
+	for (id, start, t, style) in inputs.drain(..) {
+		let element = id.value();
+		let end = start + t.len() as u32;
+		let input_start = TextPosition {
+			element,
+			offset: start,
+		};
+		let input_end = TextPosition {
+			element,
+			offset: end,
+		};
+		let overlaps = highlights
+			.iter()
+			.filter(|h| h.start < input_end && input_start < h.end)
+			.collect::<Vec<_>>();
+		if overlaps.is_empty() {
+			pieces.push((id, start, t, style));
+			continue;
+		}
+
+		let mut cuts = vec![start, end];
+		for pos in overlaps.iter().flat_map(|h| [h.start, h.end]) {
+			if pos.element == element
+				&& pos.offset > start
+				&& pos.offset < end
+				&& t.is_char_boundary((pos.offset - start) as usize)
+			{
+				cuts.push(pos.offset);
+			}
+		}
+		cuts.sort_unstable();
+		cuts.dedup();
+		for cut in cuts.windows(2) {
+			let piece = &t[(cut[0] - start) as usize..(cut[1] - start) as usize];
+			let pos = TextPosition {
+				element,
+				offset: cut[0],
+			};
+			let user_highlight = overlaps
+				.iter()
+				.find(|h| h.start <= pos && pos < h.end)
+				.map(|h| h.id);
+			pieces.push((
+				id,
+				cut[0],
+				piece,
+				TextStyle {
+					user_highlight,
+					..style
+				},
+			));
+		}
+	}
+	*inputs = pieces;
+}
+
+/// Start of each text input in chapter text, indexed by sculpter input
+fn input_sources(inputs: &[TextInput<'_, '_>]) -> Vec<TextPosition> {
+	inputs
+		.iter()
+		.map(|(id, offset, _, _)| TextPosition {
+			element: id.value(),
+			offset: *offset,
+		})
+		.collect()
+}
+
+/// Element on the open element stack during layout
+struct OpenElement<'a> {
+	id: crate::html_parser::NodeId,
+	style: ComputedStyle,
+	lang: Option<&'a str>,
+	link: Option<u32>,
+}
+
+/// Stylesheets of a book, author sheets keyed by normalized archive path.
+pub(crate) struct BookStylesheets {
+	user_agent: Stylesheet,
+	user: Stylesheet,
+	author: BTreeMap<PathBuf, Stylesheet>,
+}
+
+impl BookStylesheets {
+	pub(crate) fn new(profile: &IllustratorProfile, author: BTreeMap<PathBuf, Stylesheet>) -> Self {
+		let user_agent = Stylesheet::parse(Origin::UserAgent, &user_agent_css(profile));
+		// Page padding is set by profile
+		let user = Stylesheet::parse(
+			Origin::User,
+			"html, body { margin: 0 !important; padding: 0 !important; }",
+		);
+		Self {
+			user_agent,
+			user,
+			author,
+		}
+	}
+}
+
+fn user_agent_css(profile: &IllustratorProfile) -> String {
+	let paragraph = profile.padding.paragraph_em;
+	let text_align = match profile.text_align {
+		TextAlignConfig::Left => "left",
+		TextAlignConfig::Justify => "justify",
+		TextAlignConfig::Center => "center",
+		TextAlignConfig::Right => "right",
+	};
+	let mut css = format!(
+		r#"
+html {{ text-align: {text_align}; }}
+head, link, meta, script, style, title {{ display: none; }}
+a, abbr, b, big, cite, code, dfn, em, i, kbd, mark, q, s, samp, small, span, strong, sub, sup, time, u, var {{ display: inline; }}
+b, strong {{ font-weight: bold; }}
+cite, dfn, em, i, var {{ font-style: italic; }}
+p {{ padding-bottom: {paragraph}rem; }}
+hr, img {{ margin-bottom: {paragraph}rem; }}
+"#
+	);
+	for (name, header) in [
+		("h1", &profile.h1),
+		("h2", &profile.h2),
+		("h3", &profile.h3),
+		("h4", &profile.h4),
+		("h5", &profile.h5),
+	] {
+		css.push_str(&format!(
+			"{name} {{ font-size: {}em; padding-bottom: {}rem; }}\n",
+			header.font_size_em, header.padding_em
+		));
+	}
+	css
+}
+
 pub(crate) struct StyleSettings<'a> {
 	profile: &'a IllustratorProfile,
+	stylesheets: &'a BookStylesheets,
+	/// Book language, used when content has no `lang`
+	language: Option<&'a str>,
+	/// Search query to highlight in text
+	highlight: Option<&'a SearchQuery>,
+	/// Stored highlights of book
+	highlights: &'a [Highlight],
 
 	font_regular: FontOptions<'a>,
 	font_italic: FontOptions<'a>,
@@ -116,13 +342,24 @@ pub(crate) struct StyleSettings<'a> {
 }
 
 impl<'a> StyleSettings<'a> {
-	pub(crate) fn new(profile: &'a IllustratorProfile, params: &Params) -> Self {
+	pub(crate) fn new(
+		profile: &'a IllustratorProfile,
+		stylesheets: &'a BookStylesheets,
+		language: Option<&'a str>,
+		params: &Params,
+		highlight: Option<&'a SearchQuery>,
+		highlights: &'a [Highlight],
+	) -> Self {
 		let font_regular = into_font_options(&profile.font_regular);
 		let font_italic = into_font_options(&profile.font_italic);
 		let font_bold = into_font_options(&profile.font_bold);
 
 		Self {
 			profile,
+			stylesheets,
+			language,
+			highlight,
+			highlights,
 
 			font_regular,
 			font_italic,
@@ -134,56 +371,25 @@ impl<'a> StyleSettings<'a> {
 		}
 	}
 
-	fn text_style(&'a self, style: TextStyle) -> FontStyle<'a> {
-		use sculpter::Fixed;
-
-		let font_size = Fixed::from_num(self.font_size());
-		let line_height_em = Fixed::from_num(self.profile.line_height);
-
-		match style {
-			TextStyle::Body => FontStyle {
-				font_opts: &self.font_regular,
-				font_size,
-				line_height_em,
-			},
-			TextStyle::Bold => FontStyle {
-				font_opts: &self.font_bold,
-				font_size,
-				line_height_em,
-			},
-			TextStyle::Italic => FontStyle {
-				font_opts: &self.font_italic,
-				font_size,
-				line_height_em,
-			},
-			TextStyle::H1 => FontStyle {
-				font_opts: &self.font_regular,
-				font_size: font_size * Fixed::from_num(self.profile.h1.font_size_em),
-				line_height_em,
-			},
-			TextStyle::H2 => FontStyle {
-				font_opts: &self.font_regular,
-				font_size: font_size * Fixed::from_num(self.profile.h2.font_size_em),
-				line_height_em,
-			},
-			TextStyle::H3 => FontStyle {
-				font_opts: &self.font_regular,
-				font_size: font_size * Fixed::from_num(self.profile.h3.font_size_em),
-				line_height_em,
-			},
-			TextStyle::H4 => FontStyle {
-				font_opts: &self.font_regular,
-				font_size: font_size * Fixed::from_num(self.profile.h4.font_size_em),
-				line_height_em,
-			},
-			TextStyle::H5 => FontStyle {
-				font_opts: &self.font_regular,
-				font_size: font_size * Fixed::from_num(self.profile.h5.font_size_em),
-				line_height_em,
-			},
+	fn text_style(&'a self, style: &TextStyle<'_>) -> FontStyle<'a> {
+		let font_opts = if style.bold {
+			&self.font_bold
+		} else if style.italic {
+			&self.font_italic
+		} else {
+			&self.font_regular
+		};
+		FontStyle {
+			font_opts,
+			font_size: Fixed::from_num(style.font_size),
+			line_height_em: Fixed::from_num(self.profile.line_height),
 		}
 	}
 
+	pub(crate) fn page_width(&self) -> u32 {
+		self.page_width
+	}
+
 	fn font_size(&self) -> f32 {
 		self.profile.font_size * self.scale
 	}
@@ -217,104 +423,133 @@ impl<'a> StyleSettings<'a> {
 		self.em_to_px(self.profile.padding.left_em)
 	}
 
-	fn element_style(&self, name: &LocalName) -> Style {
-		match *name {
-			local_name!("h1") => Style {
-				display: Display::Block,
-				box_sizing: BoxSizing::ContentBox,
-				padding: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.h1.padding_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
-			},
-			local_name!("h2") => Style {
-				display: Display::Block,
-				box_sizing: BoxSizing::ContentBox,
-				padding: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.h2.padding_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
-			},
-			local_name!("h3") => Style {
-				display: Display::Block,
-				box_sizing: BoxSizing::ContentBox,
-				padding: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.h3.padding_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
-			},
-			local_name!("h4") => Style {
-				display: Display::Block,
-				box_sizing: BoxSizing::ContentBox,
-				padding: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.h4.padding_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
-			},
-			local_name!("h5") => Style {
-				display: Display::Block,
-				box_sizing: BoxSizing::ContentBox,
-				padding: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.h5.padding_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
-			},
-			local_name!("p") => Style {
-				display: Display::Block,
-				box_sizing: BoxSizing::ContentBox,
-				padding: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.padding.paragraph_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
+	fn resolve_context(&self) -> ResolveContext {
+		ResolveContext {
+			root_font_size: self.font_size(),
+			scale: self.scale,
+			width: self.page_width_padded(),
+		}
+	}
+
+	fn element_style(&self, name: &LocalName, style: &ComputedStyle) -> Style {
+		let margin = |l: ComputedLength| -> taffy::LengthPercentageAuto {
+			match l {
+				ComputedLength::Px(n) => length(n),
+				ComputedLength::Percent(n) => percent(n),
+				ComputedLength::Auto => auto(),
+			}
+		};
+		let padding = |l: ComputedLength| -> taffy::LengthPercentage {
+			match l {
+				ComputedLength::Px(n) => length(n),
+				ComputedLength::Percent(n) => percent(n),
+				ComputedLength::Auto => zero(),
+			}
+		};
+		let size = match *name {
+			local_name!("hr") => taffy::Size {
+				width: auto(),
+				height: length(self.em_to_px(1.2)),
 			},
-			local_name!("hr") => Style {
-				display: Display::Block,
-				size: taffy::Size {
-					width: auto(),
-					height: length(self.em_to_px(1.2)),
-				},
-				margin: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.padding.paragraph_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
+			_ => taffy::Size {
+				width: auto(),
+				height: auto(),
 			},
-			local_name!("img") => Style {
-				display: Display::Block,
-				margin: Rect {
-					top: zero(),
-					bottom: length(self.em_to_px(self.profile.padding.paragraph_em)),
-					left: zero(),
-					right: zero(),
-				},
-				..Style::default()
+		};
+		Style {
+			display: Display::Block,
+			box_sizing: BoxSizing::ContentBox,
+			size,
+			margin: Rect {
+				top: margin(style.margin.top),
+				bottom: margin(style.margin.bottom),
+				left: margin(style.margin.left),
+				right: margin(style.margin.right),
 			},
-			_ => Style {
-				display: Display::Block,
-				..Style::default()
+			padding: Rect {
+				top: padding(style.padding.top),
+				bottom: padding(style.padding.bottom),
+				left: padding(style.padding.left),
+				right: padding(style.padding.right),
 			},
+			..Style::default()
+		}
+	}
+}
+
+fn block_style(style: &ComputedStyle, indent: bool) -> BlockStyle {
+	BlockStyle {
+		text_indent: if indent {
+			Fixed::from_num(style.text_indent)
+		} else {
+			Fixed::ZERO
+		},
+		text_align: match style.text_align {
+			css::TextAlign::Left => TextAlign::Left,
+			css::TextAlign::Center => TextAlign::Center,
+			css::TextAlign::Right => TextAlign::Right,
+			css::TextAlign::Justify => TextAlign::Justify,
+		},
+	}
+}
+
+fn inline_declarations(el: &Element) -> Vec<Declaration> {
+	el.attr(local_name!("style"))
+		.map(parse_declarations)
+		.unwrap_or_default()
+}
+
+fn element_ref(el: &Element) -> ElementRef<'_> {
+	ElementRef {
+		name: &el.name.local,
+		id: el.attr(local_name!("id")),
+		class: el.attr(local_name!("class")),
+	}
+}
+
+/// Normalize archive path, resolving `.` and `..` components.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+	let mut normalized = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::ParentDir => {
+				normalized.pop();
+			}
+			Component::Normal(c) => normalized.push(c),
+			Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
 		}
 	}
+	normalized
+}
+
+/// Resolve link `href` relative to chapter at `path`.
+fn resolve_href(path: &Path, href: &str) -> LinkTarget {
+	let has_scheme = href
+		.find(':')
+		.is_some_and(|i| !href[..i].contains(['/', '#', '?']));
+	if has_scheme {
+		return LinkTarget::External(href.to_string());
+	}
+	let (file, fragment) = match href.split_once('#') {
+		Some((file, fragment)) => (file, Some(fragment)),
+		None => (href, None),
+	};
+	let path = if file.is_empty() {
+		normalize_path(path)
+	} else {
+		normalize_path(&path.parent().unwrap_or(Path::new("")).join(file))
+	};
+	LinkTarget::Internal {
+		path,
+		fragment: fragment.filter(|f| !f.is_empty()).map(str::to_string),
+	}
+}
+
+fn is_note(el: &Element) -> bool {
+	el.name.local == local_name!("aside")
+		|| el
+			.epub_types()
+			.any(|t| matches!(t, "footnote" | "endnote" | "note" | "rearnote"))
 }
 
 pub(crate) fn into_font_options<'a>(value: &'a FontConfig) -> FontOptions<'a> {
@@ -398,7 +633,8 @@ impl<'a, C> Iterator for TaffyTreeIter<'a, C> {
 #[derive(Debug)]
 enum NodeContent {
 	Block,
-	Text(SculpterHandle),
+	/// Shaped text, with start of each input in chapter text
+	Text(SculpterHandle, Vec<TextPosition>),
 	Svg(Arc<usvg::Tree>),
 	Image(Arc<RgbaImage>),
 }
@@ -407,34 +643,44 @@ enum NodeContent {
 struct NodeContext {
 	element: u32,
 	content: NodeContent,
+	page_break_before: PageBreak,
+	page_break_after: PageBreak,
+	page_break_inside: PageBreak,
 }
 
 impl NodeContext {
-	fn block(element: u32) -> Self {
+	fn new(element: u32, content: NodeContent) -> Self {
 		Self {
 			element,
-			content: NodeContent::Block,
+			content,
+			page_break_before: PageBreak::Auto,
+			page_break_after: PageBreak::Auto,
+			page_break_inside: PageBreak::Auto,
 		}
 	}
 
-	fn text(element: u32, handle: SculpterHandle) -> Self {
-		Self {
-			element,
-			content: NodeContent::Text(handle),
-		}
+	fn block(element: u32) -> Self {
+		Self::new(element, NodeContent::Block)
+	}
+
+	fn text(element: u32, handle: SculpterHandle, sources: Vec<TextPosition>) -> Self {
+		Self::new(element, NodeContent::Text(handle, sources))
 	}
 
 	fn svg(element: u32, tree: Arc<usvg::Tree>) -> Self {
-		Self {
-			element,
-			content: NodeContent::Svg(tree),
-		}
+		Self::new(element, NodeContent::Svg(tree))
 	}
 
 	fn image(element: u32, image: Arc<RgbaImage>) -> Self {
+		Self::new(element, NodeContent::Image(image))
+	}
+
+	fn with_page_breaks(self, style: &ComputedStyle) -> Self {
 		Self {
-			element,
-			content: NodeContent::Image(image),
+			page_break_before: style.page_break_before,
+			page_break_after: style.page_break_after,
+			page_break_inside: style.page_break_inside,
+			..self
 		}
 	}
 }
@@ -442,6 +688,9 @@ impl NodeContext {
 pub(crate) struct PageLayouterEmpty;
 pub(crate) struct PageLayouterLoaded {
 	content_id: NodeId,
+	marks: Vec<TextMark>,
+	links: Vec<Arc<Link>>,
+	anchors: ChapterAnchors,
 }
 
 pub(crate) struct PageLayouter<'a, TState = PageLayouterEmpty> {
@@ -468,6 +717,7 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 		archive: &mut ZipArchive<R>,
 		root: &Path,
 		path: &Path,
+		spine: u32,
 		settings: &StyleSettings<'settings>,
 	) -> Result<PageLayouter<'layout, PageLayouterLoaded>, IllustratorLayoutError> {
 		let Self {
@@ -500,9 +750,76 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 		let src_attr_name =
 			html5ever::QualName::new(None, html5ever::ns!(), html5ever::local_name!("src"));
 
+		let stylesheets = settings.stylesheets;
+		let style_sources = node_tree.style_sources();
+		let chapter_sheets = style_sources
+			.iter()
+			.filter_map(|source| match source {
+				StyleSource::Inline(css) => Some(Stylesheet::parse(Origin::Author, css)),
+				StyleSource::Link(_) => None,
+			})
+			.collect::<Vec<_>>();
+		let mut chapter_sheets_iter = chapter_sheets.iter();
+		let mut sheets = vec![&stylesheets.user_agent, &stylesheets.user];
+		for source in &style_sources {
+			match source {
+				StyleSource::Link(href) => {
+					let href = href.split('#').next().unwrap_or_default();
+					let sheet_path =
+						normalize_path(&path.parent().unwrap_or(Path::new("")).join(href));
+					if let Some(sheet) = stylesheets.author.get(&sheet_path) {
+						sheets.push(sheet);
+					} else {
+						log::debug!("Missing stylesheet {}", sheet_path.display());
+					}
+				}
+				StyleSource::Inline(_) => sheets.extend(chapter_sheets_iter.next()),
+			}
+		}
+		drop(style_sources);
+		let mut cascade = Cascade::new(sheets);
+		let resolve_ctx = settings.resolve_context();
+		let highlights = settings
+			.highlights
+			.iter()
+			.filter(|h| h.spine == spine)
+			.collect::<Vec<_>>();
+
+		let root_style = ComputedStyle::root(settings.font_size());
+		let mut ancestors = Vec::new();
+		let mut stack: Vec<OpenElement> = Vec::new();
+		for el in node_tree.body_ancestors() {
+			let parent = stack.last().map(|e| &e.style).unwrap_or(&root_style);
+			let lang = el
+				.el
+				.lang()
+				.or_else(|| stack.last().and_then(|e| e.lang))
+				.or(settings.language);
+			let el_ref = element_ref(el.el);
+			let style = cascade.compute(
+				&ancestors,
+				&el_ref,
+				&inline_declarations(el.el),
+				parent,
+				&resolve_ctx,
+			);
+			ancestors.push(el_ref);
+			stack.push(OpenElement {
+				id: el.id,
+				style,
+				lang,
+				link: None,
+			});
+		}
+		let stack_base = stack.len();
+
+		let mut marks = Vec::new();
+		let mut links = Vec::new();
+		let mut anchors = ChapterAnchors::default();
+		let mut notes = Vec::new();
+
 		let mut current = content_id;
 
-		let mut styles = Vec::new();
 		let mut inputs = Vec::new();
 		let mut svg_buf = String::new();
 
@@ -514,159 +831,238 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 			.ok_or(IllustratorLayoutError::MissingBody)?;
 		while let Some(edge) = node_iter.next() {
 			match edge {
-				EdgeRef::OpenElement(el) if el.local_name() == &local_name!("svg") => {
-					let svg = read_svg(&mut svg_buf, &el, &mut node_iter)?;
-
-					let container = taffy_tree.new_leaf_with_context(
-						Style {
-							display: Display::Flex,
-							justify_content: Some(AlignContent {
-								keyword: AlignContentKeyword::Center,
-								safety: AlignmentSafety::Unsafe,
-							}),
-							..Style::default()
-						},
-						NodeContext::block(el.id.value()),
-					)?;
-					taffy_tree.add_child(current, container)?;
-
-					let tree = usvg::Tree::from_str(svg, &svg_options)?;
-					let node = taffy_tree.new_leaf_with_context(
-						settings.element_style(el.local_name()),
-						NodeContext::svg(el.id.value(), Arc::new(tree)),
-					)?;
-					taffy_tree.add_child(container, node)?;
-				}
-				EdgeRef::OpenElement(el) if el.local_name() == &local_name!("hr") => {
-					take_until_closed(&mut node_iter, el.id);
-
-					let container = taffy_tree.new_leaf_with_context(
-						Style {
-							display: Display::Flex,
-							justify_content: Some(AlignContent {
-								keyword: AlignContentKeyword::Center,
-								safety: AlignmentSafety::Unsafe,
-							}),
-							..Style::default()
-						},
-						NodeContext::block(el.id.value()),
-					)?;
-					taffy_tree.add_child(current, container)?;
-
-					let node = taffy_tree.new_leaf_with_context(
-						settings.element_style(el.local_name()),
-						NodeContext::svg(el.id.value(), HORIZONTAL_RULER_SVG.clone()),
-					)?;
-					taffy_tree.add_child(container, node)?;
-				}
-				EdgeRef::OpenElement(el) if el.local_name() == &local_name!("img") => {
-					take_until_closed(&mut node_iter, el.id);
-
-					if let Some(src) = el.el.attrs.get(&src_attr_name)
-						&& let Some(image) =
-							(svg_options.image_href_resolver.resolve_string)(src, &svg_options)
+				EdgeRef::OpenElement(el) => {
+					let el_ref = element_ref(el.el);
+					let parent = stack.last().map(|e| &e.style).unwrap_or(&root_style);
+					let lang = el
+						.el
+						.lang()
+						.or_else(|| stack.last().and_then(|e| e.lang))
+						.or(settings.language);
+					let style = cascade.compute(
+						&ancestors,
+						&el_ref,
+						&inline_declarations(el.el),
+						parent,
+						&resolve_ctx,
+					);
+
+					if let Some(id) = el_ref.id {
+						anchors
+							.elements
+							.entry(id.to_string())
+							.or_insert(U26F6::from_num(el.id.value()));
+						if is_note(el.el) {
+							notes.push((id.to_string(), el.id));
+						}
+					}
+					let link = if *el.local_name() == local_name!("a")
+						&& let Some(href) = el.el.attr(local_name!("href"))
 					{
-						match image {
-							usvg::ImageKind::JPEG(data)
-							| usvg::ImageKind::PNG(data)
-							| usvg::ImageKind::GIF(data)
-							| usvg::ImageKind::WEBP(data) => {
-								if let Some(mut image) = image::load_from_memory(data.as_slice())
-									.inspect_err(|e| log::error!("Failed to load image {src}: {e}"))
-									.ok()
-									.map(|image| image.into_rgba8())
+						links.push(Arc::new(Link {
+							target: resolve_href(path, href),
+							noteref: el.el.epub_types().any(|t| t == "noteref"),
+						}));
+						Some(links.len() as u32 - 1)
+					} else {
+						stack.last().and_then(|e| e.link)
+					};
+
+					if style.display == css::Display::None {
+						take_until_closed(&mut node_iter, el.id);
+						continue;
+					}
+
+					match *el.local_name() {
+						local_name!("svg") => {
+							let svg = read_svg(&mut svg_buf, &el, &mut node_iter)?;
+
+							let container = taffy_tree.new_leaf_with_context(
+								Style {
+									display: Display::Flex,
+									justify_content: Some(AlignContent {
+										keyword: AlignContentKeyword::Center,
+										safety: AlignmentSafety::Unsafe,
+									}),
+									..Style::default()
+								},
+								NodeContext::block(el.id.value()).with_page_breaks(&style),
+							)?;
+							taffy_tree.add_child(current, container)?;
+
+							let tree = usvg::Tree::from_str(svg, &svg_options)?;
+							let node = taffy_tree.new_leaf_with_context(
+								settings.element_style(el.local_name(), &style),
+								NodeContext::svg(el.id.value(), Arc::new(tree)),
+							)?;
+							taffy_tree.add_child(container, node)?;
+						}
+						local_name!("hr") => {
+							take_until_closed(&mut node_iter, el.id);
+
+							let container = taffy_tree.new_leaf_with_context(
+								Style {
+									display: Display::Flex,
+									justify_content: Some(AlignContent {
+										keyword: AlignContentKeyword::Center,
+										safety: AlignmentSafety::Unsafe,
+									}),
+									..Style::default()
+								},
+								NodeContext::block(el.id.value()).with_page_breaks(&style),
+							)?;
+							taffy_tree.add_child(current, container)?;
+
+							let node = taffy_tree.new_leaf_with_context(
+								settings.element_style(el.local_name(), &style),
+								NodeContext::svg(el.id.value(), HORIZONTAL_RULER_SVG.clone()),
+							)?;
+							taffy_tree.add_child(container, node)?;
+						}
+						local_name!("img") => {
+							take_until_closed(&mut node_iter, el.id);
+
+							if let Some(src) = el.el.attrs.get(&src_attr_name)
+								&& let Some(image) = (svg_options
+									.image_href_resolver
+									.resolve_string)(src, &svg_options)
+							{
+								match image {
+									usvg::ImageKind::JPEG(data)
+									| usvg::ImageKind::PNG(data)
+									| usvg::ImageKind::GIF(data)
+									| usvg::ImageKind::WEBP(data) => {
+										if let Some(mut image) =
+											image::load_from_memory(data.as_slice())
+												.inspect_err(|e| {
+													log::error!("Failed to load image {src}: {e}")
+												})
+												.ok()
+												.map(|image| image.into_rgba8())
+										{
+											let _ = image
+												.set_color_space(image::metadata::Cicp::SRGB)
+												.inspect_err(|e| {
+													log::warn!(
+														"Failed to set image colorspace: {e}"
+													)
+												});
+											let node = taffy_tree.new_leaf_with_context(
+												settings.element_style(el.local_name(), &style),
+												NodeContext::image(el.id.value(), Arc::new(image))
+													.with_page_breaks(&style),
+											)?;
+											taffy_tree.add_child(current, node)?;
+										}
+									}
+									usvg::ImageKind::SVG(tree) => {
+										let container = taffy_tree.new_leaf_with_context(
+											Style {
+												display: Display::Flex,
+												justify_content: Some(AlignContent {
+													keyword: AlignContentKeyword::Center,
+													safety: AlignmentSafety::Unsafe,
+												}),
+												..Style::default()
+											},
+											NodeContext::block(el.id.value())
+												.with_page_breaks(&style),
+										)?;
+										taffy_tree.add_child(current, container)?;
+
+										let node = taffy_tree.new_leaf_with_context(
+											settings.element_style(el.local_name(), &style),
+											NodeContext::svg(el.id.value(), Arc::new(tree)),
+										)?;
+										taffy_tree.add_child(container, node)?;
+									}
+								};
+							}
+						}
+						_ if style.display == css::Display::Inline => {
+							ancestors.push(el_ref);
+							stack.push(OpenElement {
+								id: el.id,
+								style,
+								lang,
+								link,
+							});
+						}
+						_ => {
+							let text_el_id = inputs
+								.first()
+								.map(|(el_id, _, _, _)| crate::html_parser::NodeId::value(el_id));
+							if let Some(el_id) = text_el_id {
+								#[cfg(debug_assertions)]
 								{
-									let _ = image
-										.set_color_space(image::metadata::Cicp::SRGB)
-										.inspect_err(|e| {
-											log::warn!("Failed to set image colorspace: {e}")
-										});
-									let node = taffy_tree.new_leaf_with_context(
-										settings.element_style(el.local_name()),
-										NodeContext::image(el.id.value(), Arc::new(image)),
-									)?;
-									taffy_tree.add_child(current, node)?;
+									debug_assert!(
+										max_el_id < el_id,
+										"Non sequential element id in content"
+									);
+									max_el_id = el_id;
 								}
-							}
-							usvg::ImageKind::SVG(tree) => {
-								let container = taffy_tree.new_leaf_with_context(
-									Style {
-										display: Display::Flex,
-										justify_content: Some(AlignContent {
-											keyword: AlignContentKeyword::Center,
-											safety: AlignmentSafety::Unsafe,
-										}),
-										..Style::default()
-									},
-									NodeContext::block(el.id.value()),
-								)?;
-								taffy_tree.add_child(current, container)?;
-
+								let block = stack
+									.iter()
+									.rev()
+									.map(|e| &e.style)
+									.find(|s| s.display != css::Display::Inline)
+									.unwrap_or(&root_style);
+								let indent = taffy_tree.child_count(current) == 0;
+								anchors
+									.add_block(inputs.iter().map(|(id, _, t, _)| (id.value(), *t)));
+								highlight_inputs(&mut inputs, settings.highlight);
+								mark_highlights(&mut inputs, &highlights);
+								let sources = input_sources(&inputs);
+								let handle = sculpter
+									.shape(inputs.drain(..).map(|(_, _, tendril, text_style)| {
+										SculpterInput {
+											style: settings.text_style(&text_style),
+											input: tendril,
+											lang: text_style.lang,
+											span: mark_span(&mut marks, &text_style),
+										}
+									}))?
+									.with_block_style(block_style(block, indent));
 								let node = taffy_tree.new_leaf_with_context(
-									settings.element_style(el.local_name()),
-									NodeContext::svg(el.id.value(), Arc::new(tree)),
+									Style::default(),
+									NodeContext::text(el_id, handle, sources),
 								)?;
-								taffy_tree.add_child(container, node)?;
+								taffy_tree.add_child(current, node)?;
 							}
-						};
-					}
-				}
-				EdgeRef::OpenElement(el) if is_inline(el.local_name()) => {
-					if let Some(text_style) = TextStyle::try_from(el.local_name()) {
-						styles.push((el.id, text_style))
-					}
-				}
-				EdgeRef::CloseElement(id, name) if is_inline(&name.local) => {
-					if styles.last().is_some_and(|(el_id, _)| *el_id == id) {
-						styles.pop();
-					}
-				}
-				EdgeRef::OpenElement(el) => {
-					if let Some(text_style) = TextStyle::try_from(el.local_name()) {
-						styles.push((el.id, text_style))
-					}
-
-					let text_el_id = inputs
-						.first()
-						.map(|(el_id, _, _)| crate::html_parser::NodeId::value(el_id));
-					if let Some(el_id) = text_el_id {
-						#[cfg(debug_assertions)]
-						{
-							debug_assert!(
-								max_el_id < el_id,
-								"Non sequential element id in content"
-							);
-							max_el_id = el_id;
+							let node = taffy_tree.new_leaf_with_context(
+								settings.element_style(el.local_name(), &style),
+								NodeContext::block(el.id.value()).with_page_breaks(&style),
+							)?;
+
+							taffy_tree.add_child(current, node)?;
+							current = node;
+
+							ancestors.push(el_ref);
+							stack.push(OpenElement {
+								id: el.id,
+								style,
+								lang,
+								link,
+							});
 						}
-						let handle =
-							sculpter.shape(inputs.drain(..).map(|(_, tendril, style)| {
-								SculpterInput {
-									style: settings.text_style(style),
-									input: tendril,
-								}
-							}))?;
-						let node = taffy_tree.new_leaf_with_context(
-							Style::default(),
-							NodeContext::text(el_id, handle),
-						)?;
-						taffy_tree.add_child(current, node)?;
 					}
-					let node = taffy_tree.new_leaf_with_context(
-						settings.element_style(el.local_name()),
-						NodeContext::block(el.id.value()),
-					)?;
-
-					taffy_tree.add_child(current, node)?;
-					current = node;
 				}
 				EdgeRef::CloseElement(id, _name) => {
-					if styles.last().is_some_and(|(el_id, _)| *el_id == id) {
-						styles.pop();
+					let Some(OpenElement {
+						id: el_id, style, ..
+					}) = stack.pop()
+					else {
+						return Err(IllustratorLayoutError::UnexpectedExtraClose);
+					};
+					debug_assert_eq!(el_id, id, "Unexpected close element order");
+					ancestors.pop();
+					if style.display == css::Display::Inline {
+						continue;
 					}
 
 					let text_el_id = inputs
 						.first()
-						.map(|(el_id, _, _)| crate::html_parser::NodeId::value(el_id));
+						.map(|(el_id, _, _, _)| crate::html_parser::NodeId::value(el_id));
 					if let Some(el_id) = text_el_id {
 						#[cfg(debug_assertions)]
 						{
@@ -676,16 +1072,24 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 							);
 							max_el_id = el_id;
 						}
-						let handle =
-							sculpter.shape(inputs.drain(..).map(|(_, tendril, style)| {
+						let indent = taffy_tree.child_count(current) == 0;
+						anchors.add_block(inputs.iter().map(|(id, _, t, _)| (id.value(), *t)));
+						highlight_inputs(&mut inputs, settings.highlight);
+						mark_highlights(&mut inputs, &highlights);
+						let sources = input_sources(&inputs);
+						let handle = sculpter
+							.shape(inputs.drain(..).map(|(_, _, tendril, text_style)| {
 								SculpterInput {
-									style: settings.text_style(style),
+									style: settings.text_style(&text_style),
 									input: tendril,
+									lang: text_style.lang,
+									span: mark_span(&mut marks, &text_style),
 								}
-							}))?;
+							}))?
+							.with_block_style(block_style(&style, indent));
 						let node = taffy_tree.new_leaf_with_context(
 							Style::default(),
-							NodeContext::text(el_id, handle),
+							NodeContext::text(el_id, handle, sources),
 						)?;
 						taffy_tree.add_child(current, node)?;
 					}
@@ -695,16 +1099,25 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 						.ok_or(IllustratorLayoutError::UnexpectedExtraClose)?;
 				}
 				EdgeRef::Text(TextWrapper { t: Text { t }, id }) => {
-					let text_style = styles.last().map(|(_, s)| *s).unwrap_or_default();
-					inputs.push((id, t, text_style));
+					let text_style = stack
+						.last()
+						.map(|e| TextStyle::new(&e.style, e.lang, e.link))
+						.unwrap_or_else(|| TextStyle::new(&root_style, settings.language, None));
+					inputs.push((id, 0, &t[..], text_style));
 				}
 			}
 		}
 
-		debug_assert!(inputs.iter().all(|(_, s, _)| s.trim().is_empty()));
-		debug_assert!(styles.is_empty());
+		debug_assert!(inputs.iter().all(|(_, _, s, _)| s.trim().is_empty()));
+		debug_assert!(stack.len() == stack_base);
 		drop(inputs);
-		drop(styles);
+		drop(stack);
+		drop(ancestors);
+		for (id, node_id) in notes {
+			anchors
+				.notes
+				.insert(id, Arc::new(node_tree.text_content(node_id)));
+		}
 		let builder = node_tree.into_builder();
 
 		taffy_tree.compute_layout_with_measure(
@@ -734,7 +1147,7 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 				});
 
 				match node_context.content {
-					NodeContent::Text(ref handle) => {
+					NodeContent::Text(ref handle, _) => {
 						let max_width = max_width.unwrap_or(page_width);
 						let result = sculpter.measure(handle, max_width as u32, min_line_height);
 						taffy::Size {
@@ -781,7 +1194,12 @@ impl<'layout> PageLayouter<'layout, PageLayouterEmpty> {
 			buffer,
 			taffy_tree,
 			sculpter,
-			state: PageLayouterLoaded { content_id },
+			state: PageLayouterLoaded {
+				content_id,
+				marks,
+				links,
+				anchors,
+			},
 		})
 	}
 }
@@ -824,11 +1242,18 @@ impl PageBreaker {
 				flags: PageFlags::First,
 				elements: U26F6::ZERO..U26F6::ZERO,
 				items: Vec::new(),
+				links: Vec::new(),
+				highlights: Vec::new(),
+				texts: Vec::new(),
 			},
 			pages: Vec::new(),
 		}
 	}
 
+	fn has_content(&self) -> bool {
+		!self.page.items.is_empty()
+	}
+
 	fn page_remaining(&self, y: f32) -> f32 {
 		self.page_height - (y - self.page_offset)
 	}
@@ -864,6 +1289,73 @@ impl PageBreaker {
 		self.page.elements.end = el;
 	}
 
+	/// Add link and highlight areas of last added text block
+	fn add_marks(&mut self, spans: &[TextSpan], marks: &[TextMark], links: &[Arc<Link>]) {
+		let Some((x, y)) = self.page.items.last().map(|item| (item.pos.x, item.pos.y)) else {
+			return;
+		};
+		for span in spans {
+			let Some(mark) = marks.get(span.span as usize) else {
+				continue;
+			};
+			let pos = crate::Position {
+				x: x + span.pos[0],
+				y: y + span.pos[1],
+			};
+			let size = crate::Size {
+				width: span.dim[0],
+				height: span.dim[1],
+			};
+			if mark.highlight {
+				self.page.highlights.push(DisplayHighlight {
+					pos,
+					size,
+					highlight: None,
+				});
+			}
+			if let Some(id) = mark.user_highlight {
+				self.page.highlights.push(DisplayHighlight {
+					pos,
+					size,
+					highlight: Some(id),
+				});
+			}
+			if let Some(link) = mark.link.and_then(|i| links.get(i as usize)) {
+				self.page.links.push(DisplayLink {
+					pos,
+					size,
+					link: link.clone(),
+				});
+			}
+		}
+	}
+
+	/// Add text positions of clusters in last added text block
+	fn add_texts(&mut self, clusters: &[TextCluster], sources: &[TextPosition]) {
+		let Some((x, y)) = self.page.items.last().map(|item| (item.pos.x, item.pos.y)) else {
+			return;
+		};
+		for cluster in clusters {
+			let Some(source) = sources.get(cluster.input as usize) else {
+				continue;
+			};
+			self.page.texts.push(DisplayText {
+				pos: crate::Position {
+					x: x + cluster.pos[0],
+					y: y + cluster.pos[1],
+				},
+				size: crate::Size {
+					width: cluster.dim[0],
+					height: cluster.dim[1],
+				},
+				text: TextPosition {
+					element: source.element,
+					offset: source.offset + cluster.offset,
+				},
+			});
+		}
+	}
+
 	fn add_page(&mut self, y: f32) {
 		let element = self.page.elements.end;
 		let page = mem::replace(
@@ -872,6 +1364,9 @@ impl PageBreaker {
 				flags: PageFlags::empty(),
 				elements: element..element,
 				items: Vec::new(),
+				links: Vec::new(),
+				highlights: Vec::new(),
+				texts: Vec::new(),
 			},
 		);
 		self.pages.push(page);
@@ -901,14 +1396,25 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 		self,
 		pixelator: &PixelatorAssistant,
 		settings: &StyleSettings<'settings>,
-	) -> Result<(PageLayouter<'layout, PageLayouterEmpty>, Vec<PageContent>), IllustratorLayoutError>
-	{
+	) -> Result<
+		(
+			PageLayouter<'layout, PageLayouterEmpty>,
+			Vec<PageContent>,
+			ChapterAnchors,
+		),
+		IllustratorLayoutError,
+	> {
 		let Self {
 			builder,
 			mut buffer,
 			mut taffy_tree,
 			mut sculpter,
-			state: PageLayouterLoaded { content_id },
+			state: PageLayouterLoaded {
+				content_id,
+				marks,
+				links,
+				anchors,
+			},
 		} = self;
 
 		let min_line_height = Fixed::from_num(settings.min_line_height());
@@ -928,8 +1434,17 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 					let Some(ctx) = taffy_tree.get_node_context(id) else {
 						continue;
 					};
+					if breaker.has_content() {
+						let avoid_inside = matches!(ctx.page_break_inside, PageBreak::Avoid)
+							&& l.size.height <= breaker.page_height
+							&& breaker.page_remaining(cursor.y) < l.size.height;
+						if matches!(ctx.page_break_before, PageBreak::Always) || avoid_inside {
+							log::debug!("Add page {} on page break", breaker.pages.len());
+							breaker.add_page(cursor.y);
+						}
+					}
 					match &ctx.content {
-						NodeContent::Text(handle) => {
+						NodeContent::Text(handle, sources) => {
 							let mut text = handle.clone();
 							let el = U26F6::from_num(ctx.element);
 							let glyph_len = U26F6::from_num(text.glyph_range().len());
@@ -948,7 +1463,7 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 
 								let pos = cursor + taffy::Point { x: 0., y: offset };
 								let page_rem = breaker.page_remaining(pos.y);
-								let render = sculpter.render_block(
+								let mut render = sculpter.render_block(
 									&mut text,
 									l.size.width as u32,
 									page_rem as u32,
@@ -963,6 +1478,8 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 
 									let part_el =
 										U26F6::ONE - (U26F6::from_num(glyph_rem) / glyph_len);
+									let spans = mem::take(&mut render.spans);
+									let clusters = mem::take(&mut render.clusters);
 									breaker.add_content(
 										el + part_el,
 										pos,
@@ -972,6 +1489,8 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 										},
 										render,
 									);
+									breaker.add_marks(&spans, &marks, &links);
+									breaker.add_texts(&clusters, sources);
 									offset += block_height;
 									page_added = false;
 								} else if !page_added {
@@ -1068,6 +1587,13 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 				}
 				Edge::Close(id) => {
 					let l = taffy_tree.layout(id)?;
+					if let Some(ctx) = taffy_tree.get_node_context(id)
+						&& matches!(ctx.page_break_after, PageBreak::Always)
+						&& breaker.has_content()
+					{
+						log::debug!("Add page {} on page break", breaker.pages.len());
+						breaker.add_page(cursor.y + l.size.height);
+					}
 					cursor = taffy::Point {
 						x: cursor.x - l.location.x,
 						y: cursor.y - l.location.y,
@@ -1087,7 +1613,7 @@ impl<'layout> PageLayouter<'layout, PageLayouterLoaded> {
 			state: PageLayouterEmpty,
 		};
 
-		Ok((layouter, breaker.finish()))
+		Ok((layouter, breaker.finish(), anchors))
 	}
 }
 
@@ -1100,16 +1626,44 @@ impl<TState> PageLayouter<'_, TState> {
 	}
 }
 
-fn is_inline(name: &LocalName) -> bool {
-	name == &local_name!("strong")
-		|| name == &local_name!("b")
-		|| name == &local_name!("em")
-		|| name == &local_name!("i")
-		|| name == &local_name!("span")
-}
-
 fn scale_to_fit(width: f32, height: f32, max_width: f32, max_height: f32) -> f32 {
 	let ws = max_width / width;
 	let hs = max_height / height;
 	ws.min(hs)
 }
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+	use std::path::PathBuf;
+
+	use crate::LinkTarget;
+	use crate::layout::resolve_href;
+
+	#[test]
+	fn test_resolve_href() {
+		let chapter = Path::new("Text/chapter1.xhtml");
+
+		let LinkTarget::Internal { path, fragment } =
+			resolve_href(chapter, "../Text/notes.xhtml#fn1")
+		else {
+			panic!("Expected internal link");
+		};
+		assert_eq!(path, PathBuf::from("Text/notes.xhtml"), "Path missmatch");
+		assert_eq!(fragment.as_deref(), Some("fn1"), "Fragment missmatch");
+
+		let LinkTarget::Internal { path, fragment } = resolve_href(chapter, "#fn2") else {
+			panic!("Expected internal link");
+		};
+		assert_eq!(path, PathBuf::from("Text/chapter1.xhtml"), "Path missmatch");
+		assert_eq!(fragment.as_deref(), Some("fn2"), "Fragment missmatch");
+
+		assert!(
+			matches!(
+				resolve_href(chapter, "https://example.com/a#b"),
+				LinkTarget::External(_)
+			),
+			"Expected external link"
+		);
+	}
+}
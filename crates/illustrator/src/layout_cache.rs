@@ -0,0 +1,667 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fixed::types::I26F6;
+use fixed::types::U26F6;
+use scribe::BookId;
+use scribe::Highlight;
+use scribe::HighlightId;
+use scribe::TextPosition;
+use scribe_epub::Package;
+use sculpter::AtlasImage;
+use sculpter::DisplayGlyph;
+use sculpter::TextBlock;
+use sculpter::TextCluster;
+use sculpter::TextSpan;
+use zip::ZipArchive;
+
+use crate::DisplayContent;
+use crate::DisplayHighlight;
+use crate::DisplayItem;
+use crate::DisplayLink;
+use crate::DisplayText;
+use crate::Link;
+use crate::LinkTarget;
+use crate::PageContent;
+use crate::PageFlags;
+use crate::Position;
+use crate::Size;
+use crate::cache::ChapterAnchors;
+
+const MAGIC: &[u8; 4] = b"SCLC";
+/// Bumped when encoding changes, stored layouts of other versions are ignored
+const VERSION: u32 = 1;
+
+/// What a stored chapter was laid out from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChapterKey {
+	pub(crate) spine: u32,
+	/// Checksum of chapter content
+	pub(crate) crc32: u32,
+	/// Key of profile and page dimensions
+	pub(crate) layout: String,
+	/// Hash of highlights in chapter
+	pub(crate) highlights: u64,
+}
+
+impl ChapterKey {
+	pub(crate) fn new<R: io::Seek + io::Read>(
+		archive: &mut ZipArchive<R>,
+		package: &Package,
+		spine: u32,
+		layout: &str,
+		highlights: &[Highlight],
+	) -> Option<Self> {
+		let resource = package.metadata_by_spine(spine as usize)?;
+		let crc32 = archive.by_path(resource.as_path()).ok()?.crc32();
+		let mut hasher = DefaultHasher::new();
+		for h in highlights.iter().filter(|h| h.spine == spine) {
+			h.id.0.hash(&mut hasher);
+			(h.start.element, h.start.offset).hash(&mut hasher);
+			(h.end.element, h.end.offset).hash(&mut hasher);
+		}
+		Some(Self {
+			spine,
+			crc32,
+			layout: layout.to_string(),
+			highlights: hasher.finish(),
+		})
+	}
+}
+
+/// Chapter restored from layout cache, with glyph atlas pages were printed to
+pub(crate) struct StoredChapter {
+	pub(crate) pages: Vec<PageContent>,
+	pub(crate) anchors: ChapterAnchors,
+	pub(crate) atlas_width: u32,
+	pub(crate) atlas_height: u32,
+	pub(crate) atlas: Vec<u8>,
+}
+
+/// Pages of chapter last shown of each book, stored in cache folder
+///
+/// Lets a reopened book show its first page without shaping text.
+#[derive(Debug, Clone)]
+pub struct LayoutCache {
+	folder: Arc<PathBuf>,
+}
+
+impl LayoutCache {
+	pub fn new(folder: PathBuf) -> Self {
+		Self {
+			folder: Arc::new(folder),
+		}
+	}
+
+	fn path(&self, book_id: BookId) -> PathBuf {
+		self.folder.join(format!("{}.layout", book_id.into_inner()))
+	}
+
+	/// Stored chapter of book, if laid out for key
+	pub(crate) fn load(&self, book_id: BookId, key: &ChapterKey) -> Option<StoredChapter> {
+		let data = match fs::read(self.path(book_id)) {
+			Ok(data) => data,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+			Err(e) => {
+				log::warn!("Failed to read layout cache of {book_id}: {e}");
+				return None;
+			}
+		};
+		match decode(&data, key) {
+			Ok(chapter) => chapter,
+			Err(e) => {
+				log::warn!("Failed to decode layout cache of {book_id}: {e}");
+				None
+			}
+		}
+	}
+
+	/// Replace stored chapter of book, written in background
+	///
+	/// Chapters with images are not stored, pixmaps are not kept.
+	pub(crate) fn store(
+		&self,
+		book_id: BookId,
+		key: &ChapterKey,
+		pages: &[PageContent],
+		anchors: &ChapterAnchors,
+		atlas: &AtlasImage,
+	) {
+		let has_pixmap = pages.iter().any(|p| {
+			p.items
+				.iter()
+				.any(|i| matches!(i.content, DisplayContent::Pixmap(_)))
+		});
+		if has_pixmap {
+			log::debug!("Skip layout cache of chapter {} with images", key.spine);
+			return;
+		}
+
+		let data = encode(key, pages, anchors, atlas);
+		let folder = self.folder.clone();
+		let path = self.path(book_id);
+		std::thread::spawn(move || {
+			let tmp = path.with_extension("layout.tmp");
+			let result = fs::create_dir_all(folder.as_path())
+				.and_then(|_| fs::write(&tmp, data))
+				.and_then(|_| fs::rename(&tmp, &path));
+			if let Err(e) = result {
+				log::warn!("Failed to write layout cache of {book_id}: {e}");
+			}
+		});
+	}
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+	fn u8(&mut self, v: u8) {
+		self.0.push(v);
+	}
+
+	fn u32(&mut self, v: u32) {
+		self.0.extend_from_slice(&v.to_le_bytes());
+	}
+
+	fn u64(&mut self, v: u64) {
+		self.0.extend_from_slice(&v.to_le_bytes());
+	}
+
+	fn f32(&mut self, v: f32) {
+		self.0.extend_from_slice(&v.to_le_bytes());
+	}
+
+	fn len(&mut self, v: usize) {
+		self.u32(v as u32);
+	}
+
+	fn bytes(&mut self, v: &[u8]) {
+		self.len(v.len());
+		self.0.extend_from_slice(v);
+	}
+
+	fn str(&mut self, v: &str) {
+		self.bytes(v.as_bytes());
+	}
+
+	fn rect(&mut self, pos: Position, size: Size) {
+		self.f32(pos.x);
+		self.f32(pos.y);
+		self.f32(size.width);
+		self.f32(size.height);
+	}
+
+	fn pair(&mut self, v: [f32; 2]) {
+		self.f32(v[0]);
+		self.f32(v[1]);
+	}
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+	fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+		if self.0.len() < n {
+			return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+		}
+		let (head, tail) = self.0.split_at(n);
+		self.0 = tail;
+		Ok(head)
+	}
+
+	fn u8(&mut self) -> io::Result<u8> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn u32(&mut self) -> io::Result<u32> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+
+	fn u64(&mut self) -> io::Result<u64> {
+		Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+	}
+
+	fn f32(&mut self) -> io::Result<f32> {
+		Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+
+	fn len(&mut self) -> io::Result<usize> {
+		Ok(self.u32()? as usize)
+	}
+
+	fn bytes(&mut self) -> io::Result<&'a [u8]> {
+		let len = self.len()?;
+		self.take(len)
+	}
+
+	fn string(&mut self) -> io::Result<String> {
+		String::from_utf8(self.bytes()?.to_vec())
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	fn rect(&mut self) -> io::Result<(Position, Size)> {
+		Ok((
+			Position {
+				x: self.f32()?,
+				y: self.f32()?,
+			},
+			Size {
+				width: self.f32()?,
+				height: self.f32()?,
+			},
+		))
+	}
+
+	fn pair(&mut self) -> io::Result<[f32; 2]> {
+		Ok([self.f32()?, self.f32()?])
+	}
+
+	fn vec<T>(&mut self, mut f: impl FnMut(&mut Self) -> io::Result<T>) -> io::Result<Vec<T>> {
+		let len = self.len()?;
+		// Length is not trusted for allocation, data may be truncated
+		let mut v = Vec::with_capacity(len.min(1024));
+		for _ in 0..len {
+			v.push(f(self)?);
+		}
+		Ok(v)
+	}
+}
+
+fn encode(
+	key: &ChapterKey,
+	pages: &[PageContent],
+	anchors: &ChapterAnchors,
+	atlas: &AtlasImage,
+) -> Vec<u8> {
+	let mut e = Encoder(Vec::new());
+	e.0.extend_from_slice(MAGIC);
+	e.u32(VERSION);
+	e.u32(key.spine);
+	e.u32(key.crc32);
+	e.str(&key.layout);
+	e.u64(key.highlights);
+
+	e.u32(atlas.width());
+	e.u32(atlas.height());
+	e.bytes(atlas.as_raw());
+
+	e.len(anchors.elements.len());
+	for (id, el) in &anchors.elements {
+		e.str(id);
+		e.u32(el.to_bits());
+	}
+	e.len(anchors.notes.len());
+	for (id, text) in &anchors.notes {
+		e.str(id);
+		e.str(text);
+	}
+	e.len(anchors.blocks.len());
+	for (el, text) in &anchors.blocks {
+		e.u32(el.to_bits());
+		e.str(text);
+	}
+	e.len(anchors.nodes.len());
+	for (el, block, offset) in &anchors.nodes {
+		e.u32(*el);
+		e.u64(*block as u64);
+		e.u64(*offset as u64);
+	}
+
+	e.len(pages.len());
+	for page in pages {
+		e.u8(page.flags.bits());
+		e.u32(page.elements.start.to_bits());
+		e.u32(page.elements.end.to_bits());
+
+		let texts = page.items.iter().filter_map(|item| match &item.content {
+			DisplayContent::Text(block) => Some((item, block)),
+			DisplayContent::Pixmap(_) => None,
+		});
+		e.len(texts.clone().count());
+		for (item, block) in texts {
+			e.rect(item.pos, item.size);
+			e.u32(block.block_height.to_bits() as u32);
+			e.len(block.glyphs.len());
+			for g in &block.glyphs {
+				e.pair(g.pos);
+				e.pair(g.dim);
+				e.u32(g.uv_pos[0]);
+				e.u32(g.uv_pos[1]);
+				e.u32(g.uv_dim[0]);
+				e.u32(g.uv_dim[1]);
+			}
+			e.len(block.spans.len());
+			for s in &block.spans {
+				e.u32(s.span);
+				e.pair(s.pos);
+				e.pair(s.dim);
+			}
+			e.len(block.clusters.len());
+			for c in &block.clusters {
+				e.u32(c.input);
+				e.u32(c.offset);
+				e.pair(c.pos);
+				e.pair(c.dim);
+			}
+		}
+
+		e.len(page.links.len());
+		for l in &page.links {
+			e.rect(l.pos, l.size);
+			e.u8(l.link.noteref as u8);
+			match &l.link.target {
+				LinkTarget::Internal { path, fragment } => {
+					e.u8(0);
+					e.str(&path.to_string_lossy());
+					match fragment {
+						Some(fragment) => {
+							e.u8(1);
+							e.str(fragment);
+						}
+						None => e.u8(0),
+					}
+				}
+				LinkTarget::External(url) => {
+					e.u8(1);
+					e.str(url);
+				}
+			}
+		}
+
+		e.len(page.highlights.len());
+		for h in &page.highlights {
+			e.rect(h.pos, h.size);
+			match h.highlight {
+				Some(id) => {
+					e.u8(1);
+					e.u64(id.0 as u64);
+				}
+				None => e.u8(0),
+			}
+		}
+
+		e.len(page.texts.len());
+		for t in &page.texts {
+			e.rect(t.pos, t.size);
+			e.u32(t.text.element);
+			e.u32(t.text.offset);
+		}
+	}
+	e.0
+}
+
+/// Decode stored chapter, none if laid out for another key
+fn decode(data: &[u8], key: &ChapterKey) -> io::Result<Option<StoredChapter>> {
+	let mut d = Decoder(data);
+	if d.take(MAGIC.len())? != MAGIC || d.u32()? != VERSION {
+		return Ok(None);
+	}
+	let stored = ChapterKey {
+		spine: d.u32()?,
+		crc32: d.u32()?,
+		layout: d.string()?,
+		highlights: d.u64()?,
+	};
+	if stored != *key {
+		return Ok(None);
+	}
+
+	let atlas_width = d.u32()?;
+	let atlas_height = d.u32()?;
+	let atlas = d.bytes()?.to_vec();
+	if atlas.len() != atlas_width as usize * atlas_height as usize {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			"Atlas size does not match dimensions",
+		));
+	}
+
+	let mut anchors = ChapterAnchors::default();
+	for (id, el) in d.vec(|d| Ok((d.string()?, U26F6::from_bits(d.u32()?))))? {
+		anchors.elements.insert(id, el);
+	}
+	for (id, text) in d.vec(|d| Ok((d.string()?, Arc::new(d.string()?))))? {
+		anchors.notes.insert(id, text);
+	}
+	anchors.blocks = d.vec(|d| Ok((U26F6::from_bits(d.u32()?), d.string()?)))?;
+	anchors.nodes = d.vec(|d| Ok((d.u32()?, d.u64()? as usize, d.u64()? as usize)))?;
+
+	let pages = d.vec(|d| {
+		let flags = PageFlags::from_bits_truncate(d.u8()?);
+		let elements = U26F6::from_bits(d.u32()?)..U26F6::from_bits(d.u32()?);
+		let items = d.vec(|d| {
+			let (pos, size) = d.rect()?;
+			let block_height = I26F6::from_bits(d.u32()? as i32);
+			let glyphs = d.vec(|d| {
+				Ok(DisplayGlyph {
+					pos: d.pair()?,
+					dim: d.pair()?,
+					uv_pos: [d.u32()?, d.u32()?],
+					uv_dim: [d.u32()?, d.u32()?],
+				})
+			})?;
+			let spans = d.vec(|d| {
+				Ok(TextSpan {
+					span: d.u32()?,
+					pos: d.pair()?,
+					dim: d.pair()?,
+				})
+			})?;
+			let clusters = d.vec(|d| {
+				Ok(TextCluster {
+					input: d.u32()?,
+					offset: d.u32()?,
+					pos: d.pair()?,
+					dim: d.pair()?,
+				})
+			})?;
+			Ok(DisplayItem {
+				pos,
+				size,
+				content: DisplayContent::Text(TextBlock {
+					block_height,
+					glyphs,
+					spans,
+					clusters,
+				}),
+			})
+		})?;
+		let links = d.vec(|d| {
+			let (pos, size) = d.rect()?;
+			let noteref = d.u8()? != 0;
+			let target = match d.u8()? {
+				0 => LinkTarget::Internal {
+					path: PathBuf::from(d.string()?),
+					fragment: match d.u8()? {
+						0 => None,
+						_ => Some(d.string()?),
+					},
+				},
+				_ => LinkTarget::External(d.string()?),
+			};
+			Ok(DisplayLink {
+				pos,
+				size,
+				link: Arc::new(Link { target, noteref }),
+			})
+		})?;
+		let highlights = d.vec(|d| {
+			let (pos, size) = d.rect()?;
+			let highlight = match d.u8()? {
+				0 => None,
+				_ => Some(HighlightId(d.u64()? as i64)),
+			};
+			Ok(DisplayHighlight {
+				pos,
+				size,
+				highlight,
+			})
+		})?;
+		let texts = d.vec(|d| {
+			let (pos, size) = d.rect()?;
+			Ok(DisplayText {
+				pos,
+				size,
+				text: TextPosition {
+					element: d.u32()?,
+					offset: d.u32()?,
+				},
+			})
+		})?;
+		Ok(PageContent {
+			flags,
+			elements,
+			items,
+			links,
+			highlights,
+			texts,
+		})
+	})?;
+
+	Ok(Some(StoredChapter {
+		pages,
+		anchors,
+		atlas_width,
+		atlas_height,
+		atlas,
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+	use std::sync::Arc;
+
+	use fixed::types::I26F6;
+	use fixed::types::U26F6;
+	use scribe::HighlightId;
+	use scribe::TextPosition;
+	use sculpter::AtlasImage;
+	use sculpter::DisplayGlyph;
+	use sculpter::TextBlock;
+
+	use crate::DisplayContent;
+	use crate::DisplayHighlight;
+	use crate::DisplayItem;
+	use crate::DisplayLink;
+	use crate::DisplayText;
+	use crate::Link;
+	use crate::LinkTarget;
+	use crate::PageContent;
+	use crate::PageFlags;
+	use crate::Position;
+	use crate::Size;
+	use crate::cache::ChapterAnchors;
+	use crate::layout_cache::ChapterKey;
+	use crate::layout_cache::decode;
+	use crate::layout_cache::encode;
+
+	#[test]
+	fn test_layout_cache_roundtrip() {
+		let key = ChapterKey {
+			spine: 3,
+			crc32: 0xdead_beef,
+			layout: "0123456789abcdef".to_string(),
+			highlights: 42,
+		};
+		let pos = Position { x: 1.5, y: 2.0 };
+		let size = Size {
+			width: 10.0,
+			height: 4.25,
+		};
+		let mut anchors = ChapterAnchors::default();
+		anchors.add_block([(1, "Chapter"), (2, " one")]);
+		anchors
+			.elements
+			.insert("start".to_string(), U26F6::from_num(1));
+		anchors
+			.notes
+			.insert("n1".to_string(), Arc::new("Note".to_string()));
+		let pages = vec![PageContent {
+			flags: PageFlags::First | PageFlags::Last,
+			elements: U26F6::from_num(0)..U26F6::from_num(2.5),
+			items: vec![DisplayItem {
+				pos,
+				size,
+				content: DisplayContent::Text(TextBlock {
+					block_height: I26F6::from_num(4.25),
+					glyphs: vec![DisplayGlyph {
+						pos: [1., 2.],
+						dim: [3., 4.],
+						uv_pos: [5, 6],
+						uv_dim: [7, 8],
+					}],
+					spans: Vec::new(),
+					clusters: Vec::new(),
+				}),
+			}],
+			links: vec![DisplayLink {
+				pos,
+				size,
+				link: Arc::new(Link {
+					target: LinkTarget::Internal {
+						path: PathBuf::from("OEBPS/notes.xhtml"),
+						fragment: Some("n1".to_string()),
+					},
+					noteref: true,
+				}),
+			}],
+			highlights: vec![DisplayHighlight {
+				pos,
+				size,
+				highlight: Some(HighlightId(7)),
+			}],
+			texts: vec![DisplayText {
+				pos,
+				size,
+				text: TextPosition {
+					element: 2,
+					offset: 1,
+				},
+			}],
+		}];
+		let atlas = AtlasImage::default();
+
+		let data = encode(&key, &pages, &anchors, &atlas);
+		let chapter = decode(&data, &key)
+			.expect("Failed to decode")
+			.expect("Missing chapter");
+		assert_eq!(chapter.atlas_width, atlas.width(), "Atlas width missmatch");
+		assert_eq!(chapter.atlas, atlas.as_raw(), "Atlas missmatch");
+		assert_eq!(
+			chapter.anchors.blocks, anchors.blocks,
+			"Anchor blocks missmatch"
+		);
+		assert_eq!(
+			chapter.anchors.nodes, anchors.nodes,
+			"Anchor nodes missmatch"
+		);
+		assert_eq!(
+			chapter.anchors.elements, anchors.elements,
+			"Anchor elements missmatch"
+		);
+		assert_eq!(
+			format!("{:?}", chapter.pages),
+			format!("{pages:?}"),
+			"Pages missmatch"
+		);
+
+		let other = ChapterKey {
+			highlights: 43,
+			..key.clone()
+		};
+		assert!(
+			decode(&data, &other).expect("Failed to decode").is_none(),
+			"Unexpected chapter for other key"
+		);
+		assert!(
+			decode(&data[..data.len() - 1], &key).is_err(),
+			"Unexpected truncated chapter"
+		);
+	}
+}
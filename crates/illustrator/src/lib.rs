@@ -1,11 +1,21 @@
 mod cache;
+mod css;
 mod html_parser;
 mod layout;
+mod layout_cache;
+mod pagination;
+mod progress;
+mod search;
+mod session;
+mod spread;
 mod svg;
 
+use std::collections::BTreeMap;
 use std::io;
 use std::io::Cursor;
+use std::io::Read;
 use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
@@ -26,36 +36,89 @@ use pixelator::PixelatorTextures;
 use pixelator::PixmapData;
 use pixelator::PixmapRef;
 use scribe::Book;
+use scribe::BookFormat;
 use scribe::BookId;
+use scribe::HighlightId;
 use scribe::Location;
 use scribe::RecordKeeper;
 use scribe::RecordKeeperAssistant;
+use scribe::TextPosition;
 use scribe::config::IllustratorProfile;
+use scribe::config::LineBreakingConfig;
 use scribe_epub::EpubMetadata;
 use scribe_epub::Navigation;
 use scribe_epub::Package;
 use sculpter::AtlasImage;
+use sculpter::LineBreaking;
+use sculpter::Sculpter;
 use sculpter::SculpterFonts;
+use sculpter::SculpterHyphenation;
 use sculpter::SculpterOptions;
 use sculpter::TextBlock;
 use wrangler::DocumentId;
 use wrangler::content::ContentWranglerAssistant;
 use zip::ZipArchive;
 
+use crate::cache::ChapterAnchors;
 use crate::cache::NavigateError;
 use crate::cache::PageContentCache;
+use crate::css::Origin;
+use crate::css::Stylesheet;
+use crate::html_parser::NodeTreeBuilder;
+use crate::layout::BookStylesheets;
 use crate::layout::IllustratorLayoutError;
 use crate::layout::PageLayouter;
 use crate::layout::StyleSettings;
 use crate::layout::into_font_options;
+use crate::layout::normalize_path;
+use crate::layout_cache::ChapterKey;
+use crate::pagination::Pagination;
+use crate::pagination::Paginator;
+use crate::pagination::layout_key;
+use crate::progress::BookProgress;
+use crate::search::SearchQuery;
+use crate::search::search_chapter;
+use crate::session::SessionTracker;
+use crate::spread::merge_spread;
+use crate::spread::spread;
+
+pub use crate::layout_cache::LayoutCache;
+pub use crate::search::SearchMatch;
+pub use crate::search::SearchResults;
+
+/// Maximum length of footnote shown in popup, longer notes are navigated to
+const MAX_FOOTNOTE_CHARS: usize = 800;
+const MAX_HISTORY: usize = 64;
+/// Search stops after this many matches
+const MAX_SEARCH_MATCHES: usize = 1000;
 
 #[derive(Debug)]
 pub enum Request {
 	Goto(Location),
+	/// Goto element with id in chapter, resolved once chapter is laid out
+	GotoFragment {
+		spine: u32,
+		fragment: String,
+	},
+	/// Goto location, remembering current location for back
+	Jump(Location),
+	/// Goto page of book, starting from 1, once book is paginated
+	GotoPage(u32),
+	FollowLink(Arc<Link>),
+	Back,
+	/// Search book text and highlight matches, empty query clears search
+	Search(String),
+	/// Reload stored highlights after change
+	ReloadHighlights,
 	NextPage,
 	PreviousPage,
-	Resize { width: u32, height: u32 },
-	Rescale { scale: f32 },
+	Resize {
+		width: u32,
+		height: u32,
+	},
+	Rescale {
+		scale: f32,
+	},
 	Shutdown,
 }
 
@@ -63,10 +126,14 @@ pub struct IllustratorAssistant {
 	handle: JoinHandle<Result<(), IllustratorError>>,
 	req_tx: Sender<Request>,
 	working: Arc<AtomicBool>,
+	rtl: Arc<AtomicBool>,
 	title: Option<Arc<String>>,
 	state: Arc<Mutex<BookState>>,
 	navigation: Arc<Mutex<Option<Arc<Navigation>>>>,
 	cache: Arc<Mutex<PageContentCache>>,
+	footnote: Arc<Mutex<Option<Footnote>>>,
+	search: Arc<Mutex<SearchResults>>,
+	pagination: Arc<Mutex<Pagination>>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -80,6 +147,11 @@ impl IllustratorAssistant {
 		self.working.load(Ordering::Acquire)
 	}
 
+	/// Pages of book progress right to left, e.g. manga
+	pub fn rtl(&self) -> bool {
+		self.rtl.load(Ordering::Acquire)
+	}
+
 	pub fn title(&self) -> Option<Arc<String>> {
 		self.title.clone()
 	}
@@ -96,12 +168,76 @@ impl IllustratorAssistant {
 		self.cache.lock().unwrap()
 	}
 
+	pub fn search_results<'a>(&'a self) -> MutexGuard<'a, SearchResults> {
+		self.search.lock().unwrap()
+	}
+
+	/// Page of location and pages in book, once book is paginated
+	pub fn book_page(&self, loc: Location) -> Option<(u32, u32)> {
+		self.pagination.lock().unwrap().page(loc)
+	}
+
+	/// Footnote of last followed link, if short enough for popup
+	pub fn footnote(&self) -> Option<Footnote> {
+		self.footnote.lock().unwrap().clone()
+	}
+
+	pub fn dismiss_footnote(&self) {
+		self.footnote.lock().unwrap().take();
+	}
+
 	pub fn goto(&mut self, loc: Location) -> Result<(), IllustratorRequestError> {
 		self.req_tx
 			.send(Request::Goto(loc))
 			.map_err(|_| IllustratorRequestError::NotRunning)
 	}
 
+	pub fn goto_fragment(
+		&mut self,
+		spine: u32,
+		fragment: String,
+	) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::GotoFragment { spine, fragment })
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
+	pub fn jump(&mut self, loc: Location) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::Jump(loc))
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
+	pub fn goto_page(&mut self, page: u32) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::GotoPage(page))
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
+	pub fn follow_link(&mut self, link: Arc<Link>) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::FollowLink(link))
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
+	pub fn search(&mut self, query: String) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::Search(query))
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
+	pub fn reload_highlights(&mut self) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::ReloadHighlights)
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
+	pub fn back(&mut self) -> Result<(), IllustratorRequestError> {
+		self.req_tx
+			.send(Request::Back)
+			.map_err(|_| IllustratorRequestError::NotRunning)
+	}
+
 	pub fn next_page(&mut self) -> Result<(), IllustratorRequestError> {
 		self.req_tx
 			.send(Request::NextPage)
@@ -134,7 +270,7 @@ impl IllustratorAssistant {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Position {
 	pub x: f32,
 	pub y: f32,
@@ -149,7 +285,7 @@ impl From<taffy::Point<f32>> for Position {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Size {
 	pub width: f32,
 	pub height: f32,
@@ -204,11 +340,92 @@ bitflags! {
 	}
 }
 
+#[derive(Debug)]
+pub enum LinkTarget {
+	/// Resource in archive, with optional element id
+	Internal {
+		path: PathBuf,
+		fragment: Option<String>,
+	},
+	External(String),
+}
+
+#[derive(Debug)]
+pub struct Link {
+	pub target: LinkTarget,
+	/// Link is a footnote reference, `epub:type="noteref"`
+	pub noteref: bool,
+}
+
+/// Hit rectangle of link on page
+#[derive(Debug)]
+pub struct DisplayLink {
+	pub pos: Position,
+	pub size: Size,
+	pub link: Arc<Link>,
+}
+
+impl DisplayLink {
+	pub fn contains(&self, x: f32, y: f32) -> bool {
+		x >= self.pos.x
+			&& x < self.pos.x + self.size.width
+			&& y >= self.pos.y
+			&& y < self.pos.y + self.size.height
+	}
+}
+
+/// Area of highlighted text on page
+#[derive(Debug)]
+pub struct DisplayHighlight {
+	pub pos: Position,
+	pub size: Size,
+	/// Stored highlight, search match if none
+	pub highlight: Option<HighlightId>,
+}
+
+impl DisplayHighlight {
+	pub fn contains(&self, x: f32, y: f32) -> bool {
+		x >= self.pos.x
+			&& x < self.pos.x + self.size.width
+			&& y >= self.pos.y
+			&& y < self.pos.y + self.size.height
+	}
+}
+
+/// Area of printed text cluster on page
+#[derive(Debug)]
+pub struct DisplayText {
+	pub pos: Position,
+	pub size: Size,
+	/// Start of cluster in chapter text
+	pub text: TextPosition,
+}
+
+/// Text selected on page
+#[derive(Debug, Clone)]
+pub struct TextSelection {
+	pub spine: u32,
+	pub start: TextPosition,
+	pub end: TextPosition,
+	pub text: String,
+	/// Areas of selected clusters on page
+	pub areas: Vec<(Position, Size)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Footnote {
+	pub text: Arc<String>,
+	pub location: Location,
+}
+
 #[derive(Debug)]
 pub struct PageContent {
 	pub flags: PageFlags,
 	pub elements: Range<U26F6>,
 	pub items: Vec<DisplayItem>,
+	pub links: Vec<DisplayLink>,
+	pub highlights: Vec<DisplayHighlight>,
+	pub texts: Vec<DisplayText>,
 }
 
 #[derive(Debug, Clone)]
@@ -220,6 +437,8 @@ pub struct Params {
 
 pub trait Bell {
 	fn content_ready(&self, id: BookId, loc: Location);
+	fn search_updated(&self, id: BookId);
+	fn pagination_updated(&self, id: BookId);
 }
 
 #[derive(Clone)]
@@ -236,18 +455,31 @@ impl AsRef<[u8]> for SharedVec {
 pub struct BookState {
 	pub location: Location,
 	pub percent_read: u32,
+	/// Visible characters after current page in chapter
+	pub chapter_chars_left: u64,
+	/// Visible characters after current page in book
+	pub chars_left: u64,
+	/// Location history available for back
+	pub can_go_back: bool,
 }
 
 struct IllustratorBuilder {
 	profile: Arc<IllustratorProfile>,
 	fonts: SculpterFonts,
+	hyphenation: SculpterHyphenation,
+	keeper: RecordKeeper,
 	records: RecordKeeperAssistant,
 	content: ContentWranglerAssistant,
 	pixelator: PixelatorAssistant,
+	layouts: LayoutCache,
 	cache: Arc<Mutex<PageContentCache>>,
 	state: Arc<Mutex<BookState>>,
 	navigation: Arc<Mutex<Option<Arc<Navigation>>>>,
+	footnote: Arc<Mutex<Option<Footnote>>>,
+	search: Arc<Mutex<SearchResults>>,
+	pagination: Arc<Mutex<Pagination>>,
 	working: Arc<AtomicBool>,
+	rtl: Arc<AtomicBool>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -266,6 +498,8 @@ pub enum IllustratorError {
 	SculpterPrinter(#[from] sculpter::SculpterPrinterError),
 	#[error("epub error: {0}")]
 	Epub(#[from] scribe_epub::EpubError),
+	#[error("book format error: {0}")]
+	Format(#[from] scribe::BookFormatError),
 }
 
 impl From<std::io::Error> for IllustratorError {
@@ -277,8 +511,8 @@ impl From<std::io::Error> for IllustratorError {
 
 impl IllustratorBuilder {
 	fn launch(
-		self,
-		bell: impl Bell + Send + 'static,
+		mut self,
+		bell: impl Bell + Clone + Send + 'static,
 		req_rx: Receiver<Request>,
 		mut params: Params,
 		book: Book,
@@ -286,6 +520,20 @@ impl IllustratorBuilder {
 		let start = Instant::now();
 		let document = DocumentId::new(book.path.to_string_lossy().to_string());
 		let (bytes, _) = pollster::block_on(self.content.load(document))?;
+		let format = BookFormat::from_path(&book.path);
+		let bytes = match format {
+			Some(format) => format.into_epub(&book.path, bytes, |path| {
+				let document = DocumentId::new(path.to_string_lossy().to_string());
+				match pollster::block_on(self.content.load(document)) {
+					Ok((bytes, _)) => Some(bytes),
+					Err(err) => {
+						log::warn!("Failed to load resource {}: {err}", path.display());
+						None
+					}
+				}
+			})?,
+			None => bytes,
+		};
 		let bytes = SharedVec(Arc::new(bytes));
 		log::debug!(
 			"Loaded content in {}",
@@ -299,25 +547,45 @@ impl IllustratorBuilder {
 			let package = epub.package()?;
 			let navigation = Arc::new(epub.navigation()?);
 			*self.navigation.lock().unwrap() = Some(navigation);
-			package
+			Arc::new(package)
 		};
+		self.rtl.store(package.rtl, Ordering::Release);
+		let spines = package.spine.len() as u32;
 		log::debug!(
 			"Loaded epub metadata in {}",
 			Instant::now().duration_since(start).as_secs_f64()
 		);
 
 		let start = Instant::now();
-		let mut spine_bytes = Vec::new();
-		for resource in package.spine.iter().map(|id| package.manifest.get(id)) {
-			if let Some(resource) = resource {
-				let file = archive.by_path(resource.as_path())?;
-				spine_bytes.push(file.size());
-			} else {
-				spine_bytes.push(0);
+		let counted = self.records.fetch_spine_chars(book.id)?;
+		let (progress, recount) = BookProgress::count(&mut archive, &package, &counted);
+		if let Some(spines) = recount {
+			self.records.record_spine_chars(book.id, &spines)?;
+		}
+		log::debug!(
+			"Loaded spine characters in {}",
+			Instant::now().duration_since(start).as_secs_f64()
+		);
+
+		let start = Instant::now();
+		let mut author_sheets = BTreeMap::new();
+		for resource in package.manifest.values().filter(|r| r.mime == "text/css") {
+			let mut source = Vec::new();
+			match archive.by_path(resource.as_path()) {
+				Ok(mut file) => {
+					file.read_to_end(&mut source)?;
+				}
+				Err(e) => {
+					log::warn!("Failed to load stylesheet {}: {e}", resource.href);
+					continue;
+				}
 			}
+			let sheet = Stylesheet::parse(Origin::Author, &String::from_utf8_lossy(&source));
+			author_sheets.insert(normalize_path(resource.as_path()), sheet);
 		}
+		let stylesheets = Arc::new(BookStylesheets::new(&self.profile, author_sheets));
 		log::debug!(
-			"Loaded spine byte sizes in {}",
+			"Loaded stylesheets in {}",
 			Instant::now().duration_since(start).as_secs_f64()
 		);
 
@@ -333,30 +601,39 @@ impl IllustratorBuilder {
 		};
 
 		let start = Instant::now();
-		let sculpter = sculpter::create_sculpter(
-			&self.fonts,
-			&[
-				&into_font_options(&self.profile.font_regular),
-				&into_font_options(&self.profile.font_bold),
-				&into_font_options(&self.profile.font_italic),
-			],
-			SculpterOptions {
-				atlas_sub_pixel_mask: I26F6::from_bits(!0b1),
-			},
-		)?;
+		let sculpter = create_sculpter(&self.fonts, &self.profile, &self.hyphenation)?;
 		log::debug!(
 			"Created sculpter in {}",
 			Instant::now().duration_since(start).as_secs_f64()
 		);
 
 		let mut reusable_layouter = PageLayouter::new(sculpter);
+		let mut history: Vec<Location> = Vec::new();
+		let mut highlight: Option<SearchQuery> = None;
+		let mut highlights = self.records.fetch_highlights(book.id)?;
+		let mut session = SessionTracker::new(book.id, current_loc, book.percent_read.unwrap_or(0));
+		let mut search_cancel: Option<Arc<AtomicBool>> = None;
+		let mut pagination_cancel: Option<Arc<AtomicBool>> = None;
+		let mut paginated_layout: Option<String> = None;
+		// Chapter in layout cache, restored or stored
+		let mut stored_chapter: Option<ChapterKey> = None;
+		let mut restore_layout = true;
 		let mut clear_cache = true;
 		let mut atlas = AtlasImage::default();
 
 		loop {
+			// Comics show two pages side by side on landscape screens
+			let spreads = format == Some(BookFormat::Cbz)
+				&& self.profile.two_page_spreads
+				&& params.page_width > params.page_height;
 			let req = match req_rx.try_recv() {
 				Ok(req) => req,
 				Err(TryRecvError::Empty) => {
+					let layout = layout_key(&self.profile, &params, spreads);
+					if spreads {
+						current_loc = Location::from_spine(spread(current_loc.spine, spines).start);
+					}
+
 					if clear_cache || !self.cache.lock().unwrap().is_cached(current_loc) {
 						let start = Instant::now();
 
@@ -365,36 +642,153 @@ impl IllustratorBuilder {
 							clear_cache = false;
 						}
 
-						let settings = StyleSettings::new(&self.profile, &params);
-						reusable_layouter = self.load_chapter_to_cache(
-							reusable_layouter,
+						let key = ChapterKey::new(
 							&mut archive,
-							&mut atlas,
-							&settings,
 							&package,
 							current_loc.spine,
-						)?;
-
-						log::debug!(
-							"Render current chapter {} in {}",
-							current_loc.spine,
-							Instant::now().duration_since(start).as_secs_f64()
+							&layout,
+							&highlights,
 						);
+						let restored = restore_layout
+							&& !spreads && highlight.is_none()
+							&& key
+								.as_ref()
+								.is_some_and(|key| self.restore_chapter(book.id, key));
+						restore_layout = false;
+
+						if restored {
+							stored_chapter = key;
+							log::debug!(
+								"Restore current chapter {} in {}",
+								current_loc.spine,
+								Instant::now().duration_since(start).as_secs_f64()
+							);
+						} else if spreads {
+							let half = Params {
+								page_width: params.page_width / 2,
+								..params.clone()
+							};
+							let settings = StyleSettings::new(
+								&self.profile,
+								&stylesheets,
+								package.metadata.language.as_deref(),
+								&half,
+								highlight.as_ref(),
+								&highlights,
+							);
+							reusable_layouter = self.load_spread_to_cache(
+								reusable_layouter,
+								&mut archive,
+								&mut atlas,
+								&settings,
+								&package,
+								spread(current_loc.spine, spines),
+							)?;
+
+							log::debug!(
+								"Render current spread {} in {}",
+								current_loc.spine,
+								Instant::now().duration_since(start).as_secs_f64()
+							);
+						} else {
+							let settings = StyleSettings::new(
+								&self.profile,
+								&stylesheets,
+								package.metadata.language.as_deref(),
+								&params,
+								highlight.as_ref(),
+								&highlights,
+							);
+							reusable_layouter = self.load_chapter_to_cache(
+								reusable_layouter,
+								&mut archive,
+								&mut atlas,
+								&settings,
+								&package,
+								current_loc.spine,
+							)?;
+
+							log::debug!(
+								"Render current chapter {} in {}",
+								current_loc.spine,
+								Instant::now().duration_since(start).as_secs_f64()
+							);
+						}
 					}
 
-					let percent_read = self.estimate_percent_read(&spine_bytes, current_loc);
+					let position = progress.position(&self.cache.lock().unwrap(), current_loc);
+					let percent_read = progress.percent_read(position);
 
 					*self.state.lock().unwrap() = BookState {
 						location: current_loc,
 						percent_read,
+						chapter_chars_left: progress.chapter_left(current_loc, position),
+						chars_left: progress.book_left(position),
+						can_go_back: !history.is_empty(),
 					};
 					bell.content_ready(book.id, current_loc);
 					self.records
 						.record_book_state(book.id, current_loc, percent_read)?;
+					session.record(&self.records, current_loc, position, percent_read)?;
 					self.working.store(false, Ordering::Release);
 
+					// Search matches and spreads are not stored, chapter is stored again once cleared
+					if !spreads
+						&& highlight.is_none()
+						&& let Some(key) = ChapterKey::new(
+							&mut archive,
+							&package,
+							current_loc.spine,
+							&layout,
+							&highlights,
+						) && stored_chapter.as_ref() != Some(&key)
+					{
+						let cache = self.cache.lock().unwrap();
+						if let Some((pages, anchors)) = cache.chapter(key.spine) {
+							self.layouts.store(book.id, &key, pages, anchors, &atlas);
+						}
+						drop(cache);
+						stored_chapter = Some(key);
+					}
+
 					let start = Instant::now();
-					let (load_next, load_prev) = {
+					if spreads {
+						let current = spread(current_loc.spine, spines);
+						let cache = self.cache.lock().unwrap();
+						let next = (current.end < spines)
+							.then(|| spread(current.end, spines))
+							.filter(|next| !cache.is_cached(Location::from_spine(next.start)));
+						let prev = (current.start > 0)
+							.then(|| spread(current.start - 1, spines))
+							.filter(|prev| !cache.is_cached(Location::from_spine(prev.start)));
+						drop(cache);
+						let half = Params {
+							page_width: params.page_width / 2,
+							..params.clone()
+						};
+						let settings = StyleSettings::new(
+							&self.profile,
+							&stylesheets,
+							package.metadata.language.as_deref(),
+							&half,
+							highlight.as_ref(),
+							&highlights,
+						);
+						for range in next.into_iter().chain(prev) {
+							log::debug!("Load spread {} into cache", range.start);
+							reusable_layouter = self.load_spread_to_cache(
+								reusable_layouter,
+								&mut archive,
+								&mut atlas,
+								&settings,
+								&package,
+								range,
+							)?;
+						}
+					}
+					let (load_next, load_prev) = if spreads {
+						(false, false)
+					} else {
 						let cache = self.cache.lock().unwrap();
 						let load_next = current_loc.spine as usize + 1 < package.spine.len()
 							&& matches!(
@@ -410,7 +804,14 @@ impl IllustratorBuilder {
 					if load_next {
 						let next_spine = current_loc.spine + 1;
 						log::debug!("Load chapter {next_spine} into cache");
-						let settings = StyleSettings::new(&self.profile, &params);
+						let settings = StyleSettings::new(
+							&self.profile,
+							&stylesheets,
+							package.metadata.language.as_deref(),
+							&params,
+							highlight.as_ref(),
+							&highlights,
+						);
 						reusable_layouter = self.load_chapter_to_cache(
 							reusable_layouter,
 							&mut archive,
@@ -423,7 +824,14 @@ impl IllustratorBuilder {
 					if load_prev {
 						let prev_spine = current_loc.spine.saturating_sub(1);
 						log::debug!("Load chapter {prev_spine} into cache");
-						let settings = StyleSettings::new(&self.profile, &params);
+						let settings = StyleSettings::new(
+							&self.profile,
+							&stylesheets,
+							package.metadata.language.as_deref(),
+							&params,
+							highlight.as_ref(),
+							&highlights,
+						);
 						reusable_layouter = self.load_chapter_to_cache(
 							reusable_layouter,
 							&mut archive,
@@ -440,6 +848,56 @@ impl IllustratorBuilder {
 						);
 					}
 
+					if paginated_layout.as_ref() != Some(&layout) {
+						if let Some(cancel) = pagination_cancel.take() {
+							cancel.store(true, Ordering::Release);
+						}
+						*self.pagination.lock().unwrap() = Pagination::new(package.spine.len());
+						bell.pagination_updated(book.id);
+
+						let cancel = Arc::new(AtomicBool::new(false));
+						pagination_cancel = Some(cancel.clone());
+						paginated_layout = Some(layout.clone());
+						let paginator = Paginator {
+							book_id: book.id,
+							layout,
+							profile: self.profile.clone(),
+							fonts: self.fonts.clone(),
+							hyphenation: self.hyphenation.clone(),
+							stylesheets: stylesheets.clone(),
+							// Spreads are laid out as sides of half width
+							params: if spreads {
+								Params {
+									page_width: params.page_width / 2,
+									..params.clone()
+								}
+							} else {
+								params.clone()
+							},
+							spreads,
+							package: package.clone(),
+							pixelator: self.pixelator.clone(),
+							keeper: self.keeper.clone(),
+						};
+						let archive = archive.clone();
+						let pagination = self.pagination.clone();
+						let bell = bell.clone();
+						let book_id = book.id;
+						std::thread::spawn(move || {
+							let start = Instant::now();
+							let result = paginator.run(archive, &pagination, &cancel, || {
+								bell.pagination_updated(book_id)
+							});
+							match result {
+								Ok(()) => log::debug!(
+									"Paginated book in {}",
+									Instant::now().duration_since(start).as_secs_f64()
+								),
+								Err(e) => log::error!("Pagination error: {e}"),
+							}
+						});
+					}
+
 					match req_rx.recv() {
 						Ok(req) => {
 							self.working.store(true, Ordering::Release);
@@ -466,26 +924,217 @@ impl IllustratorBuilder {
 					params.scale = scale;
 				}
 				Request::NextPage => {
+					self.footnote.lock().unwrap().take();
+					let current = spread(current_loc.spine, spines);
 					// Assume next chapter is loaded into cache if needed
-					current_loc = self
-						.cache
-						.lock()
-						.unwrap()
-						.next_page(current_loc)
-						.unwrap_or(current_loc);
+					let next_loc = if spreads && current.end < spines {
+						Location::from_spine(current.end)
+					} else if spreads {
+						current_loc
+					} else {
+						self.cache
+							.lock()
+							.unwrap()
+							.next_page(current_loc)
+							.unwrap_or(current_loc)
+					};
+					if next_loc != current_loc {
+						session.turn_page();
+						current_loc = next_loc;
+					}
 				}
 				Request::PreviousPage => {
+					self.footnote.lock().unwrap().take();
+					let current = spread(current_loc.spine, spines);
 					// Assume previous chapter is loaded into cache if needed
+					let prev_loc = if spreads && current.start > 0 {
+						Location::from_spine(spread(current.start - 1, spines).start)
+					} else if spreads {
+						current_loc
+					} else {
+						self.cache
+							.lock()
+							.unwrap()
+							.previous_page(current_loc)
+							.unwrap_or(current_loc)
+					};
+					if prev_loc != current_loc {
+						session.turn_page();
+						current_loc = prev_loc;
+					}
+				}
+				Request::Goto(loc) => {
+					self.footnote.lock().unwrap().take();
+					current_loc = loc;
+				}
+				Request::GotoFragment { spine, fragment } => {
+					self.footnote.lock().unwrap().take();
+					if spine as usize >= package.spine.len() {
+						log::warn!("Fragment target {spine} not in spine");
+						continue;
+					}
+					if !self
+						.cache
+						.lock()
+						.unwrap()
+						.is_cached(Location::from_spine(spine))
+					{
+						log::debug!("Load chapter {spine} into cache for fragment");
+						let settings = StyleSettings::new(
+							&self.profile,
+							&stylesheets,
+							package.metadata.language.as_deref(),
+							&params,
+							highlight.as_ref(),
+							&highlights,
+						);
+						reusable_layouter = self.load_chapter_to_cache(
+							reusable_layouter,
+							&mut archive,
+							&mut atlas,
+							&settings,
+							&package,
+							spine,
+						)?;
+					}
 					current_loc = self
 						.cache
 						.lock()
 						.unwrap()
-						.previous_page(current_loc)
-						.unwrap_or(current_loc);
+						.anchor(spine, Some(&fragment))
+						.unwrap_or_else(|| {
+							log::debug!("Missing anchor {fragment} in chapter {spine}");
+							Location::from_spine(spine)
+						});
 				}
-				Request::Goto(loc) => {
+				Request::Jump(loc) => {
+					self.footnote.lock().unwrap().take();
+					if history.len() >= MAX_HISTORY {
+						history.remove(0);
+					}
+					history.push(current_loc);
+					current_loc = loc;
+				}
+				Request::GotoPage(page) => {
+					self.footnote.lock().unwrap().take();
+					let Some(loc) = self.pagination.lock().unwrap().location(page) else {
+						log::warn!("Page {page} not in book or book not paginated");
+						continue;
+					};
+					if history.len() >= MAX_HISTORY {
+						history.remove(0);
+					}
+					history.push(current_loc);
 					current_loc = loc;
 				}
+				Request::Search(query) => {
+					if let Some(cancel) = search_cancel.take() {
+						cancel.store(true, Ordering::Release);
+					}
+					highlight = SearchQuery::new(&query);
+					clear_cache = true;
+					*self.search.lock().unwrap() = SearchResults {
+						query,
+						matches: Vec::new(),
+						done: highlight.is_none(),
+					};
+					bell.search_updated(book.id);
+
+					if let Some(query) = highlight.clone() {
+						let cancel = Arc::new(AtomicBool::new(false));
+						search_cancel = Some(cancel.clone());
+						let archive = archive.clone();
+						let package = package.clone();
+						let results = self.search.clone();
+						let bell = bell.clone();
+						let book_id = book.id;
+						std::thread::spawn(move || {
+							let start = Instant::now();
+							search_book(archive, &package, &query, &results, &cancel, || {
+								bell.search_updated(book_id)
+							});
+							log::debug!(
+								"Searched book in {}",
+								Instant::now().duration_since(start).as_secs_f64()
+							);
+						});
+					}
+				}
+				Request::ReloadHighlights => {
+					highlights = self.records.fetch_highlights(book.id)?;
+					clear_cache = true;
+				}
+				Request::Back => {
+					self.footnote.lock().unwrap().take();
+					if let Some(loc) = history.pop() {
+						current_loc = loc;
+					}
+				}
+				Request::FollowLink(link) => {
+					let (path, fragment) = match &link.target {
+						LinkTarget::Internal { path, fragment } => (path, fragment.as_deref()),
+						LinkTarget::External(url) => {
+							log::info!("Ignore external link {url}");
+							continue;
+						}
+					};
+					let Some(spine) = package.spine.iter().position(|id| {
+						package
+							.manifest
+							.get(id)
+							.is_some_and(|r| normalize_path(r.as_path()) == *path)
+					}) else {
+						log::warn!("Link target {} not in spine", path.display());
+						continue;
+					};
+					let spine = spine as u32;
+
+					if !self
+						.cache
+						.lock()
+						.unwrap()
+						.is_cached(Location::from_spine(spine))
+					{
+						log::debug!("Load chapter {spine} into cache for link");
+						let settings = StyleSettings::new(
+							&self.profile,
+							&stylesheets,
+							package.metadata.language.as_deref(),
+							&params,
+							highlight.as_ref(),
+							&highlights,
+						);
+						reusable_layouter = self.load_chapter_to_cache(
+							reusable_layouter,
+							&mut archive,
+							&mut atlas,
+							&settings,
+							&package,
+							spine,
+						)?;
+					}
+
+					let cache = self.cache.lock().unwrap();
+					let location = cache.anchor(spine, fragment).unwrap_or_else(|| {
+						log::debug!("Missing anchor {fragment:?} in chapter {spine}");
+						Location::from_spine(spine)
+					});
+					let note = fragment.and_then(|id| cache.note(spine, id));
+					drop(cache);
+
+					match note {
+						Some(text) if text.chars().count() <= MAX_FOOTNOTE_CHARS => {
+							*self.footnote.lock().unwrap() = Some(Footnote { text, location });
+						}
+						_ => {
+							if history.len() >= MAX_HISTORY {
+								history.remove(0);
+							}
+							history.push(current_loc);
+							current_loc = location;
+						}
+					}
+				}
 				Request::Shutdown => {
 					log::debug!("Illustrator shutdown requested");
 					break;
@@ -493,23 +1142,32 @@ impl IllustratorBuilder {
 			}
 		}
 
+		if let Some(cancel) = search_cancel {
+			cancel.store(true, Ordering::Release);
+		}
+		if let Some(cancel) = pagination_cancel {
+			cancel.store(true, Ordering::Release);
+		}
+
 		Ok(())
 	}
 
-	fn estimate_percent_read(&self, spine_bytes: &[u64], current_loc: Location) -> u32 {
-		let mut bytes_total = 0;
-		let mut bytes_read = 0;
-		for (idx, b) in spine_bytes.iter().enumerate() {
-			if idx < current_loc.spine as usize {
-				bytes_read += b;
-			} else if idx == current_loc.spine as usize
-				&& let Some((_, meta)) = self.cache.lock().unwrap().page(current_loc)
-			{
-				bytes_read += (*b * meta.page as u64) / meta.pages as u64;
-			}
-			bytes_total += b;
-		}
-		(100 * bytes_read / bytes_total) as u32
+	/// Insert chapter stored in layout cache, false if none stored for key
+	fn restore_chapter(&self, book_id: BookId, key: &ChapterKey) -> bool {
+		let Some(chapter) = self.layouts.load(book_id, key) else {
+			return false;
+		};
+		let pixmap = self.pixelator.create(
+			[chapter.atlas_width, chapter.atlas_height].into(),
+			PixmapData::Luma(&chapter.atlas),
+		);
+		self.cache.lock().unwrap().insert_with_atlas(
+			key.spine,
+			chapter.pages,
+			chapter.anchors,
+			Some(pixmap),
+		);
+		true
 	}
 
 	fn load_chapter_to_cache<'layout, 'settings, R: io::Seek + io::Read + Send + Sync>(
@@ -521,6 +1179,52 @@ impl IllustratorBuilder {
 		package: &Package,
 		spine_index: u32,
 	) -> Result<PageLayouter<'layout>, IllustratorError> {
+		let (layouter, pages, anchors) =
+			self.layout_chapter(layouter, archive, atlas, settings, package, spine_index)?;
+		self.cache
+			.lock()
+			.unwrap()
+			.insert(spine_index, pages, anchors);
+		Ok(layouter)
+	}
+
+	/// Lay out spine items of spread side by side as a single page at first item
+	fn load_spread_to_cache<'layout, 'settings, R: io::Seek + io::Read + Send + Sync>(
+		&self,
+		mut layouter: PageLayouter<'layout>,
+		archive: &mut ZipArchive<R>,
+		atlas: &mut AtlasImage,
+		settings: &StyleSettings<'settings>,
+		package: &Package,
+		spread: Range<u32>,
+	) -> Result<PageLayouter<'layout>, IllustratorError> {
+		let mut sides = Vec::new();
+		let mut first_anchors = None;
+		for spine_index in spread.clone() {
+			let (next, pages, anchors) =
+				self.layout_chapter(layouter, archive, atlas, settings, package, spine_index)?;
+			layouter = next;
+			sides.push(pages);
+			first_anchors.get_or_insert(anchors);
+		}
+		let pages = merge_spread(sides, settings.page_width() as f32, package.rtl);
+		self.cache
+			.lock()
+			.unwrap()
+			.insert(spread.start, pages, first_anchors.unwrap_or_default());
+		Ok(layouter)
+	}
+
+	/// Lay out chapter and update glyph atlas of cache
+	fn layout_chapter<'layout, 'settings, R: io::Seek + io::Read + Send + Sync>(
+		&self,
+		layouter: PageLayouter<'layout>,
+		archive: &mut ZipArchive<R>,
+		atlas: &mut AtlasImage,
+		settings: &StyleSettings<'settings>,
+		package: &Package,
+		spine_index: u32,
+	) -> Result<(PageLayouter<'layout>, Vec<PageContent>, ChapterAnchors), IllustratorError> {
 		let resource = package
 			.metadata_by_spine(spine_index as usize)
 			.expect("Unexpected missing resource");
@@ -528,16 +1232,15 @@ impl IllustratorBuilder {
 			archive,
 			package.package_root.as_path(),
 			resource.as_path(),
+			spine_index,
 			settings,
 		)?;
-		let (mut layouter, pages) = layouter.layout(&self.pixelator, settings)?;
-
-		let mut cache = self.cache.lock().unwrap();
-		cache.insert(spine_index, pages);
+		let (mut layouter, pages, anchors) = layouter.layout(&self.pixelator, settings)?;
 
 		let version = atlas.version();
 		layouter.write_glyph_atlas(atlas)?;
 		if version != atlas.version() {
+			let mut cache = self.cache.lock().unwrap();
 			let pixmap_dims = [atlas.width(), atlas.height()].into();
 			let pixmap_data = PixmapData::Luma(atlas.as_raw());
 			let cache_pixmap = cache.pixmap_mut();
@@ -554,17 +1257,100 @@ impl IllustratorBuilder {
 			*cache_pixmap = Some(pixmap);
 		}
 
-		drop(cache);
+		Ok((layouter, pages, anchors))
+	}
+}
 
-		Ok(layouter)
+fn create_sculpter<'a>(
+	fonts: &'a SculpterFonts,
+	profile: &IllustratorProfile,
+	hyphenation: &SculpterHyphenation,
+) -> Result<Sculpter<'a>, sculpter::SculpterCreateError> {
+	sculpter::create_sculpter(
+		fonts,
+		&[
+			&into_font_options(&profile.font_regular),
+			&into_font_options(&profile.font_bold),
+			&into_font_options(&profile.font_italic),
+		],
+		SculpterOptions {
+			atlas_sub_pixel_mask: I26F6::from_bits(!0b1),
+			line_breaking: match profile.line_breaking {
+				LineBreakingConfig::Greedy => LineBreaking::Greedy,
+				LineBreakingConfig::TotalFit => LineBreaking::TotalFit,
+			},
+			hyphenation: hyphenation.clone(),
+		},
+	)
+}
+
+/// Search spine in order, appending matches to results until done or cancelled
+fn search_book<R: io::Seek + io::Read>(
+	mut archive: ZipArchive<R>,
+	package: &Package,
+	query: &SearchQuery,
+	results: &Mutex<SearchResults>,
+	cancel: &AtomicBool,
+	updated: impl Fn(),
+) {
+	let mut count = 0;
+	for spine in 0..package.spine.len() {
+		if cancel.load(Ordering::Acquire) {
+			return;
+		}
+		let Some(resource) = package.metadata_by_spine(spine) else {
+			continue;
+		};
+		let node_tree = match archive.by_path(resource.as_path()) {
+			Ok(file) => NodeTreeBuilder::new().read_from(file),
+			Err(e) => {
+				log::warn!("Failed to open {} for search: {e}", resource.href);
+				continue;
+			}
+		};
+		let node_tree = match node_tree {
+			Ok(node_tree) => node_tree,
+			Err(e) => {
+				log::warn!("Failed to parse {} for search: {e}", resource.href);
+				continue;
+			}
+		};
+
+		let matches = search_chapter(&node_tree, spine as u32, query);
+		if matches.is_empty() {
+			continue;
+		}
+		count += matches.len();
+		{
+			let mut results = results.lock().unwrap();
+			// Checked under lock, new search resets results after cancel
+			if cancel.load(Ordering::Acquire) {
+				return;
+			}
+			results.matches.extend(matches);
+			results.matches.truncate(MAX_SEARCH_MATCHES);
+		}
+		updated();
+		if count >= MAX_SEARCH_MATCHES {
+			break;
+		}
+	}
+
+	let mut results = results.lock().unwrap();
+	if !cancel.load(Ordering::Acquire) {
+		results.done = true;
+		drop(results);
+		updated();
 	}
 }
 
 pub struct IllustratorLanucher<B: Bell + Clone + Send + 'static> {
 	records: RecordKeeper,
 	fonts: SculpterFonts,
+	hyphenation: SculpterHyphenation,
 	content: ContentWranglerAssistant,
 	pixelator: PixelatorAssistant,
+	layouts: LayoutCache,
 	bell: B,
 }
 
@@ -578,15 +1364,19 @@ impl<B: Bell + Clone + Send + 'static> IllustratorLanucher<B> {
 	pub fn new(
 		records: RecordKeeper,
 		fonts: SculpterFonts,
+		hyphenation: SculpterHyphenation,
 		content: ContentWranglerAssistant,
 		pixelator: PixelatorAssistant,
+		layouts: LayoutCache,
 		bell: B,
 	) -> Self {
 		Self {
 			records,
 			fonts,
+			hyphenation,
 			content,
 			pixelator,
+			layouts,
 			bell,
 		}
 	}
@@ -596,10 +1386,13 @@ impl<B: Bell + Clone + Send + 'static> IllustratorLanucher<B> {
 		profile: Arc<IllustratorProfile>,
 		book_id: BookId,
 	) -> Result<IllustratorAssistant, IllustratorCreateError> {
+		let keeper = self.records.clone();
 		let records = self.records.assistant()?;
 		let fonts = self.fonts.clone();
+		let hyphenation = self.hyphenation.clone();
 		let content = self.content.clone();
 		let pixelator = self.pixelator.clone();
+		let layouts = self.layouts.clone();
 		let bell = self.bell.clone();
 
 		let book = records.fetch_book(book_id)?;
@@ -610,8 +1403,15 @@ impl<B: Bell + Clone + Send + 'static> IllustratorLanucher<B> {
 		let state = Arc::new(Mutex::new(BookState {
 			location: book.location(),
 			percent_read: book.percent_read.unwrap_or(0),
+			chapter_chars_left: 0,
+			chars_left: 0,
+			can_go_back: false,
 		}));
+		let footnote = Arc::new(Mutex::new(None));
+		let search = Arc::new(Mutex::new(SearchResults::default()));
+		let pagination = Arc::new(Mutex::new(Pagination::default()));
 		let working = Arc::new(AtomicBool::new(true));
+		let rtl = Arc::new(AtomicBool::new(false));
 
 		let (req_tx, req_rx) = channel();
 
@@ -624,13 +1424,20 @@ impl<B: Bell + Clone + Send + 'static> IllustratorLanucher<B> {
 		let builder = IllustratorBuilder {
 			profile,
 			fonts,
+			hyphenation,
+			keeper,
 			records,
 			content,
 			pixelator,
+			layouts,
 			cache: cache.clone(),
 			state: state.clone(),
 			navigation: navigation.clone(),
+			footnote: footnote.clone(),
+			search: search.clone(),
+			pagination: pagination.clone(),
 			working: working.clone(),
+			rtl: rtl.clone(),
 		};
 		let handle = std::thread::spawn(move || -> Result<(), IllustratorError> {
 			log::debug!("Launch illustrator");
@@ -641,10 +1448,14 @@ impl<B: Bell + Clone + Send + 'static> IllustratorLanucher<B> {
 			handle,
 			req_tx,
 			working,
+			rtl,
 			title,
 			state,
 			navigation,
 			cache,
+			footnote,
+			search,
+			pagination,
 		})
 	}
 }
@@ -0,0 +1,291 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use fixed::types::U26F6;
+use pixelator::PixelatorAssistant;
+use scribe::BookId;
+use scribe::Location;
+use scribe::RecordKeeper;
+use scribe::SpinePages;
+use scribe::config::IllustratorProfile;
+use scribe_epub::Package;
+use sculpter::SculpterFonts;
+use sculpter::SculpterHyphenation;
+use zip::ZipArchive;
+
+use crate::IllustratorError;
+use crate::Params;
+use crate::create_sculpter;
+use crate::layout::BookStylesheets;
+use crate::layout::PageLayouter;
+use crate::layout::StyleSettings;
+use crate::spread::spread;
+
+/// Key of profile, page dimensions and spread mode pages are laid out for
+///
+/// Hash is not stable between toolchains, book is paginated again when it changes.
+pub(crate) fn layout_key(profile: &IllustratorProfile, params: &Params, spreads: bool) -> String {
+	let mut hasher = DefaultHasher::new();
+	format!("{profile:?}").hash(&mut hasher);
+	params.page_width.hash(&mut hasher);
+	params.page_height.hash(&mut hasher);
+	params.scale.to_bits().hash(&mut hasher);
+	spreads.hash(&mut hasher);
+	format!("{:016x}", hasher.finish())
+}
+
+/// Pages of whole book, filled in by background pagination
+#[derive(Debug, Default)]
+pub(crate) struct Pagination {
+	/// Page ranges of spine items, none until laid out
+	spines: Vec<Option<Vec<Range<U26F6>>>>,
+}
+
+impl Pagination {
+	pub(crate) fn new(spines: usize) -> Self {
+		Self {
+			spines: vec![None; spines],
+		}
+	}
+
+	/// Pages in book, once all spine items are laid out
+	pub(crate) fn pages(&self) -> Option<u32> {
+		self.spines
+			.iter()
+			.try_fold(0, |sum, pages| Some(sum + pages.as_ref()?.len() as u32))
+	}
+
+	/// Page of location and pages in book, starting from 1
+	pub(crate) fn page(&self, loc: Location) -> Option<(u32, u32)> {
+		let pages = self.pages()?;
+		let before = self
+			.spines
+			.iter()
+			.take(loc.spine as usize)
+			.flatten()
+			.map(Vec::len)
+			.sum::<usize>();
+		let chapter = self.spines.get(loc.spine as usize)?.as_ref()?;
+		// Same page lookup as page content cache
+		let index = if loc.element == U26F6::ZERO {
+			0
+		} else {
+			chapter
+				.iter()
+				.position(|p| p.contains(&loc.element))
+				.unwrap_or(chapter.len().saturating_sub(1))
+		};
+		Some(((before + index) as u32 + 1, pages))
+	}
+
+	/// Location of page, starting from 1
+	pub(crate) fn location(&self, page: u32) -> Option<Location> {
+		let mut index = page.checked_sub(1)? as usize;
+		for (spine, pages) in self.spines.iter().enumerate() {
+			let pages = pages.as_ref()?;
+			match pages.get(index) {
+				Some(range) => {
+					return Some(Location {
+						spine: spine as u32,
+						element: range.start,
+					});
+				}
+				None => index -= pages.len(),
+			}
+		}
+		None
+	}
+}
+
+/// Lays out every spine item of book to number pages
+pub(crate) struct Paginator {
+	pub(crate) book_id: BookId,
+	pub(crate) layout: String,
+	pub(crate) profile: Arc<IllustratorProfile>,
+	pub(crate) fonts: SculpterFonts,
+	pub(crate) hyphenation: SculpterHyphenation,
+	pub(crate) stylesheets: Arc<BookStylesheets>,
+	/// Page dimensions, of a single side when showing spreads
+	pub(crate) params: Params,
+	/// Spine items are shown as spreads, counted as a single page each
+	pub(crate) spreads: bool,
+	pub(crate) package: Arc<Package>,
+	pub(crate) pixelator: PixelatorAssistant,
+	pub(crate) keeper: RecordKeeper,
+}
+
+impl Paginator {
+	/// Lay out spine items in order until done or cancelled
+	///
+	/// Stored pages of unchanged spine items are reused, laid out items are recorded.
+	pub(crate) fn run<R: io::Seek + io::Read + Send + Sync>(
+		self,
+		mut archive: ZipArchive<R>,
+		pagination: &Mutex<Pagination>,
+		cancel: &AtomicBool,
+		updated: impl Fn(),
+	) -> Result<(), IllustratorError> {
+		let mut records = self.keeper.assistant()?;
+		let stored = records.fetch_book_pages(self.book_id, &self.layout)?;
+
+		let sculpter = create_sculpter(&self.fonts, &self.profile, &self.hyphenation)?;
+		let mut layouter = PageLayouter::new(sculpter);
+		// Highlights do not move page breaks
+		let settings = StyleSettings::new(
+			&self.profile,
+			&self.stylesheets,
+			self.package.metadata.language.as_deref(),
+			&self.params,
+			None,
+			&[],
+		);
+
+		for spine in 0..self.package.spine.len() {
+			if cancel.load(Ordering::Acquire) {
+				return Ok(());
+			}
+			let spines = self.package.spine.len() as u32;
+			if self.spreads && spread(spine as u32, spines).start != spine as u32 {
+				// Shown on page of first item of spread
+				let mut pagination = pagination.lock().unwrap();
+				if cancel.load(Ordering::Acquire) {
+					return Ok(());
+				}
+				if let Some(entry) = pagination.spines.get_mut(spine) {
+					*entry = Some(Vec::new());
+				}
+				continue;
+			}
+			let resource = self.package.metadata_by_spine(spine);
+			let crc32 = resource
+				.and_then(|resource| archive.by_path(resource.as_path()).ok())
+				.map(|file| file.crc32());
+
+			let pages = match (resource, crc32) {
+				(Some(resource), Some(crc32)) => {
+					if let Some(s) = stored
+						.iter()
+						.find(|s| s.spine == spine as u32 && s.crc32 == crc32)
+					{
+						s.pages.clone()
+					} else {
+						let loaded = layouter.load_archive(
+							&mut archive,
+							self.package.package_root.as_path(),
+							resource.as_path(),
+							spine as u32,
+							&settings,
+						)?;
+						let (empty, mut pages, _) = loaded.layout(&self.pixelator, &settings)?;
+						layouter = empty;
+						if self.spreads {
+							// Spread shows first page of each side
+							pages.truncate(1);
+						}
+
+						let pages = SpinePages {
+							spine: spine as u32,
+							crc32,
+							pages: pages.into_iter().map(|p| p.elements).collect(),
+						};
+						records.record_spine_pages(self.book_id, &self.layout, &pages)?;
+						pages.pages
+					}
+				}
+				_ => Vec::new(),
+			};
+
+			let mut pagination = pagination.lock().unwrap();
+			// Checked under lock, new pagination resets pages after cancel
+			if cancel.load(Ordering::Acquire) {
+				return Ok(());
+			}
+			if let Some(entry) = pagination.spines.get_mut(spine) {
+				*entry = Some(pages);
+			}
+		}
+
+		updated();
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use fixed::types::U26F6;
+	use scribe::Location;
+
+	use crate::pagination::Pagination;
+
+	fn pages(starts: &[u32], end: u32) -> Option<Vec<std::ops::Range<U26F6>>> {
+		let ends = starts.iter().skip(1).copied().chain([end]);
+		Some(
+			starts
+				.iter()
+				.zip(ends)
+				.map(|(&start, end)| U26F6::from_num(start)..U26F6::from_num(end))
+				.collect(),
+		)
+	}
+
+	#[test]
+	fn test_pagination_pages() {
+		let loc = |spine: u32, element: u32| Location {
+			spine,
+			element: U26F6::from_num(element),
+		};
+		let mut pagination = Pagination::new(3);
+		pagination.spines[0] = pages(&[0], 4);
+		pagination.spines[1] = pages(&[0, 10, 20], 30);
+		assert_eq!(pagination.pages(), None, "Incomplete pages missmatch");
+		assert_eq!(
+			pagination.page(loc(0, 0)),
+			None,
+			"Incomplete page missmatch"
+		);
+
+		pagination.spines[2] = pages(&[0, 8], 16);
+		assert_eq!(pagination.pages(), Some(6), "Pages missmatch");
+		assert_eq!(
+			pagination.page(loc(0, 0)),
+			Some((1, 6)),
+			"First page missmatch"
+		);
+		assert_eq!(pagination.page(loc(1, 15)), Some((3, 6)), "Page missmatch");
+		assert_eq!(
+			pagination.page(loc(1, 40)),
+			Some((4, 6)),
+			"Page past end of chapter missmatch"
+		);
+		assert_eq!(
+			pagination.page(loc(2, 8)),
+			Some((6, 6)),
+			"Last page missmatch"
+		);
+
+		assert_eq!(pagination.location(0), None, "Page zero missmatch");
+		assert_eq!(
+			pagination.location(1),
+			Some(loc(0, 0)),
+			"First location missmatch"
+		);
+		assert_eq!(
+			pagination.location(4),
+			Some(loc(1, 20)),
+			"Location missmatch"
+		);
+		assert_eq!(
+			pagination.location(6),
+			Some(loc(2, 8)),
+			"Last location missmatch"
+		);
+		assert_eq!(pagination.location(7), None, "Location past end missmatch");
+	}
+}
@@ -0,0 +1,169 @@
+use std::io;
+
+use scribe::Location;
+use scribe::SpineChars;
+use scribe_epub::Package;
+use zip::ZipArchive;
+
+use crate::cache::PageContentCache;
+use crate::html_parser::EdgeRef;
+use crate::html_parser::NodeTreeBuilder;
+use crate::html_parser::NodeTreeResult;
+use crate::html_parser::TextWrapper;
+
+/// Characters counted for progress, whitespace is left out
+pub(crate) fn visible_chars(text: &str) -> u64 {
+	text.chars().filter(|c| !c.is_whitespace()).count() as u64
+}
+
+fn chapter_chars(node_tree: &NodeTreeResult) -> u64 {
+	let Some(iter) = node_tree.body_iter() else {
+		return 0;
+	};
+	iter.map(|edge| match edge {
+		EdgeRef::Text(TextWrapper { t, .. }) => visible_chars(&t.t),
+		_ => 0,
+	})
+	.sum()
+}
+
+/// Progress through book by visible characters of spine items
+#[derive(Debug)]
+pub(crate) struct BookProgress {
+	spines: Vec<u64>,
+}
+
+impl BookProgress {
+	/// Count characters of spine items, reusing counts of unchanged items
+	///
+	/// Returns counts to record if any item was counted.
+	pub(crate) fn count<R: io::Seek + io::Read>(
+		archive: &mut ZipArchive<R>,
+		package: &Package,
+		counted: &[SpineChars],
+	) -> (Self, Option<Vec<SpineChars>>) {
+		let mut changed = counted.len() != package.spine.len();
+		let mut spines = Vec::with_capacity(package.spine.len());
+		for spine in 0..package.spine.len() {
+			let file = package
+				.metadata_by_spine(spine)
+				.and_then(|resource| archive.by_path(resource.as_path()).ok());
+			let Some(file) = file else {
+				spines.push(SpineChars {
+					spine: spine as u32,
+					crc32: 0,
+					chars: 0,
+				});
+				continue;
+			};
+			let crc32 = file.crc32();
+			if let Some(c) = counted
+				.iter()
+				.find(|c| c.spine == spine as u32 && c.crc32 == crc32)
+			{
+				spines.push(*c);
+				continue;
+			}
+
+			changed = true;
+			let chars = match NodeTreeBuilder::new().read_from(file) {
+				Ok(node_tree) => chapter_chars(&node_tree),
+				Err(e) => {
+					log::warn!("Failed to parse spine {spine} for progress: {e}");
+					0
+				}
+			};
+			spines.push(SpineChars {
+				spine: spine as u32,
+				crc32,
+				chars,
+			});
+		}
+
+		let progress = Self {
+			spines: spines.iter().map(|c| c.chars).collect(),
+		};
+		(progress, changed.then_some(spines))
+	}
+
+	/// Characters through end of page at location
+	pub(crate) fn position(&self, cache: &PageContentCache, loc: Location) -> u64 {
+		let spine = loc.spine as usize;
+		let before = self.spines.iter().take(spine).sum::<u64>();
+		let chapter = self.spines.get(spine).copied().unwrap_or_default();
+		match cache.chars_read(loc) {
+			Some((_, true)) => before + chapter,
+			Some((chars, false)) => before + chars.min(chapter),
+			None => before,
+		}
+	}
+
+	pub(crate) fn percent_read(&self, position: u64) -> u32 {
+		match self.spines.iter().sum::<u64>() {
+			0 => 0,
+			total => (100 * position.min(total) / total) as u32,
+		}
+	}
+
+	/// Characters after position in chapter of location
+	pub(crate) fn chapter_left(&self, loc: Location, position: u64) -> u64 {
+		let end = self.spines.iter().take(loc.spine as usize + 1).sum::<u64>();
+		end.saturating_sub(position)
+	}
+
+	/// Characters after position in book
+	pub(crate) fn book_left(&self, position: u64) -> u64 {
+		self.spines.iter().sum::<u64>().saturating_sub(position)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use fixed::types::U26F6;
+	use scribe::Location;
+
+	use crate::PageFlags;
+	use crate::cache::ChapterAnchors;
+	use crate::cache::PageContentCache;
+	use crate::cache::tests::page;
+	use crate::progress::BookProgress;
+
+	#[test]
+	fn test_progress_position() {
+		let mut anchors = ChapterAnchors::default();
+		anchors.add_block([(1, "Chapter")]);
+		anchors.add_block([(5, "One two\n three four")]);
+		anchors.add_block([(12, "Five")]);
+		let mut cache = PageContentCache::default();
+		cache.insert(
+			1,
+			vec![page(PageFlags::First, 0, 10), page(PageFlags::Last, 10, 20)],
+			anchors,
+		);
+		let progress = BookProgress {
+			spines: vec![100, 29, 71],
+		};
+		let loc = |element: u32| Location {
+			spine: 1,
+			element: U26F6::from_num(element),
+		};
+
+		let position = progress.position(&cache, loc(0));
+		assert_eq!(position, 100 + 7 + 15, "First page position missmatch");
+		assert_eq!(progress.percent_read(position), 61, "Percent missmatch");
+		assert_eq!(
+			progress.chapter_left(loc(0), position),
+			7,
+			"Chapter left missmatch"
+		);
+		assert_eq!(progress.book_left(position), 78, "Book left missmatch");
+
+		let position = progress.position(&cache, loc(15));
+		assert_eq!(position, 129, "Last page position missmatch");
+		assert_eq!(
+			progress.position(&cache, Location::from_spine(2)),
+			129,
+			"Uncached chapter position missmatch"
+		);
+	}
+}
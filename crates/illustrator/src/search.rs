@@ -0,0 +1,233 @@
+use std::ops::Range;
+
+use fixed::types::U26F6;
+use scribe::Location;
+
+use crate::html_parser::EdgeRef;
+use crate::html_parser::NodeTreeResult;
+use crate::html_parser::TextWrapper;
+
+/// Characters of context on each side of a match in snippets
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Base letters of Latin-1 Supplement and Latin Extended-A, from U+00C0
+const LATIN_FOLD: &str = concat!(
+	"aaaaaaæceeeeiiiiðnooooo×ouuuuyþß",
+	"aaaaaaæceeeeiiiiðnooooo÷ouuuuyþy",
+	"aaaaaaccccccccddddeeeeeeeeeegggg",
+	"gggghhhhiiiiiiiiiiĳĳjjkkĸllllllŀ",
+	"ŀllnnnnnnŉŋŋooooooœœrrrrrrssssss",
+	"ssttttttuuuuuuuuuuuuwwyyyzzzzzzſ",
+);
+
+/// Fold character for case and diacritic insensitive matching
+///
+/// Combining marks are dropped.
+fn fold(c: char) -> Option<char> {
+	match c as u32 {
+		0x0300..=0x036f => None,
+		n @ 0x00c0..=0x017f => LATIN_FOLD.chars().nth((n - 0xc0) as usize),
+		_ => c.to_lowercase().next(),
+	}
+}
+
+/// Folded characters with byte range of source, whitespace runs collapsed
+fn fold_text(text: &str) -> Vec<(char, Range<usize>)> {
+	let mut folded: Vec<(char, Range<usize>)> = Vec::with_capacity(text.len());
+	for (i, c) in text.char_indices() {
+		let end = i + c.len_utf8();
+		if c.is_whitespace() {
+			match folded.last_mut() {
+				Some((' ', range)) => range.end = end,
+				_ => folded.push((' ', i..end)),
+			}
+		} else if let Some(f) = fold(c) {
+			folded.push((f, i..end));
+		} else if let Some((_, range)) = folded.last_mut() {
+			range.end = end;
+		}
+	}
+	folded
+}
+
+/// Search query, matched case and diacritic insensitive
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+	needle: Vec<char>,
+}
+
+impl SearchQuery {
+	/// Query from user input, `None` if blank
+	pub fn new(query: &str) -> Option<Self> {
+		let needle = fold_text(query.trim())
+			.into_iter()
+			.map(|(c, _)| c)
+			.collect::<Vec<_>>();
+		(!needle.is_empty()).then_some(Self { needle })
+	}
+
+	/// Byte ranges of non-overlapping matches in text
+	pub(crate) fn find(&self, text: &str) -> Vec<Range<usize>> {
+		let folded = fold_text(text);
+		let len = self.needle.len();
+		let mut matches = Vec::new();
+		let mut i = 0;
+		while i + len <= folded.len() {
+			if folded[i..i + len]
+				.iter()
+				.zip(&self.needle)
+				.all(|((c, _), n)| c == n)
+			{
+				matches.push(folded[i].1.start..folded[i + len - 1].1.end);
+				i += len;
+			} else {
+				i += 1;
+			}
+		}
+		matches
+	}
+
+	/// Split parts of a text block into pieces, flagging pieces that are part of a match
+	///
+	/// Matches may span several parts, pieces keep index of their part and byte offset in it.
+	pub(crate) fn split<'a>(&self, parts: &[&'a str]) -> Vec<(usize, usize, &'a str, bool)> {
+		let mut text = String::new();
+		let mut starts = Vec::with_capacity(parts.len());
+		for part in parts {
+			starts.push(text.len());
+			text.push_str(part);
+		}
+		let matches = self.find(&text);
+
+		let mut pieces = Vec::new();
+		for (index, (part, start)) in parts.iter().zip(starts).enumerate() {
+			let end = start + part.len();
+			let mut offset = 0;
+			for m in matches.iter().filter(|m| m.start < end && m.end > start) {
+				let m_start = m.start.max(start) - start;
+				let m_end = m.end.min(end) - start;
+				if m_start > offset {
+					pieces.push((index, offset, &part[offset..m_start], false));
+				}
+				pieces.push((index, m_start, &part[m_start..m_end], true));
+				offset = m_end;
+			}
+			if offset < part.len() {
+				pieces.push((index, offset, &part[offset..], false));
+			}
+		}
+		pieces
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+	pub location: Location,
+	/// Text surrounding match
+	pub snippet: String,
+}
+
+/// Results of search in book, filled in as chapters are searched
+#[derive(Debug, Clone, Default)]
+pub struct SearchResults {
+	pub query: String,
+	pub matches: Vec<SearchMatch>,
+	/// All chapters searched
+	pub done: bool,
+}
+
+/// Search text of chapter body, match location is the text node of match start
+pub(crate) fn search_chapter(
+	node_tree: &NodeTreeResult,
+	spine: u32,
+	query: &SearchQuery,
+) -> Vec<SearchMatch> {
+	let mut text = String::new();
+	let mut segments = Vec::new();
+	if let Some(iter) = node_tree.body_iter() {
+		for edge in iter {
+			if let EdgeRef::Text(TextWrapper { id, t }) = edge {
+				segments.push((text.len(), id));
+				text.push_str(&t.t);
+			}
+		}
+	}
+
+	query
+		.find(&text)
+		.into_iter()
+		.filter_map(|m| {
+			let segment = segments.partition_point(|(start, _)| *start <= m.start);
+			let (_, id) = segments.get(segment.checked_sub(1)?)?;
+			Some(SearchMatch {
+				location: Location {
+					spine,
+					element: U26F6::from_num(id.value()),
+				},
+				snippet: snippet(&text, m),
+			})
+		})
+		.collect()
+}
+
+fn snippet(text: &str, m: Range<usize>) -> String {
+	let before = text[..m.start]
+		.char_indices()
+		.rev()
+		.nth(SNIPPET_CONTEXT)
+		.map(|(i, _)| i);
+	let after = text[m.end..]
+		.char_indices()
+		.nth(SNIPPET_CONTEXT)
+		.map(|(i, _)| m.end + i);
+
+	let mut snippet = String::new();
+	if before.is_some() {
+		snippet.push('…');
+	}
+	let context = &text[before.unwrap_or(0)..after.unwrap_or(text.len())];
+	snippet.push_str(&context.split_whitespace().collect::<Vec<_>>().join(" "));
+	if after.is_some() {
+		snippet.push('…');
+	}
+	snippet
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::search::SearchQuery;
+
+	#[test]
+	fn test_search_case_and_diacritics() {
+		let query = SearchQuery::new("  Cafe  creme ").expect("Missing query");
+		let text = "Un CAFÉ\n   crème, un cafe\u{301} creme.";
+		let matches = query
+			.find(text)
+			.into_iter()
+			.map(|m| &text[m])
+			.collect::<Vec<_>>();
+		assert_eq!(
+			matches,
+			vec!["CAFÉ\n   crème", "cafe\u{301} creme"],
+			"Matches missmatch"
+		);
+		assert!(SearchQuery::new(" \n").is_none(), "Unexpected blank query");
+	}
+
+	#[test]
+	fn test_search_split_parts() {
+		let query = SearchQuery::new("brown fox").expect("Missing query");
+		let parts = ["The quick brown ", "fox", " jumps"];
+		let pieces = query.split(&parts);
+		assert_eq!(
+			pieces,
+			vec![
+				(0, 0, "The quick ", false),
+				(0, 10, "brown ", true),
+				(1, 0, "fox", true),
+				(2, 0, " jumps", false),
+			],
+			"Pieces missmatch"
+		);
+	}
+}
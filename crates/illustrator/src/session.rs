@@ -0,0 +1,188 @@
+use chrono::DateTime;
+use chrono::TimeDelta;
+use chrono::Utc;
+use scribe::BookId;
+use scribe::Location;
+use scribe::ReadingSession;
+use scribe::RecordKeeperAssistant;
+use scribe::RecordKeeperError;
+use scribe::SessionId;
+
+/// Reading session ends after this long without page turns
+const SESSION_IDLE: TimeDelta = TimeDelta::minutes(10);
+
+/// Tracks reading session of open book from page turns
+pub(crate) struct SessionTracker {
+	book_id: BookId,
+	/// Active session, id is set once recorded
+	session: Option<(Option<SessionId>, ReadingSession)>,
+	/// Page shown while no session is active, start of next session
+	resting: (DateTime<Utc>, Location, u32),
+	/// Pages turned since last update
+	turned: u32,
+	/// Characters through page last shown
+	position: u64,
+}
+
+impl SessionTracker {
+	pub(crate) fn new(book_id: BookId, loc: Location, percent_read: u32) -> Self {
+		Self {
+			book_id,
+			session: None,
+			resting: (Utc::now(), loc, percent_read),
+			turned: 0,
+			position: 0,
+		}
+	}
+
+	pub(crate) fn turn_page(&mut self) {
+		self.turned += 1;
+	}
+
+	/// Record session once page at location is shown
+	pub(crate) fn record(
+		&mut self,
+		records: &RecordKeeperAssistant,
+		loc: Location,
+		position: u64,
+		percent_read: u32,
+	) -> Result<(), RecordKeeperError> {
+		let book_id = self.book_id;
+		if let Some((id, session)) = self.advance(loc, position, percent_read, Utc::now()) {
+			match id {
+				Some(id) => records.record_reading_session(*id, session)?,
+				None => *id = Some(records.insert_reading_session(book_id, session)?),
+			}
+		}
+		Ok(())
+	}
+
+	/// Advance session to page shown at `now`, returns session if changed
+	fn advance(
+		&mut self,
+		loc: Location,
+		position: u64,
+		percent_read: u32,
+		now: DateTime<Utc>,
+	) -> Option<&mut (Option<SessionId>, ReadingSession)> {
+		let turned = std::mem::take(&mut self.turned);
+		// Characters of pages turned forward, jumps only move position
+		let chars = if turned > 0 {
+			position.saturating_sub(self.position)
+		} else {
+			0
+		};
+		self.position = position;
+
+		if let Some((_, session)) = &self.session
+			&& now - session.ended_at > SESSION_IDLE
+		{
+			self.resting = (session.ended_at, session.end, session.end_percent);
+			self.session = None;
+		}
+
+		if turned == 0 {
+			let (at, resting_loc, _) = self.resting;
+			if self.session.is_none() && (resting_loc != loc || now - at > SESSION_IDLE) {
+				self.resting = (now, loc, percent_read);
+			}
+			return None;
+		}
+
+		match &mut self.session {
+			Some((_, session)) => {
+				session.ended_at = now;
+				session.pages += turned;
+				session.chars += chars;
+				session.end = loc;
+				session.end_percent = percent_read;
+			}
+			None => {
+				let (at, start, start_percent) = self.resting;
+				// Time on page before reader went idle is unknown
+				let started_at = if now - at > SESSION_IDLE { now } else { at };
+				self.session = Some((
+					None,
+					ReadingSession {
+						started_at,
+						ended_at: now,
+						pages: turned,
+						chars,
+						start,
+						end: loc,
+						start_percent,
+						end_percent: percent_read,
+					},
+				));
+			}
+		}
+		self.session.as_mut()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::DateTime;
+	use chrono::TimeDelta;
+	use chrono::Utc;
+	use fixed::types::U26F6;
+	use scribe::BookId;
+	use scribe::Location;
+	use scribe::SessionId;
+
+	use crate::session::SessionTracker;
+
+	#[test]
+	fn test_session_advance() {
+		let loc = |element: u32| Location {
+			spine: 1,
+			element: U26F6::from_num(element),
+		};
+		let start = DateTime::<Utc>::from_timestamp(1_000_000, 0).expect("Invalid timestamp");
+		let at = |minutes: i64| start + TimeDelta::minutes(minutes);
+		let mut tracker = SessionTracker {
+			book_id: BookId(1),
+			session: None,
+			resting: (start, loc(0), 10),
+			turned: 0,
+			position: 0,
+		};
+
+		assert!(
+			tracker.advance(loc(0), 100, 10, at(1)).is_none(),
+			"Unexpected session without page turn"
+		);
+
+		tracker.turn_page();
+		let (_, session) = tracker
+			.advance(loc(5), 150, 11, at(2))
+			.expect("Missing session");
+		assert_eq!(session.started_at, start, "Start missmatch");
+		assert_eq!(session.pages, 1, "Pages missmatch");
+		assert_eq!(session.chars, 50, "Chars missmatch");
+
+		tracker.turn_page();
+		tracker.turn_page();
+		let (id, session) = tracker
+			.advance(loc(9), 170, 12, at(5))
+			.expect("Missing session");
+		*id = Some(SessionId(1));
+		assert_eq!(session.started_at, start, "Continued start missmatch");
+		assert_eq!(session.ended_at, at(5), "End missmatch");
+		assert_eq!(session.pages, 3, "Continued pages missmatch");
+		assert_eq!(session.chars, 70, "Continued chars missmatch");
+		assert_eq!(session.end, loc(9), "End location missmatch");
+		assert_eq!(session.start_percent, 10, "Start percent missmatch");
+		assert_eq!(session.end_percent, 12, "End percent missmatch");
+
+		tracker.turn_page();
+		let (id, session) = tracker
+			.advance(loc(12), 200, 13, at(60))
+			.expect("Missing session");
+		assert!(id.is_none(), "Unexpected continued session after idle");
+		assert_eq!(session.started_at, at(60), "Idle start missmatch");
+		assert_eq!(session.start, loc(9), "Idle start location missmatch");
+		assert_eq!(session.pages, 1, "Idle pages missmatch");
+		assert_eq!(session.chars, 30, "Idle chars missmatch");
+	}
+}
@@ -0,0 +1,153 @@
+use std::ops::Range;
+
+use crate::PageContent;
+use crate::PageFlags;
+
+/// Spine items shown together as spread, first item is shown alone as cover
+pub(crate) fn spread(spine: u32, spines: u32) -> Range<u32> {
+	if spine == 0 {
+		return 0..1.min(spines);
+	}
+	let start = spine - (spine + 1) % 2;
+	start..(start + 2).min(spines)
+}
+
+/// Single page of first page of each side, each side laid out `side_width` wide
+///
+/// Sides are placed left to right, or right to left if `rtl`, a single side is centered.
+/// Highlights of all sides are shown, text of later sides is not selectable
+/// as positions refer to chapter of first side.
+pub(crate) fn merge_spread(
+	sides: Vec<Vec<PageContent>>,
+	side_width: f32,
+	rtl: bool,
+) -> Vec<PageContent> {
+	let count = sides.len();
+	let mut spread: Option<PageContent> = None;
+	for (i, pages) in sides.into_iter().enumerate() {
+		let Some(mut page) = pages.into_iter().next() else {
+			continue;
+		};
+		let offset = match (count, rtl) {
+			(1, _) => side_width / 2.,
+			(_, false) => i as f32 * side_width,
+			(_, true) => (count - 1 - i) as f32 * side_width,
+		};
+		for item in &mut page.items {
+			item.pos.x += offset;
+		}
+		for link in &mut page.links {
+			link.pos.x += offset;
+		}
+		for highlight in &mut page.highlights {
+			highlight.pos.x += offset;
+		}
+		match &mut spread {
+			Some(spread) => {
+				spread.items.append(&mut page.items);
+				spread.links.append(&mut page.links);
+				spread.highlights.append(&mut page.highlights);
+			}
+			None => {
+				for text in &mut page.texts {
+					text.pos.x += offset;
+				}
+				spread = Some(page);
+			}
+		}
+	}
+	spread
+		.map(|mut page| {
+			page.flags = PageFlags::First | PageFlags::Last;
+			page
+		})
+		.into_iter()
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use fixed::types::U26F6;
+
+	use crate::DisplayHighlight;
+	use crate::DisplayLink;
+	use crate::Link;
+	use crate::LinkTarget;
+	use crate::PageContent;
+	use crate::PageFlags;
+	use crate::Position;
+	use crate::Size;
+	use crate::spread::merge_spread;
+	use crate::spread::spread;
+
+	fn page(flags: PageFlags, x: f32) -> PageContent {
+		PageContent {
+			flags,
+			elements: U26F6::ZERO..U26F6::from_num(4),
+			items: Vec::new(),
+			links: vec![DisplayLink {
+				pos: Position { x, y: 0. },
+				size: Size {
+					width: 10.,
+					height: 10.,
+				},
+				link: Arc::new(Link {
+					target: LinkTarget::External("https://example.com".to_string()),
+					noteref: false,
+				}),
+			}],
+			highlights: vec![DisplayHighlight {
+				pos: Position { x, y: 20. },
+				size: Size {
+					width: 10.,
+					height: 10.,
+				},
+				highlight: None,
+			}],
+			texts: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn test_spread() {
+		assert_eq!(spread(0, 5), 0..1, "Cover missmatch");
+		assert_eq!(spread(1, 5), 1..3, "Start missmatch");
+		assert_eq!(spread(2, 5), 1..3, "End missmatch");
+		assert_eq!(spread(4, 5), 3..5, "Last missmatch");
+		assert_eq!(spread(5, 6), 5..6, "Single missmatch");
+	}
+
+	#[test]
+	fn test_merge_spread() {
+		let sides = || {
+			vec![
+				vec![page(PageFlags::First, 5.), page(PageFlags::Last, 5.)],
+				vec![page(PageFlags::First | PageFlags::Last, 5.)],
+			]
+		};
+		let offsets = |pages: Vec<PageContent>| {
+			assert_eq!(pages.len(), 1, "Pages missmatch");
+			let links = pages[0].links.iter().map(|l| l.pos.x).collect::<Vec<_>>();
+			let highlights = pages[0]
+				.highlights
+				.iter()
+				.map(|h| h.pos.x)
+				.collect::<Vec<_>>();
+			assert_eq!(links, highlights, "Highlight offsets missmatch");
+			links
+		};
+
+		let pages = merge_spread(sides(), 100., false);
+		assert!(
+			pages[0].flags.contains(PageFlags::First | PageFlags::Last),
+			"Flags missmatch"
+		);
+		assert_eq!(offsets(pages), vec![5., 105.], "Left to right missmatch");
+		let pages = merge_spread(sides(), 100., true);
+		assert_eq!(offsets(pages), vec![105., 5.], "Right to left missmatch");
+		let pages = merge_spread(vec![vec![page(PageFlags::First, 5.)]], 100., false);
+		assert_eq!(offsets(pages), vec![55.], "Single missmatch");
+	}
+}
@@ -1,11 +1,13 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
 use std::time::Instant;
 
 use integer_sqrt::IntegerSquareRoot;
 
 const MAX_MOVES: usize = 4;
 const DEFAULT_MIN_DISTANCE: u32 = 200;
+const DEFAULT_LONG_PRESS: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Location {
@@ -81,7 +83,10 @@ impl Direction {
 struct GestureState {
 	id: u64,
 	ph: Phase,
+	origin: Location,
 	loc: Location,
+	/// Time of first recorded move
+	moved: Option<Instant>,
 	idx: usize,
 	moves: [Option<(Direction, u8)>; MAX_MOVES],
 }
@@ -115,12 +120,14 @@ impl GestureState {
 		} else {
 			log::trace!("Move first {m:?} for {id}");
 			self.moves[idx] = Some((m, 1));
+			self.moved = Some(Instant::now());
 		}
 	}
 }
 
 pub struct GestureTracker<const F: usize> {
 	min_distance: u32,
+	long_press: Duration,
 	cursor_loc: Location,
 	states: [Option<GestureState>; F],
 }
@@ -128,6 +135,8 @@ pub struct GestureTracker<const F: usize> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Gesture {
 	Tap,
+	/// Touch held before moving, event origin is where hold started
+	Hold,
 	Swipe(Direction, u8),
 	Swipe2(Direction, Direction, u8),
 	Swipe3(Direction, Direction, Direction, u8),
@@ -159,6 +168,7 @@ impl Gesture {
 pub struct GestureEvent {
 	pub start: Instant,
 	pub end: Instant,
+	pub origin: Location,
 	pub loc: Location,
 	pub gesture: Gesture,
 }
@@ -166,6 +176,7 @@ pub struct GestureEvent {
 #[derive(Clone)]
 pub struct GestureIter<'a> {
 	idx: usize,
+	long_press: Duration,
 	states: &'a [Option<GestureState>],
 }
 
@@ -178,12 +189,14 @@ impl<'a> Iterator for GestureIter<'a> {
 				&& let Phase::Ended(start, end) = state.ph
 				&& let Some(gesture) = Gesture::from_moves(&state.moves)
 			{
-				let loc = state.loc;
+				let held = state.moved.unwrap_or(end).duration_since(start) >= self.long_press;
+				let gesture = if held { Gesture::Hold } else { gesture };
 				self.idx = i + 1;
 				return Some(GestureEvent {
 					start,
 					end,
-					loc,
+					origin: state.origin,
+					loc: state.loc,
 					gesture,
 				});
 			}
@@ -214,6 +227,7 @@ impl<const F: usize> GestureTracker<F> {
 	pub fn new() -> Self {
 		Self {
 			min_distance: DEFAULT_MIN_DISTANCE,
+			long_press: DEFAULT_LONG_PRESS,
 			cursor_loc: Location::default(),
 			states: [const { None }; F],
 		}
@@ -222,6 +236,7 @@ impl<const F: usize> GestureTracker<F> {
 	pub fn events<'a>(&'a self) -> GestureIter<'a> {
 		GestureIter {
 			idx: 0,
+			long_press: self.long_press,
 			states: &self.states,
 		}
 	}
@@ -230,6 +245,10 @@ impl<const F: usize> GestureTracker<F> {
 		self.min_distance = min_distance;
 	}
 
+	pub fn set_long_press(&mut self, long_press: Duration) {
+		self.long_press = long_press;
+	}
+
 	pub fn set_min_distance_by_screen(&mut self, width: u32, height: u32) {
 		self.min_distance = width
 			.min(height)
@@ -257,10 +276,13 @@ impl<const F: usize> GestureTracker<F> {
 		if let Some(state) = self.states.iter_mut().find(|s| s.is_none()) {
 			log::trace!("Starting touch {finger_id}");
 			let t = Instant::now();
+			let l = l.into();
 			*state = Some(GestureState {
 				id: finger_id,
 				ph: Phase::Started(t),
-				loc: l.into(),
+				origin: l,
+				loc: l,
+				moved: None,
 				idx: 0,
 				moves: [const { None }; MAX_MOVES],
 			});
@@ -399,6 +421,8 @@ pub struct EventResult {
 
 #[cfg(test)]
 mod tests {
+	use std::time::Duration;
+
 	use crate::gestures::Gesture;
 	use crate::gestures::GestureTracker;
 	use crate::gestures::GestureTrackerResult;
@@ -472,4 +496,25 @@ mod tests {
 		let g = &gs[0];
 		assert_eq!(Gesture::Swipe(Direction::Right, 10), g.gesture);
 	}
+
+	#[test]
+	fn test_tracker_hold() {
+		let mut tracker = GestureTracker::<1>::new();
+		tracker.set_min_distance(0);
+		tracker.set_long_press(Duration::ZERO);
+
+		tracker.touch_start(0, L::new(5, 5));
+		tracker.touch_move(0, L::new(20, 5));
+		assert_eq!(
+			GestureTrackerResult::Captured,
+			tracker.touch_end(0, L::new(40, 5))
+		);
+
+		let gs = tracker.events().collect::<Vec<_>>();
+		assert_eq!(1, gs.len());
+		let g = &gs[0];
+		assert_eq!(Gesture::Hold, g.gesture);
+		assert_eq!((5, 5), (g.origin.x, g.origin.y));
+		assert_eq!((40, 5), (g.loc.x, g.loc.y));
+	}
 }
@@ -7,11 +7,14 @@ mod views;
 
 use std::fs;
 use std::io;
+use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
 use std::time::Instant;
 
 use config::ConfigError;
+use illustrator::LayoutCache;
+use scribe::AnnotationExporter;
 use scribe::BookId;
 use scribe::LibraryBell;
 use scribe::LibraryScribe;
@@ -23,6 +26,8 @@ use scribe::config::ScribeConfig;
 use sculpter::SculpterFontErrors;
 use sculpter::SculpterFonts;
 use sculpter::SculpterFontsBuilder;
+use sculpter::SculpterHyphenation;
+use sculpter::SculpterHyphenationBuilder;
 use winit::application::ApplicationHandler;
 use winit::error::EventLoopError;
 use winit::event::WindowEvent;
@@ -54,9 +59,12 @@ struct App<'window> {
 	gestures: GestureTracker<10>,
 	config: ScribeConfig,
 	fonts: SculpterFonts,
+	hyphenation: SculpterHyphenation,
 	keeper: RecordKeeper,
 	scribe: LibraryScribeAssistant,
 	content: ContentWranglerAssistant,
+	exporter: AnnotationExporter,
+	layouts: LayoutCache,
 }
 
 impl App<'_> {
@@ -181,7 +189,7 @@ impl<'window> ApplicationHandler<AppEvent> for App<'window> {
 				self.view
 					.library(self.keeper.clone(), self.scribe.clone(), pixelator);
 			}
-			AppEvent::OpenReader(book_id) => {
+			AppEvent::OpenReader(book_id, location) => {
 				let Some(pixelator) = self.renderer.as_ref().map(|r| r.pixelator()) else {
 					self.view.error("Renderer not available");
 					return;
@@ -192,16 +200,24 @@ impl<'window> ApplicationHandler<AppEvent> for App<'window> {
 					self.config.illustrator.clone(),
 					self.keeper.clone(),
 					self.fonts.clone(),
+					self.hyphenation.clone(),
 					self.content.clone(),
 					pixelator,
 					self.bell.clone(),
+					self.exporter.clone(),
+					self.layouts.clone(),
 					book_id,
+					location,
 				);
 			}
 			AppEvent::OpenExperiments => {
 				log::debug!("Open experiments");
 				self.view.experiments(self.fonts.clone());
 			}
+			AppEvent::OpenStatistics => {
+				log::debug!("Open statistics");
+				self.view.statistics(self.keeper.clone());
+			}
 			AppEvent::Exit => {
 				log::debug!("Exit");
 				self.view.close();
@@ -318,9 +334,12 @@ impl<'window> ApplicationHandler<AppEvent> for App<'window> {
 pub enum AppEvent {
 	OpenLibrary,
 	OpenExperiments,
-	OpenReader(BookId),
+	OpenStatistics,
+	OpenReader(BookId, Option<Location>),
 	BookUpdated(BookId),
 	BookContentReady(BookId, Location),
+	BookSearchUpdated(BookId),
+	BookPaginated(BookId),
 	KeyUp,
 	KeyDown,
 	Exit,
@@ -345,6 +364,14 @@ impl illustrator::Bell for AppBell {
 	fn content_ready(&self, id: BookId, loc: Location) {
 		self.send_event(AppEvent::BookContentReady(id, loc))
 	}
+
+	fn search_updated(&self, id: BookId) {
+		self.send_event(AppEvent::BookSearchUpdated(id))
+	}
+
+	fn pagination_updated(&self, id: BookId) {
+		self.send_event(AppEvent::BookPaginated(id))
+	}
 }
 
 impl LibraryBell for AppBell {
@@ -396,8 +423,18 @@ pub fn start(
 		bell.clone(),
 		keeper.assistant()?,
 		paths.cache_path.as_path(),
+		config.library.index_content,
+		config.library.tag_subjects,
+		config.library.export_folder.clone(),
 	);
 	let content = ContentWrangler::create(system);
+	let exporter = AnnotationExporter::new(
+		keeper.clone(),
+		content.clone(),
+		config.library.export_folder.clone(),
+	);
+
+	let layouts = LayoutCache::new(paths.cache_path.join("layouts"));
 
 	let fonts = SculpterFontsBuilder::new("Literata", "Open Sans")
 		.add_font(fonts::EB_GARAMOND_VF_TTF, 0)?
@@ -410,6 +447,7 @@ pub fn start(
 		.add_fallback(fonts::NOTO_SANS_MATH_TTF, 0)?
 		.add_fallback(fonts::NOTO_SANS_SYMBOLS_VF_TTF, 0)?
 		.build();
+	let hyphenation = load_hyphenation(&paths.config_path.join("hyphenation"));
 
 	let mut app = App {
 		input,
@@ -422,12 +460,47 @@ pub fn start(
 		gestures,
 		config,
 		fonts,
+		hyphenation,
 		keeper,
 		scribe,
 		content,
+		exporter,
+		layouts,
 	};
 
 	event_loop.run_app(&mut app)?;
 
 	Ok(())
 }
+
+/// Load hyphenation patterns from `hyph-<lang>.tex`, `hyph-<lang>.pat.txt`
+/// and `hyph-<lang>.hyp.txt` files in path
+fn load_hyphenation(path: &Path) -> SculpterHyphenation {
+	let mut builder = SculpterHyphenationBuilder::new();
+	let entries = match fs::read_dir(path) {
+		Ok(entries) => entries,
+		Err(e) => {
+			log::debug!("No hyphenation patterns in {}: {e}", path.display());
+			return builder.build();
+		}
+	};
+	for entry in entries.flatten() {
+		let file_name = entry.file_name();
+		let Some(lang) = file_name.to_str().and_then(|name| {
+			let name = name.strip_prefix("hyph-")?;
+			name.strip_suffix(".tex")
+				.or_else(|| name.strip_suffix(".pat.txt"))
+				.or_else(|| name.strip_suffix(".hyp.txt"))
+		}) else {
+			continue;
+		};
+		match fs::read_to_string(entry.path()) {
+			Ok(source) => {
+				log::debug!("Load hyphenation patterns for {lang}");
+				builder = builder.add_patterns(lang, &source);
+			}
+			Err(e) => log::warn!("Failed to read {}: {e}", entry.path().display()),
+		}
+	}
+	builder.build()
+}
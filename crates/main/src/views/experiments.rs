@@ -122,6 +122,8 @@ impl OnAction<ToolAction> for ExperimentsView {
 						},
 						// input: "Lorem ipsum \ndolor sit amet,\n consectetur adipiscing elit,\n sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.\n😀 😻 💩",
 						input: "traffic of thousands of commuters",
+						lang: None,
+						span: None,
 					}];
 					let mut handle = sculpter
 						.shape(inputs.into_iter())
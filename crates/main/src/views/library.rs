@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 use std::array;
-use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::fmt::Write;
 use std::io;
@@ -28,10 +28,16 @@ use pixelator::PixmapDimensions;
 use pixelator::PixmapRef;
 use scribe::Book;
 use scribe::BookId;
+use scribe::Collection;
+use scribe::CollectionId;
 use scribe::LibraryScribeAssistant;
+use scribe::Location;
 use scribe::RecordKeeper;
 use scribe::RecordKeeperAssistant;
 use scribe::RecordKeeperError;
+use scribe::Series;
+use scribe::ShelfOrder;
+use scribe::ShelvedBook;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -52,6 +58,10 @@ use crate::views::GestureResult;
 use crate::views::ViewHandle;
 
 pub const LIBRARY_LIST_SIZE: usize = 5;
+/// Height of shelf heading in list
+const SHELF_HEIGHT: f32 = 48.;
+/// Maximum number of books listed for a search
+const MAX_SEARCH_RESULTS: u32 = 500;
 
 struct BookCard {
 	id: BookId,
@@ -62,47 +72,63 @@ struct BookCard {
 	opened_at: Option<DateTime<Utc>>,
 	modified_at: DateTime<Utc>,
 	added_at: DateTime<Utc>,
+	new_spines: u32,
+	unread: Option<Location>,
+	series: Option<Series>,
 }
 
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+enum ShelfCard {
+	Shelf {
+		name: Arc<String>,
+		books: usize,
+		collapsed: bool,
+	},
+	Book(BookCard),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum SortBy {
 	#[default]
 	Modified,
 	Added,
 	Opened,
+	Author,
+	Series,
+	Title,
 }
 
-impl Display for SortBy {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl SortBy {
+	fn order(self) -> ShelfOrder {
 		match self {
-			SortBy::Modified => write!(f, "Modified order"),
-			SortBy::Added => write!(f, "Added order"),
-			SortBy::Opened => write!(f, "Opened order"),
+			SortBy::Modified => ShelfOrder::Modified,
+			SortBy::Added => ShelfOrder::Added,
+			SortBy::Opened => ShelfOrder::Opened,
+			SortBy::Author => ShelfOrder::Author,
+			SortBy::Series => ShelfOrder::Series,
+			SortBy::Title => ShelfOrder::Title,
 		}
 	}
-}
-
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
-struct SortKey {
-	modified_at: i64,
-	added_at: i64,
-	opened_at: Option<i64>,
-}
 
-impl SortKey {
-	fn new(book: &Book) -> Self {
-		Self {
-			modified_at: book.modified_at.timestamp(),
-			added_at: book.added_at.timestamp(),
-			opened_at: book.opened_at.as_ref().map(DateTime::timestamp),
+	/// Name of shelf for books without shelf value, `None` if order has no shelves
+	fn unshelved(self) -> Option<&'static str> {
+		match self {
+			SortBy::Modified | SortBy::Added | SortBy::Opened => None,
+			SortBy::Author => Some("Unknown author"),
+			SortBy::Series => Some("No series"),
+			SortBy::Title => Some("Untitled"),
 		}
 	}
+}
 
-	fn select(&self, sort_by: SortBy) -> i64 {
-		match sort_by {
-			SortBy::Modified => self.modified_at,
-			SortBy::Added => self.added_at,
-			SortBy::Opened => self.opened_at.unwrap_or(0),
+impl Display for SortBy {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SortBy::Modified => write!(f, "Modified order"),
+			SortBy::Added => write!(f, "Added order"),
+			SortBy::Opened => write!(f, "Opened order"),
+			SortBy::Author => write!(f, "By author"),
+			SortBy::Series => write!(f, "By series"),
+			SortBy::Title => write!(f, "By title"),
 		}
 	}
 }
@@ -111,6 +137,12 @@ impl SortKey {
 struct ViewState {
 	sort_by: SortBy,
 	page: u32,
+	/// Collapsed shelves of current order
+	#[serde(default)]
+	collapsed: BTreeSet<String>,
+	/// Id of collection books are filtered by
+	#[serde(default)]
+	collection: Option<i64>,
 }
 
 #[derive(Default, Clone)]
@@ -277,68 +309,151 @@ impl Shelves {
 	// * batch upload thumbnail to texture
 }
 
+#[derive(Debug, Clone)]
+enum ShelfRow {
+	Shelf {
+		name: Arc<String>,
+		books: usize,
+		collapsed: bool,
+	},
+	Book(BookId),
+}
+
 struct Shelves {
 	books: BTreeMap<BookId, Book>,
-	sorted: Vec<(BookId, SortKey)>,
+	/// Books in order of `sort_by`
+	shelved: Vec<ShelvedBook>,
 	sort_by: SortBy,
+	/// Books matching search, best match first
+	filter: Option<Vec<BookId>>,
+	/// Books of selected collection
+	collection: Option<BTreeSet<BookId>>,
+	/// Listed shelves and books
+	rows: Vec<ShelfRow>,
 	thumbnails: ThumbnailAtlas,
 }
 
 impl Shelves {
 	fn open(
 		pixelator: &PixelatorAssistant,
+		records: &RecordKeeperAssistant,
 		books: BTreeMap<BookId, Book>,
 		sort_by: SortBy,
+		collapsed: &BTreeSet<String>,
 	) -> Self {
 		log::info!("Open library with {} books", books.len());
 		let thumbnails = ThumbnailAtlas::new(pixelator);
-		let sorted = books.iter().map(|(id, b)| (*id, SortKey::new(b))).collect();
 		let mut shelves = Self {
 			books,
 			sort_by,
-			sorted,
+			shelved: Vec::new(),
+			filter: None,
+			collection: None,
+			rows: Vec::new(),
 			thumbnails,
 		};
-		shelves.sort_books();
+		shelves.sort_books(records, collapsed);
 		shelves
 	}
 
-	fn update(&mut self, book: Book) {
+	fn update(
+		&mut self,
+		records: &RecordKeeperAssistant,
+		book: Book,
+		collapsed: &BTreeSet<String>,
+	) {
 		self.thumbnails.remove(&book.id);
 		self.books.insert(book.id, book);
-		self.sorted
-			.splice(.., self.books.iter().map(|(id, b)| (*id, SortKey::new(b))));
-		self.sort_books();
+		self.sort_books(records, collapsed);
 	}
 
-	fn remove(&mut self, book_id: BookId) {
+	fn remove(
+		&mut self,
+		records: &RecordKeeperAssistant,
+		book_id: BookId,
+		collapsed: &BTreeSet<String>,
+	) {
 		self.thumbnails.remove(&book_id);
 		self.books.remove(&book_id);
-		self.sorted
-			.splice(.., self.books.iter().map(|(id, b)| (*id, SortKey::new(b))));
-		self.sort_books();
+		self.sort_books(records, collapsed);
 	}
 
-	fn sort_books(&mut self) {
-		self.sorted
-			.sort_by_key(|(_, k)| Reverse(k.select(self.sort_by)));
+	fn sort_books(&mut self, records: &RecordKeeperAssistant, collapsed: &BTreeSet<String>) {
+		match records.fetch_shelved_books(self.sort_by.order()) {
+			Ok(shelved) => self.shelved = shelved,
+			Err(e) => log::error!("Fetch shelved books error: {e}"),
+		}
+		self.arrange(collapsed);
+	}
+
+	/// List shelves and books, books of collapsed shelves are left out
+	fn arrange(&mut self, collapsed: &BTreeSet<String>) {
+		let books = &self.books;
+		let collection = &self.collection;
+		let listed = |id: &BookId| {
+			books.contains_key(id) && collection.as_ref().is_none_or(|c| c.contains(id))
+		};
+		self.rows.clear();
+		if let Some(filter) = &self.filter {
+			self.rows.extend(
+				filter
+					.iter()
+					.filter(|id| listed(id))
+					.map(|id| ShelfRow::Book(*id)),
+			);
+			return;
+		}
+		let Some(unshelved) = self.sort_by.unshelved() else {
+			self.rows.extend(
+				self.shelved
+					.iter()
+					.filter(|b| listed(&b.id))
+					.map(|b| ShelfRow::Book(b.id)),
+			);
+			return;
+		};
+
+		for shelf in self.shelved.chunk_by(|a, b| a.shelf == b.shelf) {
+			let ids = shelf
+				.iter()
+				.filter(|b| listed(&b.id))
+				.map(|b| b.id)
+				.collect::<Vec<_>>();
+			if ids.is_empty() {
+				continue;
+			}
+			let name = shelf[0]
+				.shelf
+				.clone()
+				.unwrap_or_else(|| Arc::new(unshelved.to_string()));
+			let is_collapsed = collapsed.contains(name.as_str());
+			self.rows.push(ShelfRow::Shelf {
+				name,
+				books: ids.len(),
+				collapsed: is_collapsed,
+			});
+			if !is_collapsed {
+				self.rows.extend(ids.into_iter().map(ShelfRow::Book));
+			}
+		}
+	}
+
+	/// Number of books listed
+	fn len(&self) -> usize {
+		self.rows
+			.iter()
+			.filter(|row| matches!(row, ShelfRow::Book(_)))
+			.count()
 	}
 
 	fn book(&self, id: BookId) -> Option<&Book> {
 		self.books.get(&id)
 	}
 
-	fn books(&self, n: std::ops::Range<u32>) -> Vec<BookId> {
-		let start = n.start as usize;
-		let end = (n.end as usize).min(self.sorted.len());
-
-		self.sorted
-			.get(start..end)
-			.into_iter()
-			.flatten()
-			.map(|(id, _)| id)
-			.cloned()
-			.collect()
+	fn rows(&self, n: std::ops::Range<u32>) -> &[ShelfRow] {
+		let start = (n.start as usize).min(self.rows.len());
+		let end = (n.end as usize).min(self.rows.len());
+		&self.rows[start..end]
 	}
 }
 
@@ -349,8 +464,12 @@ pub(crate) struct LibraryView {
 	pixelator: PixelatorAssistant,
 	shelves: Shelves,
 	state: ViewState,
-	cards: [Option<BookCard>; LIBRARY_LIST_SIZE],
+	cards: [Option<ShelfCard>; LIBRARY_LIST_SIZE],
 	statusline: Option<String>,
+	search_open: bool,
+	search_input: String,
+	collections_open: bool,
+	collections: Vec<Collection>,
 }
 
 impl LibraryView {
@@ -364,10 +483,18 @@ impl LibraryView {
 	) -> Result<Self, RecordKeeperError> {
 		let records = records.assistant()?;
 
-		let state: ViewState = records
+		let mut state: ViewState = records
 			.fetch_view_state(Self::STATE_KEY)?
 			.unwrap_or_default();
 
+		let collections = records
+			.fetch_collections()
+			.inspect_err(|e| log::error!("Fetch collections error: {e}"))
+			.unwrap_or_default();
+		state.collection = state
+			.collection
+			.filter(|id| collections.iter().any(|c| c.id.into_inner() == *id));
+
 		let books = match records.fetch_books() {
 			Ok(books) => books,
 			Err(e) => {
@@ -375,7 +502,12 @@ impl LibraryView {
 				BTreeMap::new()
 			}
 		};
-		let mut shelves = Shelves::open(&pixelator, books, state.sort_by);
+		let mut shelves =
+			Shelves::open(&pixelator, &records, books, state.sort_by, &state.collapsed);
+		if let Some(id) = state.collection {
+			shelves.collection = fetch_collection_books(&records, CollectionId(id));
+			shelves.arrange(&state.collapsed);
+		}
 		let cards = read_cards(&mut shelves, &records, &pixelator, state.page);
 
 		Ok(Self {
@@ -387,6 +519,10 @@ impl LibraryView {
 			state,
 			cards,
 			statusline: None,
+			search_open: false,
+			search_input: String::new(),
+			collections_open: false,
+			collections,
 		})
 	}
 
@@ -421,9 +557,14 @@ impl LibraryView {
 		self.shelves.sort_by = match self.shelves.sort_by {
 			SortBy::Opened => SortBy::Modified,
 			SortBy::Modified => SortBy::Added,
-			SortBy::Added => SortBy::Opened,
+			SortBy::Added => SortBy::Author,
+			SortBy::Author => SortBy::Series,
+			SortBy::Series => SortBy::Title,
+			SortBy::Title => SortBy::Opened,
 		};
-		self.shelves.sort_books();
+		self.state.collapsed.clear();
+		self.shelves
+			.sort_books(&self.records, &self.state.collapsed);
 		self.cards = read_cards(
 			&mut self.shelves,
 			&self.records,
@@ -437,6 +578,95 @@ impl LibraryView {
 			.record_view_state(Self::STATE_KEY, &self.state)
 			.inspect_err(|e| log::warn!("Error saving state: {e}"));
 	}
+
+	fn toggle_shelf(&mut self, name: &str) {
+		if !self.state.collapsed.remove(name) {
+			self.state.collapsed.insert(name.to_string());
+		}
+		self.shelves.arrange(&self.state.collapsed);
+		self.cards = read_cards(
+			&mut self.shelves,
+			&self.records,
+			&self.pixelator,
+			self.state.page,
+		);
+		let _ = self
+			.records
+			.record_view_state(Self::STATE_KEY, &self.state)
+			.inspect_err(|e| log::warn!("Error saving state: {e}"));
+	}
+
+	fn toggle_search(&mut self) {
+		self.search_open = !self.search_open;
+		if !self.search_open {
+			self.search_input.clear();
+			self.search();
+		}
+	}
+
+	fn toggle_collections(&mut self) {
+		self.collections_open = !self.collections_open;
+		if self.collections_open {
+			self.collections = self
+				.records
+				.fetch_collections()
+				.inspect_err(|e| log::error!("Fetch collections error: {e}"))
+				.unwrap_or_default();
+		}
+	}
+
+	/// Filter books by collection, all books without collection
+	fn select_collection(&mut self, id: Option<CollectionId>) {
+		self.state.collection = id.map(CollectionId::into_inner);
+		self.shelves.collection = id.and_then(|id| fetch_collection_books(&self.records, id));
+		self.shelves.arrange(&self.state.collapsed);
+		self.state.page = 0;
+		self.cards = read_cards(
+			&mut self.shelves,
+			&self.records,
+			&self.pixelator,
+			self.state.page,
+		);
+		let _ = self
+			.records
+			.record_view_state(Self::STATE_KEY, &self.state)
+			.inspect_err(|e| log::warn!("Error saving state: {e}"));
+	}
+
+	/// Filter books by search input, all books for blank input
+	fn search(&mut self) {
+		let query = self.search_input.trim();
+		self.shelves.filter = if query.is_empty() {
+			None
+		} else {
+			match self.records.search_books(query, MAX_SEARCH_RESULTS) {
+				Ok(ids) => Some(ids),
+				Err(e) => {
+					log::error!("Search books error: {e}");
+					None
+				}
+			}
+		};
+		self.shelves.arrange(&self.state.collapsed);
+		self.state.page = 0;
+		self.cards = read_cards(
+			&mut self.shelves,
+			&self.records,
+			&self.pixelator,
+			self.state.page,
+		);
+	}
+}
+
+fn fetch_collection_books(
+	records: &RecordKeeperAssistant,
+	id: CollectionId,
+) -> Option<BTreeSet<BookId>> {
+	records
+		.fetch_collection_books(id)
+		.inspect_err(|e| log::error!("Fetch collection books error: {e}"))
+		.ok()
+		.map(BTreeSet::from_iter)
 }
 
 fn read_cards(
@@ -444,13 +674,26 @@ fn read_cards(
 	records: &RecordKeeperAssistant,
 	pixelator: &PixelatorAssistant,
 	page: u32,
-) -> [Option<BookCard>; LIBRARY_LIST_SIZE] {
+) -> [Option<ShelfCard>; LIBRARY_LIST_SIZE] {
 	let start = page * LIBRARY_LIST_SIZE as u32;
 	let end = (1 + page) * LIBRARY_LIST_SIZE as u32;
-	let book_ids = shelves.books(start..end);
+	let rows = shelves.rows(start..end).to_vec();
 
 	array::from_fn(|i| {
-		let id = book_ids.get(i)?;
+		let id = match rows.get(i)? {
+			ShelfRow::Shelf {
+				name,
+				books,
+				collapsed,
+			} => {
+				return Some(ShelfCard::Shelf {
+					name: name.clone(),
+					books: *books,
+					collapsed: *collapsed,
+				});
+			}
+			ShelfRow::Book(id) => id,
+		};
 
 		if !shelves.thumbnails.contains(id) {
 			match load_thumbnail(records, *id) {
@@ -469,7 +712,7 @@ fn read_cards(
 
 		let book = shelves.book(*id)?;
 
-		Some(BookCard::new(book, shelves.sort_by))
+		Some(ShelfCard::Book(BookCard::new(book, shelves.sort_by)))
 	})
 }
 
@@ -500,6 +743,7 @@ fn load_thumbnail(
 enum MenuAction {
 	Exit,
 	Scan,
+	OpenStatistics,
 	OpenExperiment,
 }
 
@@ -508,6 +752,8 @@ enum ToolAction {
 	Prev,
 	Next,
 	Sort,
+	Search,
+	Collections,
 }
 
 impl OnAction<MenuAction> for LibraryView {
@@ -519,6 +765,9 @@ impl OnAction<MenuAction> for LibraryView {
 			MenuAction::Scan => {
 				self.scribe.scan();
 			}
+			MenuAction::OpenStatistics => {
+				self.bell.send_event(AppEvent::OpenStatistics);
+			}
 			MenuAction::OpenExperiment => {
 				self.bell.send_event(AppEvent::OpenExperiments);
 			}
@@ -532,6 +781,8 @@ impl OnAction<ToolAction> for LibraryView {
 			ToolAction::Prev => self.prev_page(),
 			ToolAction::Next => self.next_page(),
 			ToolAction::Sort => self.sort_by_next(),
+			ToolAction::Search => self.toggle_search(),
+			ToolAction::Collections => self.toggle_collections(),
 		}
 	}
 }
@@ -551,6 +802,12 @@ impl ViewHandle for LibraryView {
 					active: false,
 					action: MenuAction::Scan,
 				},
+				MenuItem {
+					icon: Icon::Clock,
+					description: "Statistics",
+					active: false,
+					action: MenuAction::OpenStatistics,
+				},
 				MenuItem {
 					icon: Icon::RefreshCw,
 					description: "Experiment",
@@ -573,7 +830,18 @@ impl ViewHandle for LibraryView {
 					action: ToolAction::Prev,
 				}),
 				None,
-				None,
+				Some(ToolItem {
+					icon: Icon::Search,
+					description: "Search",
+					active: self.search_open,
+					action: ToolAction::Search,
+				}),
+				Some(ToolItem {
+					icon: Icon::Tags,
+					description: "Collections",
+					active: self.collections_open || self.state.collection.is_some(),
+					action: ToolAction::Collections,
+				}),
 				Some(ToolItem {
 					icon: Icon::ArrowDownNarrowWide,
 					description: "Sort",
@@ -581,7 +849,6 @@ impl ViewHandle for LibraryView {
 					action: ToolAction::Sort,
 				}),
 				None,
-				None,
 				Some(ToolItem {
 					icon: Icon::ArrowRight,
 					description: "Next",
@@ -594,10 +861,21 @@ impl ViewHandle for LibraryView {
 			let mut statusline = self.statusline.take().unwrap_or_default();
 			statusline.clear();
 			let page = self.state.page + 1;
-			let books = self.shelves.books.len();
-			let (full_pages, part_page) = (books / LIBRARY_LIST_SIZE, books % LIBRARY_LIST_SIZE);
+			let books = self.shelves.len();
+			let rows = self.shelves.rows.len();
+			let (full_pages, part_page) = (rows / LIBRARY_LIST_SIZE, rows % LIBRARY_LIST_SIZE);
 			let pages = full_pages + part_page.min(1);
-			let _ = write!(statusline, "{} {} / {}", self.shelves.sort_by, page, pages);
+			let collection = self
+				.state
+				.collection
+				.and_then(|id| self.collections.iter().find(|c| c.id.into_inner() == id));
+			if self.shelves.filter.is_some() {
+				let _ = write!(statusline, "{books} found {page} / {pages}");
+			} else if let Some(collection) = collection {
+				let _ = write!(statusline, "{} {} / {}", collection.name, page, pages);
+			} else {
+				let _ = write!(statusline, "{} {} / {}", self.shelves.sort_by, page, pages);
+			}
 
 			let working = self.scribe.working();
 			let top_panel = Panel::top("top").show(ui, |ui| {
@@ -610,6 +888,78 @@ impl ViewHandle for LibraryView {
 
 			self.statusline = Some(statusline);
 
+			if self.search_open {
+				Panel::top("search").show(ui, |ui| {
+					if is_open {
+						ui.disable();
+					}
+					ui.add_space(4.);
+					ui.horizontal(|ui| {
+						let edit = ui.add(
+							egui::TextEdit::singleline(&mut self.search_input)
+								.hint_text("Title, author, subject…")
+								.font(egui::FontId::proportional(theme::M_SIZE)),
+						);
+						let submit =
+							edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+						if ui
+							.add(egui::Button::new(
+								RichText::new("Search").size(theme::M_SIZE),
+							))
+							.clicked() || submit
+						{
+							self.search();
+						}
+						if ui
+							.add(egui::Button::new(
+								RichText::new("Clear").size(theme::M_SIZE),
+							))
+							.clicked()
+						{
+							self.search_input.clear();
+							self.search();
+						}
+					});
+					ui.add_space(4.);
+				});
+			}
+
+			if self.collections_open {
+				let mut selected = None;
+				Panel::top("collections").show(ui, |ui| {
+					if is_open {
+						ui.disable();
+					}
+					ui.add_space(4.);
+					ui.horizontal_wrapped(|ui| {
+						if ui
+							.selectable_label(
+								self.state.collection.is_none(),
+								RichText::new("All").size(theme::M_SIZE),
+							)
+							.clicked()
+						{
+							selected = Some(None);
+						}
+						for collection in &self.collections {
+							let active = self.state.collection == Some(collection.id.into_inner());
+							let text = format!("{} ({})", collection.name, collection.books);
+							let mut text = RichText::new(text).size(theme::M_SIZE);
+							if collection.tag {
+								text = text.italics();
+							}
+							if ui.selectable_label(active, text).clicked() {
+								selected = Some(Some(collection.id));
+							}
+						}
+					});
+					ui.add_space(4.);
+				});
+				if let Some(id) = selected {
+					self.select_collection(id);
+				}
+			}
+
 			Panel::bottom("bottom").show(ui, |ui| ToolBar::new(self, tool_items, is_open).ui(ui));
 
 			CentralPanel::default().show(ui, |ui| {
@@ -620,8 +970,25 @@ impl ViewHandle for LibraryView {
 				let height = ui.available_height()
 					- (LIBRARY_LIST_SIZE as f32 - 1.0) * ui.spacing().item_spacing.y;
 				let card_height = height / LIBRARY_LIST_SIZE as f32;
+				let shelf_height = SHELF_HEIGHT.min(card_height);
+				let mut toggle_shelf = None;
 				ui.vertical(|ui| {
 					for card in self.cards.iter().flatten() {
+						let card = match card {
+							ShelfCard::Shelf {
+								name,
+								books,
+								collapsed,
+							} => {
+								ui.allocate_ui([ui.available_width(), shelf_height].into(), |ui| {
+									if ui.add(ShelfUi::new(name, *books, *collapsed)).clicked() {
+										toggle_shelf = Some(name.clone());
+									}
+								});
+								continue;
+							}
+							ShelfCard::Book(card) => card,
+						};
 						ui.allocate_ui([ui.available_width(), card_height].into(), |ui| {
 							let thumb = thumbnail_texture.and_then(|texture| {
 								self.shelves.thumbnails.get(&card.id).map(|r| {
@@ -645,12 +1012,20 @@ impl ViewHandle for LibraryView {
 									)
 								})
 							});
-							if ui.add(card.ui(thumb)).clicked() {
-								self.bell.send_event(AppEvent::OpenReader(card.id));
+							let mut jump_unread = false;
+							let response = ui.add(card.ui(thumb, &mut jump_unread));
+							if jump_unread {
+								self.bell
+									.send_event(AppEvent::OpenReader(card.id, card.unread));
+							} else if response.clicked() {
+								self.bell.send_event(AppEvent::OpenReader(card.id, None));
 							}
 						});
 					}
-				})
+				});
+				if let Some(name) = toggle_shelf {
+					self.toggle_shelf(&name);
+				}
 			});
 		});
 	}
@@ -658,13 +1033,19 @@ impl ViewHandle for LibraryView {
 	fn event(&mut self, event: &AppEvent) -> EventResult {
 		match event {
 			AppEvent::BookUpdated(id) => {
+				if let Some(collection) = self.state.collection {
+					self.shelves.collection =
+						fetch_collection_books(&self.records, CollectionId(collection));
+				}
 				match self.records.fetch_book(*id) {
 					Ok(book) => {
-						self.shelves.update(book);
+						self.shelves
+							.update(&self.records, book, &self.state.collapsed);
 					}
 					Err(e) => {
 						log::error!("Error fetching book {id}: {e}");
-						self.shelves.remove(*id);
+						self.shelves
+							.remove(&self.records, *id, &self.state.collapsed);
 					}
 				};
 				self.cards = read_cards(
@@ -721,6 +1102,9 @@ impl BookCard {
 			opened_at: book.opened_at,
 			modified_at: book.modified_at,
 			added_at: book.added_at,
+			new_spines: book.new_spines,
+			unread: book.unread_location(),
+			series: book.series.clone(),
 		}
 	}
 }
@@ -728,6 +1112,7 @@ impl BookCard {
 struct BookCardUi<'a> {
 	card: &'a BookCard,
 	thumbnail: Option<(egui::load::SizedTexture, egui::Rect)>,
+	jump_unread: &'a mut bool,
 }
 
 impl egui::Widget for BookCardUi<'_> {
@@ -735,6 +1120,7 @@ impl egui::Widget for BookCardUi<'_> {
 		let card = self.card;
 		ui.spacing_mut().item_spacing = Vec2::new(3., 3.);
 		ui.group(|ui| {
+			let mut unread_rect = None;
 			let height = ui.available_height();
 			ui.horizontal(|ui| {
 				let cover_width = height * 0.75;
@@ -771,6 +1157,10 @@ impl egui::Widget for BookCardUi<'_> {
 						.unwrap_or("Unknown");
 					ui.label(RichText::new(title).size(theme::L_SIZE));
 
+					if let Some(series) = &card.series {
+						ui.label(RichText::new(series.to_string()).size(theme::S_SIZE));
+					}
+
 					match card.sort_by {
 						SortBy::Modified => {
 							ui.label(
@@ -781,7 +1171,7 @@ impl egui::Widget for BookCardUi<'_> {
 								.size(theme::S_SIZE),
 							);
 						}
-						SortBy::Opened => {
+						SortBy::Opened | SortBy::Author | SortBy::Series | SortBy::Title => {
 							if let Some(opened_at) = card.opened_at {
 								ui.label(
 									RichText::new(format!(
@@ -805,6 +1195,17 @@ impl egui::Widget for BookCardUi<'_> {
 						}
 					}
 
+					if card.unread.is_some() {
+						let text = match card.new_spines {
+							1 => "1 new chapter".to_string(),
+							n => format!("{n} new chapters"),
+						};
+						let button = ui.add(egui::Button::new(
+							UiIcon::new(Icon::Sparkles).text(&text).build(),
+						));
+						unread_rect = Some(button.rect);
+					}
+
 					ui.with_layout(Layout::bottom_up(Align::Max), |ui| {
 						let read_part = card.percent_read as f32 / 100.;
 						ui.add(
@@ -816,24 +1217,80 @@ impl egui::Widget for BookCardUi<'_> {
 					});
 				});
 			});
-			ui.interact(
+			let response = ui.interact(
 				ui.min_rect(),
 				ui.id().with(card.id.into_inner()),
 				egui::Sense::click(),
-			)
+			);
+			// Interact on top of card to not have the click swallowed
+			if let Some(rect) = unread_rect
+				&& ui
+					.interact(
+						rect,
+						ui.id().with(("unread", card.id.into_inner())),
+						egui::Sense::click(),
+					)
+					.clicked()
+			{
+				*self.jump_unread = true;
+			}
+			response
 		})
 		.inner
 	}
 }
 
+struct ShelfUi<'a> {
+	name: &'a str,
+	books: usize,
+	collapsed: bool,
+}
+
+impl<'a> ShelfUi<'a> {
+	fn new(name: &'a str, books: usize, collapsed: bool) -> Self {
+		Self {
+			name,
+			books,
+			collapsed,
+		}
+	}
+}
+
+impl egui::Widget for ShelfUi<'_> {
+	fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+		let icon = if self.collapsed {
+			Icon::ChevronRight
+		} else {
+			Icon::ChevronDown
+		};
+		let response = ui
+			.horizontal_centered(|ui| {
+				ui.set_min_width(ui.available_width());
+				ui.label(UiIcon::new(icon).build());
+				ui.label(RichText::new(self.name).size(theme::L_SIZE));
+				ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+					ui.label(RichText::new(self.books.to_string()).size(theme::S_SIZE));
+				});
+			})
+			.response;
+		ui.interact(
+			response.rect,
+			ui.id().with(("shelf", self.name)),
+			egui::Sense::click(),
+		)
+	}
+}
+
 impl BookCard {
 	pub(crate) fn ui<'a>(
 		&'a self,
 		thumbnail: Option<(egui::load::SizedTexture, egui::Rect)>,
+		jump_unread: &'a mut bool,
 	) -> BookCardUi<'a> {
 		BookCardUi {
 			card: self,
 			thumbnail,
+			jump_unread,
 		}
 	}
 }
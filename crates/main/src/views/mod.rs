@@ -1,13 +1,18 @@
 mod experiments;
 mod library;
 mod reader;
+mod statistics;
 
+use illustrator::LayoutCache;
 use pixelator::PixelatorAssistant;
+use scribe::AnnotationExporter;
 use scribe::BookId;
 use scribe::LibraryScribeAssistant;
+use scribe::Location;
 use scribe::RecordKeeper;
 use scribe::config::IllustratorConfig;
 use sculpter::SculpterFonts;
+use sculpter::SculpterHyphenation;
 use wrangler::content::ContentWranglerAssistant;
 
 use crate::AppBell;
@@ -60,6 +65,7 @@ enum Views {
 	Library(library::LibraryView),
 	Reader(reader::ReaderView),
 	Experiments(experiments::ExperimentsView),
+	Statistics(statistics::StatisticsView),
 	Error(String),
 }
 
@@ -105,20 +111,28 @@ impl AppView {
 		config: IllustratorConfig,
 		keeper: RecordKeeper,
 		fonts: SculpterFonts,
+		hyphenation: SculpterHyphenation,
 		content: ContentWranglerAssistant,
 		pixelator: PixelatorAssistant,
 		bell: AppBell,
+		exporter: AnnotationExporter,
+		layouts: LayoutCache,
 		book_id: BookId,
+		location: Option<Location>,
 	) {
 		self.close();
 		match reader::ReaderView::create(
 			config,
 			keeper,
 			fonts,
+			hyphenation,
 			content,
 			pixelator,
 			bell,
+			exporter,
+			layouts,
 			book_id,
+			location,
 			self.viewport.clone(),
 		) {
 			Ok(view) => self.view = Views::Reader(view),
@@ -138,6 +152,17 @@ impl AppView {
 		))
 	}
 
+	pub(crate) fn statistics(&mut self, records: RecordKeeper) {
+		self.close();
+		match statistics::StatisticsView::create(self.bell.clone(), records) {
+			Ok(view) => self.view = Views::Statistics(view),
+			Err(e) => {
+				log::error!("Statistics view error: {}", e);
+				self.view = Views::Error(format!("Statistics view error: {}", e));
+			}
+		};
+	}
+
 	pub(crate) fn error(&mut self, error: &str) {
 		self.view = Views::Error(error.to_string());
 	}
@@ -183,6 +208,7 @@ impl ViewHandle for AppView {
 			Views::Library(view) => view.draw(painter),
 			Views::Reader(view) => view.draw(painter),
 			Views::Experiments(view) => view.draw(painter),
+			Views::Statistics(view) => view.draw(painter),
 		}
 	}
 
@@ -193,6 +219,7 @@ impl ViewHandle for AppView {
 			Views::Library(view) => view.event(event),
 			Views::Reader(view) => view.event(event),
 			Views::Experiments(view) => view.event(event),
+			Views::Statistics(view) => view.event(event),
 		}
 	}
 
@@ -203,6 +230,7 @@ impl ViewHandle for AppView {
 			Views::Library(view) => view.gesture(event),
 			Views::Reader(view) => view.gesture(event),
 			Views::Experiments(view) => view.gesture(event),
+			Views::Statistics(view) => view.gesture(event),
 		}
 	}
 
@@ -216,6 +244,7 @@ impl ViewHandle for AppView {
 			Views::Library(view) => view.resize(width, height),
 			Views::Reader(view) => view.resize(width, height),
 			Views::Experiments(view) => view.resize(width, height),
+			Views::Statistics(view) => view.resize(width, height),
 		}
 	}
 
@@ -228,6 +257,7 @@ impl ViewHandle for AppView {
 			Views::Library(view) => view.rescale(scale_factor),
 			Views::Reader(view) => view.rescale(scale_factor),
 			Views::Experiments(view) => view.rescale(scale_factor),
+			Views::Statistics(view) => view.rescale(scale_factor),
 		}
 	}
 
@@ -238,6 +268,7 @@ impl ViewHandle for AppView {
 			Views::Library(view) => view.close(),
 			Views::Reader(view) => view.close(),
 			Views::Experiments(view) => view.close(),
+			Views::Statistics(view) => view.close(),
 		}
 	}
 }
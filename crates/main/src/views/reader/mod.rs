@@ -1,27 +1,42 @@
 mod active_areas;
 
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fmt::Display;
 use std::sync::Arc;
 
+use chrono::TimeDelta;
+use chrono::Utc;
 use egui::Button;
 use egui::Color32;
 use egui::Rect;
 use egui::RichText;
+use illustrator::Footnote;
 use illustrator::IllustratorAssistant;
 use illustrator::IllustratorCreateError;
 use illustrator::IllustratorLanucher;
 use illustrator::IllustratorRequestError;
+use illustrator::LayoutCache;
+use illustrator::Position;
+use illustrator::TextSelection;
 use lucide_icons::Icon;
 use pixelator::PixelatorAssistant;
 use pixelator::PixmapInstance;
+use scribe::AnnotationExporter;
 use scribe::BookId;
+use scribe::Bookmark;
+use scribe::Collection;
+use scribe::CollectionId;
+use scribe::Highlight;
+use scribe::HighlightId;
 use scribe::Location;
+use scribe::ReadingPace;
 use scribe::RecordKeeper;
 use scribe::RecordKeeperAssistant;
 use scribe::RecordKeeperError;
 use scribe::config::IllustratorConfig;
 use sculpter::SculpterFonts;
+use sculpter::SculpterHyphenation;
 use serde::Deserialize;
 use serde::Serialize;
 use wrangler::content::ContentWranglerAssistant;
@@ -45,8 +60,11 @@ use crate::views::ViewHandle;
 use crate::views::Viewport;
 use crate::views::reader::active_areas::ActiveAreaAction;
 use crate::views::reader::active_areas::ActiveAreas;
+use crate::views::statistics::format_duration;
 
 pub const CHAPTER_LIST_SIZE: u32 = 12;
+/// Characters of page text kept in bookmarks
+const BOOKMARK_EXCERPT_CHARS: usize = 120;
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum ReaderViewCreateError {
@@ -73,6 +91,9 @@ enum ReaderMode {
 	Navigation,
 	ActionSettings,
 	ProfileSettings,
+	Search,
+	Collections,
+	Bookmarks,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -84,6 +105,13 @@ enum Action {
 	Settings,
 	Library,
 	Exit,
+	NewChapters,
+	Back,
+	Search,
+	Collections,
+	Bookmark,
+	Bookmarks,
+	Export,
 }
 
 impl Display for Action {
@@ -96,6 +124,13 @@ impl Display for Action {
 			Action::Settings => write!(f, "Settings"),
 			Action::Library => write!(f, "Library"),
 			Action::Exit => write!(f, "Exit"),
+			Action::NewChapters => write!(f, "New chapters"),
+			Action::Back => write!(f, "Back"),
+			Action::Search => write!(f, "Search"),
+			Action::Collections => write!(f, "Collections"),
+			Action::Bookmark => write!(f, "Bookmark"),
+			Action::Bookmarks => write!(f, "Bookmarks"),
+			Action::Export => write!(f, "Export annotations"),
 		}
 	}
 }
@@ -139,6 +174,7 @@ pub(crate) struct ReaderView {
 	config: IllustratorConfig,
 	bell: AppBell,
 	launcher: IllustratorLanucher<AppBell>,
+	exporter: AnnotationExporter,
 	book_id: BookId,
 
 	records: RecordKeeperAssistant,
@@ -146,10 +182,27 @@ pub(crate) struct ReaderView {
 	state: ViewState,
 
 	illustrator: Option<IllustratorAssistant>,
+	unread: Option<Location>,
 	mode: ReaderMode,
 	active_rects: Vec<Rect>,
 	chapters_page: u32,
 	chapters_cards: [Option<ChapterCard>; CHAPTER_LIST_SIZE as usize],
+	search_input: String,
+	page_input: String,
+	collections: Vec<Collection>,
+	book_collections: BTreeSet<CollectionId>,
+	collection_input: String,
+	bookmarks: Vec<Bookmark>,
+	highlights: Vec<Highlight>,
+	/// Text selected on current page, not yet highlighted
+	selection: Option<TextSelection>,
+	/// Stored highlight opened from page
+	selected_highlight: Option<HighlightId>,
+	note_input: String,
+	/// Outcome of last annotation export
+	export_status: Option<String>,
+	/// Reading pace for time left estimates
+	pace: Option<ReadingPace>,
 }
 
 impl ReaderView {
@@ -160,10 +213,14 @@ impl ReaderView {
 		config: IllustratorConfig,
 		keeper: RecordKeeper,
 		fonts: SculpterFonts,
+		hyphenation: SculpterHyphenation,
 		content: ContentWranglerAssistant,
 		pixelator: PixelatorAssistant,
 		bell: AppBell,
+		exporter: AnnotationExporter,
+		layouts: LayoutCache,
 		book_id: BookId,
+		location: Option<Location>,
 		viewport: Viewport,
 	) -> Result<Self, ReaderViewCreateError> {
 		let records = keeper.assistant()?;
@@ -174,12 +231,43 @@ impl ReaderView {
 			.unwrap_or_default()
 			.unwrap_or_default();
 
-		let launcher = IllustratorLanucher::new(keeper, fonts, content, pixelator, bell.clone());
+		// Must be read before illustrator records a new opened timestamp
+		let unread = records
+			.fetch_book(book_id)
+			.inspect_err(|e| log::warn!("Error fetching book: {e}"))
+			.ok()
+			.and_then(|book| book.unread_location());
+
+		let bookmarks = records
+			.fetch_bookmarks(book_id)
+			.inspect_err(|e| log::warn!("Error fetching bookmarks: {e}"))
+			.unwrap_or_default();
+
+		let highlights = records
+			.fetch_highlights(book_id)
+			.inspect_err(|e| log::warn!("Error fetching highlights: {e}"))
+			.unwrap_or_default();
+
+		let pace = records
+			.fetch_reading_pace(book_id)
+			.inspect_err(|e| log::warn!("Error fetching reading pace: {e}"))
+			.unwrap_or_default();
+
+		let launcher = IllustratorLanucher::new(
+			keeper,
+			fonts,
+			hyphenation,
+			content,
+			pixelator,
+			layouts,
+			bell.clone(),
+		);
 
 		let mut view = Self {
 			config,
 			bell,
 			launcher,
+			exporter,
 			book_id,
 
 			records,
@@ -187,13 +275,29 @@ impl ReaderView {
 			state,
 
 			illustrator: None,
+			unread,
 			mode: ReaderMode::ReadNoUi,
 			active_rects: Vec::new(),
 			chapters_page: 0,
 			chapters_cards: Default::default(),
+			search_input: String::new(),
+			page_input: String::new(),
+			collections: Vec::new(),
+			book_collections: BTreeSet::new(),
+			collection_input: String::new(),
+			bookmarks,
+			highlights,
+			selection: None,
+			selected_highlight: None,
+			note_input: String::new(),
+			export_status: None,
+			pace,
 		};
 
 		view.create_illustrator()?;
+		if let Some(loc) = location {
+			view.goto(loc);
+		}
 
 		Ok(view)
 	}
@@ -219,6 +323,500 @@ impl ReaderView {
 		Ok(())
 	}
 
+	fn goto(&mut self, loc: Location) {
+		let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+		let _ = illustrator
+			.goto(loc)
+			.inspect_err(|err| log::error!("Goto error: {err}"));
+	}
+
+	fn back(&mut self) {
+		let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+		let _ = illustrator
+			.back()
+			.inspect_err(|err| log::error!("Back error: {err}"));
+	}
+
+	/// Dismiss popups, follow link or open highlight at physical position on current page
+	fn tap_page(&mut self, x: f32, y: f32) -> bool {
+		if self.selection.is_some() || self.selected_highlight.is_some() {
+			self.close_selection();
+			return true;
+		}
+		let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+		if illustrator.footnote().is_some() {
+			illustrator.dismiss_footnote();
+			return true;
+		}
+		let state = illustrator.state();
+		let (link, highlight) = illustrator
+			.cache()
+			.page(state.location)
+			.map(|(page, _)| {
+				let link = page
+					.links
+					.iter()
+					.find(|l| l.contains(x, y))
+					.map(|l| l.link.clone());
+				let highlight = page
+					.highlights
+					.iter()
+					.filter(|h| h.contains(x, y))
+					.find_map(|h| h.highlight);
+				(link, highlight)
+			})
+			.unwrap_or_default();
+		if let Some(link) = link {
+			let _ = illustrator
+				.follow_link(link)
+				.inspect_err(|err| log::error!("Follow link error: {err}"));
+			return true;
+		}
+		if let Some(id) = highlight {
+			self.open_highlight(id);
+			return true;
+		}
+		false
+	}
+
+	/// Select text on current page between physical positions
+	fn select_text(&mut self, from: Position, to: Position) {
+		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+		let state = illustrator.state();
+		let selection = illustrator.cache().select(state.location, from, to);
+		self.close_selection();
+		self.selection = selection;
+	}
+
+	fn open_highlight(&mut self, id: HighlightId) {
+		self.close_selection();
+		self.note_input = self
+			.highlights
+			.iter()
+			.find(|h| h.id == id)
+			.and_then(|h| h.note.clone())
+			.unwrap_or_default();
+		self.selected_highlight = Some(id);
+	}
+
+	fn close_selection(&mut self) {
+		self.selection = None;
+		self.selected_highlight = None;
+		self.note_input.clear();
+	}
+
+	/// Store selection as highlight, with note from input
+	fn highlight_selection(&mut self) {
+		let Some(selection) = self.selection.take() else {
+			return;
+		};
+		let chapter = self.chapter_title(selection.spine);
+		let result = self
+			.records
+			.insert_highlight(
+				self.book_id,
+				selection.spine,
+				selection.start..selection.end,
+				chapter.as_deref().map(String::as_str),
+				&selection.text,
+			)
+			.and_then(|id| self.records.record_highlight_note(id, &self.note_input));
+		if let Err(e) = result {
+			log::error!("Highlight error: {e}");
+		}
+		self.close_selection();
+		self.reload_highlights();
+	}
+
+	fn reload_highlights(&mut self) {
+		self.highlights = self
+			.records
+			.fetch_highlights(self.book_id)
+			.inspect_err(|e| log::error!("Fetch highlights error: {e}"))
+			.unwrap_or_default();
+		let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+		let _ = illustrator
+			.reload_highlights()
+			.inspect_err(|err| log::error!("Reload highlights error: {err}"));
+	}
+
+	fn selection_panel(&mut self, ui: &mut egui::Ui) {
+		let text = match (&self.selection, self.selected_highlight) {
+			(Some(selection), _) => selection.text.clone(),
+			(None, Some(id)) => match self.highlights.iter().find(|h| h.id == id) {
+				Some(highlight) => highlight.text.clone(),
+				None => return,
+			},
+			(None, None) => return,
+		};
+		let is_new = self.selection.is_some();
+
+		let mut save = false;
+		let mut delete = false;
+		let mut close = false;
+		let panel = egui::Panel::bottom("selection").show(ui, |ui| {
+			ui.add_space(8.);
+			egui::ScrollArea::vertical()
+				.max_height(ui.available_height() / 4.)
+				.show(ui, |ui| {
+					ui.label(RichText::new(text.as_str()).size(theme::M_SIZE).italics());
+				});
+			ui.add_space(8.);
+			ui.add(
+				egui::TextEdit::multiline(&mut self.note_input)
+					.hint_text("Note")
+					.desired_rows(2)
+					.desired_width(f32::INFINITY)
+					.font(egui::FontId::proportional(theme::M_SIZE)),
+			);
+			ui.add_space(8.);
+			ui.horizontal(|ui| {
+				let label = if is_new { "Highlight" } else { "Save note" };
+				save = ui
+					.add(Button::new(RichText::new(label).size(theme::M_SIZE)))
+					.clicked();
+				if !is_new {
+					delete = ui
+						.add(Button::new(RichText::new("Delete").size(theme::M_SIZE)))
+						.clicked();
+				}
+				close = ui
+					.add(Button::new(RichText::new("Close").size(theme::M_SIZE)))
+					.clicked();
+			});
+			ui.add_space(8.);
+		});
+		self.active_rects.push(panel.response.interact_rect);
+
+		if save && is_new {
+			self.highlight_selection();
+		} else if let Some(id) = self.selected_highlight.filter(|_| save || delete) {
+			let result = if delete {
+				self.records.delete_highlight(id)
+			} else {
+				self.records.record_highlight_note(id, &self.note_input)
+			};
+			if let Err(e) = result {
+				log::error!("Update highlight error: {e}");
+			}
+			self.close_selection();
+			self.reload_highlights();
+		} else if close {
+			self.close_selection();
+		}
+	}
+
+	fn footnote_panel(&mut self, ui: &mut egui::Ui, footnote: &Footnote) {
+		let panel = egui::Panel::bottom("footnote").show(ui, |ui| {
+			ui.add_space(8.);
+			egui::ScrollArea::vertical()
+				.max_height(ui.available_height() / 3.)
+				.show(ui, |ui| {
+					ui.label(RichText::new(footnote.text.as_str()).size(theme::M_SIZE));
+				});
+			ui.add_space(8.);
+			ui.horizontal(|ui| {
+				let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+				if ui
+					.add(Button::new(RichText::new("Go to note").size(theme::M_SIZE)))
+					.clicked()
+				{
+					let _ = illustrator
+						.jump(footnote.location)
+						.inspect_err(|err| log::error!("Jump error: {err}"));
+				}
+				if ui
+					.add(Button::new(RichText::new("Close").size(theme::M_SIZE)))
+					.clicked()
+				{
+					illustrator.dismiss_footnote();
+				}
+			});
+			ui.add_space(8.);
+		});
+		self.active_rects.push(panel.response.interact_rect);
+	}
+
+	fn search_panel(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			let edit = ui.add(
+				egui::TextEdit::singleline(&mut self.search_input)
+					.hint_text("Search book")
+					.font(egui::FontId::proportional(theme::M_SIZE)),
+			);
+			let submit = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+			if ui
+				.add(Button::new(RichText::new("Search").size(theme::M_SIZE)))
+				.clicked() || submit
+			{
+				self.search(self.search_input.clone());
+			}
+			if ui
+				.add(Button::new(RichText::new("Clear").size(theme::M_SIZE)))
+				.clicked()
+			{
+				self.search_input.clear();
+				self.search(String::new());
+			}
+		});
+		ui.add_space(8.);
+
+		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+		let results = illustrator.search_results().clone();
+		if results.query.trim().is_empty() {
+			return;
+		}
+		let status = match (results.done, results.matches.len()) {
+			(false, _) => "Searching…".to_string(),
+			(true, 1) => "1 match".to_string(),
+			(true, n) => format!("{n} matches"),
+		};
+		ui.label(RichText::new(status).size(theme::S_SIZE));
+		ui.separator();
+
+		let mut selected = None;
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			for (i, m) in results.matches.iter().enumerate() {
+				let response = ui.add(
+					Button::new(RichText::new(&m.snippet).size(theme::M_SIZE))
+						.wrap()
+						.frame(false),
+				);
+				if response.clicked() {
+					selected = Some(m.location);
+				}
+				if i + 1 < results.matches.len() {
+					ui.separator();
+				}
+			}
+		});
+		if let Some(loc) = selected {
+			self.goto(loc);
+			self.mode = ReaderMode::Read;
+		}
+	}
+
+	fn collections_panel(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			let edit = ui.add(
+				egui::TextEdit::singleline(&mut self.collection_input)
+					.hint_text("New collection")
+					.font(egui::FontId::proportional(theme::M_SIZE)),
+			);
+			let submit = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+			if ui
+				.add(Button::new(RichText::new("Add").size(theme::M_SIZE)))
+				.clicked() || submit
+			{
+				self.create_collection();
+			}
+		});
+		ui.add_space(8.);
+
+		ui.spacing_mut().item_spacing.y = 8.;
+		ui.spacing_mut().button_padding.y = 8.;
+		let mut toggled = None;
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			ui.vertical_centered_justified(|ui| {
+				for collection in &self.collections {
+					let is_member = self.book_collections.contains(&collection.id);
+					let icon = if is_member {
+						Icon::CheckSquare
+					} else {
+						Icon::Square
+					};
+					let response = ui.add(
+						Button::new(
+							UiIcon::new(icon)
+								.large()
+								.text(collection.name.as_str())
+								.build(),
+						)
+						.selected(is_member),
+					);
+					if response.clicked() {
+						toggled = Some(collection.id);
+					}
+				}
+			});
+		});
+		if let Some(id) = toggled {
+			self.toggle_book_collection(id);
+		}
+	}
+
+	fn toggle_collections(&mut self) {
+		if matches!(self.mode, ReaderMode::Collections) {
+			self.mode = ReaderMode::Read;
+		} else {
+			self.load_collections();
+			self.mode = ReaderMode::Collections;
+		}
+	}
+
+	fn load_collections(&mut self) {
+		self.collections = self
+			.records
+			.fetch_collections()
+			.inspect_err(|e| log::error!("Fetch collections error: {e}"))
+			.unwrap_or_default();
+		self.book_collections = self
+			.records
+			.fetch_book_collections(self.book_id)
+			.inspect_err(|e| log::error!("Fetch book collections error: {e}"))
+			.map(BTreeSet::from_iter)
+			.unwrap_or_default();
+	}
+
+	/// Create collection from input and add current book to it
+	fn create_collection(&mut self) {
+		let name = self.collection_input.trim();
+		if name.is_empty() {
+			return;
+		}
+		let result = self
+			.records
+			.create_collection(name)
+			.and_then(|id| self.records.add_book_to_collection(self.book_id, id));
+		if let Err(e) = result {
+			log::error!("Create collection error: {e}");
+		}
+		self.collection_input.clear();
+		self.load_collections();
+	}
+
+	fn toggle_book_collection(&mut self, id: CollectionId) {
+		let result = if self.book_collections.contains(&id) {
+			self.records.remove_book_from_collection(self.book_id, id)
+		} else {
+			self.records.add_book_to_collection(self.book_id, id)
+		};
+		if let Err(e) = result {
+			log::error!("Toggle collection error: {e}");
+		}
+		self.load_collections();
+	}
+
+	/// Location of bookmark on current page
+	fn page_bookmark(&self) -> Option<Location> {
+		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+		let state = illustrator.state();
+		let cache = illustrator.cache();
+		let (page, _) = cache.page(state.location)?;
+		self.bookmarks
+			.iter()
+			.map(|b| b.location)
+			.filter(|loc| loc.spine == state.location.spine)
+			.find(|loc| page.elements.contains(&loc.element) || loc.element == page.elements.start)
+	}
+
+	/// Bookmark current page, or remove bookmark of current page
+	fn toggle_bookmark(&mut self) {
+		let result = if let Some(loc) = self.page_bookmark() {
+			self.records.delete_bookmark(self.book_id, loc)
+		} else {
+			let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+			let state = illustrator.state();
+			let cache = illustrator.cache();
+			let Some((page, _)) = cache.page(state.location) else {
+				return;
+			};
+			let location = Location {
+				spine: state.location.spine,
+				element: page.elements.start,
+			};
+			let bookmark = Bookmark {
+				location,
+				chapter: self.chapter_title(location.spine),
+				excerpt: cache.excerpt(location, BOOKMARK_EXCERPT_CHARS),
+				created_at: Utc::now(),
+			};
+			drop(cache);
+			self.records.record_bookmark(self.book_id, &bookmark)
+		};
+		if let Err(e) = result {
+			log::error!("Bookmark error: {e}");
+		}
+		self.load_bookmarks();
+	}
+
+	fn chapter_title(&self, spine: u32) -> Option<Arc<String>> {
+		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+		illustrator.navigation().and_then(|n| {
+			n.nav_points
+				.iter()
+				.find(|p| p.spine == Some(spine))
+				.map(|p| p.title.clone())
+		})
+	}
+
+	/// Export bookmarks and highlights of book to library
+	fn export_annotations(&mut self) {
+		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+		let navigation = illustrator.navigation().unwrap_or_default();
+		let result = pollster::block_on(self.exporter.export(self.book_id, &navigation));
+		self.export_status = Some(match result {
+			Ok(documents) => {
+				let documents = documents.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+				format!("Exported to {}", documents.join(", "))
+			}
+			Err(e) => {
+				log::error!("Export error: {e}");
+				"Export failed".to_string()
+			}
+		});
+	}
+
+	fn load_bookmarks(&mut self) {
+		self.bookmarks = self
+			.records
+			.fetch_bookmarks(self.book_id)
+			.inspect_err(|e| log::error!("Fetch bookmarks error: {e}"))
+			.unwrap_or_default();
+	}
+
+	fn toggle_bookmarks(&mut self) {
+		if matches!(self.mode, ReaderMode::Bookmarks) {
+			self.mode = ReaderMode::Read;
+		} else {
+			self.mode = ReaderMode::Bookmarks;
+		}
+	}
+
+	fn bookmarks_panel(&mut self, ui: &mut egui::Ui) {
+		if self.bookmarks.is_empty() {
+			ui.label(RichText::new("No bookmarks").size(theme::M_SIZE));
+			return;
+		}
+
+		let mut selected = None;
+		let mut removed = None;
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			for bookmark in &self.bookmarks {
+				ui.horizontal(|ui| {
+					let remove =
+						ui.add(Button::new(UiIcon::new(Icon::Trash2).large().build()).frame(false));
+					if remove.clicked() {
+						removed = Some(bookmark.location);
+					}
+					if ui.add(BookmarkCardUi { bookmark }).clicked() {
+						selected = Some(bookmark.location);
+					}
+				});
+			}
+		});
+		if let Some(loc) = removed {
+			if let Err(e) = self.records.delete_bookmark(self.book_id, loc) {
+				log::error!("Delete bookmark error: {e}");
+			}
+			self.load_bookmarks();
+		}
+		if let Some(loc) = selected {
+			self.goto(loc);
+			self.mode = ReaderMode::Read;
+		}
+	}
+
 	fn toggle_ui(&mut self) {
 		if matches!(self.mode, ReaderMode::ReadNoUi) {
 			self.mode = ReaderMode::Read;
@@ -246,6 +844,7 @@ impl ReaderView {
 				if let Some(item) = item_iter.next() {
 					*card = Some(ChapterCard {
 						location: Location::from_spine(item.spine.unwrap_or_default()),
+						fragment: item.fragment.clone(),
 						title: item.title.clone(),
 						active: item.spine.is_some_and(|s| s == state.location.spine),
 					});
@@ -258,6 +857,62 @@ impl ReaderView {
 		}
 	}
 
+	/// Jump to page of book typed in navigation
+	fn page_panel(&mut self, ui: &mut egui::Ui) {
+		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
+		let state = illustrator.state();
+		let Some((page, pages)) = illustrator.book_page(state.location) else {
+			ui.label(RichText::new("Counting pages…").size(theme::S_SIZE));
+			return;
+		};
+		let mut goto_page = None;
+		ui.horizontal(|ui| {
+			ui.label(RichText::new("Page").size(theme::M_SIZE));
+			let edit = ui.add(
+				egui::TextEdit::singleline(&mut self.page_input)
+					.hint_text(page.to_string())
+					.desired_width(80.)
+					.font(egui::FontId::proportional(theme::M_SIZE)),
+			);
+			ui.label(RichText::new(format!("/ {pages}")).size(theme::M_SIZE));
+			let submit = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+			if ui
+				.add(Button::new(RichText::new("Go").size(theme::M_SIZE)))
+				.clicked() || submit
+			{
+				goto_page = self
+					.page_input
+					.trim()
+					.parse::<u32>()
+					.ok()
+					.filter(|p| (1..=pages).contains(p));
+			}
+		});
+		if let Some(page) = goto_page {
+			let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+			let _ = illustrator
+				.goto_page(page)
+				.inspect_err(|err| log::error!("Goto page error: {err}"));
+			self.page_input.clear();
+			self.toggle_chapters();
+		}
+	}
+
+	fn toggle_search(&mut self) {
+		if matches!(self.mode, ReaderMode::Search) {
+			self.mode = ReaderMode::Read;
+		} else {
+			self.mode = ReaderMode::Search;
+		}
+	}
+
+	fn search(&mut self, query: String) {
+		let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
+		let _ = illustrator
+			.search(query)
+			.inspect_err(|err| log::error!("Search error: {err}"));
+	}
+
 	fn toggle_settings(&mut self) {
 		match self.mode {
 			ReaderMode::Read => {
@@ -276,6 +931,8 @@ impl ReaderView {
 	fn prev_page(&mut self) {
 		match self.mode {
 			ReaderMode::Read | ReaderMode::ReadNoUi => {
+				self.close_selection();
+				self.export_status = None;
 				let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
 				let _ = illustrator
 					.previous_page()
@@ -298,6 +955,7 @@ impl ReaderView {
 					if let Some(item) = item_iter.next() {
 						*card = Some(ChapterCard {
 							location: Location::from_spine(item.spine.unwrap_or_default()),
+							fragment: item.fragment.clone(),
 							title: item.title.clone(),
 							active: item.spine.is_some_and(|s| s == state.location.spine),
 						});
@@ -308,12 +966,17 @@ impl ReaderView {
 			}
 			ReaderMode::ActionSettings => {}
 			ReaderMode::ProfileSettings => {}
+			ReaderMode::Search => {}
+			ReaderMode::Collections => {}
+			ReaderMode::Bookmarks => {}
 		};
 	}
 
 	fn next_page(&mut self) {
 		match self.mode {
 			ReaderMode::Read | ReaderMode::ReadNoUi => {
+				self.close_selection();
+				self.export_status = None;
 				let illustrator = self.illustrator.as_mut().expect("Illustrator not running");
 				let _ = illustrator
 					.next_page()
@@ -337,6 +1000,7 @@ impl ReaderView {
 						if let Some(item) = item_iter.next() {
 							*card = Some(ChapterCard {
 								location: Location::from_spine(item.spine.unwrap_or_default()),
+								fragment: item.fragment.clone(),
 								title: item.title.clone(),
 								active: item.spine.is_some_and(|s| s == state.location.spine),
 							});
@@ -349,6 +1013,9 @@ impl ReaderView {
 			}
 			ReaderMode::ActionSettings => {}
 			ReaderMode::ProfileSettings => {}
+			ReaderMode::Search => {}
+			ReaderMode::Collections => {}
+			ReaderMode::Bookmarks => {}
 		};
 	}
 }
@@ -367,6 +1034,17 @@ impl OnAction<Action> for ReaderView {
 			Action::Next => self.next_page(),
 			Action::Chapters => self.toggle_chapters(),
 			Action::Settings => self.toggle_settings(),
+			Action::NewChapters => {
+				if let Some(loc) = self.unread.take() {
+					self.goto(loc);
+				}
+			}
+			Action::Back => self.back(),
+			Action::Search => self.toggle_search(),
+			Action::Collections => self.toggle_collections(),
+			Action::Bookmark => self.toggle_bookmark(),
+			Action::Bookmarks => self.toggle_bookmarks(),
+			Action::Export => self.export_annotations(),
 		}
 	}
 }
@@ -375,8 +1053,11 @@ impl ViewHandle for ReaderView {
 	fn draw(&mut self, painter: Painter<'_>) {
 		self.active_rects.clear();
 
+		let bookmarked = self.page_bookmark().is_some();
 		let illustrator = self.illustrator.as_ref().expect("Illustrator not running");
 		let mut page_meta = None;
+		let mut highlights = Vec::new();
+		let mut selected = Vec::new();
 
 		let painter = painter.draw_pixmap(|brush| {
 			if !matches!(self.mode, ReaderMode::Read | ReaderMode::ReadNoUi) {
@@ -390,7 +1071,23 @@ impl ViewHandle for ReaderView {
 				return;
 			};
 			page_meta = Some(meta);
-			let atlas_pixmap = cache.pixmap();
+			let atlas_pixmap = cache.pixmap(state.location);
+			let scale_factor = self.viewport.scale_factor;
+			highlights.extend(content.highlights.iter().map(|h| {
+				let rect = Rect::from_min_size(
+					egui::pos2(h.pos.x, h.pos.y) / scale_factor,
+					egui::vec2(h.size.width, h.size.height) / scale_factor,
+				);
+				(rect, h.highlight.is_some())
+			}));
+			if let Some(selection) = &self.selection {
+				selected.extend(selection.areas.iter().map(|(pos, size)| {
+					Rect::from_min_size(
+						egui::pos2(pos.x, pos.y) / scale_factor,
+						egui::vec2(size.width, size.height) / scale_factor,
+					)
+				}));
+			}
 
 			for item in &content.items {
 				match item {
@@ -456,12 +1153,19 @@ impl ViewHandle for ReaderView {
 					.map(|m| (m.page, m.pages))
 					.unwrap_or_default();
 
+				let book_page = illustrator.book_page(state.location);
+				let chapter_left = self.pace.map(|p| p.time_to_read(state.chapter_chars_left));
+				let book_left = self.pace.map(|p| p.time_to_read(state.chars_left));
+
 				Some(BookDetailsUI {
 					title,
 					chapter,
 					percent_read,
 					page,
 					pages,
+					book_page,
+					chapter_left,
+					book_left,
 				})
 			} else {
 				None
@@ -470,12 +1174,41 @@ impl ViewHandle for ReaderView {
 			None
 		};
 
+		let footnote = illustrator.footnote();
+		let can_go_back = illustrator.state().can_go_back;
+
 		painter.draw_ui(|ui| {
+			for (rect, stored) in &highlights {
+				let alpha = if *stored { 24 } else { 40 };
+				ui.painter()
+					.rect_filled(*rect, 0., Color32::from_black_alpha(alpha));
+			}
+			for rect in &selected {
+				ui.painter()
+					.rect_filled(*rect, 0., Color32::from_black_alpha(64));
+			}
+
 			if matches!(self.mode, ReaderMode::ReadNoUi) {
+				if let Some(footnote) = &footnote {
+					self.footnote_panel(ui, footnote);
+				}
+				self.selection_panel(ui);
 				return;
 			}
 
-			let menu_items = &[
+			let mut menu_items = vec![
+				MenuItem {
+					icon: Icon::FolderPlus,
+					description: "Collections",
+					active: matches!(self.mode, ReaderMode::Collections),
+					action: Action::Collections,
+				},
+				MenuItem {
+					icon: Icon::FileDown,
+					description: "Export annotations",
+					active: false,
+					action: Action::Export,
+				},
 				MenuItem {
 					icon: Icon::Library,
 					description: "Library",
@@ -489,6 +1222,28 @@ impl ViewHandle for ReaderView {
 					action: Action::Exit,
 				},
 			];
+			if can_go_back {
+				menu_items.insert(
+					0,
+					MenuItem {
+						icon: Icon::Undo2,
+						description: "Back",
+						active: false,
+						action: Action::Back,
+					},
+				);
+			}
+			if self.unread.is_some() {
+				menu_items.insert(
+					0,
+					MenuItem {
+						icon: Icon::Sparkles,
+						description: "New chapters",
+						active: false,
+						action: Action::NewChapters,
+					},
+				);
+			}
 			let tool_items = &[
 				None,
 				Some(ToolItem {
@@ -497,13 +1252,30 @@ impl ViewHandle for ReaderView {
 					active: false,
 					action: Action::Previous,
 				}),
-				None,
+				Some(ToolItem {
+					icon: Icon::Bookmark,
+					description: "Bookmark",
+					active: bookmarked,
+					action: Action::Bookmark,
+				}),
 				Some(ToolItem {
 					icon: Icon::ListTree,
 					description: "Chapters",
 					active: matches!(self.mode, ReaderMode::Navigation),
 					action: Action::Chapters,
 				}),
+				Some(ToolItem {
+					icon: Icon::BookMarked,
+					description: "Bookmarks",
+					active: matches!(self.mode, ReaderMode::Bookmarks),
+					action: Action::Bookmarks,
+				}),
+				Some(ToolItem {
+					icon: Icon::Search,
+					description: "Search",
+					active: matches!(self.mode, ReaderMode::Search),
+					action: Action::Search,
+				}),
 				Some(ToolItem {
 					icon: Icon::Cog,
 					description: "Settings",
@@ -513,7 +1285,6 @@ impl ViewHandle for ReaderView {
 					),
 					action: Action::Settings,
 				}),
-				None,
 				Some(ToolItem {
 					icon: Icon::ArrowRight,
 					description: "Next",
@@ -524,7 +1295,7 @@ impl ViewHandle for ReaderView {
 			];
 
 			let top_panel = egui::Panel::top("top").show(ui, |ui| {
-				let response = MainMenuBar::new(self, menu_items)
+				let response = MainMenuBar::new(self, &menu_items)
 					.with_loading(working)
 					.ui(ui);
 
@@ -532,6 +1303,10 @@ impl ViewHandle for ReaderView {
 					ui.separator();
 					ui.add(book_details.clone());
 				}
+				if let Some(status) = &self.export_status {
+					ui.separator();
+					ui.label(RichText::new(status).size(theme::S_SIZE));
+				}
 
 				response
 			});
@@ -548,12 +1323,27 @@ impl ViewHandle for ReaderView {
 				self.active_rects.push(bottom_panel.response.interact_rect);
 			}
 
+			if matches!(self.mode, ReaderMode::Read)
+				&& let Some(footnote) = &footnote
+			{
+				self.footnote_panel(ui, footnote);
+			}
+			if matches!(self.mode, ReaderMode::Read) {
+				self.selection_panel(ui);
+			}
+
 			if matches!(self.mode, ReaderMode::Navigation) {
 				let central_panel = egui::CentralPanel::default().show(ui, |ui| {
 					if is_open {
 						ui.disable();
 					}
 
+					self.page_panel(ui);
+					if !matches!(self.mode, ReaderMode::Navigation) {
+						return;
+					}
+					ui.separator();
+
 					let height = ui.available_height()
 						- (CHAPTER_LIST_SIZE as f32 - 1.0) * ui.spacing().item_spacing.y;
 					let card_height = height / CHAPTER_LIST_SIZE as f32;
@@ -565,9 +1355,13 @@ impl ViewHandle for ReaderView {
 								if ui.add(card.ui()).clicked() {
 									let illustrator =
 										self.illustrator.as_mut().expect("Illustrator not running");
-									let _ = illustrator
-										.goto(card.location)
-										.inspect_err(|err| log::error!("Goto error: {err}"));
+									let result = match &card.fragment {
+										Some(fragment) => illustrator
+											.goto_fragment(card.location.spine, fragment.clone()),
+										None => illustrator.goto(card.location),
+									};
+									let _ =
+										result.inspect_err(|err| log::error!("Goto error: {err}"));
 									untoggle = true;
 								}
 							});
@@ -580,6 +1374,36 @@ impl ViewHandle for ReaderView {
 				if !is_open {
 					self.active_rects.push(central_panel.response.interact_rect);
 				}
+			} else if matches!(self.mode, ReaderMode::Search) {
+				let central_panel = egui::CentralPanel::default().show(ui, |ui| {
+					if is_open {
+						ui.disable();
+					}
+					self.search_panel(ui);
+				});
+				if !is_open {
+					self.active_rects.push(central_panel.response.interact_rect);
+				}
+			} else if matches!(self.mode, ReaderMode::Bookmarks) {
+				let central_panel = egui::CentralPanel::default().show(ui, |ui| {
+					if is_open {
+						ui.disable();
+					}
+					self.bookmarks_panel(ui);
+				});
+				if !is_open {
+					self.active_rects.push(central_panel.response.interact_rect);
+				}
+			} else if matches!(self.mode, ReaderMode::Collections) {
+				let central_panel = egui::CentralPanel::default().show(ui, |ui| {
+					if is_open {
+						ui.disable();
+					}
+					self.collections_panel(ui);
+				});
+				if !is_open {
+					self.active_rects.push(central_panel.response.interact_rect);
+				}
 			} else if matches!(
 				self.mode,
 				ReaderMode::ActionSettings | ReaderMode::ProfileSettings
@@ -612,7 +1436,16 @@ impl ViewHandle for ReaderView {
 
 	fn event(&mut self, event: &AppEvent) -> EventResult {
 		match event {
+			AppEvent::BookContentReady(id, _) if *id == self.book_id => {
+				match self.records.fetch_reading_pace(self.book_id) {
+					Ok(pace) => self.pace = pace,
+					Err(e) => log::warn!("Error fetching reading pace: {e}"),
+				}
+				EventResult::RequestRedraw
+			}
 			AppEvent::BookContentReady(..) => EventResult::RequestRedraw,
+			AppEvent::BookSearchUpdated(id) if *id == self.book_id => EventResult::RequestRedraw,
+			AppEvent::BookPaginated(id) if *id == self.book_id => EventResult::RequestRedraw,
 			AppEvent::KeyUp => {
 				self.on_action(self.state.action_key_up);
 				EventResult::None
@@ -626,20 +1459,44 @@ impl ViewHandle for ReaderView {
 	}
 
 	fn gesture(&mut self, event: &GestureEvent) -> GestureResult {
+		// Sides are mirrored for books with pages progressing right to left
+		let rtl = self
+			.illustrator
+			.as_ref()
+			.is_some_and(|illustrator| illustrator.rtl());
+		let (area_left, area_right, swipe_left, swipe_right) = if rtl {
+			(
+				self.state.action_area_right,
+				self.state.action_area_left,
+				self.state.swipe_right,
+				self.state.swipe_left,
+			)
+		} else {
+			(
+				self.state.action_area_left,
+				self.state.action_area_right,
+				self.state.swipe_left,
+				self.state.swipe_right,
+			)
+		};
 		match event.gesture {
 			Gesture::Tap => {
 				let pos =
 					egui::pos2(event.loc.x as f32, event.loc.y as f32) / self.viewport.scale_factor;
 				if self.active_rects.iter().any(|r| r.contains(pos)) {
 					GestureResult::Unhandled
+				} else if matches!(self.mode, ReaderMode::Read | ReaderMode::ReadNoUi)
+					&& self.tap_page(event.loc.x as f32, event.loc.y as f32)
+				{
+					GestureResult::Consumed
 				} else {
 					let areas =
 						ActiveAreas::new(self.viewport.screen_width, self.viewport.screen_height);
 					if let Some(action) = areas.action(event.loc) {
 						match action {
 							ActiveAreaAction::Main => self.toggle_ui(),
-							ActiveAreaAction::Left => self.on_action(self.state.action_area_left),
-							ActiveAreaAction::Right => self.on_action(self.state.action_area_right),
+							ActiveAreaAction::Left => self.on_action(area_left),
+							ActiveAreaAction::Right => self.on_action(area_right),
 						};
 						GestureResult::Consumed
 					} else {
@@ -647,8 +1504,24 @@ impl ViewHandle for ReaderView {
 					}
 				}
 			}
+			Gesture::Hold if matches!(self.mode, ReaderMode::Read | ReaderMode::ReadNoUi) => {
+				let pos = egui::pos2(event.origin.x as f32, event.origin.y as f32)
+					/ self.viewport.scale_factor;
+				if self.active_rects.iter().any(|r| r.contains(pos)) {
+					return GestureResult::Unhandled;
+				}
+				let at = |x: u32, y: u32| Position {
+					x: x as f32,
+					y: y as f32,
+				};
+				self.select_text(
+					at(event.origin.x, event.origin.y),
+					at(event.loc.x, event.loc.y),
+				);
+				GestureResult::Consumed
+			}
 			Gesture::Swipe(Direction::Right, _) => {
-				self.on_action(self.state.swipe_right);
+				self.on_action(swipe_right);
 				GestureResult::Consumed
 			}
 			Gesture::Swipe(Direction::Down, _) => {
@@ -656,7 +1529,7 @@ impl ViewHandle for ReaderView {
 				GestureResult::Consumed
 			}
 			Gesture::Swipe(Direction::Left, _) => {
-				self.on_action(self.state.swipe_left);
+				self.on_action(swipe_left);
 				GestureResult::Consumed
 			}
 			Gesture::Swipe(Direction::Up, _) => {
@@ -694,6 +1567,12 @@ struct BookDetailsUI {
 	percent_read: u32,
 	page: u32,
 	pages: u32,
+	/// Page and pages of book, once book is paginated
+	book_page: Option<(u32, u32)>,
+	/// Estimated time to finish chapter
+	chapter_left: Option<TimeDelta>,
+	/// Estimated time to finish book
+	book_left: Option<TimeDelta>,
 }
 
 impl egui::Widget for BookDetailsUI {
@@ -703,6 +1582,15 @@ impl egui::Widget for BookDetailsUI {
 			ui.vertical(|ui| {
 				ui.with_layout(egui::Layout::right_to_left(egui::Align::Max), |ui| {
 					ui.label(RichText::new(format!("{}%", self.percent_read)).size(theme::L_SIZE));
+					if let Some((page, pages)) = self.book_page {
+						ui.label(RichText::new(format!("{page} / {pages}")).size(theme::S_SIZE));
+					}
+					if let Some(left) = self.book_left {
+						ui.label(
+							RichText::new(format!("{} left", format_duration(left)))
+								.size(theme::S_SIZE),
+						);
+					}
 					ui.with_layout(egui::Layout::left_to_right(egui::Align::Max), |ui| {
 						ui.label(RichText::new(self.title.as_ref()).size(theme::L_SIZE));
 					});
@@ -711,6 +1599,12 @@ impl egui::Widget for BookDetailsUI {
 					ui.label(
 						RichText::new(format!("{}/{}", self.page, self.pages)).size(theme::S_SIZE),
 					);
+					if let Some(left) = self.chapter_left {
+						ui.label(
+							RichText::new(format!("{} left", format_duration(left)))
+								.size(theme::S_SIZE),
+						);
+					}
 					ui.with_layout(egui::Layout::left_to_right(egui::Align::Max), |ui| {
 						ui.label(RichText::new(self.chapter.as_ref()).size(theme::S_SIZE));
 					});
@@ -779,6 +1673,8 @@ impl egui::Widget for SettingsPanelUi<'_> {
 						Action::Chapters,
 						Action::Settings,
 						Action::Library,
+						Action::Back,
+						Action::Bookmark,
 					];
 					ui.vertical(|ui| {
 						ui.label(RichText::new("").size(theme::M_SIZE));
@@ -928,8 +1824,50 @@ impl egui::Widget for ActionSelectableUi<'_> {
 	}
 }
 
+struct BookmarkCardUi<'a> {
+	bookmark: &'a Bookmark,
+}
+
+impl egui::Widget for BookmarkCardUi<'_> {
+	fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+		let bookmark = self.bookmark;
+
+		egui::Frame::group(ui.style())
+			.show(ui, |ui| {
+				ui.set_min_width(ui.available_width());
+				ui.vertical(|ui| {
+					let chapter = bookmark
+						.chapter
+						.as_deref()
+						.map(String::as_str)
+						.unwrap_or("Unknown chapter");
+					ui.label(RichText::new(chapter).size(theme::M_SIZE));
+					if let Some(excerpt) = &bookmark.excerpt {
+						ui.label(RichText::new(excerpt).size(theme::S_SIZE));
+					}
+					ui.label(
+						RichText::new(format!(
+							"Added {}",
+							bookmark.created_at.format("%e %b %H:%M")
+						))
+						.size(theme::S_SIZE)
+						.weak(),
+					);
+				});
+				ui.interact(
+					ui.min_rect(),
+					ui.id()
+						.with((bookmark.location.spine, bookmark.location.element)),
+					egui::Sense::click(),
+				)
+			})
+			.inner
+	}
+}
+
 pub(crate) struct ChapterCard {
 	location: Location,
+	fragment: Option<String>,
 	title: Arc<String>,
 	active: bool,
 }
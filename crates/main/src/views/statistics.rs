@@ -0,0 +1,372 @@
+use std::fmt::Write;
+
+use chrono::Days;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::TimeDelta;
+use chrono::Utc;
+use egui::Align;
+use egui::CentralPanel;
+use egui::CornerRadius;
+use egui::Layout;
+use egui::Panel;
+use egui::ProgressBar;
+use egui::RichText;
+use lucide_icons::Icon;
+use scribe::BookReadingTime;
+use scribe::DayReading;
+use scribe::RecordKeeper;
+use scribe::RecordKeeperAssistant;
+use scribe::RecordKeeperError;
+
+use crate::AppBell;
+use crate::AppEvent;
+use crate::gestures::Direction;
+use crate::gestures::Gesture;
+use crate::gestures::GestureEvent;
+use crate::renderer::Painter;
+use crate::ui::MainMenuBar;
+use crate::ui::MenuItem;
+use crate::ui::OnAction;
+use crate::ui::ToolBar;
+use crate::ui::ToolItem;
+use crate::ui::theme;
+use crate::views::EventResult;
+use crate::views::GestureResult;
+use crate::views::ViewHandle;
+
+/// Days shown in pages per day
+const READING_DAYS: u32 = 7;
+const BOOK_LIST_SIZE: usize = 5;
+
+/// Duration as hours and minutes, e.g. `2h 05m`
+pub(crate) fn format_duration(time: TimeDelta) -> String {
+	let minutes = time.num_minutes();
+	match (minutes / 60, minutes % 60) {
+		(0, 0) => "<1m".to_string(),
+		(0, m) => format!("{m}m"),
+		(h, m) => format!("{h}h {m:02}m"),
+	}
+}
+
+pub(crate) struct StatisticsView {
+	bell: AppBell,
+	records: RecordKeeperAssistant,
+
+	books: Vec<BookReadingTime>,
+	days: Vec<DayReading>,
+	page: usize,
+	statusline: Option<String>,
+}
+
+impl StatisticsView {
+	pub(crate) fn create(bell: AppBell, records: RecordKeeper) -> Result<Self, RecordKeeperError> {
+		let records = records.assistant()?;
+		let books = records.fetch_reading_time()?;
+		let days = reading_days(&records)?;
+
+		Ok(Self {
+			bell,
+			records,
+
+			books,
+			days,
+			page: 0,
+			statusline: None,
+		})
+	}
+
+	fn pages(&self) -> usize {
+		self.books.len().div_ceil(BOOK_LIST_SIZE).max(1)
+	}
+
+	fn prev_page(&mut self) {
+		self.page = self.page.saturating_sub(1);
+	}
+
+	fn next_page(&mut self) {
+		if self.page + 1 < self.pages() {
+			self.page += 1;
+		}
+	}
+
+	fn refresh(&mut self) {
+		let result = self
+			.records
+			.fetch_reading_time()
+			.and_then(|books| Ok((books, reading_days(&self.records)?)));
+		match result {
+			Ok((books, days)) => {
+				self.books = books;
+				self.days = days;
+				self.page = self.page.min(self.pages() - 1);
+			}
+			Err(e) => log::error!("Error fetching statistics: {e}"),
+		}
+	}
+}
+
+/// Reading of last days, oldest first, days without reading included
+fn reading_days(records: &RecordKeeperAssistant) -> Result<Vec<DayReading>, RecordKeeperError> {
+	let today = Local::now().date_naive();
+	let days = (0..READING_DAYS)
+		.rev()
+		.filter_map(|n| today.checked_sub_days(Days::new(n as u64)))
+		.collect::<Vec<NaiveDate>>();
+	let read = records.fetch_reading_days(Utc::now() - TimeDelta::days(READING_DAYS as i64))?;
+	Ok(days
+		.into_iter()
+		.map(|day| {
+			read.iter()
+				.find(|r| r.day == day)
+				.cloned()
+				.unwrap_or(DayReading {
+					day,
+					pages: 0,
+					time: TimeDelta::zero(),
+				})
+		})
+		.collect())
+}
+
+#[derive(Clone, Copy)]
+enum MenuAction {
+	Exit,
+	OpenLibrary,
+	Refresh,
+}
+
+#[derive(Clone, Copy)]
+enum ToolAction {
+	Prev,
+	Next,
+}
+
+impl OnAction<MenuAction> for StatisticsView {
+	fn on_action(&mut self, action: MenuAction) {
+		match action {
+			MenuAction::Exit => {
+				self.bell.send_event(AppEvent::Exit);
+			}
+			MenuAction::OpenLibrary => {
+				self.bell.send_event(AppEvent::OpenLibrary);
+			}
+			MenuAction::Refresh => self.refresh(),
+		}
+	}
+}
+
+impl OnAction<ToolAction> for StatisticsView {
+	fn on_action(&mut self, action: ToolAction) {
+		match action {
+			ToolAction::Prev => self.prev_page(),
+			ToolAction::Next => self.next_page(),
+		}
+	}
+}
+
+impl ViewHandle for StatisticsView {
+	fn draw(&mut self, painter: Painter<'_>) {
+		painter.draw_ui(|ui| {
+			let menu_items = &[
+				MenuItem {
+					icon: Icon::Library,
+					description: "Library",
+					active: false,
+					action: MenuAction::OpenLibrary,
+				},
+				MenuItem {
+					icon: Icon::RefreshCw,
+					description: "Refresh",
+					active: false,
+					action: MenuAction::Refresh,
+				},
+				MenuItem {
+					icon: Icon::LogOut,
+					description: "Exit",
+					active: false,
+					action: MenuAction::Exit,
+				},
+			];
+			let tool_items = &[
+				None,
+				Some(ToolItem {
+					icon: Icon::ArrowLeft,
+					description: "Previous",
+					active: false,
+					action: ToolAction::Prev,
+				}),
+				None,
+				Some(ToolItem {
+					icon: Icon::ArrowRight,
+					description: "Next",
+					active: false,
+					action: ToolAction::Next,
+				}),
+				None,
+			];
+
+			let mut statusline = self.statusline.take().unwrap_or_default();
+			statusline.clear();
+			let _ = write!(
+				statusline,
+				"Statistics {} / {}",
+				self.page + 1,
+				self.pages()
+			);
+
+			let top_panel = Panel::top("top").show(ui, |ui| {
+				MainMenuBar::new(self, menu_items)
+					.with_status(Some(&statusline))
+					.ui(ui)
+			});
+			let is_open = top_panel.inner.context_menu_opened();
+
+			self.statusline = Some(statusline);
+
+			Panel::bottom("bottom").show(ui, |ui| ToolBar::new(self, tool_items, is_open).ui(ui));
+
+			CentralPanel::default().show(ui, |ui| {
+				if is_open {
+					ui.disable();
+				}
+
+				let total = self
+					.books
+					.iter()
+					.fold(TimeDelta::zero(), |total, b| total + b.time);
+				let total_pages = self.books.iter().map(|b| b.pages).sum::<u32>();
+				ui.label(RichText::new("Reading").size(theme::L_SIZE));
+				ui.label(
+					RichText::new(format!(
+						"{} in {} books, {} pages",
+						format_duration(total),
+						self.books.len(),
+						total_pages
+					))
+					.size(theme::M_SIZE),
+				);
+				ui.separator();
+
+				ui.label(RichText::new("Pages per day").size(theme::L_SIZE));
+				let max_pages = self.days.iter().map(|d| d.pages).max().unwrap_or(0).max(1);
+				for day in &self.days {
+					ui.add(DayReadingUi {
+						day,
+						part: day.pages as f32 / max_pages as f32,
+					});
+				}
+				ui.separator();
+
+				ui.label(RichText::new("Books").size(theme::L_SIZE));
+				if self.books.is_empty() {
+					ui.label(RichText::new("Nothing read yet").size(theme::M_SIZE));
+				}
+				for book in self
+					.books
+					.iter()
+					.skip(self.page * BOOK_LIST_SIZE)
+					.take(BOOK_LIST_SIZE)
+				{
+					ui.add(BookReadingTimeUi { book });
+				}
+			});
+		});
+	}
+
+	fn event(&mut self, _event: &AppEvent) -> EventResult {
+		EventResult::None
+	}
+
+	fn gesture(&mut self, event: &GestureEvent) -> GestureResult {
+		match event.gesture {
+			Gesture::Swipe(Direction::Right, _) => {
+				self.prev_page();
+				GestureResult::Consumed
+			}
+			Gesture::Swipe(Direction::Left, _) => {
+				self.next_page();
+				GestureResult::Consumed
+			}
+			_ => GestureResult::Unhandled,
+		}
+	}
+}
+
+struct DayReadingUi<'a> {
+	day: &'a DayReading,
+	/// Pages of day relative to day with most pages
+	part: f32,
+}
+
+impl egui::Widget for DayReadingUi<'_> {
+	fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+		ui.vertical(|ui| {
+			ui.horizontal(|ui| {
+				ui.set_min_width(ui.available_width());
+				ui.label(
+					RichText::new(self.day.day.format("%a %e %b").to_string()).size(theme::S_SIZE),
+				);
+				ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+					let text = if self.day.pages == 0 {
+						"-".to_string()
+					} else {
+						format!(
+							"{} pages, {}",
+							self.day.pages,
+							format_duration(self.day.time)
+						)
+					};
+					ui.label(RichText::new(text).size(theme::S_SIZE));
+				});
+			});
+			ui.add(
+				ProgressBar::new(self.part)
+					.corner_radius(CornerRadius::ZERO)
+					.fill(theme::SECONDARY_COLOR)
+					.desired_height(3.),
+			);
+		})
+		.response
+	}
+}
+
+struct BookReadingTimeUi<'a> {
+	book: &'a BookReadingTime,
+}
+
+impl egui::Widget for BookReadingTimeUi<'_> {
+	fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+		let book = self.book;
+		ui.vertical(|ui| {
+			ui.horizontal(|ui| {
+				ui.set_min_width(ui.available_width());
+				let title = book
+					.title
+					.as_deref()
+					.map(String::as_str)
+					.unwrap_or("Unknown");
+				ui.label(RichText::new(title).size(theme::M_SIZE));
+				ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+					ui.label(RichText::new(format_duration(book.time)).size(theme::M_SIZE));
+				});
+			});
+			let sessions = match book.sessions {
+				1 => "1 session".to_string(),
+				n => format!("{n} sessions"),
+			};
+			ui.label(
+				RichText::new(format!(
+					"{} pages, {}, last read {}",
+					book.pages,
+					sessions,
+					book.last_read_at
+						.with_timezone(&Local)
+						.format("%e %b %H:%M")
+				))
+				.size(theme::S_SIZE),
+			);
+		})
+		.response
+	}
+}
@@ -0,0 +1,484 @@
+//! Comic book archives converted to epub archives
+//!
+//! Every image of the archive becomes a spine item showing only that image, in
+//! natural order of file names. Metadata is read from `ComicInfo.xml` when present.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+
+use quick_xml::XmlVersion;
+use quick_xml::escape::escape;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use scribe_epub::Creator;
+use scribe_epub::EpubBuilder;
+use scribe_epub::Metadata;
+use scribe_epub::Series;
+use scribe_epub::TextEncoding;
+use zip::ZipArchive;
+
+const COMIC_INFO: &str = "comicinfo.xml";
+
+const STYLESHEET: &str = r#".page { text-align: center; margin: 0; }
+"#;
+
+/// People of ComicInfo, as field and MARC relator code
+const PEOPLE: &[(&str, &str)] = &[
+	("Writer", "aut"),
+	("Penciller", "art"),
+	("Inker", "art"),
+	("Colorist", "clr"),
+	("Letterer", "ill"),
+	("CoverArtist", "cov"),
+	("Editor", "edt"),
+	("Translator", "trl"),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum CbzError {
+	#[error(transparent)]
+	QuickXml(#[from] quick_xml::Error),
+	#[error(transparent)]
+	Encoding(#[from] quick_xml::encoding::EncodingError),
+	#[error(transparent)]
+	Epub(#[from] scribe_epub::EpubError),
+	#[error("at {1}: {0}")]
+	Zip(
+		zip::result::ZipError,
+		&'static std::panic::Location<'static>,
+	),
+	#[error("at {1}: {0}")]
+	Io(io::Error, &'static std::panic::Location<'static>),
+	#[error("No images in archive")]
+	NoImages,
+}
+
+impl From<zip::result::ZipError> for CbzError {
+	#[track_caller]
+	fn from(err: zip::result::ZipError) -> Self {
+		Self::Zip(err, std::panic::Location::caller())
+	}
+}
+
+impl From<io::Error> for CbzError {
+	#[track_caller]
+	fn from(err: io::Error) -> Self {
+		Self::Io(err, std::panic::Location::caller())
+	}
+}
+
+/// Page of `Pages` in ComicInfo
+#[derive(Debug, Default)]
+struct ComicPage {
+	/// Index of image in archive order
+	image: Option<usize>,
+	kind: Option<String>,
+	bookmark: Option<String>,
+}
+
+impl ComicPage {
+	fn read(e: &BytesStart<'_>, reader: &quick_xml::Reader<&[u8]>) -> Result<Self, CbzError> {
+		let mut page = Self::default();
+		for attr in e.attributes() {
+			let attr = attr.map_err(quick_xml::Error::from)?;
+			let value =
+				attr.decoded_and_normalized_value(XmlVersion::Implicit1_0, reader.decoder())?;
+			let value = value.trim();
+			match attr.key.local_name().as_ref() {
+				b"Image" => page.image = value.parse().ok(),
+				b"Type" => page.kind = Some(value.to_string()),
+				b"Bookmark" if !value.is_empty() => page.bookmark = Some(value.to_string()),
+				_ => {}
+			}
+		}
+		Ok(page)
+	}
+}
+
+/// Fields of `ComicInfo.xml`, by element name
+#[derive(Debug, Default)]
+struct ComicInfo {
+	fields: BTreeMap<String, String>,
+	pages: Vec<ComicPage>,
+}
+
+impl ComicInfo {
+	fn parse(text: &str) -> Result<Self, CbzError> {
+		let mut reader = quick_xml::Reader::from_str(text);
+		reader.config_mut().check_end_names = false;
+
+		let mut info = Self::default();
+		let mut depth = 0;
+		let mut field = None;
+		let mut value = String::new();
+		loop {
+			match reader.read_event()? {
+				Event::Start(e) => {
+					depth += 1;
+					if depth == 2 {
+						field = Some(String::from_utf8_lossy(e.local_name().as_ref()).to_string());
+						value.clear();
+					} else if e.local_name().as_ref() == b"Page" {
+						info.pages.push(ComicPage::read(&e, &reader)?);
+					}
+				}
+				Event::Empty(e) if e.local_name().as_ref() == b"Page" => {
+					info.pages.push(ComicPage::read(&e, &reader)?);
+				}
+				Event::End(_) => {
+					if depth == 2
+						&& let Some(field) = field.take()
+						&& !value.trim().is_empty()
+					{
+						info.fields.insert(field, value.trim().to_string());
+					}
+					depth -= 1;
+				}
+				Event::Text(t) if depth == 2 => value.push_str(&t.decode()?),
+				Event::CData(t) if depth == 2 => value.push_str(&t.decode()?),
+				Event::GeneralRef(r) if depth == 2 => {
+					if let Some(c) = r.resolve_char_ref()? {
+						value.push(c);
+					} else if let Some(s) = resolve_predefined_entity(&r.decode()?) {
+						value.push_str(s);
+					}
+				}
+				Event::Eof => break,
+				_ => {}
+			}
+		}
+		Ok(info)
+	}
+
+	fn field(&self, name: &str) -> Option<&str> {
+		self.fields.get(name).map(String::as_str)
+	}
+
+	/// Values of comma separated field
+	fn list(&self, name: &str) -> impl Iterator<Item = &str> {
+		self.field(name)
+			.into_iter()
+			.flat_map(|value| value.split(','))
+			.map(str::trim)
+			.filter(|value| !value.is_empty())
+	}
+
+	fn metadata(&self) -> Metadata {
+		let series = self.field("Series").map(|name| Series {
+			name: name.to_string(),
+			index: self.field("Number").and_then(|n| n.parse().ok()),
+		});
+		let title = self.field("Title").map(str::to_string).or_else(|| {
+			let series = self.field("Series")?;
+			Some(match self.field("Number") {
+				Some(number) => format!("{series} #{number}"),
+				None => series.to_string(),
+			})
+		});
+		let creators = PEOPLE
+			.iter()
+			.flat_map(|(field, role)| {
+				self.list(field).map(|name| Creator {
+					name: name.to_string(),
+					role: Some(role.to_string()),
+					file_as: None,
+				})
+			})
+			.collect::<Vec<_>>();
+		let date = self.field("Year").map(|year| {
+			let part = |name| self.field(name).and_then(|v| v.parse::<u32>().ok());
+			match (part("Month"), part("Day")) {
+				(Some(month), Some(day)) => format!("{year}-{month:02}-{day:02}"),
+				(Some(month), None) => format!("{year}-{month:02}"),
+				_ => year.to_string(),
+			}
+		});
+		Metadata {
+			identifier: self.field("GTIN").map(str::to_string),
+			title,
+			creator: creators.first().map(|c| c.name.clone()),
+			creators,
+			description: self.field("Summary").map(str::to_string),
+			publisher: self.field("Publisher").map(str::to_string),
+			subjects: self
+				.list("Genre")
+				.chain(self.list("Tags"))
+				.map(str::to_string)
+				.collect(),
+			language: self.field("LanguageISO").map(str::to_string),
+			date,
+			series,
+			..Default::default()
+		}
+	}
+
+	fn rtl(&self) -> bool {
+		self.field("Manga") == Some("YesAndRightToLeft")
+	}
+}
+
+/// Convert comic book archive to epub archive, each image one page
+pub fn cbz_to_epub<R: Read + Seek>(reader: R) -> Result<Vec<u8>, CbzError> {
+	let mut archive = ZipArchive::new(reader)?;
+	let mut images = archive
+		.file_names()
+		.filter(|name| !name.starts_with("__MACOSX/"))
+		.filter(|name| !name.rsplit('/').next().is_some_and(|n| n.starts_with('.')))
+		.filter_map(|name| Some((name.to_string(), image_mime(name)?)))
+		.collect::<Vec<_>>();
+	images.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+	if images.is_empty() {
+		return Err(CbzError::NoImages);
+	}
+
+	let info_name = archive
+		.file_names()
+		.find(|name| name.to_lowercase() == COMIC_INFO)
+		.map(str::to_string);
+	let info = match info_name {
+		Some(name) => {
+			let mut bytes = Vec::new();
+			archive.by_name(&name)?.read_to_end(&mut bytes)?;
+			let text = TextEncoding::detect(&bytes).decode(&bytes);
+			ComicInfo::parse(&text).unwrap_or_else(|e| {
+				log::warn!("Failed to parse ComicInfo: {e}");
+				ComicInfo::default()
+			})
+		}
+		None => ComicInfo::default(),
+	};
+
+	let mut epub = EpubBuilder::new(info.metadata());
+	epub.stylesheet(STYLESHEET);
+	if info.rtl() {
+		epub.rtl();
+	}
+	let cover = info
+		.pages
+		.iter()
+		.find(|p| p.kind.as_deref() == Some("FrontCover"))
+		.and_then(|p| p.image)
+		.filter(|i| *i < images.len())
+		.unwrap_or(0);
+	for (i, (name, mime)) in images.iter().enumerate() {
+		let extension = name
+			.rsplit_once('.')
+			.map(|(_, ext)| ext)
+			.unwrap_or_default();
+		let image_href = format!("images/page{i}.{}", extension.to_lowercase());
+		let href = format!("page{i}.xhtml");
+		let body = format!(
+			"<div class=\"page\"><img src=\"{}\" alt=\"{}\"/></div>\n",
+			escape(image_href.as_str()),
+			i + 1
+		);
+		epub.add_document(&href, &(i + 1).to_string(), &body);
+		if let Some(bookmark) = info
+			.pages
+			.iter()
+			.filter(|p| p.image == Some(i))
+			.find_map(|p| p.bookmark.as_deref())
+		{
+			epub.add_toc(0, bookmark, &href);
+		}
+
+		let mut data = Vec::new();
+		archive.by_name(name)?.read_to_end(&mut data)?;
+		epub.add_resource(&image_href, mime, data);
+		if i == cover {
+			epub.cover(&image_href);
+		}
+	}
+	Ok(epub.finish()?)
+}
+
+fn image_mime(name: &str) -> Option<&'static str> {
+	let (_, extension) = name.rsplit_once('.')?;
+	match extension.to_lowercase().as_str() {
+		"jpg" | "jpeg" => Some("image/jpeg"),
+		"png" => Some("image/png"),
+		"gif" => Some("image/gif"),
+		"webp" => Some("image/webp"),
+		"bmp" => Some("image/bmp"),
+		_ => None,
+	}
+}
+
+/// Runs of digits and other characters
+fn chunks(text: &str) -> impl Iterator<Item = (bool, &str)> {
+	let mut rest = text;
+	std::iter::from_fn(move || {
+		let digits = rest.chars().next()?.is_ascii_digit();
+		let end = rest
+			.find(|c: char| c.is_ascii_digit() != digits)
+			.unwrap_or(rest.len());
+		let (chunk, tail) = rest.split_at(end);
+		rest = tail;
+		Some((digits, chunk))
+	})
+}
+
+/// Compare names with numbers by value, so `page2` sorts before `page10`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+	let mut a = chunks(a);
+	let mut b = chunks(b);
+	loop {
+		let ord = match (a.next(), b.next()) {
+			(None, None) => return Ordering::Equal,
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(Some((true, x)), Some((true, y))) => {
+				let x = x.trim_start_matches('0');
+				let y = y.trim_start_matches('0');
+				x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+			}
+			(Some((_, x)), Some((_, y))) => x.to_lowercase().cmp(&y.to_lowercase()),
+		};
+		if ord != Ordering::Equal {
+			return ord;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::io::Read;
+	use std::io::Write;
+
+	use scribe_epub::EpubMetadata;
+	use zip::ZipArchive;
+	use zip::ZipWriter;
+	use zip::write::SimpleFileOptions;
+
+	use crate::cbz_to_epub;
+	use crate::natural_cmp;
+
+	const COMIC_INFO: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Series>Moon &amp; Sun</Series>
+  <Number>3</Number>
+  <Summary>Night falls.</Summary>
+  <Year>2020</Year>
+  <Month>4</Month>
+  <Writer>Aiko Tanaka, Ken Mori</Writer>
+  <Penciller>Ken Mori</Penciller>
+  <Genre>Fantasy, Drama</Genre>
+  <LanguageISO>ja</LanguageISO>
+  <Manga>YesAndRightToLeft</Manga>
+  <Pages>
+    <Page Image="0" Type="FrontCover"/>
+    <Page Image="1" Bookmark="Chapter 1"/>
+    <Page Image="3" Bookmark="Chapter 2"/>
+  </Pages>
+</ComicInfo>
+"#;
+
+	fn comic() -> Vec<u8> {
+		let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+		let options = SimpleFileOptions::default();
+		for (name, data) in [
+			("ComicInfo.xml", COMIC_INFO.as_bytes()),
+			("pages/page10.jpg", b"ten"),
+			("pages/page2.jpg", b"two"),
+			("pages/page1.jpg", b"one"),
+			("pages/page3.PNG", b"three"),
+			("__MACOSX/pages/._page1.jpg", b"fork"),
+			("notes.txt", b"notes"),
+		] {
+			zip.start_file(name, options).expect("Failed to start file");
+			zip.write_all(data).expect("Failed to write file");
+		}
+		zip.finish().expect("Failed to finish zip").into_inner()
+	}
+
+	#[test]
+	fn test_cbz_to_epub() {
+		let epub = cbz_to_epub(Cursor::new(comic())).expect("Failed to convert");
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		let m = &package.metadata;
+		assert_eq!(m.title.as_deref(), Some("Moon & Sun #3"), "Title missmatch");
+		assert_eq!(
+			m.creator.as_deref(),
+			Some("Aiko Tanaka"),
+			"Creator missmatch"
+		);
+		let creators = m
+			.creators
+			.iter()
+			.map(|c| (c.name.as_str(), c.role.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			creators,
+			vec![
+				("Aiko Tanaka", Some("aut")),
+				("Ken Mori", Some("aut")),
+				("Ken Mori", Some("art")),
+			],
+			"Creators missmatch"
+		);
+		assert_eq!(m.subjects, vec!["Fantasy", "Drama"], "Subjects missmatch");
+		assert_eq!(m.date.as_deref(), Some("2020-04"), "Date missmatch");
+		let series = m.series.as_ref().expect("Missing series");
+		assert_eq!(series.name, "Moon & Sun", "Series missmatch");
+		assert_eq!(series.index, Some(3.0), "Series index missmatch");
+		assert!(package.rtl, "Expected right to left");
+
+		assert_eq!(package.spine.len(), 4, "Spine missmatch");
+		let titles = navigation
+			.nav_points
+			.iter()
+			.map(|p| (p.title.as_str(), p.spine))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			titles,
+			vec![("Chapter 1", Some(1)), ("Chapter 2", Some(3))],
+			"Navigation missmatch"
+		);
+
+		let cover = m
+			.cover
+			.as_ref()
+			.and_then(|id| package.manifest.get(id))
+			.expect("Missing cover");
+		assert_eq!(cover.mime, "image/jpeg", "Cover mime missmatch");
+		let cover_path = cover.as_path().to_path_buf();
+		let mut data = Vec::new();
+		archive
+			.by_path(&cover_path)
+			.expect("Missing cover file")
+			.read_to_end(&mut data)
+			.expect("Failed to read cover");
+		assert_eq!(data, b"one", "Cover data missmatch");
+
+		let mut page = String::new();
+		archive
+			.by_path("page3.xhtml")
+			.expect("Missing page")
+			.read_to_string(&mut page)
+			.expect("Failed to read page");
+		assert!(
+			page.contains(r#"<img src="images/page3.jpg" alt="4"/>"#),
+			"Image missing in {page}"
+		);
+	}
+
+	#[test]
+	fn test_natural_cmp() {
+		let mut names = vec!["p10.jpg", "P2.jpg", "p1b.jpg", "p01.jpg", "a.jpg"];
+		names.sort_by(|a, b| natural_cmp(a, b));
+		assert_eq!(
+			names,
+			vec!["a.jpg", "p01.jpg", "p1b.jpg", "P2.jpg", "p10.jpg"],
+			"Order missmatch"
+		);
+	}
+}
@@ -0,0 +1,360 @@
+use std::io;
+use std::io::Write;
+
+use quick_xml::escape::escape;
+use zip::CompressionMethod;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::Creator;
+use crate::EPUB_CONTAINER_PATH;
+use crate::EpubError;
+use crate::Metadata;
+
+const PACKAGE_PATH: &str = "content.opf";
+const NAV_PATH: &str = "nav.xhtml";
+const STYLESHEET_PATH: &str = "style.css";
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+struct Resource {
+	href: String,
+	mime: String,
+	data: Vec<u8>,
+}
+
+/// Table of contents entry, level 0 is top level
+struct TocEntry {
+	level: usize,
+	title: String,
+	href: String,
+}
+
+/// Epub archive built in memory, to read other book formats as epub
+///
+/// Spine follows the order documents are added in.
+pub struct EpubBuilder {
+	metadata: Metadata,
+	stylesheet: Option<String>,
+	documents: Vec<Resource>,
+	resources: Vec<Resource>,
+	cover: Option<String>,
+	toc: Vec<TocEntry>,
+	rtl: bool,
+}
+
+impl EpubBuilder {
+	/// Cover and navigation of metadata are ignored, they are set by builder
+	pub fn new(metadata: Metadata) -> Self {
+		Self {
+			metadata,
+			stylesheet: None,
+			documents: Vec::new(),
+			resources: Vec::new(),
+			cover: None,
+			toc: Vec::new(),
+			rtl: false,
+		}
+	}
+
+	/// Stylesheet linked from every document
+	pub fn stylesheet(&mut self, css: &str) {
+		self.stylesheet = Some(css.to_string());
+	}
+
+	/// Add xhtml body content as document at href
+	pub fn add_document(&mut self, href: &str, title: &str, body: &str) {
+		let lang = self
+			.metadata
+			.language
+			.as_deref()
+			.map(|lang| format!(" xml:lang=\"{0}\" lang=\"{0}\"", escape(lang)))
+			.unwrap_or_default();
+		let mut content = format!(
+			concat!(
+				"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+				"<!DOCTYPE html>\n",
+				"<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\"{}>\n",
+				"<head>\n<title>{}</title>\n",
+			),
+			lang,
+			escape(title),
+		);
+		if self.stylesheet.is_some() {
+			content.push_str(&format!(
+				"<link rel=\"stylesheet\" type=\"text/css\" href=\"{STYLESHEET_PATH}\"/>\n"
+			));
+		}
+		content.push_str("</head>\n<body>\n");
+		content.push_str(body);
+		content.push_str("</body>\n</html>\n");
+		self.documents.push(Resource {
+			href: href.to_string(),
+			mime: "application/xhtml+xml".to_string(),
+			data: content.into_bytes(),
+		});
+	}
+
+	/// Add complete xhtml document at href as is, stylesheet is not linked
+	pub fn add_xhtml(&mut self, href: &str, content: Vec<u8>) {
+		self.documents.push(Resource {
+			href: href.to_string(),
+			mime: "application/xhtml+xml".to_string(),
+			data: content,
+		});
+	}
+
+	/// Add resource referenced by documents, e.g. an image
+	pub fn add_resource(&mut self, href: &str, mime: &str, data: Vec<u8>) {
+		self.resources.push(Resource {
+			href: href.to_string(),
+			mime: mime.to_string(),
+			data,
+		});
+	}
+
+	/// Use resource at href as cover image
+	pub fn cover(&mut self, href: &str) {
+		self.cover = Some(href.to_string());
+	}
+
+	/// Pages progress right to left, e.g. manga
+	pub fn rtl(&mut self) {
+		self.rtl = true;
+	}
+
+	/// Add table of contents entry, href may have a fragment
+	///
+	/// Without entries, every document is listed by its position.
+	pub fn add_toc(&mut self, level: usize, title: &str, href: &str) {
+		self.toc.push(TocEntry {
+			level,
+			title: title.to_string(),
+			href: href.to_string(),
+		});
+	}
+
+	pub fn finish(self) -> Result<Vec<u8>, EpubError> {
+		let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+		// Archive only lives in memory, content is kept uncompressed
+		let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+		zip.start_file("mimetype", options)?;
+		zip.write_all(b"application/epub+zip")?;
+		zip.start_file(EPUB_CONTAINER_PATH, options)?;
+		zip.write_all(CONTAINER.as_bytes())?;
+		zip.start_file(PACKAGE_PATH, options)?;
+		zip.write_all(self.package().as_bytes())?;
+		zip.start_file(NAV_PATH, options)?;
+		zip.write_all(self.nav().as_bytes())?;
+		if let Some(css) = &self.stylesheet {
+			zip.start_file(STYLESHEET_PATH, options)?;
+			zip.write_all(css.as_bytes())?;
+		}
+		for resource in self.documents.iter().chain(&self.resources) {
+			zip.start_file(resource.href.as_str(), options)?;
+			zip.write_all(&resource.data)?;
+		}
+
+		Ok(zip.finish()?.into_inner())
+	}
+
+	fn package(&self) -> String {
+		let m = &self.metadata;
+		let mut out = String::from(concat!(
+			"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+			"<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n",
+			"<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+		));
+		let mut metas = Vec::new();
+		let mut dc = |name: &str, attrs: &str, value: &str| {
+			out.push_str(&format!(
+				"<dc:{name}{attrs}>{}</dc:{name}>\n",
+				escape(value)
+			));
+		};
+
+		if let Some(identifier) = &m.identifier {
+			dc("identifier", " id=\"book-id\"", identifier);
+		}
+		if let Some(title) = &m.title {
+			dc("title", "", title);
+		}
+		let people = m
+			.creators
+			.iter()
+			.map(|c| ("creator", c))
+			.chain(m.contributors.iter().map(|c| ("contributor", c)));
+		for (
+			i,
+			(
+				element,
+				Creator {
+					name,
+					role,
+					file_as,
+				},
+			),
+		) in people.enumerate()
+		{
+			dc(element, &format!(" id=\"person{i}\""), name);
+			if let Some(role) = role {
+				metas.push(format!(
+					"<meta refines=\"#person{i}\" property=\"role\" scheme=\"marc:relators\">{}</meta>\n",
+					escape(role.as_str())
+				));
+			}
+			if let Some(file_as) = file_as {
+				metas.push(format!(
+					"<meta refines=\"#person{i}\" property=\"file-as\">{}</meta>\n",
+					escape(file_as.as_str())
+				));
+			}
+		}
+		if let Some(description) = &m.description {
+			dc("description", "", description);
+		}
+		if let Some(publisher) = &m.publisher {
+			dc("publisher", "", publisher);
+		}
+		for subject in &m.subjects {
+			dc("subject", "", subject);
+		}
+		if let Some(language) = &m.language {
+			dc("language", "", language);
+		}
+		if let Some(date) = &m.date {
+			dc("date", "", date);
+		}
+		if let Some(rights) = &m.rights {
+			dc("rights", "", rights);
+		}
+		if let Some(series) = &m.series {
+			metas.push(format!(
+				"<meta property=\"belongs-to-collection\" id=\"series\">{}</meta>\n",
+				escape(series.name.as_str())
+			));
+			metas.push(
+				"<meta refines=\"#series\" property=\"collection-type\">series</meta>\n"
+					.to_string(),
+			);
+			if let Some(index) = series.index {
+				metas.push(format!(
+					"<meta refines=\"#series\" property=\"group-position\">{index}</meta>\n"
+				));
+			}
+		}
+		for meta in metas {
+			out.push_str(&meta);
+		}
+		let cover = self
+			.cover
+			.as_ref()
+			.and_then(|href| self.resources.iter().position(|r| r.href == *href));
+		if let Some(index) = cover {
+			out.push_str(&format!(
+				"<meta name=\"cover\" content=\"resource{index}\"/>\n"
+			));
+		}
+		out.push_str("</metadata>\n<manifest>\n");
+
+		out.push_str(&format!(
+			"<item id=\"nav\" href=\"{NAV_PATH}\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n"
+		));
+		if self.stylesheet.is_some() {
+			out.push_str(&format!(
+				"<item id=\"style\" href=\"{STYLESHEET_PATH}\" media-type=\"text/css\"/>\n"
+			));
+		}
+		for (i, doc) in self.documents.iter().enumerate() {
+			out.push_str(&format!(
+				"<item id=\"document{i}\" href=\"{}\" media-type=\"{}\"/>\n",
+				escape(doc.href.as_str()),
+				doc.mime
+			));
+		}
+		for (i, resource) in self.resources.iter().enumerate() {
+			let properties = if cover == Some(i) {
+				" properties=\"cover-image\""
+			} else {
+				""
+			};
+			out.push_str(&format!(
+				"<item id=\"resource{i}\" href=\"{}\" media-type=\"{}\"{properties}/>\n",
+				escape(resource.href.as_str()),
+				escape(resource.mime.as_str())
+			));
+		}
+		out.push_str(if self.rtl {
+			"</manifest>\n<spine page-progression-direction=\"rtl\">\n"
+		} else {
+			"</manifest>\n<spine>\n"
+		});
+		for i in 0..self.documents.len() {
+			out.push_str(&format!("<itemref idref=\"document{i}\"/>\n"));
+		}
+		out.push_str("</spine>\n</package>\n");
+		out
+	}
+
+	fn nav(&self) -> String {
+		let fallback = self
+			.documents
+			.iter()
+			.enumerate()
+			.map(|(i, doc)| TocEntry {
+				level: 0,
+				title: (i + 1).to_string(),
+				href: doc.href.clone(),
+			})
+			.collect::<Vec<_>>();
+		let entries = if self.toc.is_empty() {
+			&fallback
+		} else {
+			&self.toc
+		};
+
+		let mut out = String::from(concat!(
+			"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+			"<!DOCTYPE html>\n",
+			"<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n",
+			"<head>\n<title>Contents</title>\n</head>\n<body>\n",
+			"<nav epub:type=\"toc\">\n<ol>\n",
+		));
+		let mut level = 0;
+		for (i, entry) in entries.iter().enumerate() {
+			if i > 0 {
+				// Levels can only be entered one at a time
+				let depth = entry.level.min(level + 1);
+				if depth > level {
+					out.push_str("\n<ol>\n");
+				} else {
+					out.push_str("</li>\n");
+					for _ in depth..level {
+						out.push_str("</ol>\n</li>\n");
+					}
+				}
+				level = depth;
+			}
+			out.push_str(&format!(
+				"<li><a href=\"{}\">{}</a>",
+				escape(entry.href.as_str()),
+				escape(entry.title.as_str())
+			));
+		}
+		if !entries.is_empty() {
+			out.push_str("</li>\n");
+			for _ in 0..level {
+				out.push_str("</ol>\n</li>\n");
+			}
+		}
+		out.push_str("</ol>\n</nav>\n</body>\n</html>\n");
+		out
+	}
+}
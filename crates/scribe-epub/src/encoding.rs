@@ -0,0 +1,113 @@
+/// Characters of windows-1251 from 0x80 to 0xbf, 0x98 is undefined
+const WINDOWS_1251_HIGH: [char; 64] = [
+	'Ђ', 'Ѓ', '‚', 'ѓ', '„', '…', '†', '‡', '€', '‰', 'Љ', '‹', 'Њ', 'Ќ', 'Ћ', 'Џ', //
+	'ђ', '‘', '’', '“', '”', '•', '–', '—', '\u{fffd}', '™', 'љ', '›', 'њ', 'ќ', 'ћ', 'џ', //
+	'\u{a0}', 'Ў', 'ў', 'Ј', '¤', 'Ґ', '¦', '§', 'Ё', '©', 'Є', '«', '¬', '\u{ad}', '®',
+	'Ї', //
+	'°', '±', 'І', 'і', 'ґ', 'µ', '¶', '·', 'ё', '№', 'є', '»', 'ј', 'Ѕ', 'ѕ', 'ї', //
+];
+
+/// Characters of windows-1252 from 0x80 to 0x9f, rest is latin-1
+const WINDOWS_1252_HIGH: [char; 32] = [
+	'€', '\u{fffd}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{fffd}', 'Ž',
+	'\u{fffd}', //
+	'\u{fffd}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{fffd}', 'ž',
+	'Ÿ', //
+];
+
+/// Text encoding of book content converted to epub
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+	Utf8,
+	Utf16Le,
+	Utf16Be,
+	Windows1251,
+	/// Also used for latin-1, as browsers do
+	Windows1252,
+}
+
+impl TextEncoding {
+	/// Encoding by label, e.g. of xml declaration
+	pub fn from_label(label: &str) -> Option<Self> {
+		match label.trim().to_ascii_lowercase().as_str() {
+			"utf-8" | "utf8" => Some(Self::Utf8),
+			"utf-16le" => Some(Self::Utf16Le),
+			"utf-16" | "utf-16be" => Some(Self::Utf16Be),
+			"windows-1251" | "cp1251" => Some(Self::Windows1251),
+			"windows-1252" | "cp1252" | "iso-8859-1" | "latin1" | "us-ascii" | "ascii" => {
+				Some(Self::Windows1252)
+			}
+			_ => None,
+		}
+	}
+
+	/// Guess encoding of text without declared encoding
+	///
+	/// Byte order marks and valid utf-8 are trusted. Otherwise text with more
+	/// high bytes than ascii letters is taken as cyrillic, as in windows-1251
+	/// text all letters are high bytes while latin text only has a few accented letters.
+	pub fn detect(bytes: &[u8]) -> Self {
+		if bytes.starts_with(b"\xef\xbb\xbf") {
+			return Self::Utf8;
+		} else if bytes.starts_with(b"\xff\xfe") {
+			return Self::Utf16Le;
+		} else if bytes.starts_with(b"\xfe\xff") {
+			return Self::Utf16Be;
+		} else if std::str::from_utf8(bytes).is_ok() {
+			return Self::Utf8;
+		}
+		let high = bytes.iter().filter(|b| **b >= 0x80).count();
+		let letters = bytes.iter().filter(|b| b.is_ascii_alphabetic()).count();
+		if high > letters {
+			Self::Windows1251
+		} else {
+			Self::Windows1252
+		}
+	}
+
+	/// Decode text, byte order mark is dropped and invalid sequences replaced
+	pub fn decode(self, bytes: &[u8]) -> String {
+		match self {
+			Self::Utf8 => {
+				let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+				String::from_utf8_lossy(bytes).into_owned()
+			}
+			Self::Utf16Le | Self::Utf16Be => {
+				// Byte order mark takes precedence over label
+				let (le, bytes) = if let Some(bytes) = bytes.strip_prefix(b"\xff\xfe") {
+					(true, bytes)
+				} else if let Some(bytes) = bytes.strip_prefix(b"\xfe\xff") {
+					(false, bytes)
+				} else {
+					(self == Self::Utf16Le, bytes)
+				};
+				let units = bytes.chunks_exact(2).map(|c| {
+					if le {
+						u16::from_le_bytes([c[0], c[1]])
+					} else {
+						u16::from_be_bytes([c[0], c[1]])
+					}
+				});
+				char::decode_utf16(units)
+					.map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+					.collect()
+			}
+			Self::Windows1251 => bytes
+				.iter()
+				.map(|&b| match b {
+					0..0x80 => b as char,
+					0x80..0xc0 => WINDOWS_1251_HIGH[(b - 0x80) as usize],
+					_ => char::from_u32(0x410 + (b - 0xc0) as u32)
+						.unwrap_or(char::REPLACEMENT_CHARACTER),
+				})
+				.collect(),
+			Self::Windows1252 => bytes
+				.iter()
+				.map(|&b| match b {
+					0x80..0xa0 => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+					_ => b as char,
+				})
+				.collect(),
+		}
+	}
+}
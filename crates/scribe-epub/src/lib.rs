@@ -1,3 +1,6 @@
+mod builder;
+mod encoding;
+
 use std::borrow::Borrow;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
@@ -11,11 +14,15 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use quick_xml::XmlVersion;
+use quick_xml::encoding::Decoder;
 use quick_xml::escape::unescape;
 use quick_xml::events::BytesStart;
 use quick_xml::name::QName;
 use zip::ZipArchive;
 
+pub use builder::EpubBuilder;
+pub use encoding::TextEncoding;
+
 pub const EPUB_CONTAINER_PATH: &str = "META-INF/container.xml";
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
@@ -65,14 +72,39 @@ pub struct Metadata {
 
 	/// Creator of book
 	///
-	/// Maps to `dc:creator`.
+	/// Maps to first `dc:creator` with role `aut`, or first `dc:creator`.
 	pub creator: Option<String>,
 
+	/// All creators of book in document order
+	///
+	/// Maps to `dc:creator`.
+	pub creators: Vec<Creator>,
+
+	/// Contributors to book, e.g. editors and illustrators
+	///
+	/// Maps to `dc:contributor`.
+	pub contributors: Vec<Creator>,
+
+	/// Description of book, may contain markup
+	///
+	/// Maps to `dc:description`.
+	pub description: Option<String>,
+
+	/// Series book belongs to
+	///
+	/// Maps to `belongs-to-collection` meta, or `calibre:series` meta.
+	pub series: Option<Series>,
+
 	/// Publisher of book
 	///
 	/// Maps to `dc:publisher`.
 	pub publisher: Option<String>,
 
+	/// Subjects or keywords of book
+	///
+	/// Maps to `dc:subject`, may occur multiple times.
+	pub subjects: Vec<String>,
+
 	/// Language code
 	///
 	/// Maps to `dc:language`.
@@ -99,6 +131,29 @@ pub struct Metadata {
 	pub navigation: Option<ResourceId>,
 }
 
+/// Creator or contributor of book
+#[derive(Debug, Clone, PartialEq)]
+pub struct Creator {
+	pub name: String,
+	/// MARC relator code, e.g. `aut` or `ill`
+	///
+	/// Maps to `opf:role` or refining `role` meta.
+	pub role: Option<String>,
+	/// Name used for sorting, e.g. `Tolkien, J. R. R.`
+	///
+	/// Maps to `opf:file-as` or refining `file-as` meta.
+	pub file_as: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+	pub name: String,
+	/// Position of book in series
+	///
+	/// Maps to refining `group-position` meta or `calibre:series_index` meta.
+	pub index: Option<f64>,
+}
+
 #[derive(Debug)]
 pub struct ResourceItem {
 	pub id: ResourceId,
@@ -124,6 +179,8 @@ pub enum EpubError {
 	),
 	#[error("No epub package root file in zip")]
 	NoEpubRootFile,
+	#[error("at {1}: {0}")]
+	Io(io::Error, &'static std::panic::Location<'static>),
 }
 
 impl From<zip::result::ZipError> for EpubError {
@@ -133,6 +190,13 @@ impl From<zip::result::ZipError> for EpubError {
 	}
 }
 
+impl From<io::Error> for EpubError {
+	#[track_caller]
+	fn from(err: io::Error) -> Self {
+		Self::Io(err, std::panic::Location::caller())
+	}
+}
+
 pub struct EpubMetadata<'a, R> {
 	archive: &'a mut ZipArchive<R>,
 	package: Option<Arc<Package>>,
@@ -183,6 +247,7 @@ impl<'a, R: io::Read + io::Seek> EpubMetadata<'a, R> {
 				.enumerate()
 				.map(|(index, id)| NavPoint {
 					idref: id.clone(),
+					fragment: None,
 					title: id.clone().into_inner(),
 					parent: None,
 					spine: Some(index as u32),
@@ -321,6 +386,10 @@ pub struct Package {
 	pub metadata: Metadata,
 	pub manifest: BTreeMap<ResourceId, ResourceItem>,
 	pub spine: Vec<ResourceId>,
+	/// Pages progress right to left, e.g. manga
+	///
+	/// Maps to `page-progression-direction` of spine.
+	pub rtl: bool,
 }
 
 impl Package {
@@ -347,7 +416,10 @@ enum PackageElement {
 	DcIdentifier,
 	DcTitle,
 	DcCreator,
+	DcContributor,
+	DcDescription,
 	DcPublisher,
+	DcSubject,
 	DcLanguage,
 	DcDate,
 	DcRights,
@@ -376,7 +448,10 @@ impl PackageElement {
 				b"identifier" => Self::DcIdentifier,
 				b"title" => Self::DcTitle,
 				b"creator" => Self::DcCreator,
+				b"contributor" => Self::DcContributor,
+				b"description" => Self::DcDescription,
 				b"publisher" => Self::DcPublisher,
+				b"subject" => Self::DcSubject,
 				b"language" => Self::DcLanguage,
 				b"date" => Self::DcDate,
 				b"rights" => Self::DcRights,
@@ -388,6 +463,113 @@ impl PackageElement {
 	}
 }
 
+/// Attributes of metadata elements
+#[derive(Debug, Default)]
+struct MetaAttributes {
+	id: Option<String>,
+	name: Option<String>,
+	content: Option<String>,
+	property: Option<String>,
+	refines: Option<String>,
+	role: Option<String>,
+	file_as: Option<String>,
+}
+
+impl MetaAttributes {
+	fn read(e: &BytesStart<'_>, version: XmlVersion, decoder: Decoder) -> Self {
+		let mut attrs = Self::default();
+		for attr in e.attributes() {
+			let Ok(attr) = attr.inspect_err(|e| log::warn!("Attr error: {e}")) else {
+				continue;
+			};
+			let Ok(value) = attr
+				.decoded_and_normalized_value(version, decoder)
+				.inspect_err(|e| log::warn!("Attr value decode error: {e}"))
+			else {
+				continue;
+			};
+			let field = match attr.key.local_name().as_ref() {
+				b"id" => &mut attrs.id,
+				b"name" => &mut attrs.name,
+				b"content" => &mut attrs.content,
+				b"property" => &mut attrs.property,
+				b"refines" => &mut attrs.refines,
+				b"role" => &mut attrs.role,
+				b"file-as" => &mut attrs.file_as,
+				_ => continue,
+			};
+			*field = Some(value.trim().to_string());
+		}
+		attrs
+	}
+}
+
+/// Apply refining metas of EPUB 3 to creators and pick series.
+///
+/// Calibre series is used when no collection is a series.
+fn refine_metadata(
+	metadata: &mut Metadata,
+	creator_ids: &BTreeMap<String, (bool, usize)>,
+	metas: Vec<(MetaAttributes, String)>,
+	calibre_series: Option<Series>,
+) {
+	let mut collections = Vec::new();
+	for (attrs, value) in &metas {
+		if attrs.refines.is_none() && attrs.property.as_deref() == Some("belongs-to-collection") {
+			collections.push((attrs.id.as_deref(), value, None, None));
+		}
+	}
+
+	for (attrs, value) in &metas {
+		let (Some(refines), Some(property)) = (attrs.refines.as_deref(), attrs.property.as_deref())
+		else {
+			continue;
+		};
+		let refines = refines.trim_start_matches('#');
+		if let Some((contributor, index)) = creator_ids.get(refines) {
+			let creators = if *contributor {
+				&mut metadata.contributors
+			} else {
+				&mut metadata.creators
+			};
+			let Some(creator) = creators.get_mut(*index) else {
+				continue;
+			};
+			match property {
+				"role" => creator.role = Some(value.clone()),
+				"file-as" => creator.file_as = Some(value.clone()),
+				_ => {}
+			}
+		} else if let Some((_, _, kind, position)) = collections
+			.iter_mut()
+			.find(|(id, ..)| id.is_some_and(|id| id == refines))
+		{
+			match property {
+				"collection-type" => *kind = Some(value.as_str()),
+				"group-position" => *position = value.parse::<f64>().ok(),
+				_ => {}
+			}
+		}
+	}
+
+	let series = collections
+		.iter()
+		.find(|(_, _, kind, _)| *kind == Some("series"))
+		.or_else(|| collections.iter().find(|(_, _, kind, _)| kind.is_none()))
+		.map(|(_, name, _, index)| Series {
+			name: name.to_string(),
+			index: *index,
+		});
+	metadata.series = series.or(calibre_series);
+
+	metadata.creator = metadata
+		.creators
+		.iter()
+		.find(|c| c.role.as_deref() == Some("aut"))
+		.or(metadata.creators.first())
+		.map(|c| c.name.clone());
+}
+
 pub fn parse_package<R: BufRead>(
 	package_root: &Path,
 	mut reader: quick_xml::Reader<R>,
@@ -403,6 +585,12 @@ pub fn parse_package<R: BufRead>(
 	let mut metadata = Metadata::default();
 	let mut resources = BTreeMap::new();
 	let mut spine = Vec::new();
+	let mut rtl = false;
+
+	let mut creator_ids = BTreeMap::new();
+	let mut metas = Vec::new();
+	let mut calibre_series: Option<Series> = None;
+	let mut calibre_series_index = None;
 
 	let mut buf = Vec::new();
 	let mut txt_buf = Vec::new();
@@ -426,11 +614,61 @@ pub fn parse_package<R: BufRead>(
 				let field = match el {
 					PackageElement::DcIdentifier => Some(&mut metadata.identifier),
 					PackageElement::DcTitle => Some(&mut metadata.title),
-					PackageElement::DcCreator => Some(&mut metadata.creator),
+					PackageElement::DcDescription => Some(&mut metadata.description),
+					PackageElement::DcCreator | PackageElement::DcContributor => {
+						let attrs = MetaAttributes::read(&e, version, reader.decoder());
+						let value = reader.read_text_into(e.name(), &mut txt_buf)?.decode()?;
+						let contributor = matches!(el, PackageElement::DcContributor);
+						let creators = if contributor {
+							&mut metadata.contributors
+						} else {
+							&mut metadata.creators
+						};
+						if let Some(id) = attrs.id {
+							creator_ids.insert(id, (contributor, creators.len()));
+						}
+						creators.push(Creator {
+							name: unescape(&value)?.trim().to_string(),
+							role: attrs.role,
+							file_as: attrs.file_as,
+						});
+						None
+					}
+					PackageElement::Meta
+						if path.iter().any(|el| matches!(el, PackageElement::Metadata)) =>
+					{
+						let attrs = MetaAttributes::read(&e, version, reader.decoder());
+						if attrs.property.is_some() {
+							let value = reader.read_text_into(e.name(), &mut txt_buf)?.decode()?;
+							let value = unescape(&value)?.trim().to_string();
+							metas.push((attrs, value));
+						} else {
+							path.push(el);
+						}
+						None
+					}
 					PackageElement::DcPublisher => Some(&mut metadata.publisher),
 					PackageElement::DcLanguage => Some(&mut metadata.language),
 					PackageElement::DcDate => Some(&mut metadata.date),
 					PackageElement::DcRights => Some(&mut metadata.rights),
+					PackageElement::DcSubject => {
+						let value = reader.read_text_into(e.name(), &mut txt_buf)?.decode()?;
+						let value = unescape(&value)?;
+						let value = value.trim();
+						if !value.is_empty() {
+							metadata.subjects.push(value.to_string());
+						}
+						None
+					}
+					PackageElement::Spine => {
+						rtl = e
+							.try_get_attribute("page-progression-direction")
+							.ok()
+							.flatten()
+							.is_some_and(|attr| attr.value.as_ref() == b"rtl");
+						path.push(el);
+						None
+					}
 					_ => {
 						path.push(el);
 						None
@@ -453,6 +691,19 @@ pub fn parse_package<R: BufRead>(
 				let el = PackageElement::from(e.name());
 
 				if matches!(el, PackageElement::Meta) {
+					let attrs = MetaAttributes::read(&e, version, reader.decoder());
+					match (attrs.name.as_deref(), attrs.content) {
+						(Some("calibre:series"), Some(name)) if !name.is_empty() => {
+							calibre_series = Some(Series { name, index: None });
+							continue;
+						}
+						(Some("calibre:series_index"), Some(index)) => {
+							calibre_series_index = index.parse::<f64>().ok();
+							continue;
+						}
+						_ => {}
+					}
+
 					let is_cover = e.attributes().any(|attr| {
 						attr.inspect_err(|e| log::warn!("Meta attr error: {e}"))
 							.is_ok_and(|attr| {
@@ -616,17 +867,25 @@ pub fn parse_package<R: BufRead>(
 	}
 	debug_assert!(path.is_empty(), "Path should be empty");
 
+	if let Some(series) = calibre_series.as_mut() {
+		series.index = calibre_series_index;
+	}
+	refine_metadata(&mut metadata, &creator_ids, metas, calibre_series);
+
 	Ok(Package {
 		package_root,
 		metadata,
 		manifest: resources,
 		spine,
+		rtl,
 	})
 }
 
 #[derive(Debug)]
 pub struct NavPoint {
 	pub idref: ResourceId,
+	/// Element id in resource, from `#fragment` of href
+	pub fragment: Option<String>,
 	pub title: Arc<String>,
 	pub parent: Option<ResourceId>,
 	pub spine: Option<u32>,
@@ -640,6 +899,7 @@ pub struct Navigation {
 
 struct NavEntry {
 	idref: Option<ResourceId>,
+	fragment: Option<String>,
 	title: Option<String>,
 	parent_index: Option<usize>,
 }
@@ -687,6 +947,15 @@ impl NavElement {
 	}
 }
 
+/// Split href into path and non-empty fragment
+fn split_fragment(href: &str) -> (&str, Option<String>) {
+	match href.split_once('#') {
+		Some((path, fragment)) if !fragment.is_empty() => (path, Some(fragment.to_string())),
+		Some((path, _)) => (path, None),
+		None => (href, None),
+	}
+}
+
 pub fn parse_nav<R: BufRead>(
 	package: &Package,
 	mut reader: quick_xml::Reader<R>,
@@ -736,6 +1005,7 @@ pub fn parse_nav<R: BufRead>(
 					let idx = entries.len();
 					entries.push(NavEntry {
 						idref: None,
+						fragment: None,
 						title: None,
 						parent_index: stack.last().cloned(),
 					});
@@ -759,7 +1029,8 @@ pub fn parse_nav<R: BufRead>(
 						let value = unescape(&value)?.to_string();
 						entries[idx].title = Some(value);
 
-						let href = href.split('#').next().unwrap_or(href.as_ref());
+						let (href, fragment) = split_fragment(&href);
+						entries[idx].fragment = fragment;
 						let path = Path::new(href);
 						if path.is_relative() {
 							let path = package_root.join(path);
@@ -825,6 +1096,7 @@ pub fn parse_nav<R: BufRead>(
 		let spine = spine_lookup.get(&idref).cloned();
 		nav_points.push(NavPoint {
 			idref,
+			fragment: entry.fragment,
 			title,
 			parent,
 			spine,
@@ -918,6 +1190,7 @@ pub fn parse_ncx<R: BufRead>(
 					let idx = entries.len();
 					entries.push(NavEntry {
 						idref: None,
+						fragment: None,
 						title: None,
 						parent_index: stack.last().cloned(),
 					});
@@ -961,7 +1234,8 @@ pub fn parse_ncx<R: BufRead>(
 					if let Some(src) = src
 						&& let Some(idx) = stack.last().cloned()
 					{
-						let src = src.split('#').next().unwrap_or(src.as_ref());
+						let (src, fragment) = split_fragment(&src);
+						entries[idx].fragment = fragment;
 						let path = Path::new(src);
 						if path.is_relative() {
 							let path = package_root.join(path);
@@ -1008,6 +1282,7 @@ pub fn parse_ncx<R: BufRead>(
 		let spine = spine_lookup.get(&idref).cloned();
 		nav_points.push(NavPoint {
 			idref,
+			fragment: entry.fragment,
 			title,
 			parent,
 			spine,
@@ -1024,6 +1299,9 @@ pub fn parse_ncx<R: BufRead>(
 mod tests {
 	use std::path::Path;
 
+	use crate::Creator;
+	use crate::Series;
+	use crate::TextEncoding;
 	use crate::parse_container;
 	use crate::parse_nav;
 	use crate::parse_ncx;
@@ -1225,6 +1503,143 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_opf_v3_metadata_parse() -> Result<(), quick_xml::de::DeError> {
+		let input = r##"
+<?xml version="1.0" encoding="UTF-8"?>
+<package version="3.0" xmlns="http://www.idpf.org/2007/opf" unique-identifier="epub-id-1">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"
+            xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:identifier id="epub-id-1">urn:uuid:b3003f46-8d2c-4646-8402-6e65185543dd</dc:identifier>
+    <dc:title>Amelia Thornheart</dc:title>
+    <dc:creator id="epub-creator-0">Keene</dc:creator>
+    <dc:contributor id="epub-contributor-0">Anna Lindqvist</dc:contributor>
+    <meta refines="#epub-contributor-0" property="role" scheme="marc:relators">ill</meta>
+    <dc:subject>Fantasy</dc:subject>
+    <dc:subject>Adventure &amp; Magic</dc:subject>
+    <meta refines="#epub-creator-0" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#epub-creator-0" property="file-as">Keene, A.</meta>
+    <meta property="belongs-to-collection" id="c01">Thornheart Saga</meta>
+    <meta refines="#c01" property="collection-type">series</meta>
+    <meta refines="#c01" property="group-position">2</meta>
+  </metadata>
+  <manifest>
+    <item media-type="application/xhtml+xml" id="a" href="a.xhtml"/>
+  </manifest>
+  <spine>
+  	<itemref idref="a"/>
+  </spine>
+</package>
+"##;
+
+		let reader = quick_xml::Reader::from_str(input);
+		let result = parse_package(Path::new("OEBPS"), reader).expect("Parse failed");
+
+		assert_eq!(
+			result.metadata.creator.as_deref(),
+			Some("Keene"),
+			"Creator missmatch"
+		);
+		assert_eq!(
+			result.metadata.subjects,
+			vec!["Fantasy", "Adventure & Magic"],
+			"Subjects missmatch"
+		);
+		assert_eq!(
+			result.metadata.creators,
+			vec![Creator {
+				name: "Keene".to_string(),
+				role: Some("aut".to_string()),
+				file_as: Some("Keene, A.".to_string()),
+			}],
+			"Creators missmatch"
+		);
+		assert_eq!(
+			result.metadata.contributors,
+			vec![Creator {
+				name: "Anna Lindqvist".to_string(),
+				role: Some("ill".to_string()),
+				file_as: None,
+			}],
+			"Contributors missmatch"
+		);
+		assert_eq!(
+			result.metadata.series,
+			Some(Series {
+				name: "Thornheart Saga".to_string(),
+				index: Some(2.0),
+			}),
+			"Series missmatch"
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_opf_v2_metadata_parse() -> Result<(), quick_xml::de::DeError> {
+		let input = r##"
+<?xml version="1.0" encoding="UTF-8"?>
+<package version="2.0" xmlns="http://www.idpf.org/2007/opf" unique-identifier="epub-id-1">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"
+            xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:identifier id="epub-id-1">urn:uuid:2ce9fc1f-1168-499e-8389-0d2e1ccbf77e</dc:identifier>
+    <dc:title>Amelia Thornheart</dc:title>
+    <dc:creator opf:role="edt">Editor</dc:creator>
+    <dc:creator opf:role="aut" opf:file-as="Keene, A.">Keene</dc:creator>
+    <dc:description>A &lt;b&gt;tale&lt;/b&gt;</dc:description>
+    <meta name="calibre:series" content="Thornheart Saga"/>
+    <meta name="calibre:series_index" content="1.5"/>
+  </metadata>
+  <manifest>
+    <item media-type="application/xhtml+xml" id="a" href="a.xhtml"/>
+  </manifest>
+  <spine>
+  	<itemref idref="a" />
+  </spine>
+</package>
+"##;
+
+		let reader = quick_xml::Reader::from_str(input);
+		let result = parse_package(Path::new("OEBPS"), reader).expect("Parse failed");
+
+		assert_eq!(
+			result.metadata.creator.as_deref(),
+			Some("Keene"),
+			"Creator missmatch"
+		);
+		assert_eq!(
+			result.metadata.creators,
+			vec![
+				Creator {
+					name: "Editor".to_string(),
+					role: Some("edt".to_string()),
+					file_as: None,
+				},
+				Creator {
+					name: "Keene".to_string(),
+					role: Some("aut".to_string()),
+					file_as: Some("Keene, A.".to_string()),
+				},
+			],
+			"Creators missmatch"
+		);
+		assert_eq!(
+			result.metadata.description.as_deref(),
+			Some("A <b>tale</b>"),
+			"Description missmatch"
+		);
+		assert_eq!(
+			result.metadata.series,
+			Some(Series {
+				name: "Thornheart Saga".to_string(),
+				index: Some(1.5),
+			}),
+			"Series missmatch"
+		);
+
+		Ok(())
+	}
+
 	#[test]
 	fn test_ncx_parse() -> Result<(), quick_xml::de::DeError> {
 		let input = r##"
@@ -1419,4 +1834,153 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_ncx_fragment_parse() -> Result<(), quick_xml::de::DeError> {
+		let input = r##"
+<?xml version="1.0" encoding="UTF-8"?>
+<package version="2.0" xmlns="http://www.idpf.org/2007/opf" unique-identifier="epub-id-1">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="epub-id-1">urn:uuid:2ce9fc1f-1168-499e-8389-0d2e1ccbf77e</dc:identifier>
+    <dc:title>Amelia Thornheart</dc:title>
+  </metadata>
+  <manifest>
+    <item media-type="application/xhtml+xml" id="a" href="a.xhtml"/>
+    <item media-type="application/xhtml+xml" id="b" href="b.xhtml"/>
+  </manifest>
+  <spine>
+  	<itemref idref="a" />
+  	<itemref idref="b" />
+  </spine>
+</package>
+"##;
+
+		let reader = quick_xml::Reader::from_str(input);
+		let package = parse_package(Path::new("OEBPS"), reader).expect("Parse failed");
+
+		let input = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<ncx version="2005-1" xmlns="http://www.daisy.org/z3986/2005/ncx/">
+  <navMap>
+    <navPoint id="navPoint-1">
+      <navLabel><text>Cover</text></navLabel>
+      <content src="a.xhtml"/>
+    </navPoint>
+    <navPoint id="navPoint-2">
+      <navLabel><text>Part One</text></navLabel>
+      <content src="b.xhtml#part-1"/>
+    </navPoint>
+    <navPoint id="navPoint-3">
+      <navLabel><text>Part Two</text></navLabel>
+      <content src="b.xhtml#part-2"/>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+
+		let reader = quick_xml::Reader::from_str(input);
+		let result = parse_ncx(&package, reader).expect("Parse failed");
+
+		let fragments = result
+			.nav_points
+			.iter()
+			.map(|n| (&*n.idref, n.fragment.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			fragments,
+			vec![("a", None), ("b", Some("part-1")), ("b", Some("part-2"))],
+			"Fragments missmatch"
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_nav_fragment_parse() -> Result<(), quick_xml::de::DeError> {
+		let input = r##"
+<?xml version="1.0" encoding="UTF-8"?>
+<package version="2.0" xmlns="http://www.idpf.org/2007/opf" unique-identifier="epub-id-1">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="epub-id-1">urn:uuid:2ce9fc1f-1168-499e-8389-0d2e1ccbf77e</dc:identifier>
+    <dc:title>Amelia Thornheart</dc:title>
+  </metadata>
+  <manifest>
+    <item media-type="application/xhtml+xml" id="a" href="a.xhtml"/>
+    <item media-type="application/xhtml+xml" id="b" href="b.xhtml"/>
+  </manifest>
+  <spine>
+  	<itemref idref="a" />
+  	<itemref idref="b" />
+  </spine>
+</package>
+"##;
+
+		let reader = quick_xml::Reader::from_str(input);
+		let package = parse_package(Path::new("OEBPS"), reader).expect("Parse failed");
+
+		let input = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body>
+  <nav epub:type="toc" id="toc">
+    <ol>
+      <li><a href="a.xhtml">Cover</a></li>
+      <li><a href="b.xhtml#part-1">Part One</a></li>
+      <li><a href="b.xhtml#part-2">Part Two</a></li>
+    </ol>
+  </nav>
+</body>
+</html>
+"#;
+
+		let reader = quick_xml::Reader::from_str(input);
+		let result = parse_nav(&package, reader)
+			.expect("Parse failed")
+			.expect("Expected xhtml navigation");
+
+		let fragments = result
+			.nav_points
+			.iter()
+			.map(|n| (&*n.idref, n.fragment.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			fragments,
+			vec![("a", None), ("b", Some("part-1")), ("b", Some("part-2"))],
+			"Fragments missmatch"
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_text_encoding() {
+		let cp1251 = b"\xcf\xf0\xe8\xe2\xe5\xf2, \xab\xa8\xbb";
+		let encoding = TextEncoding::detect(cp1251);
+		assert_eq!(encoding, TextEncoding::Windows1251, "Cyrillic missmatch");
+		assert_eq!(
+			encoding.decode(cp1251),
+			"Привет, «Ё»",
+			"Cyrillic text missmatch"
+		);
+
+		let cp1252 = b"Caf\xe9 \x93quoted\x94";
+		let encoding = TextEncoding::detect(cp1252);
+		assert_eq!(encoding, TextEncoding::Windows1252, "Latin missmatch");
+		assert_eq!(
+			encoding.decode(cp1252),
+			"Café “quoted”",
+			"Latin text missmatch"
+		);
+
+		let utf16 = b"\xff\xfeH\x00i\x00";
+		let encoding = TextEncoding::detect(utf16);
+		assert_eq!(encoding, TextEncoding::Utf16Le, "Utf-16 missmatch");
+		assert_eq!(encoding.decode(utf16), "Hi", "Utf-16 text missmatch");
+
+		assert_eq!(
+			TextEncoding::from_label("Windows-1251"),
+			Some(TextEncoding::Windows1251),
+			"Label missmatch"
+		);
+	}
 }
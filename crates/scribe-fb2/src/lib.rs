@@ -0,0 +1,947 @@
+//! FictionBook 2 books converted to epub archives
+//!
+//! Top level sections of the main body become spine items, other bodies become
+//! note documents and binaries become images, so books read as any other epub.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+
+use quick_xml::XmlVersion;
+use quick_xml::encoding::Decoder;
+use quick_xml::escape::escape;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use scribe_epub::Creator;
+use scribe_epub::EpubBuilder;
+use scribe_epub::Metadata;
+use scribe_epub::Series;
+use scribe_epub::TextEncoding;
+use zip::ZipArchive;
+
+const STYLESHEET: &str = r#".title { text-align: center; }
+.subtitle { text-align: center; font-weight: bold; }
+.epigraph { margin-left: 4em; font-style: italic; }
+.cite { margin-left: 2em; margin-right: 2em; }
+.text-author { text-align: right; font-style: italic; }
+.poem { margin-left: 2em; }
+.stanza { margin-bottom: 1em; }
+.verse { text-indent: 0; }
+.image { text-align: center; }
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Fb2Error {
+	#[error(transparent)]
+	QuickXml(#[from] quick_xml::Error),
+	#[error(transparent)]
+	Encoding(#[from] quick_xml::encoding::EncodingError),
+	#[error(transparent)]
+	Epub(#[from] scribe_epub::EpubError),
+	#[error("at {1}: {0}")]
+	Zip(
+		zip::result::ZipError,
+		&'static std::panic::Location<'static>,
+	),
+	#[error("at {1}: {0}")]
+	Io(io::Error, &'static std::panic::Location<'static>),
+	#[error("Unsupported encoding: {0}")]
+	UnsupportedEncoding(String),
+	#[error("No FictionBook element in document")]
+	NoFictionBook,
+	#[error("No fb2 file in zip")]
+	NoFb2File,
+}
+
+impl From<zip::result::ZipError> for Fb2Error {
+	#[track_caller]
+	fn from(err: zip::result::ZipError) -> Self {
+		Self::Zip(err, std::panic::Location::caller())
+	}
+}
+
+impl From<io::Error> for Fb2Error {
+	#[track_caller]
+	fn from(err: io::Error) -> Self {
+		Self::Io(err, std::panic::Location::caller())
+	}
+}
+
+/// Convert FictionBook document to epub archive
+pub fn fb2_to_epub(bytes: &[u8]) -> Result<Vec<u8>, Fb2Error> {
+	let text = decode_document(bytes)?;
+	let mut root = parse_document(&text)?;
+	let mut next_id = 0;
+	for body in root.elements_mut().filter(|el| el.name == "body") {
+		assign_section_ids(body, &mut next_id);
+	}
+	Book::new(&root).write_epub()
+}
+
+/// Convert first FictionBook document in zip archive to epub archive
+pub fn fb2_zip_to_epub<R: Read + Seek>(reader: R) -> Result<Vec<u8>, Fb2Error> {
+	let mut archive = ZipArchive::new(reader)?;
+	let name = archive
+		.file_names()
+		.find(|name| name.to_lowercase().ends_with(".fb2"))
+		.map(str::to_string)
+		.ok_or(Fb2Error::NoFb2File)?;
+	let mut bytes = Vec::new();
+	archive.by_name(&name)?.read_to_end(&mut bytes)?;
+	fb2_to_epub(&bytes)
+}
+
+/// Document as utf-8, by encoding in xml declaration or detected
+fn decode_document(bytes: &[u8]) -> Result<String, Fb2Error> {
+	let encoding = match declared_encoding(bytes) {
+		Some(label) => {
+			TextEncoding::from_label(&label).ok_or(Fb2Error::UnsupportedEncoding(label))?
+		}
+		None => TextEncoding::detect(bytes),
+	};
+	Ok(encoding.decode(bytes))
+}
+
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+	let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+	let decl = bytes.strip_prefix(b"<?xml")?;
+	let end = decl.windows(2).position(|w| w == b"?>")?;
+	let decl = std::str::from_utf8(&decl[..end]).ok()?;
+	let (_, value) = decl.split_once("encoding")?;
+	let value = value.trim_start().strip_prefix('=')?.trim_start();
+	let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+	let (value, _) = value[1..].split_once(quote)?;
+	Some(value.trim().to_string())
+}
+
+/// Decode base64 content of binary, whitespace is skipped
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(text.len() / 4 * 3);
+	let mut acc = 0u32;
+	let mut bits = 0;
+	for b in text.bytes() {
+		let value = match b {
+			b'A'..=b'Z' => b - b'A',
+			b'a'..=b'z' => b - b'a' + 26,
+			b'0'..=b'9' => b - b'0' + 52,
+			b'+' | b'-' => 62,
+			b'/' | b'_' => 63,
+			b'=' => break,
+			b if b.is_ascii_whitespace() => continue,
+			_ => return None,
+		};
+		acc = (acc << 6) | value as u32;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((acc >> bits) as u8);
+			acc &= (1 << bits) - 1;
+		}
+	}
+	Some(out)
+}
+
+#[derive(Debug)]
+enum Node {
+	Element(Element),
+	Text(String),
+}
+
+#[derive(Debug, Default)]
+struct Element {
+	/// Local name, namespace prefixes are dropped
+	name: String,
+	attrs: Vec<(String, String)>,
+	children: Vec<Node>,
+}
+
+impl Element {
+	fn read(e: &BytesStart<'_>, decoder: Decoder) -> Result<Self, quick_xml::Error> {
+		let mut attrs = Vec::new();
+		for attr in e.attributes() {
+			let attr = attr?;
+			let value = attr.decoded_and_normalized_value(XmlVersion::Implicit1_0, decoder)?;
+			let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).to_string();
+			attrs.push((key, value.to_string()));
+		}
+		Ok(Self {
+			name: String::from_utf8_lossy(e.local_name().as_ref()).to_string(),
+			attrs,
+			children: Vec::new(),
+		})
+	}
+
+	fn attr(&self, name: &str) -> Option<&str> {
+		self.attrs
+			.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	fn elements(&self) -> impl Iterator<Item = &Element> {
+		self.children.iter().filter_map(|node| match node {
+			Node::Element(el) => Some(el),
+			Node::Text(_) => None,
+		})
+	}
+
+	fn elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+		self.children.iter_mut().filter_map(|node| match node {
+			Node::Element(el) => Some(el),
+			Node::Text(_) => None,
+		})
+	}
+
+	fn child(&self, name: &str) -> Option<&Element> {
+		self.elements().find(|el| el.name == name)
+	}
+
+	fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+		self.elements().filter(move |el| el.name == name)
+	}
+
+	/// Text content with whitespace collapsed
+	fn text(&self) -> String {
+		fn collect(el: &Element, out: &mut String) {
+			for node in &el.children {
+				match node {
+					Node::Text(t) => out.push_str(t),
+					Node::Element(el) => {
+						collect(el, out);
+						// Keep words of paragraphs apart
+						if matches!(el.name.as_str(), "p" | "v" | "subtitle" | "text-author") {
+							out.push(' ');
+						}
+					}
+				}
+			}
+		}
+		let mut text = String::new();
+		collect(self, &mut text);
+		text.split_whitespace().collect::<Vec<_>>().join(" ")
+	}
+
+	/// Text of child element, none if missing or blank
+	fn child_text(&self, name: &str) -> Option<String> {
+		self.child(name)
+			.map(Element::text)
+			.filter(|text| !text.is_empty())
+	}
+}
+
+fn parse_document(text: &str) -> Result<Element, Fb2Error> {
+	fn push_node(stack: &mut [Element], node: Node) {
+		let Some(parent) = stack.last_mut() else {
+			return;
+		};
+		match (parent.children.last_mut(), node) {
+			(Some(Node::Text(text)), Node::Text(t)) => text.push_str(&t),
+			(_, node) => parent.children.push(node),
+		}
+	}
+
+	fn close(stack: &mut Vec<Element>) {
+		if stack.len() > 1
+			&& let Some(el) = stack.pop()
+		{
+			push_node(stack, Node::Element(el));
+		}
+	}
+
+	let mut reader = quick_xml::Reader::from_str(text);
+	reader.config_mut().check_end_names = false;
+
+	let mut stack = vec![Element::default()];
+	loop {
+		match reader.read_event()? {
+			Event::Start(e) => stack.push(Element::read(&e, reader.decoder())?),
+			Event::Empty(e) => {
+				let el = Element::read(&e, reader.decoder())?;
+				push_node(&mut stack, Node::Element(el));
+			}
+			Event::End(_) => close(&mut stack),
+			Event::Text(t) => push_node(&mut stack, Node::Text(t.decode()?.to_string())),
+			Event::CData(t) => push_node(&mut stack, Node::Text(t.decode()?.to_string())),
+			Event::GeneralRef(r) => {
+				if let Some(c) = r.resolve_char_ref()? {
+					push_node(&mut stack, Node::Text(c.to_string()));
+				} else if let Some(s) = resolve_predefined_entity(&r.decode()?) {
+					push_node(&mut stack, Node::Text(s.to_string()));
+				}
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+	}
+	while stack.len() > 1 {
+		close(&mut stack);
+	}
+
+	stack
+		.pop()
+		.into_iter()
+		.flat_map(|root| root.children)
+		.find_map(|node| match node {
+			Node::Element(el) if el.name == "FictionBook" => Some(el),
+			_ => None,
+		})
+		.ok_or(Fb2Error::NoFictionBook)
+}
+
+/// Give sections without id one, so navigation can point to them
+fn assign_section_ids(el: &mut Element, next_id: &mut usize) {
+	for child in el.elements_mut().filter(|el| el.name == "section") {
+		if child.attr("id").is_none() {
+			child
+				.attrs
+				.push(("id".to_string(), format!("section-{next_id}")));
+			*next_id += 1;
+		}
+		assign_section_ids(child, next_id);
+	}
+}
+
+/// Spine document of converted book
+struct Document<'a> {
+	href: String,
+	title: Option<String>,
+	/// Sections are footnotes
+	notes: bool,
+	content: Vec<&'a Element>,
+}
+
+struct Image {
+	href: String,
+	mime: String,
+	data: Vec<u8>,
+}
+
+struct Book<'a> {
+	root: &'a Element,
+	documents: Vec<Document<'a>>,
+	/// Index of document of each element id, for links
+	targets: BTreeMap<&'a str, usize>,
+	/// Images of binary ids
+	images: BTreeMap<&'a str, Image>,
+}
+
+impl<'a> Book<'a> {
+	fn new(root: &'a Element) -> Self {
+		fn document<'a>(index: usize, notes: bool, content: Vec<&'a Element>) -> Document<'a> {
+			let title = content.iter().find_map(|el| match el.name.as_str() {
+				"title" => Some(el.text()),
+				"section" => el.child_text("title"),
+				_ => None,
+			});
+			Document {
+				href: format!("text{index}.xhtml"),
+				title,
+				notes,
+				content,
+			}
+		}
+
+		fn collect_targets<'a>(
+			el: &'a Element,
+			index: usize,
+			targets: &mut BTreeMap<&'a str, usize>,
+		) {
+			if let Some(id) = el.attr("id") {
+				targets.entry(id).or_insert(index);
+			}
+			for child in el.elements() {
+				collect_targets(child, index, targets);
+			}
+		}
+
+		let mut documents = Vec::new();
+		// First body is main text, following ones are notes and comments
+		for (i, body) in root.children_named("body").enumerate() {
+			if i > 0 {
+				let mut doc = document(documents.len(), true, body.elements().collect());
+				// Titles of note sections are only note numbers
+				doc.title = body
+					.child_text("title")
+					.or_else(|| body.attr("name").map(str::to_string));
+				documents.push(doc);
+				continue;
+			}
+			let mut loose = Vec::new();
+			for el in body.elements() {
+				if el.name == "section" {
+					if !loose.is_empty() {
+						documents.push(document(
+							documents.len(),
+							false,
+							std::mem::take(&mut loose),
+						));
+					}
+					documents.push(document(documents.len(), false, vec![el]));
+				} else {
+					loose.push(el);
+				}
+			}
+			if !loose.is_empty() {
+				documents.push(document(documents.len(), false, loose));
+			}
+		}
+
+		let mut targets = BTreeMap::new();
+		for (index, doc) in documents.iter().enumerate() {
+			for el in &doc.content {
+				collect_targets(el, index, &mut targets);
+			}
+		}
+
+		let mut images = BTreeMap::new();
+		for (i, binary) in root.children_named("binary").enumerate() {
+			let (Some(id), Some(mime)) = (binary.attr("id"), binary.attr("content-type")) else {
+				continue;
+			};
+			let ext = match mime {
+				"image/jpeg" | "image/jpg" => "jpg",
+				"image/png" => "png",
+				"image/gif" => "gif",
+				"image/webp" => "webp",
+				"image/svg+xml" => "svg",
+				_ => "bin",
+			};
+			let text = binary.children.iter().map(|node| match node {
+				Node::Text(t) => t.as_str(),
+				Node::Element(_) => "",
+			});
+			let Some(data) = decode_base64(&text.collect::<String>()) else {
+				log::warn!("Failed to decode binary {id}");
+				continue;
+			};
+			images.insert(
+				id,
+				Image {
+					href: format!("images/image{i}.{ext}"),
+					mime: mime.to_string(),
+					data,
+				},
+			);
+		}
+
+		Self {
+			root,
+			documents,
+			targets,
+			images,
+		}
+	}
+
+	fn title_info(&self) -> Option<&'a Element> {
+		self.root.child("description")?.child("title-info")
+	}
+
+	fn cover(&self) -> Option<&Image> {
+		let href = self
+			.title_info()?
+			.child("coverpage")?
+			.child("image")?
+			.attr("href")?;
+		self.images.get(href.trim_start_matches('#'))
+	}
+
+	fn write_epub(self) -> Result<Vec<u8>, Fb2Error> {
+		let mut epub = EpubBuilder::new(self.metadata());
+		epub.stylesheet(STYLESHEET);
+		for doc in &self.documents {
+			let title = doc.title.as_deref().unwrap_or_default();
+			epub.add_document(&doc.href, title, &self.document(doc));
+		}
+		for (level, title, href) in self.toc() {
+			epub.add_toc(level, &title, &href);
+		}
+		if let Some(cover) = self.cover() {
+			epub.cover(&cover.href);
+		}
+		for image in self.images.into_values() {
+			epub.add_resource(&image.href, &image.mime, image.data);
+		}
+		Ok(epub.finish()?)
+	}
+
+	fn metadata(&self) -> Metadata {
+		let description = self.root.child("description");
+		let document_info = description.and_then(|d| d.child("document-info"));
+		let publish_info = description.and_then(|d| d.child("publish-info"));
+		let title_info = self.title_info().into_iter();
+
+		let people = |name: &'static str, role: &'static str| {
+			title_info
+				.clone()
+				.flat_map(move |t| t.children_named(name))
+				.filter_map(person_name)
+				.map(|(name, file_as)| Creator {
+					name,
+					role: Some(role.to_string()),
+					file_as,
+				})
+				.collect::<Vec<_>>()
+		};
+		let creators = people("author", "aut");
+		let series = title_info
+			.clone()
+			.find_map(|t| t.child("sequence"))
+			.and_then(|sequence| {
+				let name = sequence.attr("name").filter(|name| !name.is_empty())?;
+				Some(Series {
+					name: name.to_string(),
+					index: sequence.attr("number").and_then(|n| n.parse().ok()),
+				})
+			});
+		let description =
+			title_info
+				.clone()
+				.find_map(|t| t.child("annotation"))
+				.map(|annotation| {
+					annotation
+						.elements()
+						.map(Element::text)
+						.filter(|text| !text.is_empty())
+						.collect::<Vec<_>>()
+						.join("\n")
+				});
+		let date = title_info
+			.clone()
+			.find_map(|t| t.child("date"))
+			.map(|d| {
+				d.attr("value")
+					.map(str::to_string)
+					.unwrap_or_else(|| d.text())
+			})
+			.or_else(|| publish_info.and_then(|p| p.child_text("year")))
+			.filter(|date| !date.is_empty());
+
+		Metadata {
+			identifier: document_info
+				.and_then(|d| d.child_text("id"))
+				.or_else(|| publish_info.and_then(|p| p.child_text("isbn"))),
+			title: title_info.clone().find_map(|t| t.child_text("book-title")),
+			creator: creators.first().map(|c| c.name.clone()),
+			creators,
+			contributors: people("translator", "trl"),
+			description,
+			series,
+			publisher: publish_info.and_then(|p| p.child_text("publisher")),
+			subjects: title_info
+				.clone()
+				.flat_map(|t| t.children_named("genre"))
+				.map(Element::text)
+				.collect(),
+			language: title_info.clone().find_map(|t| t.child_text("lang")),
+			date,
+			..Default::default()
+		}
+	}
+
+	/// Table of contents entries, as level, title and href
+	fn toc(&self) -> Vec<(usize, String, String)> {
+		fn sections(
+			el: &Element,
+			depth: usize,
+			href: &str,
+			entries: &mut Vec<(usize, String, String)>,
+		) {
+			for section in el.children_named("section") {
+				match (section.child_text("title"), section.attr("id")) {
+					(Some(title), Some(id)) => {
+						entries.push((depth, title, format!("{href}#{id}")));
+						sections(section, depth + 1, href, entries);
+					}
+					_ => sections(section, depth, href, entries),
+				}
+			}
+		}
+
+		let mut entries = Vec::new();
+		for (i, doc) in self.documents.iter().enumerate() {
+			let title = doc.title.clone().unwrap_or_else(|| (i + 1).to_string());
+			entries.push((0, title, doc.href.clone()));
+			if !doc.notes {
+				for el in &doc.content {
+					sections(el, 1, &doc.href, &mut entries);
+				}
+			}
+		}
+		entries
+	}
+
+	/// Body content of document
+	fn document(&self, doc: &Document<'_>) -> String {
+		let mut out = String::new();
+		for el in &doc.content {
+			if doc.notes && el.name == "section" {
+				out.push_str("<aside epub:type=\"footnote\"");
+				write_id(&mut out, el);
+				out.push_str(">\n");
+				for child in el.elements() {
+					self.write_block(&mut out, child, 3);
+				}
+				out.push_str("</aside>\n");
+			} else {
+				self.write_block(&mut out, el, 1);
+			}
+		}
+		out
+	}
+
+	fn write_block(&self, out: &mut String, el: &Element, depth: usize) {
+		match el.name.as_str() {
+			"section" => self.write_wrapped(out, el, "div", "section", depth),
+			"epigraph" => self.write_wrapped(out, el, "blockquote", "epigraph", depth),
+			"cite" => self.write_wrapped(out, el, "blockquote", "cite", depth),
+			"annotation" => self.write_wrapped(out, el, "div", "annotation", depth),
+			"poem" => self.write_wrapped(out, el, "div", "poem", depth + 1),
+			"stanza" => self.write_wrapped(out, el, "div", "stanza", depth),
+			"title" => {
+				let tag = format!("h{}", depth.clamp(1, 6));
+				out.push_str(&format!("<{tag} class=\"title\""));
+				write_id(out, el);
+				out.push('>');
+				for (i, p) in el.elements().filter(|p| p.name != "empty-line").enumerate() {
+					if i > 0 {
+						out.push_str("<br/>");
+					}
+					self.write_inline(out, p);
+				}
+				out.push_str(&format!("</{tag}>\n"));
+			}
+			"p" => self.write_paragraph(out, el, None),
+			"subtitle" => self.write_paragraph(out, el, Some("subtitle")),
+			"v" => self.write_paragraph(out, el, Some("verse")),
+			"text-author" => self.write_paragraph(out, el, Some("text-author")),
+			"date" => self.write_paragraph(out, el, Some("date")),
+			"empty-line" => out.push_str("<p class=\"empty-line\">&#160;</p>\n"),
+			"image" => {
+				out.push_str("<div class=\"image\"");
+				write_id(out, el);
+				out.push('>');
+				self.write_image(out, el);
+				out.push_str("</div>\n");
+			}
+			"table" => {
+				out.push_str("<table");
+				write_id(out, el);
+				out.push_str(">\n");
+				for row in el.children_named("tr") {
+					out.push_str("<tr>");
+					for cell in row.elements() {
+						let tag = if cell.name == "th" { "th" } else { "td" };
+						out.push_str(&format!("<{tag}>"));
+						self.write_inline(out, cell);
+						out.push_str(&format!("</{tag}>"));
+					}
+					out.push_str("</tr>\n");
+				}
+				out.push_str("</table>\n");
+			}
+			_ => {
+				for child in el.elements() {
+					self.write_block(out, child, depth);
+				}
+			}
+		}
+	}
+
+	/// Block wrapping child blocks, nested sections are one level deeper
+	fn write_wrapped(&self, out: &mut String, el: &Element, tag: &str, class: &str, depth: usize) {
+		out.push_str(&format!("<{tag} class=\"{class}\""));
+		write_id(out, el);
+		out.push_str(">\n");
+		for child in el.elements() {
+			let depth = if child.name == "section" {
+				depth + 1
+			} else {
+				depth
+			};
+			self.write_block(out, child, depth);
+		}
+		out.push_str(&format!("</{tag}>\n"));
+	}
+
+	fn write_paragraph(&self, out: &mut String, el: &Element, class: Option<&str>) {
+		out.push_str("<p");
+		if let Some(class) = class {
+			out.push_str(&format!(" class=\"{class}\""));
+		}
+		write_id(out, el);
+		out.push('>');
+		self.write_inline(out, el);
+		out.push_str("</p>\n");
+	}
+
+	fn write_inline(&self, out: &mut String, el: &Element) {
+		for node in &el.children {
+			let child = match node {
+				Node::Text(t) => {
+					out.push_str(&escape(t.as_str()));
+					continue;
+				}
+				Node::Element(child) => child,
+			};
+			let tag = match child.name.as_str() {
+				"emphasis" => "em",
+				"strong" => "strong",
+				"strikethrough" => "del",
+				"sub" => "sub",
+				"sup" => "sup",
+				"code" => "code",
+				"style" => "span",
+				"a" => {
+					self.write_link(out, child);
+					continue;
+				}
+				"image" => {
+					self.write_image(out, child);
+					continue;
+				}
+				_ => {
+					self.write_inline(out, child);
+					continue;
+				}
+			};
+			out.push_str(&format!("<{tag}>"));
+			self.write_inline(out, child);
+			out.push_str(&format!("</{tag}>"));
+		}
+	}
+
+	fn write_link(&self, out: &mut String, el: &Element) {
+		let Some(href) = el.attr("href") else {
+			self.write_inline(out, el);
+			return;
+		};
+		let href = match href.strip_prefix('#') {
+			Some(id) => match self.targets.get(id) {
+				Some(index) => format!("{}#{id}", self.documents[*index].href),
+				None => href.to_string(),
+			},
+			None => href.to_string(),
+		};
+		out.push_str(&format!("<a href=\"{}\"", escape(href.as_str())));
+		if el.attr("type") == Some("note") {
+			out.push_str(" epub:type=\"noteref\"");
+		}
+		out.push('>');
+		self.write_inline(out, el);
+		out.push_str("</a>");
+	}
+
+	fn write_image(&self, out: &mut String, el: &Element) {
+		let Some(image) = el
+			.attr("href")
+			.and_then(|href| self.images.get(href.trim_start_matches('#')))
+		else {
+			return;
+		};
+		out.push_str(&format!(
+			"<img src=\"{}\" alt=\"{}\"/>",
+			image.href,
+			escape(el.attr("alt").unwrap_or_default())
+		));
+	}
+}
+
+fn write_id(out: &mut String, el: &Element) {
+	if let Some(id) = el.attr("id") {
+		out.push_str(&format!(" id=\"{}\"", escape(id)));
+	}
+}
+
+/// Display name of author or translator, with name used for sorting
+fn person_name(person: &Element) -> Option<(String, Option<String>)> {
+	let first = person.child_text("first-name");
+	let middle = person.child_text("middle-name");
+	let last = person.child_text("last-name");
+	let given = [first, middle].into_iter().flatten().collect::<Vec<_>>();
+	let name = given
+		.iter()
+		.chain(&last)
+		.cloned()
+		.collect::<Vec<_>>()
+		.join(" ");
+	if name.is_empty() {
+		return person.child_text("nickname").map(|name| (name, None));
+	}
+	let file_as = last
+		.filter(|_| !given.is_empty())
+		.map(|last| format!("{last}, {}", given.join(" ")));
+	Some((name, file_as))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::io::Read;
+
+	use scribe_epub::EpubMetadata;
+	use zip::ZipArchive;
+
+	use crate::decode_base64;
+	use crate::decode_document;
+	use crate::fb2_to_epub;
+
+	const BOOK: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:l="http://www.w3.org/1999/xlink">
+  <description>
+    <title-info>
+      <genre>sf_fantasy</genre>
+      <author><first-name>Amelia</first-name><last-name>Thornheart</last-name></author>
+      <book-title>The Thornheart Saga</book-title>
+      <annotation><p>First line.</p><p>Second line.</p></annotation>
+      <date value="2021-05-01">2021</date>
+      <coverpage><image l:href="#cover.png"/></coverpage>
+      <lang>en</lang>
+      <sequence name="Thornheart" number="2"/>
+    </title-info>
+    <document-info><id>fb2-thornheart</id></document-info>
+  </description>
+  <body>
+    <title><p>The Thornheart Saga</p></title>
+    <section>
+      <title><p>Chapter 1</p><p>Beginnings</p></title>
+      <p>Once upon a time<a l:href="#n1" type="note">1</a> &amp; more.</p>
+      <section id="part"><title><p>Part A</p></title><p>Nested</p></section>
+    </section>
+    <section>
+      <title><p>Chapter 2</p></title>
+      <p><emphasis>The</emphasis> end.</p>
+    </section>
+  </body>
+  <body name="notes">
+    <section id="n1"><title><p>1</p></title><p>A note.</p></section>
+  </body>
+  <binary id="cover.png" content-type="image/png">iVBORw0K
+  Ggo=</binary>
+</FictionBook>
+"##;
+
+	#[test]
+	fn test_fb2_to_epub() {
+		let epub = fb2_to_epub(BOOK.as_bytes()).expect("Failed to convert");
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		let m = &package.metadata;
+		assert_eq!(
+			m.identifier.as_deref(),
+			Some("fb2-thornheart"),
+			"Identifier missmatch"
+		);
+		assert_eq!(
+			m.title.as_deref(),
+			Some("The Thornheart Saga"),
+			"Title missmatch"
+		);
+		assert_eq!(
+			m.creator.as_deref(),
+			Some("Amelia Thornheart"),
+			"Creator missmatch"
+		);
+		assert_eq!(
+			m.creators[0].file_as.as_deref(),
+			Some("Thornheart, Amelia"),
+			"File as missmatch"
+		);
+		assert_eq!(
+			m.description.as_deref(),
+			Some("First line.\nSecond line."),
+			"Description missmatch"
+		);
+		assert_eq!(m.subjects, vec!["sf_fantasy"], "Subjects missmatch");
+		assert_eq!(m.language.as_deref(), Some("en"), "Language missmatch");
+		assert_eq!(m.date.as_deref(), Some("2021-05-01"), "Date missmatch");
+		let series = m.series.as_ref().expect("Missing series");
+		assert_eq!(series.name, "Thornheart", "Series missmatch");
+		assert_eq!(series.index, Some(2.0), "Series index missmatch");
+
+		let cover = m
+			.cover
+			.as_ref()
+			.and_then(|id| package.manifest.get(id))
+			.expect("Missing cover");
+		assert_eq!(cover.mime, "image/png", "Cover mime missmatch");
+		let cover_path = cover.as_path().to_path_buf();
+
+		assert_eq!(package.spine.len(), 4, "Spine missmatch");
+		let titles = navigation
+			.nav_points
+			.iter()
+			.map(|p| (p.title.as_str(), p.spine, p.fragment.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			titles,
+			vec![
+				("The Thornheart Saga", Some(0), None),
+				("Chapter 1 Beginnings", Some(1), None),
+				("Part A", Some(1), Some("part")),
+				("Chapter 2", Some(2), None),
+				("notes", Some(3), None),
+			],
+			"Navigation missmatch"
+		);
+
+		let mut data = Vec::new();
+		archive
+			.by_path(&cover_path)
+			.expect("Missing cover file")
+			.read_to_end(&mut data)
+			.expect("Failed to read cover");
+		assert_eq!(data, b"\x89PNG\r\n\x1a\n", "Cover data missmatch");
+
+		let mut chapter = String::new();
+		archive
+			.by_path("text1.xhtml")
+			.expect("Missing chapter")
+			.read_to_string(&mut chapter)
+			.expect("Failed to read chapter");
+		assert!(
+			chapter.contains(r#"<a href="text3.xhtml#n1" epub:type="noteref">1</a> &amp; more."#),
+			"Note link missing in {chapter}"
+		);
+		assert!(
+			chapter.contains(r#"<h1 class="title">Chapter 1<br/>Beginnings</h1>"#),
+			"Title missing in {chapter}"
+		);
+		assert!(
+			chapter.contains(r#"<h2 class="title">Part A</h2>"#),
+			"Nested title missing in {chapter}"
+		);
+
+		let mut notes = String::new();
+		archive
+			.by_path("text3.xhtml")
+			.expect("Missing notes")
+			.read_to_string(&mut notes)
+			.expect("Failed to read notes");
+		assert!(
+			notes.contains(r#"<aside epub:type="footnote" id="n1">"#),
+			"Footnote missing in {notes}"
+		);
+	}
+
+	#[test]
+	fn test_fb2_decode() {
+		let doc = b"<?xml version=\"1.0\" encoding=\"windows-1251\"?><p>\xcf\xf0\xe8\xe2\xe5\xf2 \xab\xa8\xbb</p>";
+		let text = decode_document(doc).expect("Failed to decode");
+		assert!(
+			text.ends_with("<p>Привет «Ё»</p>"),
+			"Text missmatch: {text}"
+		);
+
+		assert_eq!(
+			decode_base64("SGVs\nbG8=").as_deref(),
+			Some(&b"Hello"[..]),
+			"Base64 missmatch"
+		);
+		assert_eq!(decode_base64("SGVs*"), None, "Invalid base64 missmatch");
+	}
+}
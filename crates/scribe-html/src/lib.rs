@@ -0,0 +1,646 @@
+//! Standalone HTML documents and zipped web pages converted to epub archives
+//!
+//! The document is kept as is as the only spine item, with images it
+//! references stored in the archive. Headings up to `h3` make up the table of
+//! contents, so long articles can be navigated by chapter.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::ops::Range;
+
+use quick_xml::escape::escape;
+use quick_xml::escape::resolve_html5_entity;
+use quick_xml::escape::unescape_with;
+use scribe_epub::Creator;
+use scribe_epub::EpubBuilder;
+use scribe_epub::Metadata;
+use scribe_epub::TextEncoding;
+use zip::ZipArchive;
+
+const DOCUMENT_PATH: &str = "index.html";
+
+/// Leading bytes searched for declared charset, as browsers do
+const CHARSET_BYTES: usize = 1024;
+
+/// Elements with content that is not markup
+const RAW_TEXT: &[&str] = &["script", "style", "title", "textarea"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum HtmlError {
+	#[error(transparent)]
+	Epub(#[from] scribe_epub::EpubError),
+	#[error("at {1}: {0}")]
+	Zip(
+		zip::result::ZipError,
+		&'static std::panic::Location<'static>,
+	),
+	#[error("at {1}: {0}")]
+	Io(io::Error, &'static std::panic::Location<'static>),
+	#[error("No html file in zip")]
+	NoHtmlFile,
+}
+
+impl From<zip::result::ZipError> for HtmlError {
+	#[track_caller]
+	fn from(err: zip::result::ZipError) -> Self {
+		Self::Zip(err, std::panic::Location::caller())
+	}
+}
+
+impl From<io::Error> for HtmlError {
+	#[track_caller]
+	fn from(err: io::Error) -> Self {
+		Self::Io(err, std::panic::Location::caller())
+	}
+}
+
+/// Start or end tag of document
+struct Tag<'a> {
+	/// Lowercase name
+	name: String,
+	end: bool,
+	/// Text between name and end of tag
+	attrs: &'a str,
+	/// Position of attributes in document
+	attrs_start: usize,
+	range: Range<usize>,
+}
+
+/// Attribute of tag, range of raw value in document includes quotes
+struct Attribute<'a> {
+	name: String,
+	value: &'a str,
+	range: Range<usize>,
+}
+
+struct Heading {
+	level: usize,
+	id: String,
+	title: String,
+}
+
+/// Images of document, loaded with `resources` by source
+struct Images<F> {
+	resources: F,
+	/// Href by source, none if not loaded
+	sources: BTreeMap<String, Option<String>>,
+	images: Vec<(String, &'static str, Vec<u8>)>,
+}
+
+impl<F: FnMut(&str) -> Option<Vec<u8>>> Images<F> {
+	fn new(resources: F) -> Self {
+		Self {
+			resources,
+			sources: BTreeMap::new(),
+			images: Vec::new(),
+		}
+	}
+
+	/// Href of image by source, external images are not loaded
+	fn href(&mut self, src: &str) -> Option<String> {
+		if let Some(href) = self.sources.get(src) {
+			return href.clone();
+		}
+		let path = src.split(['#', '?']).next().unwrap_or_default();
+		let extension = path
+			.rsplit_once('.')
+			.map(|(_, ext)| ext.to_ascii_lowercase())
+			.unwrap_or_default();
+		let mime = match extension.as_str() {
+			"png" => Some("image/png"),
+			"jpg" | "jpeg" => Some("image/jpeg"),
+			"gif" => Some("image/gif"),
+			"webp" => Some("image/webp"),
+			"svg" => Some("image/svg+xml"),
+			_ => None,
+		};
+		let href = mime
+			.filter(|_| !src.contains(':') && !src.starts_with("//"))
+			.and_then(|mime| Some((mime, (self.resources)(src)?)))
+			.map(|(mime, data)| {
+				let href = format!("images/image{}.{extension}", self.images.len());
+				self.images.push((href.clone(), mime, data));
+				href
+			});
+		if href.is_none() {
+			log::debug!("Image not loaded: {src}");
+		}
+		self.sources.insert(src.to_string(), href.clone());
+		href
+	}
+}
+
+/// Convert HTML document to epub archive
+///
+/// Images are loaded with `resources` by source as written in document, images that
+/// are external or not found are left as is.
+/// Title is taken from `title` element or first top level heading, else `title` is used.
+pub fn html_to_epub(
+	bytes: &[u8],
+	title: &str,
+	resources: impl FnMut(&str) -> Option<Vec<u8>>,
+) -> Result<Vec<u8>, HtmlError> {
+	let html = decode_document(bytes);
+	convert(&html, title, resources)
+}
+
+/// Convert zip archive of a web page to epub archive
+///
+/// Document is `index.html`, or first HTML file, closest to archive root. Images
+/// are loaded from archive relative to document.
+pub fn html_zip_to_epub<R: Read + Seek>(reader: R, title: &str) -> Result<Vec<u8>, HtmlError> {
+	let mut archive = ZipArchive::new(reader)?;
+	let name = archive
+		.file_names()
+		.filter(|name| is_html(name))
+		.min_by_key(|name| {
+			let file_name = name.rsplit('/').next().unwrap_or_default();
+			let is_index = matches!(
+				file_name.to_lowercase().as_str(),
+				"index.html" | "index.htm"
+			);
+			(name.matches('/').count(), !is_index, name.to_string())
+		})
+		.map(str::to_string)
+		.ok_or(HtmlError::NoHtmlFile)?;
+	let mut bytes = Vec::new();
+	archive.by_name(&name)?.read_to_end(&mut bytes)?;
+	let html = decode_document(&bytes);
+
+	let dir = name.rsplit_once('/').map_or("", |(dir, _)| dir);
+	convert(&html, title, |src| {
+		let path = archive_path(dir, src)?;
+		let mut data = Vec::new();
+		match archive.by_name(&path) {
+			Ok(mut file) => file.read_to_end(&mut data).ok().map(|_| data),
+			Err(e) => {
+				log::debug!("Failed to load {path}: {e}");
+				None
+			}
+		}
+	})
+}
+
+fn is_html(name: &str) -> bool {
+	let name = name.to_lowercase();
+	name.ends_with(".html") || name.ends_with(".htm") || name.ends_with(".xhtml")
+}
+
+/// Path in archive of source relative to directory, none if outside of archive
+fn archive_path(dir: &str, src: &str) -> Option<String> {
+	let src = src.split(['#', '?']).next()?.replace("%20", " ");
+	if src.starts_with('/') {
+		return None;
+	}
+	let mut path = dir
+		.split('/')
+		.filter(|name| !name.is_empty())
+		.collect::<Vec<_>>();
+	for name in src.split('/') {
+		match name {
+			"" | "." => {}
+			".." => {
+				path.pop()?;
+			}
+			name => path.push(name),
+		}
+	}
+	Some(path.join("/"))
+}
+
+/// Document as utf-8, by declared charset or detected
+fn decode_document(bytes: &[u8]) -> String {
+	let declared = declared_charset(bytes).and_then(|label| {
+		let encoding = TextEncoding::from_label(&label);
+		if encoding.is_none() {
+			log::warn!("Unsupported charset {label}, detecting encoding");
+		}
+		encoding
+	});
+	declared
+		.unwrap_or_else(|| TextEncoding::detect(bytes))
+		.decode(bytes)
+}
+
+/// Charset of `meta` element, e.g. `<meta charset="utf-8">`
+fn declared_charset(bytes: &[u8]) -> Option<String> {
+	let head = &bytes[..bytes.len().min(CHARSET_BYTES)];
+	let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+	let (_, value) = head.split_once("charset=")?;
+	let value = value.trim_start_matches(['"', '\'']);
+	let end = value
+		.find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | ';' | '/' | '>'))
+		.unwrap_or(value.len());
+	Some(value[..end].to_string()).filter(|value| !value.is_empty())
+}
+
+fn convert(
+	html: &str,
+	title: &str,
+	resources: impl FnMut(&str) -> Option<Vec<u8>>,
+) -> Result<Vec<u8>, HtmlError> {
+	let tags = tags(html);
+	// Content of element opened by tag at index, up to its end tag
+	let content = |i: usize| {
+		let tag: &Tag<'_> = &tags[i];
+		let end = tags[i + 1..]
+			.iter()
+			.find(|t| t.end && t.name == tag.name)
+			.map_or(html.len(), |t| t.range.start);
+		&html[tag.range.end..end]
+	};
+
+	let mut images = Images::new(resources);
+	let mut headings = Vec::new();
+	// Replacements of ranges in document, in document order
+	let mut edits = Vec::new();
+	let mut metadata = Metadata::default();
+	let mut doc_title = None;
+	for (i, tag) in tags.iter().enumerate().filter(|(_, tag)| !tag.end) {
+		let attrs = attributes(tag);
+		let attr = |name: &str| attrs.iter().find(|a| a.name == name);
+		let value = |name: &str| {
+			attr(name)
+				.map(|a| plain_text(a.value))
+				.filter(|v| !v.is_empty())
+		};
+		match tag.name.as_str() {
+			"html" => metadata.language = value("lang").or_else(|| value("xml:lang")),
+			"title" if doc_title.is_none() => {
+				doc_title = Some(plain_text(content(i))).filter(|t| !t.is_empty());
+			}
+			"meta" => {
+				let name = value("name").map(|name| name.to_lowercase());
+				match name.as_deref() {
+					Some("author") => metadata.creator = value("content"),
+					Some("description") => metadata.description = value("content"),
+					_ => {}
+				}
+			}
+			"h1" | "h2" | "h3" => {
+				let title = plain_text(content(i));
+				if title.is_empty() {
+					continue;
+				}
+				let id = match value("id") {
+					Some(id) => id,
+					None => {
+						let id = format!("heading{}", headings.len());
+						edits.push((tag.attrs_start..tag.attrs_start, format!(" id=\"{id}\"")));
+						id
+					}
+				};
+				headings.push(Heading {
+					level: tag.name[1..].parse().unwrap_or(1),
+					id,
+					title,
+				});
+			}
+			"img" => {
+				if let Some(src) = attr("src")
+					&& let Some(href) = images.href(&unescape_text(src.value))
+				{
+					edits.push((src.range.clone(), format!("\"{}\"", escape(href.as_str()))));
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let mut document = String::with_capacity(html.len());
+	let mut pos = 0;
+	for (range, text) in edits {
+		document.push_str(&html[pos..range.start]);
+		document.push_str(&text);
+		pos = range.end;
+	}
+	document.push_str(&html[pos..]);
+
+	metadata.title = doc_title
+		.or_else(|| {
+			headings
+				.iter()
+				.find(|h| h.level == 1)
+				.map(|h| h.title.clone())
+		})
+		.or_else(|| Some(title.to_string()));
+	metadata.creators = metadata
+		.creator
+		.iter()
+		.map(|name| Creator {
+			name: name.clone(),
+			role: Some("aut".to_string()),
+			file_as: None,
+		})
+		.collect();
+
+	let mut epub = EpubBuilder::new(metadata);
+	epub.add_xhtml(DOCUMENT_PATH, document.into_bytes());
+	let top = headings.iter().map(|h| h.level).min().unwrap_or(1);
+	for heading in &headings {
+		let href = format!("{DOCUMENT_PATH}#{}", heading.id);
+		epub.add_toc(heading.level - top, &heading.title, &href);
+	}
+	for (href, mime, data) in images.images {
+		epub.add_resource(&href, mime, data);
+	}
+	Ok(epub.finish()?)
+}
+
+/// Tags of document in order, comments and content of scripts and styles are skipped
+fn tags(html: &str) -> Vec<Tag<'_>> {
+	let bytes = html.as_bytes();
+	let mut tags = Vec::new();
+	let mut pos = 0;
+	while let Some(start) = html[pos..].find('<').map(|i| pos + i) {
+		if html[start..].starts_with("<!--") {
+			pos = html[start..]
+				.find("-->")
+				.map_or(html.len(), |end| start + end + 3);
+			continue;
+		}
+		let end = bytes.get(start + 1) == Some(&b'/');
+		let name_start = start + 1 + usize::from(end);
+		let name_end = html[name_start..]
+			.find(|c: char| !c.is_ascii_alphanumeric() && c != ':' && c != '-')
+			.map_or(html.len(), |len| name_start + len);
+		// Not a tag, e.g. `a < b` or a doctype
+		if !bytes.get(name_start).is_some_and(u8::is_ascii_alphabetic) {
+			pos = start + 1;
+			continue;
+		}
+		let Some(tag_end) = tag_end(html, name_end) else {
+			break;
+		};
+		let name = html[name_start..name_end].to_ascii_lowercase();
+		pos = tag_end;
+		if !end && RAW_TEXT.contains(&name.as_str()) {
+			let close = format!("</{name}");
+			pos = find_ignore_case(html, tag_end, &close).unwrap_or(html.len());
+		}
+		tags.push(Tag {
+			name,
+			end,
+			attrs: &html[name_end..tag_end - 1],
+			attrs_start: name_end,
+			range: start..tag_end,
+		});
+	}
+	tags
+}
+
+/// Position after `>` ending tag, quoted values may contain `>`
+fn tag_end(html: &str, from: usize) -> Option<usize> {
+	let mut quote = None;
+	for (i, c) in html[from..].char_indices() {
+		match (quote, c) {
+			(Some(q), c) if q == c => quote = None,
+			(Some(_), _) => {}
+			(None, '"' | '\'') => quote = Some(c),
+			(None, '>') => return Some(from + i + 1),
+			_ => {}
+		}
+	}
+	None
+}
+
+fn find_ignore_case(html: &str, from: usize, needle: &str) -> Option<usize> {
+	html.as_bytes()
+		.get(from..)?
+		.windows(needle.len())
+		.position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+		.map(|i| from + i)
+}
+
+fn attributes<'a>(tag: &Tag<'a>) -> Vec<Attribute<'a>> {
+	let text = tag.attrs;
+	let bytes = text.as_bytes();
+	let skip_space = |mut pos: usize| {
+		while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+			pos += 1;
+		}
+		pos
+	};
+	let mut attrs = Vec::new();
+	let mut pos = 0;
+	while pos < bytes.len() {
+		if bytes[pos].is_ascii_whitespace() || matches!(bytes[pos], b'/' | b'=') {
+			pos += 1;
+			continue;
+		}
+		let name_start = pos;
+		while pos < bytes.len()
+			&& !bytes[pos].is_ascii_whitespace()
+			&& !matches!(bytes[pos], b'/' | b'=')
+		{
+			pos += 1;
+		}
+		let name = text[name_start..pos].to_ascii_lowercase();
+		let after_name = skip_space(pos);
+		if bytes.get(after_name) != Some(&b'=') {
+			attrs.push(Attribute {
+				name,
+				value: "",
+				range: tag.attrs_start + pos..tag.attrs_start + pos,
+			});
+			continue;
+		}
+		let start = skip_space(after_name + 1);
+		let value = match bytes.get(start) {
+			Some(quote @ (b'"' | b'\'')) => {
+				let end = text[start + 1..]
+					.find(*quote as char)
+					.map_or(text.len(), |end| start + 1 + end);
+				pos = (end + 1).min(text.len());
+				&text[start + 1..end]
+			}
+			_ => {
+				pos = start;
+				while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+					pos += 1;
+				}
+				&text[start..pos]
+			}
+		};
+		attrs.push(Attribute {
+			name,
+			value,
+			range: tag.attrs_start + start..tag.attrs_start + pos,
+		});
+	}
+	attrs
+}
+
+/// Text with character references resolved, kept as is if invalid
+fn unescape_text(text: &str) -> String {
+	unescape_with(text, resolve_html5_entity)
+		.map(|text| text.into_owned())
+		.unwrap_or_else(|_| text.to_string())
+}
+
+/// Text content of markup, tags are dropped and whitespace collapsed
+fn plain_text(markup: &str) -> String {
+	let mut text = String::with_capacity(markup.len());
+	let mut rest = markup;
+	while let Some(start) = rest.find('<') {
+		text.push_str(&rest[..start]);
+		rest = tag_end(rest, start).map_or("", |end| &rest[end..]);
+	}
+	text.push_str(rest);
+	unescape_text(&text)
+		.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::io::Read;
+	use std::io::Write;
+
+	use scribe_epub::EpubMetadata;
+	use zip::ZipArchive;
+	use zip::ZipWriter;
+	use zip::write::SimpleFileOptions;
+
+	use crate::html_to_epub;
+	use crate::html_zip_to_epub;
+
+	const PNG: &[u8] = b"\x89PNG\r\n\x1a\nimage";
+
+	fn read_document(archive: &mut ZipArchive<Cursor<Vec<u8>>>, path: &str) -> String {
+		let mut content = String::new();
+		archive
+			.by_path(path)
+			.expect("Missing document")
+			.read_to_string(&mut content)
+			.expect("Failed to read document");
+		content
+	}
+
+	#[test]
+	fn test_html_to_epub() {
+		let doc = concat!(
+			"<!DOCTYPE html>\n<html lang=\"sv\"><head><meta charset=\"windows-1252\">",
+			"<title>R\u{e4}ksm\u{f6}rg\u{e5}s &amp; mer</title>",
+			"<meta name=\"author\" content=\"Jane Doe\">",
+			"<script>document.write('<h1>Nope</h1>');</script></head>\n",
+			"<body><h1>Article</h1><p>Intro <img src=\"files/a b.png\" alt=\"a\"> ",
+			"<img src='https://example.com/b.png'></p>\n",
+			"<!-- <h2>Hidden</h2> -->",
+			"<H2 class=\"x\">First <em>part</em></H2><p>1 &lt; 2</p>\n",
+			"<h3 id=\"deep\">Detail</h3><h4>Small</h4>\n",
+			"<h2>Second</h2></body></html>\n",
+		);
+		let bytes = doc
+			.chars()
+			.map(|c| u8::try_from(u32::from(c)).expect("Not latin-1"))
+			.collect::<Vec<_>>();
+		let mut requested = Vec::new();
+		let epub = html_to_epub(&bytes, "file", |src| {
+			requested.push(src.to_string());
+			Some(PNG.to_vec())
+		})
+		.expect("Failed to convert");
+		assert_eq!(requested, vec!["files/a b.png"], "Resources missmatch");
+
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		let m = &package.metadata;
+		assert_eq!(
+			m.title.as_deref(),
+			Some("Räksmörgås & mer"),
+			"Title missmatch"
+		);
+		assert_eq!(m.creator.as_deref(), Some("Jane Doe"), "Creator missmatch");
+		assert_eq!(m.language.as_deref(), Some("sv"), "Language missmatch");
+		assert_eq!(package.spine.len(), 1, "Spine missmatch");
+		let titles = navigation
+			.nav_points
+			.iter()
+			.map(|p| (p.title.as_str(), p.spine, p.fragment.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			titles,
+			vec![
+				("Article", Some(0), Some("heading0")),
+				("First part", Some(0), Some("heading1")),
+				("Detail", Some(0), Some("deep")),
+				("Second", Some(0), Some("heading3")),
+			],
+			"Navigation missmatch"
+		);
+
+		let document = read_document(&mut archive, "index.html");
+		assert!(
+			document.contains("<h1 id=\"heading0\">Article</h1>"),
+			"Heading id missing in {document}"
+		);
+		assert!(
+			document.contains("<H2 id=\"heading1\" class=\"x\">"),
+			"Heading id missing in {document}"
+		);
+		assert!(
+			document.contains(
+				"<img src=\"images/image0.png\" alt=\"a\"> <img src='https://example.com/b.png'>"
+			),
+			"Images missing in {document}"
+		);
+		assert!(
+			document.contains("<title>Räksmörgås &amp; mer</title>"),
+			"Text not decoded in {document}"
+		);
+	}
+
+	#[test]
+	fn test_html_zip_to_epub() {
+		let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+		let files: [(&str, &[u8]); 4] = [
+			("site/page/other.html", b"<h1>Other</h1>"),
+			(
+				"site/page/index.html",
+				b"<p><img src=\"./img/a.png\"><img src=\"../shared/b.png\"><img src=\"../../../c.png\"></p>",
+			),
+			("site/page/img/a.png", PNG),
+			("site/shared/b.png", PNG),
+		];
+		for (name, data) in files {
+			zip.start_file(name, SimpleFileOptions::default())
+				.expect("Failed to start file");
+			zip.write_all(data).expect("Failed to write file");
+		}
+		let bytes = zip.finish().expect("Failed to finish zip").into_inner();
+
+		let epub = html_zip_to_epub(Cursor::new(bytes), "saved").expect("Failed to convert");
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		assert_eq!(
+			package.metadata.title.as_deref(),
+			Some("saved"),
+			"Title missmatch"
+		);
+		assert_eq!(navigation.nav_points.len(), 1, "Navigation missmatch");
+		let document = read_document(&mut archive, "index.html");
+		assert_eq!(
+			document,
+			"<p><img src=\"images/image0.png\"><img src=\"images/image1.png\"><img src=\"../../../c.png\"></p>",
+			"Document missmatch"
+		);
+		let mut image = Vec::new();
+		archive
+			.by_path("images/image1.png")
+			.expect("Missing image")
+			.read_to_end(&mut image)
+			.expect("Failed to read image");
+		assert_eq!(image, PNG, "Image missmatch");
+	}
+}
@@ -0,0 +1,308 @@
+use crate::MobiError;
+use crate::palmdb::be_u16;
+use crate::palmdb::be_u32;
+
+/// Nesting of dictionary phrases compressed with other phrases
+const MAX_PHRASE_DEPTH: usize = 32;
+
+/// Decompress PalmDOC text record, LZ77 with byte pairs for space and letter
+pub(crate) fn palmdoc_decompress(data: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(data.len() * 2);
+	let mut i = 0;
+	while let Some(&c) = data.get(i) {
+		i += 1;
+		match c {
+			0x01..=0x08 => {
+				let end = (i + c as usize).min(data.len());
+				out.extend_from_slice(&data[i..end]);
+				i = end;
+			}
+			0x00 | 0x09..=0x7f => out.push(c),
+			0x80..=0xbf => {
+				let Some(&next) = data.get(i) else {
+					break;
+				};
+				i += 1;
+				let pair = (u16::from(c) << 8 | u16::from(next)) & 0x3fff;
+				let distance = (pair >> 3) as usize;
+				let length = (pair & 0x7) as usize + 3;
+				if distance == 0 || distance > out.len() {
+					log::debug!("Invalid PalmDOC distance {distance}");
+					continue;
+				}
+				let start = out.len() - distance;
+				for j in start..start + length {
+					out.push(out[j]);
+				}
+			}
+			0xc0..=0xff => {
+				out.push(b' ');
+				out.push(c ^ 0x80);
+			}
+		}
+	}
+	out
+}
+
+/// Size of trailing entries at end of text record, by extra data flags of header
+pub(crate) fn trailing_size(record: &[u8], flags: u16) -> usize {
+	let mut size = 0;
+	for bit in 1..16 {
+		if flags & (1 << bit) != 0 {
+			let end = record.len().saturating_sub(size);
+			size += backward_int(&record[..end]);
+		}
+	}
+	// Multibyte character overlapping next record, count in lowest bits
+	if flags & 1 != 0
+		&& let Some(last) = record.len().checked_sub(size + 1).map(|i| record[i])
+	{
+		size += (last & 0x3) as usize + 1;
+	}
+	size.min(record.len())
+}
+
+/// Variable width integer read backwards from end of data, first byte has high bit set
+fn backward_int(data: &[u8]) -> usize {
+	let mut value = 0;
+	for (i, byte) in data.iter().rev().take(4).enumerate() {
+		value |= ((byte & 0x7f) as usize) << (7 * i);
+		if byte & 0x80 != 0 {
+			break;
+		}
+	}
+	value
+}
+
+#[derive(Debug, Clone)]
+enum Phrase {
+	Packed(Vec<u8>),
+	Unpacked(Vec<u8>),
+	/// Phrase being unpacked, refers to itself if seen again
+	Pending,
+}
+
+/// Huffman code lengths of a code prefix byte
+#[derive(Debug, Clone, Copy)]
+struct CodeLength {
+	length: u32,
+	terminal: bool,
+	max_code: u64,
+}
+
+/// Decompressor of HUFF/CDIC text records, Huffman codes of dictionary phrases
+pub(crate) struct HuffCdic {
+	lengths: Vec<CodeLength>,
+	min_codes: [u64; 33],
+	max_codes: [u64; 33],
+	phrases: Vec<Phrase>,
+}
+
+impl HuffCdic {
+	pub(crate) fn new(huff: &[u8], cdics: &[&[u8]]) -> Result<Self, MobiError> {
+		let invalid = || MobiError::InvalidRecord("HUFF");
+		if !huff.starts_with(b"HUFF\x00\x00\x00\x18") {
+			return Err(invalid());
+		}
+		let lengths_offset = be_u32(huff, 8).ok_or_else(invalid)? as usize;
+		let ranges_offset = be_u32(huff, 12).ok_or_else(invalid)? as usize;
+
+		let mut lengths = Vec::with_capacity(256);
+		for i in 0..256 {
+			let value = be_u32(huff, lengths_offset + i * 4).ok_or_else(invalid)?;
+			let length = value & 0x1f;
+			let terminal = value & 0x80 != 0;
+			if length == 0 || (length <= 8 && !terminal) {
+				return Err(invalid());
+			}
+			let max_code = ((u64::from(value >> 8) + 1) << (32 - length)) - 1;
+			lengths.push(CodeLength {
+				length,
+				terminal,
+				max_code,
+			});
+		}
+		let mut min_codes = [0; 33];
+		let mut max_codes = [u32::MAX.into(); 33];
+		for length in 1..=32 {
+			let offset = ranges_offset + (length - 1) * 8;
+			let min = be_u32(huff, offset).ok_or_else(invalid)?;
+			let max = be_u32(huff, offset + 4).ok_or_else(invalid)?;
+			min_codes[length] = u64::from(min) << (32 - length);
+			max_codes[length] = ((u64::from(max) + 1) << (32 - length)) - 1;
+		}
+
+		let mut phrases = Vec::new();
+		for cdic in cdics {
+			let invalid = || MobiError::InvalidRecord("CDIC");
+			if !cdic.starts_with(b"CDIC\x00\x00\x00\x10") {
+				return Err(invalid());
+			}
+			let count = be_u32(cdic, 8).ok_or_else(invalid)? as usize;
+			let bits = be_u32(cdic, 12).ok_or_else(invalid)?;
+			if bits > 16 {
+				return Err(invalid());
+			}
+			let n = (1 << bits).min(count.saturating_sub(phrases.len()));
+			for i in 0..n {
+				let offset = be_u16(cdic, 16 + i * 2).ok_or_else(invalid)? as usize;
+				let len = be_u16(cdic, 16 + offset).ok_or_else(invalid)?;
+				let start = 18 + offset;
+				let data = cdic
+					.get(start..start + (len & 0x7fff) as usize)
+					.ok_or_else(invalid)?
+					.to_vec();
+				phrases.push(if len & 0x8000 != 0 {
+					Phrase::Unpacked(data)
+				} else {
+					Phrase::Packed(data)
+				});
+			}
+		}
+
+		Ok(Self {
+			lengths,
+			min_codes,
+			max_codes,
+			phrases,
+		})
+	}
+
+	pub(crate) fn decompress(&mut self, data: &[u8]) -> Result<Vec<u8>, MobiError> {
+		self.unpack(data, 0)
+	}
+
+	fn unpack(&mut self, data: &[u8], depth: usize) -> Result<Vec<u8>, MobiError> {
+		let invalid = || MobiError::InvalidRecord("HUFF");
+		if depth > MAX_PHRASE_DEPTH {
+			return Err(invalid());
+		}
+		let word = |pos: usize| {
+			let mut bytes = [0; 8];
+			for (i, byte) in bytes.iter_mut().enumerate() {
+				*byte = data.get(pos + i).copied().unwrap_or(0);
+			}
+			u64::from_be_bytes(bytes)
+		};
+
+		let mut out = Vec::new();
+		let mut bits_left = data.len() as i64 * 8;
+		let mut pos = 0;
+		let mut x = word(pos);
+		let mut n: i32 = 32;
+		loop {
+			if n <= 0 {
+				pos += 4;
+				x = word(pos);
+				n += 32;
+			}
+			let code = (x >> n) & 0xffff_ffff;
+			let CodeLength {
+				mut length,
+				terminal,
+				mut max_code,
+			} = self.lengths[(code >> 24) as usize];
+			if !terminal {
+				while length < 32 && code < self.min_codes[length as usize] {
+					length += 1;
+				}
+				max_code = self.max_codes[length as usize];
+			}
+			n -= length as i32;
+			bits_left -= i64::from(length);
+			if bits_left < 0 {
+				break;
+			}
+
+			let index = (max_code.checked_sub(code).ok_or_else(invalid)? >> (32 - length)) as usize;
+			let phrase = self.phrases.get_mut(index).ok_or_else(invalid)?;
+			match std::mem::replace(phrase, Phrase::Pending) {
+				Phrase::Unpacked(bytes) => {
+					out.extend_from_slice(&bytes);
+					self.phrases[index] = Phrase::Unpacked(bytes);
+				}
+				Phrase::Packed(bytes) => {
+					let bytes = self.unpack(&bytes, depth + 1)?;
+					out.extend_from_slice(&bytes);
+					self.phrases[index] = Phrase::Unpacked(bytes);
+				}
+				Phrase::Pending => return Err(invalid()),
+			}
+		}
+		Ok(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::compression::HuffCdic;
+	use crate::compression::palmdoc_decompress;
+	use crate::compression::trailing_size;
+
+	#[test]
+	fn test_palmdoc_decompress() {
+		// Literal, space pair, copied bytes and distance/length pair
+		let mut data = b"ab".to_vec();
+		data.push(b'c' ^ 0x80);
+		data.extend([0x02, 0xe9, 0xff]);
+		// Distance 5, length 4
+		let pair: u16 = 0x8000 | (5 << 3) | (4 - 3);
+		data.extend(pair.to_be_bytes());
+		assert_eq!(
+			palmdoc_decompress(&data),
+			b"ab c\xe9\xffb c\xe9".to_vec(),
+			"Text missmatch"
+		);
+	}
+
+	#[test]
+	fn test_trailing_size() {
+		// Entry of three bytes ending with its size, before it one overlapping byte and its count
+		let record = b"text\xc3\xa5\x01\x7f\x7f\x83";
+		assert_eq!(trailing_size(record, 0b11), 5, "Trailing size missmatch");
+		assert_eq!(trailing_size(record, 0b10), 3, "Entry size missmatch");
+	}
+
+	/// HUFF record where every byte is a code of eight bits for phrase `255 - byte`
+	fn huff() -> Vec<u8> {
+		let mut huff = b"HUFF\x00\x00\x00\x18".to_vec();
+		huff.extend(24u32.to_be_bytes());
+		huff.extend((24 + 256 * 4u32).to_be_bytes());
+		huff.extend([0; 8]);
+		for _ in 0..256 {
+			huff.extend((255 << 8 | 0x80 | 8u32).to_be_bytes());
+		}
+		huff.extend([0; 64 * 4]);
+		huff
+	}
+
+	fn cdic(phrases: &[(&[u8], bool)]) -> Vec<u8> {
+		let mut cdic = b"CDIC\x00\x00\x00\x10".to_vec();
+		cdic.extend((phrases.len() as u32).to_be_bytes());
+		cdic.extend(8u32.to_be_bytes());
+		let mut offset = phrases.len() * 2;
+		let mut data = Vec::new();
+		for (phrase, literal) in phrases {
+			cdic.extend((offset as u16).to_be_bytes());
+			let len = phrase.len() as u16 | if *literal { 0x8000 } else { 0 };
+			data.extend(len.to_be_bytes());
+			data.extend_from_slice(phrase);
+			offset += 2 + phrase.len();
+		}
+		cdic.extend(data);
+		cdic
+	}
+
+	#[test]
+	fn test_huff_cdic() {
+		// Phrase 2 is packed, codes of phrase 0 and 1
+		let cdic = cdic(&[(b"Hello", true), (b", ", true), (&[255, 254], false)]);
+		let mut huff = HuffCdic::new(&huff(), &[&cdic]).expect("Failed to read tables");
+		assert_eq!(
+			huff.decompress(&[253, 255, 255])
+				.expect("Failed to decompress"),
+			b"Hello, HelloHello".to_vec(),
+			"Text missmatch"
+		);
+	}
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use crate::MobiError;
+use crate::palmdb::PalmDb;
+use crate::palmdb::be_u16;
+use crate::palmdb::be_u32;
+
+/// Tag of TAGX table, how values of a tag are stored in index entries
+#[derive(Debug, Clone, Copy)]
+struct TagDefinition {
+	tag: u8,
+	values_per_entry: usize,
+	mask: u8,
+	/// Marks end of a control byte
+	end: bool,
+}
+
+/// Entry of index, label and values by tag
+#[derive(Debug)]
+pub(crate) struct IndexEntry {
+	pub(crate) label: String,
+	pub(crate) tags: BTreeMap<u8, Vec<u32>>,
+}
+
+impl IndexEntry {
+	pub(crate) fn value(&self, tag: u8, i: usize) -> Option<u32> {
+		self.tags.get(&tag)?.get(i).copied()
+	}
+}
+
+/// INDX records, e.g. table of contents or KF8 skeletons and fragments
+#[derive(Debug)]
+pub(crate) struct Index {
+	pub(crate) entries: Vec<IndexEntry>,
+	/// Strings referenced by entries, by offset
+	strings: BTreeMap<u32, String>,
+}
+
+impl Index {
+	/// Read index starting with header record at `index`
+	pub(crate) fn read(db: &PalmDb<'_>, index: usize) -> Result<Self, MobiError> {
+		let invalid = || MobiError::InvalidRecord("INDX");
+		let header = db.record(index)?;
+		if !header.starts_with(b"INDX") {
+			return Err(invalid());
+		}
+		let records = be_u32(header, 24).ok_or_else(invalid)? as usize;
+		let string_records = be_u32(header, 52).ok_or_else(invalid)? as usize;
+		let tagx_offset = be_u32(header, 180).ok_or_else(invalid)? as usize;
+		let tagx = header.get(tagx_offset..).ok_or_else(invalid)?;
+		if !tagx.starts_with(b"TAGX") {
+			return Err(invalid());
+		}
+		let tagx_end = be_u32(tagx, 4).ok_or_else(invalid)? as usize;
+		let control_bytes = be_u32(tagx, 8).ok_or_else(invalid)? as usize;
+		let definitions = tagx
+			.get(12..tagx_end)
+			.ok_or_else(invalid)?
+			.chunks_exact(4)
+			.map(|t| TagDefinition {
+				tag: t[0],
+				values_per_entry: t[1] as usize,
+				mask: t[2],
+				end: t[3] & 0x1 != 0,
+			})
+			.collect::<Vec<_>>();
+
+		let mut strings = BTreeMap::new();
+		for i in 0..string_records {
+			let data = db.record(index + records + 1 + i)?;
+			let mut pos = 0;
+			while pos < data.len() {
+				let (len, read) = forward_int(&data[pos..]);
+				let len = len as usize;
+				if len > 0
+					&& let Some(text) = data.get(pos + read..pos + read + len)
+				{
+					let offset = (i * 0x10000 + pos) as u32;
+					strings.insert(offset, String::from_utf8_lossy(text).into_owned());
+				}
+				pos += read + len;
+			}
+		}
+
+		let mut entries = Vec::new();
+		for i in 0..records {
+			let data = db.record(index + 1 + i)?;
+			if !data.starts_with(b"INDX") {
+				return Err(invalid());
+			}
+			let idxt = be_u32(data, 20).ok_or_else(invalid)? as usize;
+			let count = be_u32(data, 24).ok_or_else(invalid)? as usize;
+			let mut starts = (0..count)
+				.map(|j| be_u16(data, idxt + 4 + j * 2).map(usize::from))
+				.collect::<Option<Vec<_>>>()
+				.ok_or_else(invalid)?;
+			starts.push(idxt);
+			for range in starts.windows(2) {
+				let entry = data.get(range[0]..range[1]).ok_or_else(invalid)?;
+				let label_len = *entry.first().ok_or_else(invalid)? as usize;
+				let label = entry.get(1..1 + label_len).ok_or_else(invalid)?;
+				let tags = tag_values(&definitions, control_bytes, &entry[1 + label_len..]);
+				entries.push(IndexEntry {
+					label: String::from_utf8_lossy(label).into_owned(),
+					tags,
+				});
+			}
+		}
+		Ok(Self { entries, strings })
+	}
+
+	pub(crate) fn string(&self, offset: u32) -> Option<&str> {
+		self.strings.get(&offset).map(String::as_str)
+	}
+}
+
+/// Variable width integer, last byte has high bit set, as value and bytes read
+pub(crate) fn forward_int(data: &[u8]) -> (u32, usize) {
+	let mut value: u32 = 0;
+	for (i, byte) in data.iter().take(5).enumerate() {
+		value = value << 7 | u32::from(byte & 0x7f);
+		if byte & 0x80 != 0 {
+			return (value, i + 1);
+		}
+	}
+	(value, data.len().min(5))
+}
+
+/// Values of entry by tag, control bytes tell which tags are present and how many values
+fn tag_values(
+	definitions: &[TagDefinition],
+	control_bytes: usize,
+	data: &[u8],
+) -> BTreeMap<u8, Vec<u32>> {
+	let Some((controls, mut rest)) = data.split_at_checked(control_bytes) else {
+		return BTreeMap::new();
+	};
+	// Tags present, with count of values or bytes of values
+	let mut present = Vec::new();
+	let mut control = 0;
+	for definition in definitions {
+		if definition.end {
+			control += 1;
+			continue;
+		}
+		let Some(byte) = controls.get(control) else {
+			break;
+		};
+		let value = byte & definition.mask;
+		if value == 0 {
+			continue;
+		}
+		if value == definition.mask && definition.mask.count_ones() > 1 {
+			let (bytes, read) = forward_int(rest);
+			rest = &rest[read..];
+			present.push((definition, None, Some(bytes as usize)));
+		} else {
+			let count = (value >> definition.mask.trailing_zeros()) as usize;
+			present.push((definition, Some(count), None));
+		}
+	}
+
+	let mut tags = BTreeMap::new();
+	for (definition, count, bytes) in present {
+		let mut values = Vec::new();
+		if let Some(count) = count {
+			for _ in 0..count * definition.values_per_entry {
+				let (value, read) = forward_int(rest);
+				rest = &rest[read..];
+				values.push(value);
+			}
+		} else if let Some(bytes) = bytes {
+			let mut consumed = 0;
+			while consumed < bytes && !rest.is_empty() {
+				let (value, read) = forward_int(rest);
+				rest = &rest[read..];
+				consumed += read;
+				values.push(value);
+			}
+		}
+		tags.insert(definition.tag, values);
+	}
+	tags
+}
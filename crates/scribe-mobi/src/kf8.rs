@@ -0,0 +1,276 @@
+use std::ops::Range;
+
+use scribe_epub::EpubBuilder;
+
+use crate::Images;
+use crate::MobiError;
+use crate::index::Index;
+use crate::palmdb::MobiHeader;
+use crate::palmdb::PalmDb;
+use crate::palmdb::be_u32;
+use crate::part_href;
+use crate::read_ncx;
+
+/// Fragment of text inserted into a skeleton
+#[derive(Debug)]
+struct Fragment {
+	/// Position in text of part the fragment is inserted at
+	insert: usize,
+	len: usize,
+}
+
+/// Assembled xhtml document of a skeleton and its fragments
+struct Part {
+	/// Range in text, skeleton followed by its fragments
+	range: Range<usize>,
+	content: Vec<u8>,
+}
+
+/// Convert text of KF8 book, one document per skeleton
+///
+/// Text is split into flows, the first holds skeletons and fragments, the
+/// others stylesheets and images referenced as `kindle:flow`.
+pub(crate) fn convert(
+	epub: &mut EpubBuilder,
+	images: &mut Images<'_, '_>,
+	db: &PalmDb<'_>,
+	header: &MobiHeader<'_>,
+	start: usize,
+	text: &[u8],
+) -> Result<(), MobiError> {
+	let flows = match header.fdst_index {
+		Some(index) => read_flows(db.record(start + index)?, text.len())?,
+		None => vec![Range {
+			start: 0,
+			end: text.len(),
+		}],
+	};
+	let markup = &text[flows[0].clone()];
+	let fragments = match header.fragment_index {
+		Some(index) => read_fragments(db, start + index)?,
+		None => Vec::new(),
+	};
+	let parts = match header.skeleton_index {
+		Some(index) => assemble_parts(db, start + index, markup, &fragments)?,
+		None => vec![Part {
+			range: 0..markup.len(),
+			content: markup.to_vec(),
+		}],
+	};
+
+	let mut links = Links {
+		flows: Vec::with_capacity(flows.len()),
+		fragments: &fragments,
+		parts: &parts,
+	};
+	links.flows.push(None);
+	for (n, range) in flows.iter().enumerate().skip(1) {
+		let data = &text[range.clone()];
+		let (href, mime) = if data.windows(4).any(|window| window == b"<svg") {
+			(format!("flow{n:04}.svg"), "image/svg+xml")
+		} else {
+			(format!("flow{n:04}.css"), "text/css")
+		};
+		let data = rewrite_urls(data, |url| links.resolve(epub, images, url));
+		epub.add_resource(&href, mime, data);
+		links.flows.push(Some(href));
+	}
+
+	for (i, part) in parts.iter().enumerate() {
+		let content = rewrite_urls(&part.content, |url| links.resolve(epub, images, url));
+		epub.add_xhtml(&part_href(i), content);
+	}
+
+	if let Some(index) = header.ncx_index {
+		for entry in read_ncx(db, start + index) {
+			let href = match (entry.fragment, entry.pos) {
+				(Some((fid, offset)), _) => links.position(fid, offset),
+				(None, Some(pos)) => links.part(pos as usize).map(part_href),
+				(None, None) => None,
+			};
+			if let Some(href) = href {
+				epub.add_toc(entry.level, &entry.title, &href);
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Flow ranges of FDST record
+fn read_flows(fdst: &[u8], len: usize) -> Result<Vec<Range<usize>>, MobiError> {
+	let invalid = || MobiError::InvalidRecord("FDST");
+	if !fdst.starts_with(b"FDST") {
+		return Err(invalid());
+	}
+	let count = be_u32(fdst, 8).ok_or_else(invalid)? as usize;
+	let flows = (0..count)
+		.map(|i| {
+			let start = be_u32(fdst, 12 + i * 8)? as usize;
+			let end = be_u32(fdst, 16 + i * 8)? as usize;
+			Some(start.min(len)..end.clamp(start.min(len), len))
+		})
+		.collect::<Option<Vec<_>>>()
+		.ok_or_else(invalid)?;
+	if flows.is_empty() {
+		return Err(invalid());
+	}
+	Ok(flows)
+}
+
+fn read_fragments(db: &PalmDb<'_>, index: usize) -> Result<Vec<Fragment>, MobiError> {
+	let index = Index::read(db, index)?;
+	index
+		.entries
+		.iter()
+		.map(|entry| {
+			Some(Fragment {
+				insert: entry.label.parse().ok()?,
+				len: entry.value(6, 1)? as usize,
+			})
+		})
+		.collect::<Option<Vec<_>>>()
+		.ok_or(MobiError::InvalidRecord("fragment index"))
+}
+
+/// Insert fragments into skeletons, fragments follow their skeleton in text
+fn assemble_parts(
+	db: &PalmDb<'_>,
+	index: usize,
+	markup: &[u8],
+	fragments: &[Fragment],
+) -> Result<Vec<Part>, MobiError> {
+	let invalid = || MobiError::InvalidRecord("skeleton index");
+	let skeletons = Index::read(db, index)?;
+	let mut fragments = fragments.iter();
+	let mut parts = Vec::with_capacity(skeletons.entries.len());
+	for entry in &skeletons.entries {
+		let count = entry.value(1, 0).ok_or_else(invalid)? as usize;
+		let start = entry.value(6, 0).ok_or_else(invalid)? as usize;
+		let len = entry.value(6, 1).ok_or_else(invalid)? as usize;
+		let mut content = markup.get(start..start + len).ok_or_else(invalid)?.to_vec();
+		let mut pos = start + len;
+		for fragment in fragments.by_ref().take(count) {
+			let data = markup.get(pos..pos + fragment.len).ok_or_else(invalid)?;
+			let insert = fragment
+				.insert
+				.checked_sub(start)
+				.filter(|insert| *insert <= content.len())
+				.ok_or_else(invalid)?;
+			content.splice(insert..insert, data.iter().copied());
+			pos += fragment.len;
+		}
+		parts.push(Part {
+			range: start..pos,
+			content,
+		});
+	}
+	Ok(parts)
+}
+
+/// Targets of `kindle:` urls
+struct Links<'a> {
+	/// Href of flow, none for the first flow
+	flows: Vec<Option<String>>,
+	fragments: &'a [Fragment],
+	parts: &'a [Part],
+}
+
+impl Links<'_> {
+	/// Href of url without `kindle:` prefix, e.g. `embed:0001?mime=image/jpeg`
+	fn resolve(
+		&self,
+		epub: &mut EpubBuilder,
+		images: &mut Images<'_, '_>,
+		url: &str,
+	) -> Option<String> {
+		let mut fields = url.split([':', '?']);
+		match fields.next()? {
+			"embed" => {
+				let offset = base32(fields.next()?)?.checked_sub(1)?;
+				images.href(epub, offset as usize)
+			}
+			"flow" => self.flows.get(base32(fields.next()?)? as usize)?.clone(),
+			"pos" => {
+				let (_, fid, _, offset) = (
+					fields.next()?,
+					fields.next()?,
+					fields.next()?,
+					fields.next()?,
+				);
+				self.position(base32(fid)?, base32(offset)?)
+			}
+			_ => None,
+		}
+	}
+
+	fn part(&self, pos: usize) -> Option<usize> {
+		self.parts.iter().position(|part| part.range.contains(&pos))
+	}
+
+	/// Href of position at offset in fragment, with id of element at or before it
+	fn position(&self, fid: u32, offset: u32) -> Option<String> {
+		let fragment = self.fragments.get(fid as usize)?;
+		let pos = fragment.insert + offset as usize;
+		let i = self.part(pos)?;
+		let part = &self.parts[i];
+		let href = part_href(i);
+		Some(match id_before(&part.content, pos - part.range.start) {
+			Some(id) => format!("{href}#{id}"),
+			None => href,
+		})
+	}
+}
+
+fn base32(value: &str) -> Option<u32> {
+	u32::from_str_radix(value, 32).ok()
+}
+
+/// Id of last element starting at or before position, including a tag the position is in
+fn id_before(content: &[u8], pos: usize) -> Option<String> {
+	let pos = pos.min(content.len());
+	let end = match content[pos..].iter().position(|c| matches!(c, b'<' | b'>')) {
+		Some(i) if content[pos + i] == b'>' || i == 0 => content[pos + i..]
+			.iter()
+			.position(|c| *c == b'>')
+			.map_or(content.len(), |end| pos + i + end + 1),
+		_ => pos,
+	};
+	let before = &content[..end];
+	let start = before.windows(4).rposition(|window| {
+		window[0].is_ascii_whitespace() && window[1..].eq_ignore_ascii_case(b"id=")
+	})? + 4;
+	let quote = *before.get(start)?;
+	if !matches!(quote, b'"' | b'\'') {
+		return None;
+	}
+	let len = before[start + 1..].iter().position(|c| *c == quote)?;
+	Some(String::from_utf8_lossy(&before[start + 1..start + 1 + len]).into_owned())
+}
+
+/// Replace `kindle:` urls, left as is if not resolved
+fn rewrite_urls(data: &[u8], mut resolve: impl FnMut(&str) -> Option<String>) -> Vec<u8> {
+	const PREFIX: &[u8] = b"kindle:";
+	let mut out = Vec::with_capacity(data.len());
+	let mut pos = 0;
+	while let Some(start) = data[pos..]
+		.windows(PREFIX.len())
+		.position(|window| window == PREFIX)
+		.map(|i| pos + i)
+	{
+		let end = data[start..]
+			.iter()
+			.position(|c| {
+				c.is_ascii_whitespace() || matches!(c, b'"' | b'\'' | b'(' | b')' | b'<' | b'>')
+			})
+			.map_or(data.len(), |end| start + end);
+		out.extend_from_slice(&data[pos..start]);
+		let url = String::from_utf8_lossy(&data[start + PREFIX.len()..end]);
+		match resolve(&url) {
+			Some(href) => out.extend(href.bytes()),
+			None => out.extend_from_slice(&data[start..end]),
+		}
+		pos = end;
+	}
+	out.extend_from_slice(&data[pos..]);
+	out
+}
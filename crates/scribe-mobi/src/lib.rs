@@ -0,0 +1,642 @@
+//! Unencrypted MOBI and KF8 (AZW3) books converted to epub archives
+//!
+//! KF8 books, also the KF8 part of joint MOBI files, have a document per
+//! skeleton part. Older MOBI books are split into documents at page breaks.
+
+mod compression;
+mod index;
+mod kf8;
+mod mobi6;
+mod palmdb;
+
+use std::collections::BTreeMap;
+
+use scribe_epub::Creator;
+use scribe_epub::EpubBuilder;
+use scribe_epub::Metadata;
+
+use crate::compression::HuffCdic;
+use crate::compression::palmdoc_decompress;
+use crate::compression::trailing_size;
+use crate::index::Index;
+use crate::palmdb::Compression;
+use crate::palmdb::MobiHeader;
+use crate::palmdb::PalmDb;
+
+const EXTH_AUTHOR: u32 = 100;
+const EXTH_PUBLISHER: u32 = 101;
+const EXTH_DESCRIPTION: u32 = 103;
+const EXTH_ISBN: u32 = 104;
+const EXTH_SUBJECT: u32 = 105;
+const EXTH_DATE: u32 = 106;
+const EXTH_RIGHTS: u32 = 109;
+const EXTH_ASIN: u32 = 113;
+/// Header record of KF8 part of joint file
+const EXTH_KF8_BOUNDARY: u32 = 121;
+/// Cover image, as offset from first image record
+const EXTH_COVER_OFFSET: u32 = 201;
+const EXTH_TITLE: u32 = 503;
+const EXTH_LANGUAGE: u32 = 524;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MobiError {
+	#[error(transparent)]
+	Epub(#[from] scribe_epub::EpubError),
+	#[error("Not a MOBI book")]
+	NotMobi,
+	#[error("Book is encrypted")]
+	Encrypted,
+	#[error("Unknown compression {0}")]
+	Compression(u16),
+	#[error("Invalid {0} record")]
+	InvalidRecord(&'static str),
+	#[error("Missing record {0}")]
+	MissingRecord(usize),
+}
+
+/// Entry of NCX index, table of contents
+#[derive(Debug)]
+struct NcxEntry {
+	title: String,
+	level: usize,
+	/// Position in text
+	pos: Option<u32>,
+	/// Fragment and offset in fragment, of KF8 books
+	fragment: Option<(u32, u32)>,
+}
+
+/// Images of book, added to epub once referenced
+struct Images<'a, 'db> {
+	db: &'a PalmDb<'db>,
+	first: Option<usize>,
+	/// Href by offset from first image record, none if not an image
+	hrefs: BTreeMap<usize, Option<String>>,
+}
+
+impl<'a, 'db> Images<'a, 'db> {
+	fn new(db: &'a PalmDb<'db>, first: Option<usize>) -> Self {
+		Self {
+			db,
+			first,
+			hrefs: BTreeMap::new(),
+		}
+	}
+
+	/// Href of image at offset from first image record
+	fn href(&mut self, epub: &mut EpubBuilder, offset: usize) -> Option<String> {
+		if let Some(href) = self.hrefs.get(&offset) {
+			return href.clone();
+		}
+		let href = self
+			.first
+			.and_then(|first| self.db.record(first + offset).ok())
+			.and_then(|data| {
+				let (extension, mime) = image_type(data)?;
+				let href = format!("images/image{offset:05}.{extension}");
+				epub.add_resource(&href, mime, data.to_vec());
+				Some(href)
+			});
+		if href.is_none() {
+			log::debug!("Image record {offset} not found");
+		}
+		self.hrefs.insert(offset, href.clone());
+		href
+	}
+}
+
+/// Convert MOBI or KF8 book to epub archive
+pub fn mobi_to_epub(bytes: &[u8]) -> Result<Vec<u8>, MobiError> {
+	let db = PalmDb::parse(bytes)?;
+	let header = MobiHeader::parse(db.record(0)?)?;
+	let kf8_start = if header.version >= 8 {
+		Some(0)
+	} else {
+		header
+			.exth
+			.number(EXTH_KF8_BOUNDARY)
+			.and_then(|boundary| kf8_record(&db, boundary as usize))
+	};
+
+	let mut epub = EpubBuilder::new(metadata(&header));
+	// Images of joint files are shared, first image is only valid in first header
+	let mut images = Images::new(&db, header.first_image);
+	match kf8_start {
+		Some(start) => {
+			let kf8 = MobiHeader::parse(db.record(start)?)?;
+			let text = read_text(&db, &kf8, start)?;
+			kf8::convert(&mut epub, &mut images, &db, &kf8, start, &text)?;
+		}
+		None => {
+			let text = read_text(&db, &header, 0)?;
+			mobi6::convert(&mut epub, &mut images, &db, &header, &text)?;
+		}
+	}
+	if let Some(offset) = header.exth.number(EXTH_COVER_OFFSET)
+		&& let Some(href) = images.href(&mut epub, offset as usize)
+	{
+		epub.cover(&href);
+	}
+	Ok(epub.finish()?)
+}
+
+/// Header record of KF8 part, boundary offset points at or after the `BOUNDARY` record
+fn kf8_record(db: &PalmDb<'_>, boundary: usize) -> Option<usize> {
+	let is_boundary = |index: usize| {
+		db.record(index)
+			.is_ok_and(|record| record.starts_with(b"BOUNDARY"))
+	};
+	if boundary > 0 && is_boundary(boundary - 1) {
+		Some(boundary)
+	} else if is_boundary(boundary) {
+		Some(boundary + 1)
+	} else {
+		log::warn!("KF8 boundary {boundary} not found");
+		None
+	}
+}
+
+/// Decompressed text of text records following header record at `start`
+fn read_text(db: &PalmDb<'_>, header: &MobiHeader<'_>, start: usize) -> Result<Vec<u8>, MobiError> {
+	let mut huff = match header.compression {
+		Compression::HuffCdic => {
+			let first = start + header.huff_record.ok_or(MobiError::InvalidRecord("HUFF"))?;
+			let cdics = (1..header.huff_count)
+				.map(|i| db.record(first + i))
+				.collect::<Result<Vec<_>, _>>()?;
+			Some(HuffCdic::new(db.record(first)?, &cdics)?)
+		}
+		Compression::None | Compression::PalmDoc => None,
+	};
+	let mut text = Vec::with_capacity(header.text_length);
+	for i in 1..=header.text_records {
+		let record = db.record(start + i)?;
+		let record = &record[..record.len() - trailing_size(record, header.extra_flags)];
+		match &mut huff {
+			Some(huff) => text.extend(huff.decompress(record)?),
+			None if header.compression == Compression::PalmDoc => {
+				text.extend(palmdoc_decompress(record))
+			}
+			None => text.extend_from_slice(record),
+		}
+	}
+	text.truncate(header.text_length);
+	Ok(text)
+}
+
+/// Table of contents of NCX index, empty if book has none
+fn read_ncx(db: &PalmDb<'_>, index: usize) -> Vec<NcxEntry> {
+	let ncx = match Index::read(db, index) {
+		Ok(ncx) => ncx,
+		Err(e) => {
+			log::warn!("Failed to read NCX index: {e}");
+			return Vec::new();
+		}
+	};
+	ncx.entries
+		.iter()
+		.filter_map(|entry| {
+			let title = ncx.string(entry.value(3, 0)?)?.trim().to_string();
+			Some(NcxEntry {
+				title,
+				level: entry.value(4, 0).unwrap_or(0) as usize,
+				pos: entry.value(1, 0),
+				fragment: entry.value(6, 0).zip(entry.value(6, 1)),
+			})
+		})
+		.collect()
+}
+
+fn metadata(header: &MobiHeader<'_>) -> Metadata {
+	let exth = &header.exth;
+	let text = |value: &[u8]| {
+		Some(header.encoding.decode(value).trim().to_string()).filter(|v| !v.is_empty())
+	};
+	let field = |kind| exth.value(kind).and_then(text);
+	let creators = exth
+		.values(EXTH_AUTHOR)
+		.filter_map(text)
+		.map(|name| Creator {
+			name,
+			role: Some("aut".to_string()),
+			file_as: None,
+		})
+		.collect::<Vec<_>>();
+	Metadata {
+		identifier: field(EXTH_ISBN).or_else(|| field(EXTH_ASIN)),
+		title: field(EXTH_TITLE).or_else(|| text(header.full_name)),
+		creator: creators.first().map(|c| c.name.clone()),
+		creators,
+		description: field(EXTH_DESCRIPTION),
+		publisher: field(EXTH_PUBLISHER),
+		subjects: exth.values(EXTH_SUBJECT).filter_map(text).collect(),
+		language: field(EXTH_LANGUAGE).or_else(|| locale_language(header.locale)),
+		date: field(EXTH_DATE),
+		rights: field(EXTH_RIGHTS),
+		..Default::default()
+	}
+}
+
+/// Language of Windows locale of header, low byte is primary language
+fn locale_language(locale: u32) -> Option<String> {
+	let code = match locale & 0xff {
+		0x04 => "zh",
+		0x06 => "da",
+		0x07 => "de",
+		0x09 => "en",
+		0x0a => "es",
+		0x0b => "fi",
+		0x0c => "fr",
+		0x10 => "it",
+		0x11 => "ja",
+		0x12 => "ko",
+		0x13 => "nl",
+		0x14 => "nb",
+		0x15 => "pl",
+		0x16 => "pt",
+		0x19 => "ru",
+		0x1d => "sv",
+		0x1f => "tr",
+		_ => return None,
+	};
+	Some(code.to_string())
+}
+
+fn image_type(data: &[u8]) -> Option<(&'static str, &'static str)> {
+	if data.starts_with(b"\xff\xd8\xff") {
+		Some(("jpg", "image/jpeg"))
+	} else if data.starts_with(b"\x89PNG") {
+		Some(("png", "image/png"))
+	} else if data.starts_with(b"GIF8") {
+		Some(("gif", "image/gif"))
+	} else if data.starts_with(b"BM") {
+		Some(("bmp", "image/bmp"))
+	} else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+		Some(("webp", "image/webp"))
+	} else {
+		None
+	}
+}
+
+fn part_href(i: usize) -> String {
+	format!("part{i:04}.xhtml")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::io::Read;
+
+	use scribe_epub::EpubMetadata;
+	use zip::ZipArchive;
+
+	use crate::mobi_to_epub;
+
+	const HEADER_LENGTH: usize = 248;
+
+	fn set(record: &mut [u8], offset: usize, value: u32) {
+		record[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+	}
+
+	/// Palm database of records
+	fn palmdb(records: &[Vec<u8>]) -> Vec<u8> {
+		let mut db = b"Test".to_vec();
+		db.resize(60, 0);
+		db.extend(b"BOOKMOBI");
+		db.extend([0; 8]);
+		db.extend((records.len() as u16).to_be_bytes());
+		let mut offset = 78 + records.len() * 8 + 2;
+		for (i, record) in records.iter().enumerate() {
+			db.extend((offset as u32).to_be_bytes());
+			db.extend((i as u32 * 2).to_be_bytes());
+			offset += record.len();
+		}
+		db.extend([0; 2]);
+		for record in records {
+			db.extend(record);
+		}
+		db
+	}
+
+	/// Record 0 with MOBI header fields at offsets, others unset, followed by EXTH and full name
+	fn header_record(
+		compression: u16,
+		text: &[u8],
+		fields: &[(usize, u32)],
+		exth: &[(u32, &[u8])],
+		name: &str,
+	) -> Vec<u8> {
+		let mut record = vec![0; 16];
+		record[..2].copy_from_slice(&compression.to_be_bytes());
+		set(&mut record, 4, text.len() as u32);
+		record[8..10].copy_from_slice(&1u16.to_be_bytes());
+		record.extend(b"MOBI");
+		record.resize(16 + HEADER_LENGTH, 0xff);
+		set(&mut record, 20, HEADER_LENGTH as u32);
+		set(&mut record, 128, 0x40);
+		set(&mut record, 240, 0);
+		for &(offset, value) in fields {
+			set(&mut record, offset, value);
+		}
+
+		let mut records = Vec::new();
+		for (kind, value) in exth {
+			records.extend(kind.to_be_bytes());
+			records.extend((value.len() as u32 + 8).to_be_bytes());
+			records.extend(*value);
+		}
+		record.extend(b"EXTH");
+		record.extend((records.len() as u32 + 12).to_be_bytes());
+		record.extend((exth.len() as u32).to_be_bytes());
+		record.extend(records);
+		let name_offset = record.len() as u32;
+		record.extend(name.as_bytes());
+		set(&mut record, 84, name_offset);
+		set(&mut record, 88, name.len() as u32);
+		record
+	}
+
+	fn forward_int(value: u32) -> Vec<u8> {
+		let mut bytes = vec![(value & 0x7f) as u8 | 0x80];
+		let mut value = value >> 7;
+		while value > 0 {
+			bytes.insert(0, (value & 0x7f) as u8);
+			value >>= 7;
+		}
+		bytes
+	}
+
+	/// Strings record and offsets of strings
+	fn cncx(strings: &[&str]) -> (Vec<u8>, Vec<u32>) {
+		let mut record = Vec::new();
+		let mut offsets = Vec::new();
+		for string in strings {
+			offsets.push(record.len() as u32);
+			record.extend(forward_int(string.len() as u32));
+			record.extend(string.as_bytes());
+		}
+		(record, offsets)
+	}
+
+	/// Label of index entry and values by tag
+	type Entry<'a> = (&'a str, &'a [(u8, &'a [u32])]);
+
+	/// Index header and entry records, tags with values per entry and one control byte
+	fn index(tags: &[(u8, u8)], entries: &[Entry<'_>], cncx: Option<Vec<u8>>) -> Vec<Vec<u8>> {
+		let mut header = vec![0; 192];
+		header[..4].copy_from_slice(b"INDX");
+		set(&mut header, 24, 1);
+		set(&mut header, 52, u32::from(cncx.is_some()));
+		set(&mut header, 180, 192);
+		header.extend(b"TAGX");
+		header.extend((12 + 4 * (tags.len() as u32 + 1)).to_be_bytes());
+		header.extend(1u32.to_be_bytes());
+		for (i, (tag, values)) in tags.iter().enumerate() {
+			header.extend([*tag, *values, 1 << i, 0]);
+		}
+		header.extend([0, 0, 0, 1]);
+
+		let mut record = vec![0; 32];
+		record[..4].copy_from_slice(b"INDX");
+		let mut starts = Vec::new();
+		for (label, values) in entries {
+			starts.push(record.len() as u16);
+			record.push(label.len() as u8);
+			record.extend(label.as_bytes());
+			let control = tags
+				.iter()
+				.enumerate()
+				.filter(|(_, (tag, _))| values.iter().any(|(t, _)| t == tag))
+				.fold(0, |control, (i, _)| control | 1 << i);
+			record.push(control);
+			for (tag, _) in tags {
+				for (_, values) in values.iter().filter(|(t, _)| t == tag) {
+					for value in *values {
+						record.extend(forward_int(*value));
+					}
+				}
+			}
+		}
+		let idxt = record.len() as u32;
+		set(&mut record, 20, idxt);
+		set(&mut record, 24, entries.len() as u32);
+		record.extend(b"IDXT");
+		for start in starts {
+			record.extend(start.to_be_bytes());
+		}
+
+		let mut records = vec![header, record];
+		records.extend(cncx);
+		records
+	}
+
+	fn read(archive: &mut ZipArchive<Cursor<Vec<u8>>>, path: &str) -> String {
+		let mut content = String::new();
+		archive
+			.by_path(path)
+			.expect("Missing file")
+			.read_to_string(&mut content)
+			.expect("Failed to read file");
+		content
+	}
+
+	const PNG: &[u8] = b"\x89PNG\r\n\x1a\nimage";
+
+	#[test]
+	fn test_mobi6_to_epub() {
+		let mut text = b"<html><head></head><body><p>Caf\xe9 <a filepos=0000000000 >next</a></p><img recindex=\"00001\"><mbp:pagebreak/><p>Two</p></body></html>".to_vec();
+		let find = |text: &[u8], needle: &[u8]| {
+			text.windows(needle.len())
+				.position(|window| window == needle)
+				.expect("Missing needle")
+		};
+		let pagebreak = find(&text, b"<mbp:");
+		let filepos = find(&text, b"0000000000");
+		text.splice(filepos..filepos + 10, format!("{pagebreak:010}").bytes());
+		// PalmDOC literals, bytes above 0x7f in runs of one, and trailing entry of one byte
+		let mut record = Vec::new();
+		for byte in &text {
+			if *byte >= 0x80 {
+				record.push(1);
+			}
+			record.push(*byte);
+		}
+		record.push(0x81);
+
+		let header = header_record(
+			2,
+			&text,
+			&[(28, 1252), (36, 6), (108, 2), (240, 0b10)],
+			&[
+				(100, b"Jane Doe"),
+				(503, b"Title"),
+				(201, &0u32.to_be_bytes()),
+			],
+			"Name",
+		);
+		let book = palmdb(&[header, record, PNG.to_vec()]);
+		let epub = mobi_to_epub(&book).expect("Failed to convert");
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+
+		let m = &package.metadata;
+		assert_eq!(m.title.as_deref(), Some("Title"), "Title missmatch");
+		assert_eq!(m.creator.as_deref(), Some("Jane Doe"), "Creator missmatch");
+		assert_eq!(package.spine.len(), 2, "Spine missmatch");
+		let cover = m
+			.cover
+			.as_ref()
+			.and_then(|id| package.manifest.get(id))
+			.expect("Missing cover");
+		assert_eq!(cover.mime, "image/png", "Cover mime missmatch");
+
+		let first = read(&mut archive, "part0000.xhtml");
+		assert!(first.contains("<p>Café "), "Text missing in {first}");
+		assert!(
+			first.contains(&format!(
+				"<a href=\"part0001.xhtml#filepos{pagebreak}\" >next</a>"
+			)),
+			"Link missing in {first}"
+		);
+		assert!(
+			first.contains("<img src=\"images/image00000.png\">"),
+			"Image missing in {first}"
+		);
+		let second = read(&mut archive, "part0001.xhtml");
+		assert!(
+			second.contains(&format!(
+				"<body>\n<a id=\"filepos{pagebreak}\"></a><p>Two</p></body>"
+			)),
+			"Anchor missing in {second}"
+		);
+	}
+
+	#[test]
+	fn test_kf8_to_epub() {
+		let skeletons: [&[u8]; 2] = [
+			b"<html><head><link href=\"kindle:flow:0001?mime=text/css\" rel=\"stylesheet\"/></head><body></body></html>",
+			b"<html><head></head><body></body></html>",
+		];
+		let fragments: [&[u8]; 2] = [
+			b"<p>One <a href=\"kindle:pos:fid:0001:off:0000000000\">next</a><img src=\"kindle:embed:0001?mime=image/png\"/></p>",
+			b"<div id=\"start\"><p>Two</p></div>",
+		];
+		let css = b"p { margin: 0 }";
+		let mut text = Vec::new();
+		let mut skeleton_entries = Vec::new();
+		let mut fragment_entries = Vec::new();
+		for (skeleton, fragment) in skeletons.iter().zip(fragments) {
+			let start = text.len() as u32;
+			let insert = start
+				+ skeleton
+					.windows(7)
+					.position(|window| window == b"</body>")
+					.expect("Missing body") as u32;
+			skeleton_entries.push((start, skeleton.len() as u32));
+			fragment_entries.push((insert.to_string(), fragment.len() as u32));
+			text.extend(*skeleton);
+			text.extend(fragment);
+		}
+		let markup = text.len() as u32;
+		text.extend(css);
+
+		let mut fdst = b"FDST".to_vec();
+		for value in [12, 2, 0, markup, markup, text.len() as u32] {
+			fdst.extend(value.to_be_bytes());
+		}
+		let skeleton_values = skeleton_entries
+			.iter()
+			.map(|(start, len)| [*start, *len])
+			.collect::<Vec<_>>();
+		let skeleton_index = index(
+			&[(1, 1), (6, 2)],
+			&[
+				("SKEL0000", &[(1, &[1]), (6, &skeleton_values[0])]),
+				("SKEL0001", &[(1, &[1]), (6, &skeleton_values[1])]),
+			],
+			None,
+		);
+		let fragment_values = fragment_entries
+			.iter()
+			.map(|(_, len)| [0, *len])
+			.collect::<Vec<_>>();
+		let fragment_index = index(
+			&[(6, 2)],
+			&[
+				(&fragment_entries[0].0, &[(6, &fragment_values[0])]),
+				(&fragment_entries[1].0, &[(6, &fragment_values[1])]),
+			],
+			None,
+		);
+		let (strings, offsets) = cncx(&["One", "Two"]);
+		let ncx_index = index(
+			&[(3, 1), (4, 1), (6, 2)],
+			&[
+				("0", &[(3, &[offsets[0]]), (4, &[0]), (6, &[0, 0])]),
+				("1", &[(3, &[offsets[1]]), (4, &[0]), (6, &[1, 0])]),
+			],
+			Some(strings),
+		);
+
+		let header = header_record(
+			1,
+			&text,
+			&[
+				(28, 65001),
+				(36, 8),
+				(108, 2),
+				(192, 3),
+				(244, 8),
+				(248, 6),
+				(252, 4),
+			],
+			&[(503, "Titel å".as_bytes()), (524, b"sv")],
+			"Name",
+		);
+		let mut records = vec![header, text, PNG.to_vec(), fdst];
+		records.extend(skeleton_index);
+		records.extend(fragment_index);
+		records.extend(ncx_index);
+		let epub = mobi_to_epub(&palmdb(&records)).expect("Failed to convert");
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		let m = &package.metadata;
+		assert_eq!(m.title.as_deref(), Some("Titel å"), "Title missmatch");
+		assert_eq!(m.language.as_deref(), Some("sv"), "Language missmatch");
+		assert_eq!(package.spine.len(), 2, "Spine missmatch");
+		let titles = navigation
+			.nav_points
+			.iter()
+			.map(|p| (p.title.as_str(), p.spine, p.fragment.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			titles,
+			vec![("One", Some(0), None), ("Two", Some(1), Some("start"))],
+			"Navigation missmatch"
+		);
+
+		let first = read(&mut archive, "part0000.xhtml");
+		assert_eq!(
+			first,
+			concat!(
+				"<html><head><link href=\"flow0001.css\" rel=\"stylesheet\"/></head><body>",
+				"<p>One <a href=\"part0001.xhtml#start\">next</a><img src=\"images/image00000.png\"/></p>",
+				"</body></html>",
+			),
+			"First part missmatch"
+		);
+		let second = read(&mut archive, "part0001.xhtml");
+		assert_eq!(
+			second, "<html><head></head><body><div id=\"start\"><p>Two</p></div></body></html>",
+			"Second part missmatch"
+		);
+		assert_eq!(
+			read(&mut archive, "flow0001.css"),
+			"p { margin: 0 }",
+			"Flow missmatch"
+		);
+	}
+}
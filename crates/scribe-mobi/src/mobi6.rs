@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use scribe_epub::EpubBuilder;
+
+use crate::Images;
+use crate::MobiError;
+use crate::palmdb::MobiHeader;
+use crate::palmdb::PalmDb;
+use crate::part_href;
+use crate::read_ncx;
+
+const PAGEBREAK: &[u8] = b"<mbp:pagebreak";
+
+/// Convert text of MOBI book, one document per page break
+///
+/// Links point at byte positions in text, anchors are inserted at every
+/// linked position before text is split.
+pub(crate) fn convert(
+	epub: &mut EpubBuilder,
+	images: &mut Images<'_, '_>,
+	db: &PalmDb<'_>,
+	header: &MobiHeader<'_>,
+	text: &[u8],
+) -> Result<(), MobiError> {
+	let ncx = header
+		.ncx_index
+		.map(|index| read_ncx(db, index))
+		.unwrap_or_default();
+	let mut targets = (0..text.len())
+		.filter_map(|pos| attribute(text, pos, b"filepos="))
+		.filter_map(|(value, _)| number(value))
+		.collect::<BTreeSet<_>>();
+	targets.extend(
+		ncx.iter()
+			.filter_map(|entry| entry.pos)
+			.map(|pos| pos as usize),
+	);
+
+	// Anchor positions in text with anchors
+	let mut anchors = BTreeMap::new();
+	let mut anchored = Vec::with_capacity(text.len() + targets.len() * 32);
+	let mut copied = 0;
+	for target in targets {
+		let pos = anchor_position(text, target).max(copied);
+		anchored.extend_from_slice(&text[copied..pos]);
+		anchors.insert(target, anchored.len());
+		anchored.extend(format!("<a id=\"filepos{target}\"></a>").bytes());
+		copied = pos;
+	}
+	anchored.extend_from_slice(&text[copied..]);
+
+	let parts = split_parts(&anchored);
+	// Part of each anchor, anchors in dropped parts belong to the next part
+	let anchors = anchors
+		.into_iter()
+		.map(|(target, pos)| {
+			let part = parts.partition_point(|range| range.end <= pos);
+			(target, part.min(parts.len().saturating_sub(1)))
+		})
+		.collect::<BTreeMap<_, _>>();
+
+	for (i, range) in parts.iter().enumerate() {
+		let part = &anchored[range.clone()];
+		let mut body = Vec::with_capacity(part.len());
+		let mut pos = 0;
+		while pos < part.len() {
+			if let Some((value, end)) = attribute(part, pos, b"filepos=") {
+				if let Some(target) = number(value)
+					&& let Some(part) = anchors.get(&target)
+				{
+					body.extend(format!("href=\"{}#filepos{target}\"", part_href(*part)).bytes());
+				}
+				pos = end;
+			} else if let Some((value, end)) = attribute(part, pos, b"recindex=")
+				&& let Some(href) = number(value)
+					.and_then(|index| index.checked_sub(1))
+					.and_then(|offset| images.href(epub, offset))
+			{
+				body.extend(format!("src=\"{href}\"").bytes());
+				pos = end;
+			} else {
+				body.push(part[pos]);
+				pos += 1;
+			}
+		}
+		let body = header.encoding.decode(&body);
+		epub.add_document(&part_href(i), &(i + 1).to_string(), &body);
+	}
+
+	for entry in &ncx {
+		let Some(pos) = entry.pos.map(|pos| pos as usize) else {
+			continue;
+		};
+		if let Some(part) = anchors.get(&pos) {
+			let href = format!("{}#filepos{pos}", part_href(*part));
+			epub.add_toc(entry.level, &entry.title, &href);
+		}
+	}
+	Ok(())
+}
+
+/// Value and end of attribute `name` starting at `pos`, name includes `=`
+fn attribute<'t>(text: &'t [u8], pos: usize, name: &[u8]) -> Option<(&'t [u8], usize)> {
+	if pos == 0
+		|| !text[pos - 1].is_ascii_whitespace()
+		|| !text.get(pos..pos + name.len())?.eq_ignore_ascii_case(name)
+	{
+		return None;
+	}
+	let start = pos + name.len();
+	match text.get(start) {
+		Some(quote @ (b'"' | b'\'')) => {
+			let end = start + 1 + text[start + 1..].iter().position(|c| c == quote)?;
+			Some((&text[start + 1..end], end + 1))
+		}
+		_ => {
+			let end = text[start..]
+				.iter()
+				.position(|c| c.is_ascii_whitespace() || matches!(c, b'>' | b'/'))
+				.map_or(text.len(), |end| start + end);
+			Some((&text[start..end], end))
+		}
+	}
+}
+
+fn number(value: &[u8]) -> Option<usize> {
+	std::str::from_utf8(value).ok()?.trim().parse().ok()
+}
+
+/// Position to insert anchor of target at, never inside a tag or before a page break
+fn anchor_position(text: &[u8], target: usize) -> usize {
+	let target = target.min(text.len());
+	let in_tag = text[..target]
+		.iter()
+		.rposition(|c| matches!(c, b'<' | b'>'))
+		.is_some_and(|i| text[i] == b'<');
+	let at_pagebreak = text[target..]
+		.get(..PAGEBREAK.len())
+		.is_some_and(|tag| tag.eq_ignore_ascii_case(PAGEBREAK));
+	if in_tag || at_pagebreak {
+		text[target..]
+			.iter()
+			.position(|c| *c == b'>')
+			.map_or(text.len(), |end| target + end + 1)
+	} else {
+		target
+	}
+}
+
+fn find(text: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+	text.get(from..)?
+		.windows(needle.len())
+		.position(|window| window.eq_ignore_ascii_case(needle))
+		.map(|i| from + i)
+}
+
+/// Ranges of body content between page breaks, empty parts are dropped
+fn split_parts(text: &[u8]) -> Vec<Range<usize>> {
+	let start = find(text, 0, b"<body")
+		.and_then(|body| find(text, body, b">"))
+		.map_or(0, |end| end + 1);
+	let end = find(text, start, b"</body").unwrap_or(text.len());
+
+	let mut parts = Vec::new();
+	let mut pos = start;
+	while let Some(pagebreak) = find(&text[..end], pos, PAGEBREAK) {
+		parts.push(pos..pagebreak);
+		pos = find(&text[..end], pagebreak, b">").map_or(end, |end| end + 1);
+	}
+	parts.push(pos..end);
+	parts.retain(|range| !text[range.clone()].trim_ascii().is_empty());
+	parts
+}
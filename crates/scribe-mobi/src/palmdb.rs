@@ -0,0 +1,187 @@
+use scribe_epub::TextEncoding;
+
+use crate::MobiError;
+
+/// Index value for missing record
+const NULL_INDEX: u32 = 0xffff_ffff;
+const HEADER_SIZE: usize = 78;
+/// Size of PalmDOC header preceding MOBI header in record 0
+const PALMDOC_HEADER_SIZE: usize = 16;
+
+pub(crate) fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+	let bytes = data.get(offset..offset + 2)?;
+	Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+	let bytes = data.get(offset..offset + 4)?;
+	Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Records of Palm database
+pub(crate) struct PalmDb<'a> {
+	records: Vec<&'a [u8]>,
+}
+
+impl<'a> PalmDb<'a> {
+	pub(crate) fn parse(bytes: &'a [u8]) -> Result<Self, MobiError> {
+		if bytes.get(60..68) != Some(b"BOOKMOBI") {
+			return Err(MobiError::NotMobi);
+		}
+		let count = be_u16(bytes, 76).ok_or(MobiError::NotMobi)? as usize;
+		let offsets = (0..count)
+			.map(|i| be_u32(bytes, HEADER_SIZE + i * 8).map(|offset| offset as usize))
+			.collect::<Option<Vec<_>>>()
+			.ok_or(MobiError::InvalidRecord("record list"))?;
+		let mut records = Vec::with_capacity(count);
+		for (i, start) in offsets.iter().enumerate() {
+			let end = offsets.get(i + 1).copied().unwrap_or(bytes.len());
+			let record = bytes
+				.get(*start..end)
+				.ok_or(MobiError::InvalidRecord("record list"))?;
+			records.push(record);
+		}
+		Ok(Self { records })
+	}
+
+	pub(crate) fn record(&self, index: usize) -> Result<&'a [u8], MobiError> {
+		self.records
+			.get(index)
+			.copied()
+			.ok_or(MobiError::MissingRecord(index))
+	}
+}
+
+/// Compression of text records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+	None,
+	PalmDoc,
+	HuffCdic,
+}
+
+/// EXTH metadata records, by type
+#[derive(Debug, Default)]
+pub(crate) struct Exth<'a> {
+	records: Vec<(u32, &'a [u8])>,
+}
+
+impl<'a> Exth<'a> {
+	fn parse(data: &'a [u8]) -> Option<Self> {
+		if !data.starts_with(b"EXTH") {
+			return None;
+		}
+		let count = be_u32(data, 8)?;
+		let mut records = Vec::new();
+		let mut offset = 12;
+		for _ in 0..count {
+			let kind = be_u32(data, offset)?;
+			let len = be_u32(data, offset + 4)? as usize;
+			let value = data.get(offset + 8..offset + len.max(8))?;
+			records.push((kind, value));
+			offset += len.max(8);
+		}
+		Some(Self { records })
+	}
+
+	pub(crate) fn values(&self, kind: u32) -> impl Iterator<Item = &'a [u8]> {
+		self.records
+			.iter()
+			.filter(move |(k, _)| *k == kind)
+			.map(|(_, value)| *value)
+	}
+
+	pub(crate) fn value(&self, kind: u32) -> Option<&'a [u8]> {
+		self.values(kind).next()
+	}
+
+	pub(crate) fn number(&self, kind: u32) -> Option<u32> {
+		self.value(kind)
+			.and_then(|value| be_u32(value, 0))
+			.filter(|value| *value != NULL_INDEX)
+	}
+}
+
+/// PalmDOC and MOBI headers of record 0, of the book or of the KF8 part of a joint file
+#[derive(Debug)]
+pub(crate) struct MobiHeader<'a> {
+	pub(crate) compression: Compression,
+	/// Length of uncompressed text
+	pub(crate) text_length: usize,
+	pub(crate) text_records: usize,
+	pub(crate) encoding: TextEncoding,
+	pub(crate) version: u32,
+	pub(crate) full_name: &'a [u8],
+	pub(crate) locale: u32,
+	pub(crate) first_image: Option<usize>,
+	pub(crate) huff_record: Option<usize>,
+	pub(crate) huff_count: usize,
+	/// Flags of trailing entries of text records
+	pub(crate) extra_flags: u16,
+	pub(crate) ncx_index: Option<usize>,
+	pub(crate) fdst_index: Option<usize>,
+	pub(crate) fragment_index: Option<usize>,
+	pub(crate) skeleton_index: Option<usize>,
+	pub(crate) exth: Exth<'a>,
+}
+
+impl<'a> MobiHeader<'a> {
+	pub(crate) fn parse(record: &'a [u8]) -> Result<Self, MobiError> {
+		let compression = match be_u16(record, 0).ok_or(MobiError::InvalidRecord("header"))? {
+			1 => Compression::None,
+			2 => Compression::PalmDoc,
+			17480 => Compression::HuffCdic,
+			other => return Err(MobiError::Compression(other)),
+		};
+		if be_u16(record, 12).is_some_and(|encryption| encryption != 0) {
+			return Err(MobiError::Encrypted);
+		}
+		if record.get(16..20) != Some(b"MOBI") {
+			return Err(MobiError::InvalidRecord("header"));
+		}
+		let header_end = PALMDOC_HEADER_SIZE
+			+ be_u32(record, 20).ok_or(MobiError::InvalidRecord("header"))? as usize;
+		// Older headers are shorter, fields past header are missing
+		let field = |offset: usize| {
+			be_u32(record, offset).filter(|value| offset + 4 <= header_end && *value != NULL_INDEX)
+		};
+		let index = |offset: usize| field(offset).map(|value| value as usize);
+
+		let full_name = match (index(84), index(88)) {
+			(Some(start), Some(len)) => record.get(start..start + len).unwrap_or_default(),
+			_ => &[],
+		};
+		let exth = match field(128) {
+			Some(flags) if flags & 0x40 != 0 => record
+				.get(header_end..)
+				.and_then(Exth::parse)
+				.unwrap_or_default(),
+			_ => Exth::default(),
+		};
+		let version = field(36).unwrap_or(0);
+		let kf8 = version >= 8;
+		Ok(Self {
+			compression,
+			text_length: be_u32(record, 4).unwrap_or(0) as usize,
+			text_records: be_u16(record, 8).unwrap_or(0) as usize,
+			encoding: match field(28) {
+				Some(65001) => TextEncoding::Utf8,
+				_ => TextEncoding::Windows1252,
+			},
+			version,
+			full_name,
+			locale: field(92).unwrap_or(0),
+			first_image: index(108),
+			huff_record: index(112),
+			huff_count: index(116).unwrap_or(0),
+			extra_flags: be_u16(record, 242)
+				.filter(|_| header_end >= PALMDOC_HEADER_SIZE + 0xe4)
+				.unwrap_or(0),
+			ncx_index: index(244),
+			fdst_index: index(192).filter(|_| kf8),
+			fragment_index: index(248).filter(|_| kf8),
+			skeleton_index: index(252).filter(|_| kf8),
+			exth,
+		})
+	}
+}
@@ -0,0 +1,379 @@
+//! Plain text and Markdown books converted to epub archives
+//!
+//! Text is split into chapters at headings, or at the next paragraph once a
+//! chapter grows large, so books read as any other epub.
+
+mod markdown;
+
+use std::mem;
+
+use quick_xml::escape::escape;
+use scribe_epub::Creator;
+use scribe_epub::EpubBuilder;
+use scribe_epub::Metadata;
+use scribe_epub::TextEncoding;
+
+use crate::markdown::Image;
+use crate::markdown::Markdown;
+
+/// Chapters are cut at the next block once this large, keeps layout of a chapter quick
+const CHAPTER_BYTES: usize = 32 * 1024;
+
+/// Characters of paragraph used as title of plain text chapters
+const TITLE_CHARS: usize = 40;
+
+const STYLESHEET: &str = r#"blockquote { margin-left: 2em; margin-right: 2em; }
+pre { margin-left: 1em; }
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TextError {
+	#[error(transparent)]
+	Epub(#[from] scribe_epub::EpubError),
+}
+
+/// Block level element of document
+struct Block {
+	html: String,
+	/// Title of chapter starting with block
+	title: Option<String>,
+	/// Heading level and id
+	heading: Option<(usize, String)>,
+}
+
+#[derive(Default)]
+struct Chapter {
+	title: Option<String>,
+	body: String,
+	/// Headings within chapter, as table of contents level, title and id
+	headings: Vec<(usize, String, String)>,
+}
+
+/// Convert plain text to epub archive
+///
+/// Paragraphs are separated by blank lines, text without blank lines has a paragraph per line.
+pub fn text_to_epub(bytes: &[u8], title: &str) -> Result<Vec<u8>, TextError> {
+	let text = TextEncoding::detect(bytes).decode(bytes);
+	let blocks = paragraphs(&text)
+		.into_iter()
+		.map(|p| Block {
+			html: format!("<p>{}</p>\n", escape(p.as_str())),
+			title: Some(truncate(&p, TITLE_CHARS)),
+			heading: None,
+		})
+		.collect();
+	let metadata = Metadata {
+		title: Some(title.to_string()),
+		..Default::default()
+	};
+	write_epub(metadata, chapters(blocks, None), Vec::new())
+}
+
+/// Convert Markdown document to epub archive
+///
+/// Images are loaded with `resources` by source as written in document, images that
+/// are external or not found are replaced by their alt text.
+/// Title is taken from front matter or a leading single top level heading, else `title` is used.
+pub fn markdown_to_epub(
+	bytes: &[u8],
+	title: &str,
+	resources: impl FnMut(&str) -> Option<Vec<u8>>,
+) -> Result<Vec<u8>, TextError> {
+	let text = TextEncoding::detect(bytes).decode(bytes);
+	let (fields, body) = front_matter(&text);
+	let mut markdown = Markdown::new(resources);
+	let blocks = markdown.blocks(body);
+
+	let levels = blocks
+		.iter()
+		.filter_map(|b| b.heading.as_ref().map(|(level, _)| *level))
+		.collect::<Vec<_>>();
+	let top = levels.iter().min().copied();
+	// Single top level heading leading document is title of book, chapters are split below it
+	let title_heading = top.filter(|top| {
+		levels.iter().filter(|l| *l == top).count() == 1
+			&& blocks[0].heading.as_ref().is_some_and(|(l, _)| l == top)
+	});
+	let split_level = match title_heading {
+		Some(top) => levels.iter().filter(|l| **l > top).min().copied(),
+		None => top,
+	};
+
+	let field = |key: &str| {
+		fields
+			.iter()
+			.find(|(k, _)| k.eq_ignore_ascii_case(key))
+			.map(|(_, v)| v.to_string())
+			.filter(|v| !v.is_empty())
+	};
+	let title = field("title")
+		.or_else(|| title_heading.and_then(|_| blocks[0].title.clone()))
+		.unwrap_or_else(|| title.to_string());
+	let metadata = Metadata {
+		title: Some(title),
+		creator: field("author"),
+		creators: field("author")
+			.map(|name| Creator {
+				name,
+				role: Some("aut".to_string()),
+				file_as: None,
+			})
+			.into_iter()
+			.collect(),
+		description: field("description"),
+		language: field("lang").or_else(|| field("language")),
+		date: field("date"),
+		..Default::default()
+	};
+	write_epub(
+		metadata,
+		chapters(blocks, split_level),
+		markdown.into_images(),
+	)
+}
+
+/// Paragraphs of text with lines joined, or lines if text has no blank lines
+fn paragraphs(text: &str) -> Vec<String> {
+	let lines = text.lines().map(str::trim).collect::<Vec<_>>();
+	if !lines.iter().any(|l| l.is_empty()) {
+		return lines.into_iter().map(str::to_string).collect();
+	}
+	lines
+		.split(|l| l.is_empty())
+		.filter(|p| !p.is_empty())
+		.map(|p| p.join(" "))
+		.collect()
+}
+
+fn truncate(text: &str, chars: usize) -> String {
+	match text.char_indices().nth(chars) {
+		Some((end, _)) => format!("{}…", text[..end].trim_end()),
+		None => text.to_string(),
+	}
+}
+
+/// Metadata fields of YAML front matter and rest of document
+fn front_matter(text: &str) -> (Vec<(&str, &str)>, &str) {
+	let Some(rest) = text
+		.strip_prefix("---\n")
+		.or_else(|| text.strip_prefix("---\r\n"))
+	else {
+		return (Vec::new(), text);
+	};
+	let mut fields = Vec::new();
+	let mut offset = 0;
+	for line in rest.split_inclusive('\n') {
+		offset += line.len();
+		let line = line.trim_end();
+		if line == "---" || line == "..." {
+			return (fields, &rest[offset..]);
+		}
+		if let Some((key, value)) = line.split_once(':') {
+			let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+			fields.push((key.trim(), value));
+		}
+	}
+	(Vec::new(), text)
+}
+
+/// Split blocks into chapters at headings up to `split_level` and by size
+fn chapters(blocks: Vec<Block>, split_level: Option<usize>) -> Vec<Chapter> {
+	let mut chapters = Vec::new();
+	let mut current = Chapter::default();
+	for block in blocks {
+		let at_heading = block
+			.heading
+			.as_ref()
+			.is_some_and(|(level, _)| split_level.is_some_and(|split| *level <= split));
+		if !current.body.is_empty() && (at_heading || current.body.len() >= CHAPTER_BYTES) {
+			chapters.push(mem::take(&mut current));
+		}
+		if current.body.is_empty() {
+			current.title = block.title;
+		} else if let (Some((level, id)), Some(title)) = (block.heading, block.title) {
+			let level = level.saturating_sub(split_level.unwrap_or(0)).max(1);
+			current.headings.push((level, title, id));
+		}
+		current.body.push_str(&block.html);
+	}
+	if !current.body.is_empty() || chapters.is_empty() {
+		chapters.push(current);
+	}
+	chapters
+}
+
+fn write_epub(
+	metadata: Metadata,
+	chapters: Vec<Chapter>,
+	images: Vec<Image>,
+) -> Result<Vec<u8>, TextError> {
+	let mut epub = EpubBuilder::new(metadata);
+	epub.stylesheet(STYLESHEET);
+	for (i, chapter) in chapters.iter().enumerate() {
+		let href = format!("text{i}.xhtml");
+		let title = chapter.title.clone().unwrap_or_else(|| (i + 1).to_string());
+		epub.add_document(&href, &title, &chapter.body);
+		if let Some(title) = &chapter.title {
+			epub.add_toc(0, title, &href);
+		}
+		for (level, title, id) in &chapter.headings {
+			epub.add_toc(*level, title, &format!("{href}#{id}"));
+		}
+	}
+	for image in images {
+		epub.add_resource(&image.href, image.mime, image.data);
+	}
+	Ok(epub.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::io::Read;
+
+	use scribe_epub::EpubMetadata;
+	use zip::ZipArchive;
+
+	use crate::markdown::Markdown;
+	use crate::markdown_to_epub;
+	use crate::text_to_epub;
+
+	fn read_document(archive: &mut ZipArchive<Cursor<Vec<u8>>>, path: &str) -> String {
+		let mut content = String::new();
+		archive
+			.by_path(path)
+			.expect("Missing document")
+			.read_to_string(&mut content)
+			.expect("Failed to read document");
+		content
+	}
+
+	#[test]
+	fn test_text_to_epub() {
+		let text = "Första stycket\nfortsätter här.\n\n\nAndra stycket & mer.\n";
+		let bytes = text
+			.chars()
+			.map(|c| u8::try_from(u32::from(c)).expect("Not latin-1"))
+			.collect::<Vec<_>>();
+		let epub = text_to_epub(&bytes, "notes").expect("Failed to convert");
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		assert_eq!(
+			package.metadata.title.as_deref(),
+			Some("notes"),
+			"Title missmatch"
+		);
+		assert_eq!(package.spine.len(), 1, "Spine missmatch");
+		let titles = navigation
+			.nav_points
+			.iter()
+			.map(|p| p.title.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(
+			titles,
+			vec!["Första stycket fortsätter här."],
+			"Navigation missmatch"
+		);
+
+		let chapter = read_document(&mut archive, "text0.xhtml");
+		assert!(
+			chapter.contains(
+				"<p>Första stycket fortsätter här.</p>\n<p>Andra stycket &amp; mer.</p>\n"
+			),
+			"Paragraphs missing in {chapter}"
+		);
+	}
+
+	#[test]
+	fn test_markdown_to_epub() {
+		let doc = concat!(
+			"# The Book\n\n",
+			"Intro with ![map](images/map.png) and ![web](https://example.com/a.png).\n\n",
+			"## First\n\n",
+			"Text *one*.\n\n",
+			"### Detail\n\n",
+			"- a\n- b\n  - c\n\n",
+			"Second\n------\n\n",
+			"```\nlet a = 1 < 2;\n```\n",
+		);
+		let mut requested = Vec::new();
+		let epub = markdown_to_epub(doc.as_bytes(), "file", |src| {
+			requested.push(src.to_string());
+			Some(b"\x89PNG\r\n\x1a\n".to_vec())
+		})
+		.expect("Failed to convert");
+		assert_eq!(requested, vec!["images/map.png"], "Resources missmatch");
+
+		let mut archive = ZipArchive::new(Cursor::new(epub)).expect("Failed to open epub");
+		let mut metadata = EpubMetadata::new(&mut archive);
+		let package = metadata.package().expect("Failed to read package");
+		let navigation = metadata.navigation().expect("Failed to read navigation");
+
+		assert_eq!(
+			package.metadata.title.as_deref(),
+			Some("The Book"),
+			"Title missmatch"
+		);
+		assert_eq!(package.spine.len(), 3, "Spine missmatch");
+		let titles = navigation
+			.nav_points
+			.iter()
+			.map(|p| (p.title.as_str(), p.spine, p.fragment.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			titles,
+			vec![
+				("The Book", Some(0), None),
+				("First", Some(1), None),
+				("Detail", Some(1), Some("heading2")),
+				("Second", Some(2), None),
+			],
+			"Navigation missmatch"
+		);
+
+		let intro = read_document(&mut archive, "text0.xhtml");
+		assert!(
+			intro.contains(r#"Intro with <img src="images/image0.png" alt="map"/> and web.</p>"#),
+			"Images missing in {intro}"
+		);
+		let first = read_document(&mut archive, "text1.xhtml");
+		assert!(
+			first.contains("<ul>\n<li>a\n</li>\n<li>b\n<ul>\n<li>c\n</li>\n</ul>\n</li>\n</ul>\n"),
+			"List missing in {first}"
+		);
+		let second = read_document(&mut archive, "text2.xhtml");
+		assert!(
+			second.contains("<pre><code>let a = 1 &lt; 2;</code></pre>"),
+			"Code missing in {second}"
+		);
+	}
+
+	#[test]
+	fn test_markdown_inline() {
+		let mut markdown = Markdown::new(|_: &str| None);
+		let cases = [
+			(
+				"*em* **strong** ***both***",
+				"<em>em</em> <strong>strong</strong> <em><strong>both</strong></em>",
+			),
+			("*a **b** c*", "<em>a <strong>b</strong> c</em>"),
+			("snake_case_name", "snake_case_name"),
+			("`a * b` ~~gone~~", "<code>a * b</code> <del>gone</del>"),
+			(
+				"[link *x*](http://a.b/?c=1&d=2 \"title\")",
+				"<a href=\"http://a.b/?c=1&amp;d=2\">link <em>x</em></a>",
+			),
+			(
+				"<https://a.b> 1 < 2 \\*",
+				"<a href=\"https://a.b\">https://a.b</a> 1 &lt; 2 *",
+			),
+			("line  \nbreak", "line<br/>\nbreak"),
+		];
+		for (text, html) in cases {
+			assert_eq!(markdown.inline(text), html, "Inline missmatch for {text}");
+		}
+	}
+}
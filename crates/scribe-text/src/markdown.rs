@@ -0,0 +1,702 @@
+use std::collections::BTreeMap;
+
+use quick_xml::escape::escape;
+use quick_xml::escape::unescape;
+
+use crate::Block;
+
+/// Characters that may start inline markup
+const INLINE_SPECIAL: &[char] = &['\\', '`', '!', '[', '<', '*', '_', '~', '\n'];
+
+pub(crate) struct Image {
+	pub(crate) href: String,
+	pub(crate) mime: &'static str,
+	pub(crate) data: Vec<u8>,
+}
+
+/// List item marker, `offset` is column of item content
+#[derive(Debug, Clone, Copy)]
+struct ListMarker {
+	ordered: bool,
+	delimiter: char,
+	start: u32,
+	offset: usize,
+}
+
+/// Renders the common subset of Markdown to xhtml
+///
+/// Blocks are headings, paragraphs, block quotes, lists, code and thematic breaks.
+/// Inline are emphasis, code, links, images, autolinks and hard breaks.
+/// Reference links and html are kept as text.
+pub(crate) struct Markdown<F> {
+	resources: F,
+	/// Image href by source, none if not loaded
+	sources: BTreeMap<String, Option<String>>,
+	images: Vec<Image>,
+	headings: usize,
+}
+
+impl<F: FnMut(&str) -> Option<Vec<u8>>> Markdown<F> {
+	pub(crate) fn new(resources: F) -> Self {
+		Self {
+			resources,
+			sources: BTreeMap::new(),
+			images: Vec::new(),
+			headings: 0,
+		}
+	}
+
+	pub(crate) fn into_images(self) -> Vec<Image> {
+		self.images
+	}
+
+	/// Top level blocks of document
+	pub(crate) fn blocks(&mut self, text: &str) -> Vec<Block> {
+		let lines = text.lines().map(expand_tabs).collect::<Vec<_>>();
+		self.parse(&lines, false)
+	}
+
+	fn render(&mut self, lines: &[String], tight: bool) -> String {
+		self.parse(lines, tight)
+			.into_iter()
+			.map(|block| block.html)
+			.collect()
+	}
+
+	/// Parse blocks of lines, paragraphs of `tight` lists are not wrapped
+	fn parse(&mut self, lines: &[String], tight: bool) -> Vec<Block> {
+		let mut blocks = Vec::new();
+		let mut i = 0;
+		while i < lines.len() {
+			let line = lines[i].as_str();
+			if is_blank(line) {
+				i += 1;
+			} else if let Some((fence, len)) = fence(line) {
+				let indent = indent(line);
+				let mut code = Vec::new();
+				i += 1;
+				while i < lines.len() {
+					let line = lines[i].as_str();
+					i += 1;
+					if closes_fence(line, fence, len) {
+						break;
+					}
+					code.push(&line[indent.min(indent_of(line))..]);
+				}
+				blocks.push(block(code_block(&code.join("\n"))));
+			} else if let Some((level, text)) = atx_heading(line) {
+				blocks.push(self.heading(level, text));
+				i += 1;
+			} else if thematic_break(line) {
+				blocks.push(block("<hr/>\n".to_string()));
+				i += 1;
+			} else if quote(line).is_some() {
+				let mut inner: Vec<String> = Vec::new();
+				while i < lines.len() {
+					let line = lines[i].as_str();
+					if let Some(rest) = quote(line) {
+						inner.push(rest.to_string());
+					} else if !is_blank(line)
+						&& inner.last().is_some_and(|l| !is_blank(l))
+						&& !interrupts_paragraph(line)
+					{
+						// Lazy continuation of paragraph in quote
+						inner.push(line.to_string());
+					} else {
+						break;
+					}
+					i += 1;
+				}
+				let html = format!(
+					"<blockquote>\n{}</blockquote>\n",
+					self.render(&inner, false)
+				);
+				blocks.push(block(html));
+			} else if let Some(marker) = list_marker(line) {
+				let (html, next) = self.list(lines, i, marker);
+				blocks.push(block(html));
+				i = next;
+			} else if indent(line) >= 4 {
+				let mut code = Vec::new();
+				while i < lines.len() && (is_blank(&lines[i]) || indent(&lines[i]) >= 4) {
+					code.push(lines[i].get(4..).unwrap_or(""));
+					i += 1;
+				}
+				while code.last().is_some_and(|l| is_blank(l)) {
+					code.pop();
+				}
+				blocks.push(block(code_block(&code.join("\n"))));
+			} else {
+				let mut text = vec![line.trim_start()];
+				let mut setext = None;
+				i += 1;
+				while i < lines.len() {
+					let line = lines[i].as_str();
+					if is_blank(line) {
+						break;
+					} else if let Some(level) = setext_underline(line) {
+						setext = Some(level);
+						i += 1;
+						break;
+					} else if interrupts_paragraph(line) {
+						break;
+					}
+					text.push(line.trim_start());
+					i += 1;
+				}
+				let text = text.join("\n");
+				if let Some(level) = setext {
+					blocks.push(self.heading(level, text.trim_end()));
+				} else if tight {
+					blocks.push(block(format!("{}\n", self.inline(&text))));
+				} else {
+					blocks.push(block(format!("<p>{}</p>\n", self.inline(&text))));
+				}
+			}
+		}
+		blocks
+	}
+
+	fn heading(&mut self, level: usize, text: &str) -> Block {
+		let id = format!("heading{}", self.headings);
+		self.headings += 1;
+		let html = self.inline(text);
+		Block {
+			title: Some(plain_text(&html)),
+			html: format!("<h{level} id=\"{id}\">{html}</h{level}>\n"),
+			heading: Some((level, id)),
+		}
+	}
+
+	/// Render list starting at line `i`, returns html and line after list
+	fn list(&mut self, lines: &[String], mut i: usize, first: ListMarker) -> (String, usize) {
+		let same_list = |line: &str| {
+			list_marker(line)
+				.filter(|m| m.ordered == first.ordered && m.delimiter == first.delimiter)
+		};
+		let mut items = Vec::new();
+		let mut loose = false;
+		while let Some(marker) = lines.get(i).and_then(|line| same_list(line)) {
+			let mut item = vec![lines[i].get(marker.offset..).unwrap_or("").to_string()];
+			i += 1;
+			while i < lines.len() {
+				let line = lines[i].as_str();
+				if is_blank(line) {
+					item.push(String::new());
+				} else if indent(line) >= marker.offset {
+					item.push(line[marker.offset..].to_string());
+				} else if item.last().is_some_and(|l| l.is_empty())
+					|| interrupts_paragraph(line)
+					|| list_marker(line).is_some()
+				{
+					break;
+				} else {
+					// Lazy continuation of paragraph in item
+					item.push(line.trim_start().to_string());
+				}
+				i += 1;
+			}
+			let mut trailing = 0;
+			while item.last().is_some_and(|l| l.is_empty()) {
+				item.pop();
+				trailing += 1;
+			}
+			if item.iter().any(|l| l.is_empty())
+				|| (trailing > 0 && lines.get(i).is_some_and(|line| same_list(line).is_some()))
+			{
+				loose = true;
+			}
+			items.push(item);
+		}
+
+		let (tag, start) = match first.ordered {
+			true if first.start != 1 => ("ol", format!(" start=\"{}\"", first.start)),
+			true => ("ol", String::new()),
+			false => ("ul", String::new()),
+		};
+		let mut html = format!("<{tag}{start}>\n");
+		for item in items {
+			html.push_str("<li>");
+			html.push_str(&self.render(&item, !loose));
+			html.push_str("</li>\n");
+		}
+		html.push_str(&format!("</{tag}>\n"));
+		(html, i)
+	}
+
+	/// Render inline content of paragraph or heading
+	pub(crate) fn inline(&mut self, text: &str) -> String {
+		let mut out = String::new();
+		let mut pos = 0;
+		while let Some(c) = text[pos..].chars().next() {
+			let rest = &text[pos..];
+			match c {
+				'\\' => match rest[1..].chars().next() {
+					Some('\n') => {
+						out.push_str("<br/>\n");
+						pos += 2;
+					}
+					Some(next) if next.is_ascii_punctuation() => {
+						out.push_str(&escape(&rest[1..2]));
+						pos += 2;
+					}
+					_ => {
+						out.push('\\');
+						pos += 1;
+					}
+				},
+				'`' => {
+					let n = run_length(rest, '`');
+					if let Some((code, len)) = code_span(rest, n) {
+						out.push_str(&format!("<code>{}</code>", escape(code.as_str())));
+						pos += len;
+					} else {
+						out.push_str(&rest[..n]);
+						pos += n;
+					}
+				}
+				'!' if rest[1..].starts_with('[') => {
+					if let Some((alt, src, len)) = link(&rest[1..]) {
+						let html = self.image(alt, src);
+						out.push_str(&html);
+						pos += 1 + len;
+					} else {
+						out.push('!');
+						pos += 1;
+					}
+				}
+				'[' => {
+					if let Some((label, href, len)) = link(rest) {
+						let label = self.inline(label);
+						out.push_str(&format!("<a href=\"{}\">{label}</a>", escape(href)));
+						pos += len;
+					} else {
+						out.push('[');
+						pos += 1;
+					}
+				}
+				'<' => {
+					if let Some((href, len)) = autolink(rest) {
+						out.push_str(&format!(
+							"<a href=\"{}\">{}</a>",
+							escape(href.as_str()),
+							escape(&rest[1..len - 1])
+						));
+						pos += len;
+					} else {
+						out.push_str("&lt;");
+						pos += 1;
+					}
+				}
+				'*' | '_' | '~' => {
+					let n = run_length(rest, c);
+					match emphasis(text, pos, c, n) {
+						Some((used, close)) => {
+							let (open, end) = match (c, used) {
+								('~', _) => ("<del>", "</del>"),
+								(_, 1) => ("<em>", "</em>"),
+								(_, 2) => ("<strong>", "</strong>"),
+								_ => ("<em><strong>", "</strong></em>"),
+							};
+							let inner = self.inline(&text[pos + n..close]);
+							out.push_str(&rest[..n - used]);
+							out.push_str(&format!("{open}{inner}{end}"));
+							pos = close + used;
+						}
+						None => {
+							out.push_str(&rest[..n]);
+							pos += n;
+						}
+					}
+				}
+				'\n' => {
+					if out.ends_with("  ") {
+						out.truncate(out.trim_end_matches(' ').len());
+						out.push_str("<br/>");
+					}
+					out.push('\n');
+					pos += 1;
+				}
+				_ => {
+					let first = c.len_utf8();
+					let end = rest[first..]
+						.find(INLINE_SPECIAL)
+						.map(|end| end + first)
+						.unwrap_or(rest.len());
+					out.push_str(&escape(&rest[..end]));
+					pos += end;
+				}
+			}
+		}
+		out.truncate(out.trim_end().len());
+		out
+	}
+
+	fn image(&mut self, alt: &str, src: &str) -> String {
+		let alt = self.inline(alt);
+		let alt = plain_text(&alt);
+		match self.resource(src) {
+			Some(href) => format!(
+				"<img src=\"{}\" alt=\"{}\"/>",
+				escape(href.as_str()),
+				escape(alt.as_str())
+			),
+			None => escape(alt.as_str()).into_owned(),
+		}
+	}
+
+	/// Load image by source, external images are not loaded
+	fn resource(&mut self, src: &str) -> Option<String> {
+		if let Some(href) = self.sources.get(src) {
+			return href.clone();
+		}
+		let extension = src
+			.rsplit_once('.')
+			.map(|(_, ext)| ext.to_ascii_lowercase())
+			.unwrap_or_default();
+		let mime = match extension.as_str() {
+			"png" => Some("image/png"),
+			"jpg" | "jpeg" => Some("image/jpeg"),
+			"gif" => Some("image/gif"),
+			"webp" => Some("image/webp"),
+			"svg" => Some("image/svg+xml"),
+			_ => None,
+		};
+		let href = mime
+			.filter(|_| !src.contains(':'))
+			.and_then(|mime| Some((mime, (self.resources)(src)?)))
+			.map(|(mime, data)| {
+				let href = format!("images/image{}.{extension}", self.images.len());
+				self.images.push(Image {
+					href: href.clone(),
+					mime,
+					data,
+				});
+				href
+			});
+		if href.is_none() {
+			log::debug!("Image not loaded: {src}");
+		}
+		self.sources.insert(src.to_string(), href.clone());
+		href
+	}
+}
+
+fn block(html: String) -> Block {
+	Block {
+		html,
+		title: None,
+		heading: None,
+	}
+}
+
+fn code_block(code: &str) -> String {
+	format!("<pre><code>{}</code></pre>\n", escape(code))
+}
+
+/// Text of rendered inline html, with whitespace collapsed
+fn plain_text(html: &str) -> String {
+	let mut text = String::new();
+	let mut in_tag = false;
+	for c in html.chars() {
+		match c {
+			'<' => in_tag = true,
+			'>' => in_tag = false,
+			c if !in_tag => text.push(c),
+			_ => {}
+		}
+	}
+	let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+	unescape(&text).map(|t| t.into_owned()).unwrap_or(text)
+}
+
+/// Line with leading tabs expanded to tab stops of four
+fn expand_tabs(line: &str) -> String {
+	let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+	let mut out = String::with_capacity(line.len());
+	for c in line[..indent].chars() {
+		if c == '\t' {
+			out.push_str(&"    "[out.len() % 4..]);
+		} else {
+			out.push(c);
+		}
+	}
+	out.push_str(&line[indent..]);
+	out
+}
+
+fn is_blank(line: &str) -> bool {
+	line.trim().is_empty()
+}
+
+fn indent(line: &str) -> usize {
+	line.len() - line.trim_start_matches(' ').len()
+}
+
+fn indent_of(line: &str) -> usize {
+	if is_blank(line) {
+		line.len()
+	} else {
+		indent(line)
+	}
+}
+
+fn run_length(text: &str, c: char) -> usize {
+	text.len() - text.trim_start_matches(c).len()
+}
+
+/// Line starts block that ends a paragraph
+fn interrupts_paragraph(line: &str) -> bool {
+	fence(line).is_some()
+		|| atx_heading(line).is_some()
+		|| thematic_break(line)
+		|| quote(line).is_some()
+		|| list_marker(line).is_some_and(|m| !m.ordered || m.start == 1)
+}
+
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+	if indent(line) > 3 {
+		return None;
+	}
+	let line = line.trim_start();
+	let level = run_length(line, '#');
+	let rest = &line[level..];
+	if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+		return None;
+	}
+	let text = rest.trim();
+	let closed = text.trim_end_matches('#');
+	if closed.is_empty() || closed.ends_with([' ', '\t']) {
+		Some((level, closed.trim_end()))
+	} else {
+		Some((level, text))
+	}
+}
+
+fn setext_underline(line: &str) -> Option<usize> {
+	if indent(line) > 3 {
+		return None;
+	}
+	match line.trim() {
+		l if l.chars().all(|c| c == '=') => Some(1),
+		l if l.chars().all(|c| c == '-') => Some(2),
+		_ => None,
+	}
+}
+
+fn thematic_break(line: &str) -> bool {
+	if indent(line) > 3 {
+		return false;
+	}
+	let mut chars = line.chars().filter(|c| !c.is_whitespace());
+	let Some(first @ ('-' | '*' | '_')) = chars.next() else {
+		return false;
+	};
+	let mut count = 1;
+	for c in chars {
+		if c != first {
+			return false;
+		}
+		count += 1;
+	}
+	count >= 3
+}
+
+fn fence(line: &str) -> Option<(char, usize)> {
+	if indent(line) > 3 {
+		return None;
+	}
+	let line = line.trim_start();
+	let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+	let len = run_length(line, c);
+	// Info string of backtick fence may not contain backticks
+	if len < 3 || (c == '`' && line[len..].contains('`')) {
+		return None;
+	}
+	Some((c, len))
+}
+
+fn closes_fence(line: &str, fence: char, len: usize) -> bool {
+	let trimmed = line.trim();
+	indent(line) <= 3
+		&& run_length(trimmed, fence) >= len
+		&& trimmed.trim_end_matches(fence).is_empty()
+}
+
+fn quote(line: &str) -> Option<&str> {
+	if indent(line) > 3 {
+		return None;
+	}
+	let rest = line.trim_start().strip_prefix('>')?;
+	Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn list_marker(line: &str) -> Option<ListMarker> {
+	let indent = indent(line);
+	if indent > 3 {
+		return None;
+	}
+	let rest = &line[indent..];
+	let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+	let (ordered, delimiter, start, width) = match rest.chars().nth(digits)? {
+		c @ ('-' | '*' | '+') if digits == 0 => (false, c, 0, 1),
+		c @ ('.' | ')') if (1..=9).contains(&digits) => {
+			(true, c, rest[..digits].parse().ok()?, digits + 1)
+		}
+		_ => return None,
+	};
+	let after = &rest[width..];
+	let spaces = run_length(after, ' ');
+	if !after.is_empty() && spaces == 0 {
+		return None;
+	}
+	// Content indented more than four spaces after marker is indented code
+	let spaces = if spaces > 4 || after.trim().is_empty() {
+		1
+	} else {
+		spaces
+	};
+	Some(ListMarker {
+		ordered,
+		delimiter,
+		start,
+		offset: indent + width + spaces,
+	})
+}
+
+/// Code span starting with run of `n` backticks, as code and length of span
+fn code_span(text: &str, n: usize) -> Option<(String, usize)> {
+	let mut pos = n;
+	while let Some(start) = text[pos..].find('`') {
+		let start = pos + start;
+		let len = run_length(&text[start..], '`');
+		if len == n {
+			let code = text[n..start].replace('\n', " ");
+			let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+				Some(stripped) if !stripped.trim().is_empty() => stripped.to_string(),
+				_ => code,
+			};
+			return Some((code, start + len));
+		}
+		pos = start + len;
+	}
+	None
+}
+
+/// Inline link `[label](destination "title")`, as label, destination and length of link
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+	let mut depth = 0;
+	let mut pos = 0;
+	let label_end = loop {
+		let c = text[pos..].chars().next()?;
+		match c {
+			'\\' => pos += 1,
+			'`' => {
+				let n = run_length(&text[pos..], '`');
+				pos += code_span(&text[pos..], n).map(|(_, len)| len).unwrap_or(n) - 1;
+			}
+			'[' => depth += 1,
+			']' if depth == 1 => break pos,
+			']' => depth -= 1,
+			_ => {}
+		}
+		pos += text[pos..].chars().next().map(char::len_utf8).unwrap_or(1);
+	};
+	let rest = text[label_end + 1..].strip_prefix('(')?;
+	let after_ws = rest.trim_start();
+	let (destination, after) = if let Some(inner) = after_ws.strip_prefix('<') {
+		let end = inner.find('>')?;
+		(&inner[..end], &inner[end + 1..])
+	} else {
+		let mut parens = 0;
+		let end = after_ws
+			.char_indices()
+			.find(|(_, c)| match c {
+				'(' => {
+					parens += 1;
+					false
+				}
+				')' if parens == 0 => true,
+				')' => {
+					parens -= 1;
+					false
+				}
+				c => c.is_whitespace(),
+			})
+			.map(|(i, _)| i)
+			.unwrap_or(after_ws.len());
+		(&after_ws[..end], &after_ws[end..])
+	};
+	let after = after.trim_start();
+	let after = match after.chars().next()? {
+		')' => after,
+		quote @ ('"' | '\'' | '(') => {
+			let close = if quote == '(' { ')' } else { quote };
+			let end = after[1..].find(close)?;
+			after[end + 2..].trim_start()
+		}
+		_ => return None,
+	};
+	after.strip_prefix(')')?;
+	let len = text.len() - after.len() + 1;
+	Some((&text[1..label_end], destination, len))
+}
+
+/// Autolink `<scheme:address>` or `<user@host>`, as href and length of link
+fn autolink(text: &str) -> Option<(String, usize)> {
+	let end = text.find('>')?;
+	let inner = &text[1..end];
+	if inner.is_empty() || inner.contains(|c: char| c.is_whitespace() || c == '<') {
+		return None;
+	}
+	let scheme = inner.split_once(':').is_some_and(|(scheme, _)| {
+		scheme.len() > 1
+			&& scheme
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '.' || c == '-')
+	});
+	if scheme {
+		Some((inner.to_string(), end + 1))
+	} else if inner.contains('@') {
+		Some((format!("mailto:{inner}"), end + 1))
+	} else {
+		None
+	}
+}
+
+/// Emphasis opened by run of `n` delimiters at `pos`, as delimiters used and start of closing run
+fn emphasis(text: &str, pos: usize, c: char, n: usize) -> Option<(usize, usize)> {
+	let before = text[..pos].chars().next_back();
+	let after = text[pos + n..].chars().next();
+	if after.is_none_or(char::is_whitespace)
+		|| (c == '_' && before.is_some_and(char::is_alphanumeric))
+		|| (c == '~' && n < 2)
+	{
+		return None;
+	}
+	let mut closers = Vec::new();
+	let mut i = pos + n;
+	while let Some(start) = text[i..].find(c) {
+		let start = i + start;
+		let len = run_length(&text[start..], c);
+		i = start + len;
+		let before = text[..start].chars().next_back();
+		let after = text[i..].chars().next();
+		if before.is_none_or(char::is_whitespace)
+			|| before == Some('\\')
+			|| (c == '_' && after.is_some_and(char::is_alphanumeric))
+			|| (c == '~' && len < 2)
+		{
+			continue;
+		}
+		closers.push((start, len));
+	}
+	// Closing run of same length is preferred, nested emphasis is then kept together
+	let (close, len) = closers
+		.iter()
+		.find(|(_, len)| *len == n)
+		.or(closers.first())
+		.copied()?;
+	let used = if c == '~' { 2 } else { n.min(len).min(3) };
+	Some((used, close))
+}
@@ -105,12 +105,24 @@ impl ScribeConfig {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Library {
 	pub path: Arc<String>,
+	/// Add body text of books to library search index
+	#[serde(default)]
+	pub index_content: bool,
+	/// Add books to tags named after their subjects
+	#[serde(default)]
+	pub tag_subjects: bool,
+	/// Folder in library for exported annotations
+	#[serde(default = "default_export_folder")]
+	pub export_folder: Arc<String>,
 }
 
 impl Default for Library {
 	fn default() -> Self {
 		Self {
 			path: Arc::new("~/Documents/ebooks".to_string()),
+			index_content: false,
+			tag_subjects: false,
+			export_folder: default_export_folder(),
 		}
 	}
 }
@@ -151,6 +163,17 @@ pub struct IllustratorProfile {
 
 	#[serde(default = "default_padding")]
 	pub padding: PaddingConfig,
+
+	#[serde(default)]
+	pub line_breaking: LineBreakingConfig,
+
+	/// Default text alignment, overridden by book stylesheets
+	#[serde(default)]
+	pub text_align: TextAlignConfig,
+
+	/// Show two pages of comics side by side when screen is landscape
+	#[serde(default)]
+	pub two_page_spreads: bool,
 }
 
 impl Default for IllustratorProfile {
@@ -167,6 +190,9 @@ impl Default for IllustratorProfile {
 			h4: default_h4(),
 			h5: default_h5(),
 			padding: default_padding(),
+			line_breaking: LineBreakingConfig::default(),
+			text_align: TextAlignConfig::default(),
+			two_page_spreads: false,
 		}
 	}
 }
@@ -193,6 +219,26 @@ impl AsRef<FontConfig> for FontConfig {
 	}
 }
 
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineBreakingConfig {
+	/// Fill each line before moving to next
+	#[default]
+	Greedy,
+	/// Balance line lengths over whole paragraph
+	TotalFit,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlignConfig {
+	#[default]
+	Left,
+	Justify,
+	Center,
+	Right,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HeaderConfig {
 	pub font_size_em: f32,
@@ -299,3 +345,7 @@ fn default_padding() -> PaddingConfig {
 		paragraph_em: 1.2,
 	}
 }
+
+fn default_export_folder() -> Arc<String> {
+	Arc::new("Annotations".to_string())
+}
@@ -0,0 +1,531 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Utc;
+use scribe_epub::Navigation;
+use serde::Serialize;
+use wrangler::DocumentId;
+use wrangler::content::ContentWranglerAssistant;
+
+use crate::Book;
+use crate::BookId;
+use crate::Bookmark;
+use crate::Highlight;
+use crate::Location;
+use crate::TextPosition;
+use crate::records::RecordKeeper;
+use crate::records::RecordKeeperError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+	#[error(transparent)]
+	RecordKeeper(#[from] RecordKeeperError),
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+	#[error("at {1}: {0}")]
+	Io(io::Error, &'static std::panic::Location<'static>),
+}
+
+impl From<io::Error> for ExportError {
+	#[track_caller]
+	fn from(err: io::Error) -> Self {
+		Self::Io(err, std::panic::Location::caller())
+	}
+}
+
+/// Exports bookmarks, highlights and reading state of books
+///
+/// Writes a Markdown and a JSON document per book to export folder in library.
+#[derive(Clone)]
+pub struct AnnotationExporter {
+	keeper: RecordKeeper,
+	content: ContentWranglerAssistant,
+	folder: Arc<String>,
+}
+
+impl AnnotationExporter {
+	pub fn new(
+		keeper: RecordKeeper,
+		content: ContentWranglerAssistant,
+		folder: Arc<String>,
+	) -> Self {
+		Self {
+			keeper,
+			content,
+			folder,
+		}
+	}
+
+	/// Export annotations of book, grouped by chapters of `navigation`
+	///
+	/// Documents are named after book file and id, and replaced on each export.
+	pub async fn export(
+		&self,
+		book_id: BookId,
+		navigation: &Navigation,
+	) -> Result<Vec<DocumentId>, ExportError> {
+		let (book, bookmarks, highlights) = {
+			let records = self.keeper.assistant()?;
+			(
+				records.fetch_book(book_id)?,
+				records.fetch_bookmarks(book_id)?,
+				records.fetch_highlights(book_id)?,
+			)
+		};
+		let chapters = chapters(navigation, &bookmarks, &highlights);
+		let name = export_name(&book);
+		let exported_at = Utc::now();
+
+		let markdown = markdown(&book, &name, &chapters, exported_at);
+		let json = serde_json::to_string_pretty(&ExportBook::new(&book, &chapters, exported_at))?;
+
+		let folder = Path::new(self.folder.as_str());
+		let mut documents = Vec::with_capacity(2);
+		for (extension, content) in [("md", markdown), ("json", json)] {
+			let path = folder.join(format!("{name}.{extension}"));
+			let document = self.content.save(path, content).await?;
+			log::info!("Exported annotations of {book_id} to {document}");
+			documents.push(document);
+		}
+		Ok(documents)
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Annotation<'a> {
+	Bookmark(&'a Bookmark),
+	Highlight(&'a Highlight),
+}
+
+impl Annotation<'_> {
+	fn location(&self) -> Location {
+		match self {
+			Annotation::Bookmark(bookmark) => bookmark.location,
+			Annotation::Highlight(highlight) => highlight.location(),
+		}
+	}
+
+	/// Chapter title recorded with annotation
+	fn chapter(&self) -> Option<&Arc<String>> {
+		match self {
+			Annotation::Bookmark(bookmark) => bookmark.chapter.as_ref(),
+			Annotation::Highlight(highlight) => highlight.chapter.as_ref(),
+		}
+	}
+}
+
+type Chapter<'a> = (Option<Arc<String>>, Vec<Annotation<'a>>);
+
+/// Annotations in reading order, grouped by consecutive chapter titles
+///
+/// Titles are taken from navigation, falling back to title recorded with annotation.
+fn chapters<'a>(
+	navigation: &Navigation,
+	bookmarks: &'a [Bookmark],
+	highlights: &'a [Highlight],
+) -> Vec<Chapter<'a>> {
+	let mut annotations = bookmarks
+		.iter()
+		.map(Annotation::Bookmark)
+		.chain(highlights.iter().map(Annotation::Highlight))
+		.collect::<Vec<_>>();
+	annotations.sort_by_key(|a| a.location());
+
+	let mut chapters: Vec<Chapter<'a>> = Vec::new();
+	for annotation in annotations {
+		let title = chapter_title(navigation, annotation.location().spine)
+			.or_else(|| annotation.chapter().cloned());
+		match chapters.last_mut() {
+			Some((t, annotations)) if *t == title => annotations.push(annotation),
+			_ => chapters.push((title, vec![annotation])),
+		}
+	}
+	chapters
+}
+
+/// Title of first nav point of closest spine item at or before `spine`
+fn chapter_title(navigation: &Navigation, spine: u32) -> Option<Arc<String>> {
+	navigation
+		.nav_points
+		.iter()
+		.filter(|p| p.spine.is_some_and(|s| s <= spine))
+		.min_by_key(|p| Reverse(p.spine))
+		.map(|p| p.title.clone())
+}
+
+/// File name of exported documents, from file name and id of book
+///
+/// Id keeps exports of books with same file name in different folders apart.
+fn export_name(book: &Book) -> String {
+	let stem = book
+		.path
+		.file_stem()
+		.map(|s| s.to_string_lossy())
+		.unwrap_or_default();
+	let name = stem
+		.chars()
+		.map(|c| match c {
+			'/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+			c if c.is_control() => '_',
+			c => c,
+		})
+		.collect::<String>();
+	let name = match name.trim() {
+		"" => "book",
+		name => name,
+	};
+	format!("{name}-{}", book.id.into_inner())
+}
+
+fn markdown(book: &Book, name: &str, chapters: &[Chapter], exported_at: DateTime<Utc>) -> String {
+	let mut out = String::new();
+	let title = book.title.as_deref().map(String::as_str).unwrap_or(name);
+	let _ = writeln!(out, "# {title}\n");
+	if let Some(author) = &book.author {
+		let _ = writeln!(out, "{author}\n");
+	}
+	if let Some(percent_read) = book.percent_read {
+		let _ = write!(out, "Read {percent_read}%");
+		if let Some(opened_at) = book.opened_at {
+			let _ = write!(out, ", last opened {}", opened_at.format("%Y-%m-%d"));
+		}
+		let _ = writeln!(out, "  ");
+	}
+	let _ = writeln!(out, "Exported {}\n", exported_at.format("%Y-%m-%d %H:%M"));
+
+	for (title, annotations) in chapters {
+		let _ = writeln!(
+			out,
+			"## {}\n",
+			title.as_deref().map(String::as_str).unwrap_or("Untitled")
+		);
+		for annotation in annotations {
+			match annotation {
+				Annotation::Bookmark(bookmark) => match &bookmark.excerpt {
+					Some(excerpt) => {
+						let _ = writeln!(out, "**Bookmark** {excerpt}\n");
+					}
+					None => {
+						let _ = writeln!(out, "**Bookmark**\n");
+					}
+				},
+				Annotation::Highlight(highlight) => {
+					for line in highlight.text.lines() {
+						let _ = writeln!(out, "> {line}");
+					}
+					let _ = writeln!(out);
+					if let Some(note) = &highlight.note {
+						let _ = writeln!(out, "{note}\n");
+					}
+				}
+			}
+		}
+	}
+	out
+}
+
+#[derive(Debug, Serialize)]
+struct ExportLocation {
+	spine: u32,
+	element: f64,
+}
+
+impl From<Location> for ExportLocation {
+	fn from(loc: Location) -> Self {
+		Self {
+			spine: loc.spine,
+			element: loc.element.to_num(),
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct ExportPosition {
+	element: u32,
+	offset: u32,
+}
+
+impl From<TextPosition> for ExportPosition {
+	fn from(pos: TextPosition) -> Self {
+		Self {
+			element: pos.element,
+			offset: pos.offset,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct ExportBookmark<'a> {
+	chapter: Option<&'a str>,
+	location: ExportLocation,
+	excerpt: Option<&'a str>,
+	created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportHighlight<'a> {
+	chapter: Option<&'a str>,
+	spine: u32,
+	start: ExportPosition,
+	end: ExportPosition,
+	text: &'a str,
+	note: Option<&'a str>,
+	created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportBook<'a> {
+	title: Option<&'a str>,
+	author: Option<&'a str>,
+	document: String,
+	percent_read: Option<u32>,
+	location: ExportLocation,
+	opened_at: Option<DateTime<Utc>>,
+	exported_at: DateTime<Utc>,
+	bookmarks: Vec<ExportBookmark<'a>>,
+	highlights: Vec<ExportHighlight<'a>>,
+}
+
+impl<'a> ExportBook<'a> {
+	fn new(book: &'a Book, chapters: &'a [Chapter<'a>], exported_at: DateTime<Utc>) -> Self {
+		let mut bookmarks = Vec::new();
+		let mut highlights = Vec::new();
+		for (title, annotations) in chapters {
+			let chapter = title.as_deref().map(String::as_str);
+			for annotation in annotations {
+				match annotation {
+					Annotation::Bookmark(bookmark) => bookmarks.push(ExportBookmark {
+						chapter,
+						location: bookmark.location.into(),
+						excerpt: bookmark.excerpt.as_deref(),
+						created_at: bookmark.created_at,
+					}),
+					Annotation::Highlight(highlight) => highlights.push(ExportHighlight {
+						chapter,
+						spine: highlight.spine,
+						start: highlight.start.into(),
+						end: highlight.end.into(),
+						text: &highlight.text,
+						note: highlight.note.as_deref(),
+						created_at: highlight.created_at,
+					}),
+				}
+			}
+		}
+
+		Self {
+			title: book.title.as_deref().map(String::as_str),
+			author: book.author.as_deref().map(String::as_str),
+			document: book.path.to_string_lossy().into_owned(),
+			percent_read: book.percent_read,
+			location: book.location().into(),
+			opened_at: book.opened_at,
+			exported_at,
+			bookmarks,
+			highlights,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+	use std::path::PathBuf;
+	use std::sync::Arc;
+
+	use chrono::DateTime;
+	use fixed::types::U26F6;
+	use scribe_epub::Navigation;
+	use scribe_epub::parse_ncx;
+	use scribe_epub::parse_package;
+
+	use crate::Book;
+	use crate::BookId;
+	use crate::Bookmark;
+	use crate::Highlight;
+	use crate::HighlightId;
+	use crate::Location;
+	use crate::TextPosition;
+	use crate::export::Annotation;
+	use crate::export::Chapter;
+	use crate::export::chapters;
+	use crate::export::export_name;
+
+	/// Navigation with "One" at spine item `b` and "Two" at `d`
+	fn navigation() -> Navigation {
+		let input = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<package version="2.0" xmlns="http://www.idpf.org/2007/opf" unique-identifier="epub-id-1">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="epub-id-1">urn:uuid:2ce9fc1f-1168-499e-8389-0d2e1ccbf77e</dc:identifier>
+    <dc:title>Amelia Thornheart</dc:title>
+  </metadata>
+  <manifest>
+    <item media-type="application/xhtml+xml" id="a" href="a.xhtml"/>
+    <item media-type="application/xhtml+xml" id="b" href="b.xhtml"/>
+    <item media-type="application/xhtml+xml" id="c" href="c.xhtml"/>
+    <item media-type="application/xhtml+xml" id="d" href="d.xhtml"/>
+  </manifest>
+  <spine>
+    <itemref idref="a" />
+    <itemref idref="b" />
+    <itemref idref="c" />
+    <itemref idref="d" />
+  </spine>
+</package>
+"#;
+		let reader = quick_xml::Reader::from_str(input);
+		let package = parse_package(Path::new("OEBPS"), reader).expect("Parse failed");
+
+		let input = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<ncx version="2005-1" xmlns="http://www.daisy.org/z3986/2005/ncx/">
+  <navMap>
+    <navPoint id="navPoint-1">
+      <navLabel><text>One</text></navLabel>
+      <content src="b.xhtml"/>
+    </navPoint>
+    <navPoint id="navPoint-2">
+      <navLabel><text>Two</text></navLabel>
+      <content src="d.xhtml"/>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+		let reader = quick_xml::Reader::from_str(input);
+		let mut navigation = parse_ncx(&package, reader).expect("Parse failed");
+		for point in &mut navigation.nav_points {
+			point.spine = package
+				.spine
+				.iter()
+				.position(|id| **id == *point.idref)
+				.map(|index| index as u32);
+		}
+		navigation
+	}
+
+	fn bookmark(spine: u32, element: u32, chapter: Option<&str>) -> Bookmark {
+		Bookmark {
+			location: Location {
+				spine,
+				element: U26F6::from_num(element),
+			},
+			chapter: chapter.map(|c| Arc::new(c.to_string())),
+			excerpt: None,
+			created_at: DateTime::UNIX_EPOCH,
+		}
+	}
+
+	fn highlight(id: i64, spine: u32, element: u32, chapter: Option<&str>) -> Highlight {
+		Highlight {
+			id: HighlightId(id),
+			spine,
+			start: TextPosition { element, offset: 0 },
+			end: TextPosition { element, offset: 4 },
+			chapter: chapter.map(|c| Arc::new(c.to_string())),
+			text: "text".to_string(),
+			note: None,
+			created_at: DateTime::UNIX_EPOCH,
+		}
+	}
+
+	fn book(path: &str) -> Book {
+		Book {
+			id: BookId(7),
+			path: PathBuf::from(path),
+			title: None,
+			author: None,
+			size: 0,
+			modified_at: DateTime::UNIX_EPOCH,
+			added_at: DateTime::UNIX_EPOCH,
+			opened_at: None,
+			percent_read: None,
+			spine: None,
+			element: None,
+			new_spines: 0,
+			first_unread_spine: None,
+			series: None,
+		}
+	}
+
+	/// Chapter title and annotations, `b<spine>` for bookmarks and `h<id>` for highlights
+	fn summary(chapters: Vec<Chapter>) -> Vec<String> {
+		chapters
+			.into_iter()
+			.map(|(title, annotations)| {
+				let mut line = format!("{}:", title.as_deref().map_or("Untitled", String::as_str));
+				for annotation in annotations {
+					match annotation {
+						Annotation::Bookmark(b) => {
+							line.push_str(&format!(" b{}", b.location.spine))
+						}
+						Annotation::Highlight(h) => {
+							line.push_str(&format!(" h{}", h.id.into_inner()))
+						}
+					}
+				}
+				line
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_export_chapters() {
+		let navigation = navigation();
+		let bookmarks = [
+			bookmark(2, 3, Some("Recorded")),
+			bookmark(1, 2, None),
+			bookmark(0, 1, Some("Cover")),
+		];
+		let highlights = [highlight(1, 3, 1, None), highlight(2, 1, 5, Some("One"))];
+
+		assert_eq!(
+			summary(chapters(&navigation, &bookmarks, &highlights)),
+			vec!["Cover: b0", "One: b1 h2 b2", "Two: h1"],
+			"Chapters missmatch"
+		);
+		assert_eq!(
+			summary(chapters(&Navigation::default(), &bookmarks, &[])),
+			vec!["Cover: b0", "Untitled: b1", "Recorded: b2"],
+			"Recorded chapters missmatch"
+		);
+	}
+
+	#[test]
+	fn test_export_name() {
+		assert_eq!(
+			export_name(&book("books/Dune: Part <1>?.epub")),
+			"Dune_ Part _1__-7",
+			"Reserved characters missmatch"
+		);
+		assert_eq!(
+			export_name(&book("books/a|b*c\"d\te.epub")),
+			"a_b_c_d_e-7",
+			"Reserved and control characters missmatch"
+		);
+		assert_eq!(
+			export_name(&book("books/ Title .epub")),
+			"Title-7",
+			"Whitespace missmatch"
+		);
+		assert_eq!(
+			export_name(&book("books/ .epub")),
+			"book-7",
+			"Empty stem missmatch"
+		);
+		assert_eq!(export_name(&book("")), "book-7", "No file name missmatch");
+
+		let other = Book {
+			id: BookId(8),
+			..book("other/Dune.epub")
+		};
+		assert_ne!(
+			export_name(&book("books/Dune.epub")),
+			export_name(&other),
+			"Same file name missmatch"
+		);
+	}
+}
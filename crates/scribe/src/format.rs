@@ -0,0 +1,167 @@
+use std::io;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// File format of book, other formats than epub are converted to epub when read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookFormat {
+	Epub,
+	Fb2,
+	/// Zip archive with a single fb2 document
+	Fb2Zip,
+	Text,
+	Markdown,
+	/// Comic book archive, a zip archive of images
+	Cbz,
+	/// Unencrypted MOBI or KF8 (AZW3) book
+	Mobi,
+	/// Standalone HTML document, e.g. a saved web article
+	Html,
+	/// Zip archive of a web page, an html document and its assets
+	///
+	/// Only `.htmlz` and `.html.zip` names are taken as books, other zip
+	/// archives are not read to find out what they hold.
+	HtmlZip,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BookFormatError {
+	#[error(transparent)]
+	Fb2(#[from] scribe_fb2::Fb2Error),
+	#[error(transparent)]
+	Text(#[from] scribe_text::TextError),
+	#[error(transparent)]
+	Cbz(#[from] scribe_cbz::CbzError),
+	#[error(transparent)]
+	Mobi(#[from] scribe_mobi::MobiError),
+	#[error(transparent)]
+	Html(#[from] scribe_html::HtmlError),
+}
+
+impl BookFormat {
+	/// Format of book by file name, none if not a book
+	pub fn from_path(path: &Path) -> Option<Self> {
+		let name = path.file_name()?.to_str()?.to_lowercase();
+		if name.ends_with(".epub") {
+			Some(Self::Epub)
+		} else if name.ends_with(".fb2") {
+			Some(Self::Fb2)
+		} else if name.ends_with(".fb2.zip") {
+			Some(Self::Fb2Zip)
+		} else if name.ends_with(".txt") {
+			Some(Self::Text)
+		} else if name.ends_with(".md") || name.ends_with(".markdown") {
+			Some(Self::Markdown)
+		} else if name.ends_with(".cbz") {
+			Some(Self::Cbz)
+		} else if name.ends_with(".mobi") || name.ends_with(".azw3") || name.ends_with(".azw") {
+			Some(Self::Mobi)
+		} else if name.ends_with(".html") || name.ends_with(".htm") {
+			Some(Self::Html)
+		} else if name.ends_with(".htmlz")
+			|| name.ends_with(".html.zip")
+			|| name.ends_with(".htm.zip")
+		{
+			Some(Self::HtmlZip)
+		} else {
+			None
+		}
+	}
+
+	/// Epub archive of book content at `path`
+	///
+	/// Files referenced by book, e.g. images of Markdown documents, are loaded
+	/// with `resources` by their path next to the book.
+	pub fn into_epub(
+		self,
+		path: &Path,
+		content: Vec<u8>,
+		mut resources: impl FnMut(&Path) -> Option<Vec<u8>>,
+	) -> Result<Vec<u8>, BookFormatError> {
+		let title = path
+			.file_stem()
+			.map(|stem| stem.to_string_lossy())
+			.unwrap_or_default();
+		match self {
+			Self::Epub => Ok(content),
+			Self::Fb2 => Ok(scribe_fb2::fb2_to_epub(&content)?),
+			Self::Fb2Zip => Ok(scribe_fb2::fb2_zip_to_epub(io::Cursor::new(content))?),
+			Self::Text => Ok(scribe_text::text_to_epub(&content, &title)?),
+			Self::Markdown => Ok(scribe_text::markdown_to_epub(&content, &title, |src| {
+				resources(&resource_path(path, src)?)
+			})?),
+			Self::Cbz => Ok(scribe_cbz::cbz_to_epub(io::Cursor::new(content))?),
+			Self::Mobi => Ok(scribe_mobi::mobi_to_epub(&content)?),
+			Self::Html => Ok(scribe_html::html_to_epub(&content, &title, |src| {
+				resources(&resource_path(path, src)?)
+			})?),
+			Self::HtmlZip => {
+				let title = title.trim_end_matches(".html").trim_end_matches(".htm");
+				Ok(scribe_html::html_zip_to_epub(
+					io::Cursor::new(content),
+					title,
+				)?)
+			}
+		}
+	}
+}
+
+/// Path of resource referenced relative to book, none if outside of library
+///
+/// Book paths are relative to library root, resolving stops at the root.
+fn resource_path(book: &Path, src: &str) -> Option<PathBuf> {
+	let src = src.split(['#', '?']).next()?.replace("%20", " ");
+	let mut path = PathBuf::new();
+	for component in book
+		.parent()?
+		.components()
+		.chain(Path::new(&src).components())
+	{
+		match component {
+			Component::Normal(name) => path.push(name),
+			Component::ParentDir if path.pop() => {}
+			Component::CurDir => {}
+			_ => return None,
+		}
+	}
+	Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+	use std::path::PathBuf;
+
+	use crate::format::resource_path;
+
+	#[test]
+	fn test_resource_path() {
+		let book = Path::new("books/fantasy/tale.md");
+		assert_eq!(
+			resource_path(book, "images/map%20one.png#top"),
+			Some(PathBuf::from("books/fantasy/images/map one.png")),
+			"Relative path missmatch"
+		);
+		assert_eq!(
+			resource_path(book, "../../covers/./tale.jpg"),
+			Some(PathBuf::from("covers/tale.jpg")),
+			"Path inside library missmatch"
+		);
+		assert_eq!(
+			resource_path(book, "../../../etc/passwd"),
+			None,
+			"Path outside library missmatch"
+		);
+		assert_eq!(
+			resource_path(book, "/etc/passwd"),
+			None,
+			"Absolute path missmatch"
+		);
+		assert_eq!(
+			resource_path(Path::new("/books/tale.md"), "image.png"),
+			None,
+			"Absolute book path missmatch"
+		);
+	}
+}
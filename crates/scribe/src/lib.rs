@@ -1,21 +1,33 @@
 pub mod config;
+mod export;
+mod format;
 mod library;
 mod records;
 
 use std::fmt::Display;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::TimeDelta;
 use chrono::Utc;
 use fixed::types::U26F6;
 
+pub use export::AnnotationExporter;
+pub use export::ExportError;
+pub use format::BookFormat;
+pub use format::BookFormatError;
 pub use library::LibraryBell;
 pub use library::LibraryScribe;
 pub use library::LibraryScribeAssistant;
 pub use records::RecordKeeper;
 pub use records::RecordKeeperAssistant;
 pub use records::RecordKeeperError;
+pub use records::ShelfOrder;
+pub use records::ShelvedBook;
+pub use records::SpineItem;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Location {
@@ -54,6 +66,79 @@ impl BookId {
 	}
 }
 
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
+pub struct CollectionId(pub i64);
+
+impl Display for CollectionId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "[c{}]", self.0)
+	}
+}
+
+impl CollectionId {
+	pub fn into_inner(self) -> i64 {
+		let CollectionId(id) = self;
+		id
+	}
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
+pub struct HighlightId(pub i64);
+
+impl Display for HighlightId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "[h{}]", self.0)
+	}
+}
+
+impl HighlightId {
+	pub fn into_inner(self) -> i64 {
+		let HighlightId(id) = self;
+		id
+	}
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
+pub struct SessionId(pub i64);
+
+impl Display for SessionId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "[rs{}]", self.0)
+	}
+}
+
+impl SessionId {
+	pub fn into_inner(self) -> i64 {
+		let SessionId(id) = self;
+		id
+	}
+}
+
+/// Position in chapter text, byte offset into text of element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPosition {
+	pub element: u32,
+	pub offset: u32,
+}
+
+impl Display for TextPosition {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "[el{}_o{}]", self.element, self.offset)
+	}
+}
+
+/// User collection of books
+///
+/// Tags are collections created from book subjects.
+#[derive(Debug, Clone)]
+pub struct Collection {
+	pub id: CollectionId,
+	pub name: Arc<String>,
+	pub tag: bool,
+	/// Number of books in library
+	pub books: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct Book {
 	pub id: BookId,
@@ -67,6 +152,10 @@ pub struct Book {
 	pub percent_read: Option<u32>,
 	pub spine: Option<u32>,
 	pub element: Option<U26F6>,
+	/// Spine items added since the book was last opened.
+	pub new_spines: u32,
+	pub first_unread_spine: Option<u32>,
+	pub series: Option<Series>,
 }
 
 impl Book {
@@ -76,4 +165,158 @@ impl Book {
 			element: self.element.unwrap_or(U26F6::ZERO),
 		}
 	}
+
+	pub fn unread_location(&self) -> Option<Location> {
+		if self.new_spines > 0 {
+			self.first_unread_spine.map(Location::from_spine)
+		} else {
+			None
+		}
+	}
+}
+
+/// Saved location in book
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+	pub location: Location,
+	/// Title of chapter at location
+	pub chapter: Option<Arc<String>>,
+	/// Text at start of bookmarked page
+	pub excerpt: Option<String>,
+	pub created_at: DateTime<Utc>,
+}
+
+/// Highlighted text in chapter, with optional note
+#[derive(Debug, Clone)]
+pub struct Highlight {
+	pub id: HighlightId,
+	pub spine: u32,
+	/// Start of text, inclusive
+	pub start: TextPosition,
+	/// End of text, exclusive
+	pub end: TextPosition,
+	/// Title of chapter with text
+	pub chapter: Option<Arc<String>>,
+	pub text: String,
+	pub note: Option<String>,
+	pub created_at: DateTime<Utc>,
+}
+
+impl Highlight {
+	pub fn location(&self) -> Location {
+		Location {
+			spine: self.spine,
+			element: U26F6::from_num(self.start.element),
+		}
+	}
+
+	pub fn contains(&self, spine: u32, pos: TextPosition) -> bool {
+		self.spine == spine && self.start <= pos && pos < self.end
+	}
+}
+
+/// Continuous reading of book
+///
+/// Session ends when book is closed or no page is turned for a while.
+#[derive(Debug, Clone)]
+pub struct ReadingSession {
+	pub started_at: DateTime<Utc>,
+	pub ended_at: DateTime<Utc>,
+	/// Pages turned, forward or back
+	pub pages: u32,
+	/// Visible characters of pages turned forward
+	pub chars: u64,
+	pub start: Location,
+	pub end: Location,
+	pub start_percent: u32,
+	pub end_percent: u32,
+}
+
+/// Time spent reading book, summed over sessions
+#[derive(Debug, Clone)]
+pub struct BookReadingTime {
+	pub id: BookId,
+	pub title: Option<Arc<String>>,
+	pub time: TimeDelta,
+	pub pages: u32,
+	pub sessions: u32,
+	pub last_read_at: DateTime<Utc>,
+}
+
+/// Reading during a day, in local time
+#[derive(Debug, Clone)]
+pub struct DayReading {
+	pub day: NaiveDate,
+	pub pages: u32,
+	pub time: TimeDelta,
+}
+
+/// Reading speed estimated from sessions
+#[derive(Debug, Clone, Copy)]
+pub struct ReadingPace {
+	pub seconds_per_char: f64,
+}
+
+impl ReadingPace {
+	/// Estimated time to read visible characters
+	pub fn time_to_read(&self, chars: u64) -> TimeDelta {
+		TimeDelta::seconds((self.seconds_per_char * chars as f64) as i64)
+	}
+}
+
+/// Visible characters of spine item
+///
+/// Counted for content with checksum `crc32`, recounted when content changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpineChars {
+	pub spine: u32,
+	pub crc32: u32,
+	pub chars: u64,
+}
+
+/// Page boundaries of spine item in a layout
+///
+/// Laid out for content with checksum `crc32`, laid out again when content changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpinePages {
+	pub spine: u32,
+	pub crc32: u32,
+	/// Element range of each page, in order
+	pub pages: Vec<Range<U26F6>>,
+}
+
+/// Creator or contributor of book
+#[derive(Debug, Clone, PartialEq)]
+pub struct Creator {
+	pub name: String,
+	/// MARC relator code, e.g. `aut`
+	pub role: Option<String>,
+	/// Name used for sorting
+	pub file_as: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+	pub name: Arc<String>,
+	/// Position of book in series
+	pub index: Option<f64>,
+}
+
+impl Display for Series {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.index {
+			Some(index) => write!(f, "{} #{index}", self.name),
+			None => write!(f, "{}", self.name),
+		}
+	}
+}
+
+/// Extended metadata of book, fetched separately from [`Book`]
+#[derive(Debug, Clone, Default)]
+pub struct BookMetadata {
+	pub description: Option<String>,
+	pub creators: Vec<Creator>,
+	pub contributors: Vec<Creator>,
+	pub subjects: Vec<String>,
+	pub series: Option<Series>,
 }
@@ -1,7 +1,9 @@
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::BinaryHeap;
 use std::fs;
 use std::io;
+use std::io::BufRead;
 use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
@@ -17,7 +19,10 @@ use chrono::Utc;
 use image::ImageReader;
 use image::codecs::png;
 use image::codecs::png::PngEncoder;
+use quick_xml::escape::resolve_html5_entity;
 use scribe_epub::EPUB_CONTAINER_PATH;
+use scribe_epub::Package;
+use scribe_epub::ResourceId;
 use scribe_epub::parse_container;
 use scribe_epub::parse_package;
 use wrangler::Discovery;
@@ -31,12 +36,22 @@ use wrangler::WranglerSystem;
 use zip::ZipArchive;
 
 use crate::Book;
+use crate::BookFormat;
+use crate::BookFormatError;
 use crate::BookId;
+use crate::BookMetadata;
+use crate::Creator;
+use crate::Series;
+use crate::SpineItem;
+use crate::records::IndexBook;
 use crate::records::InsertBook;
 use crate::records::RecordKeeperAssistant;
 use crate::records::RecordKeeperError;
 use crate::records::UpdateBook;
 
+/// Maximum bytes of body text indexed per book
+const MAX_INDEX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
 pub trait LibraryBell {
 	fn book_updated(&self, book_id: BookId);
 }
@@ -54,10 +69,14 @@ pub struct LibraryScribe<B: LibraryBell> {
 	records: RecordKeeperAssistant,
 	bell: B,
 	thumbnail_path: PathBuf,
+	index_content: bool,
+	tag_subjects: bool,
+	export_folder: Arc<String>,
 	tasks: Arc<Mutex<BTreeMap<Ticket, LibraryTask>>>,
 	discovery_ticket: Option<Ticket>,
 	library_books: BTreeMap<PathBuf, Book>,
 	stale_books: BinaryHeap<(SystemTime, BookId, DocumentId)>,
+	new_books: BTreeSet<BookId>,
 	buffer: Vec<u8>,
 }
 
@@ -73,11 +92,17 @@ impl<B: LibraryBell + Send + 'static> LibraryScribe<B> {
 	///
 	/// Should be called once during init.
 	/// Returned assistant can be cheaply cloned.
+	/// With `index_content`, body text of books is added to search index.
+	/// With `tag_subjects`, books are tagged with their subjects.
+	/// Files in `export_folder` are not books, exported annotations are kept there.
 	pub fn create(
 		system: WranglerSystem,
 		bell: B,
 		records: RecordKeeperAssistant,
 		cache_path: &Path,
+		index_content: bool,
+		tag_subjects: bool,
+		export_folder: Arc<String>,
 	) -> LibraryScribeAssistant {
 		let thumbnail_path = cache_path.join("thumbnails");
 		let working = Arc::new(AtomicBool::new(false));
@@ -89,10 +114,14 @@ impl<B: LibraryBell + Send + 'static> LibraryScribe<B> {
 			records,
 			bell,
 			thumbnail_path,
+			index_content,
+			tag_subjects,
+			export_folder,
 			tasks: tasks.clone(),
 			discovery_ticket: None,
 			library_books: BTreeMap::new(),
 			stale_books: BinaryHeap::new(),
+			new_books: BTreeSet::new(),
 			buffer: Vec::new(),
 		}));
 
@@ -128,15 +157,19 @@ enum ProcessError {
 
 impl<B: LibraryBell> LibraryScribe<B> {
 	fn process(&mut self, doc: &wrangler::DiscoveryDocument<'_>) -> Result<(), ProcessError> {
-		if Path::new(doc.file_name)
-			.extension()
-			.is_none_or(|e| e != "epub")
+		if BookFormat::from_path(Path::new(doc.file_name)).is_none() {
+			log::debug!("Ignoring non-book file: {}", doc.file_name);
+			return Ok(());
+		}
+		let path = doc.document.path();
+		if path
+			.parent()
+			.is_some_and(|parent| parent.ends_with(self.export_folder.as_str()))
 		{
-			log::debug!("Ignoring non-epub file: {}", doc.file_name);
+			log::debug!("Ignoring exported file: {}", doc.file_name);
 			return Ok(());
 		}
 
-		let path = doc.document.path();
 		let file_name = doc.file_name;
 		let modified_at: DateTime<Utc> = <DateTime<Utc>>::from(doc.timestamp).trunc_subsecs(0);
 
@@ -166,19 +199,28 @@ impl<B: LibraryBell> LibraryScribe<B> {
 				added_at: Utc::now(),
 			};
 			let book_id = self.records.upsert_book(book)?;
+			self.new_books.insert(book_id);
 			self.stale_books
 				.push((doc.timestamp, book_id, doc.document.clone()));
 		}
 		Ok(())
 	}
 
+	/// Mark books not found during discovery as unexisting.
+	///
+	/// Unexisting books are kept so they can be re-pointed to a new path,
+	/// once a new book is read and found to have same identity.
 	fn finish(&mut self) -> Result<(), ProcessError> {
 		let unexist_ids = self
 			.library_books
 			.values()
 			.map(|book| book.id)
 			.collect::<Vec<_>>();
-		log::debug!("Got {} unexist books", unexist_ids.len());
+		log::debug!(
+			"Got {} unexist books and {} new books",
+			unexist_ids.len(),
+			self.new_books.len()
+		);
 		self.records.unexist_books(&unexist_ids)?;
 		for book_id in unexist_ids {
 			self.bell.book_updated(book_id);
@@ -219,6 +261,8 @@ enum ProcessFileError {
 	QuickXml(#[from] quick_xml::Error),
 	#[error(transparent)]
 	CreateThumbnail(#[from] CreateThumbnailError),
+	#[error(transparent)]
+	Format(#[from] BookFormatError),
 	#[error("No epub package root file in zip")]
 	NoEpubRootFile,
 	#[error("at {1}: {0}")]
@@ -245,7 +289,30 @@ impl<B: LibraryBell> LibraryScribe<B> {
 		book_id: BookId,
 		content: &FileContent<'_>,
 	) -> Result<(), ProcessFileError> {
-		let mut archive = ZipArchive::new(content.file)?;
+		match BookFormat::from_path(content.document.path()) {
+			Some(BookFormat::Epub) | None => {
+				let mut archive = ZipArchive::new(content.file)?;
+				self.process_archive(book_id, &mut archive, content.timestamp)
+			}
+			Some(format) => {
+				let mut bytes = Vec::new();
+				let mut file = content.file;
+				file.read_to_end(&mut bytes)?;
+				// Resources are only needed for reading, not for metadata
+				let epub = format.into_epub(content.document.path(), bytes, |_| None)?;
+				let mut archive = ZipArchive::new(io::Cursor::new(epub))?;
+				self.process_archive(book_id, &mut archive, content.timestamp)
+			}
+		}
+	}
+
+	fn process_archive<R: io::Read + io::Seek>(
+		&mut self,
+		mut book_id: BookId,
+		archive: &mut ZipArchive<R>,
+		timestamp: SystemTime,
+	) -> Result<(), ProcessFileError> {
+		let fingerprint = fingerprint(archive)?;
 
 		let file = archive.by_path(Path::new(EPUB_CONTAINER_PATH))?;
 		let root_path = parse_container(quick_xml::Reader::from_reader(io::BufReader::new(file)))?;
@@ -264,14 +331,58 @@ impl<B: LibraryBell> LibraryScribe<B> {
 			package.metadata.title.as_deref().unwrap_or_default()
 		);
 
-		let modified_at: DateTime<Utc> = <DateTime<Utc>>::from(content.timestamp).trunc_subsecs(0);
+		if self.new_books.remove(&book_id)
+			&& let Some(identifier) = package.metadata.identifier.as_deref()
+			&& let Some(unexist_id) = self.records.fetch_unexist_book(identifier, &fingerprint)?
+			&& unexist_id != book_id
+		{
+			log::info!("Book {book_id} is moved book {unexist_id}, re-point");
+			self.records.repoint_book(unexist_id, book_id)?;
+			self.bell.book_updated(book_id);
+			book_id = unexist_id;
+		}
+
+		let modified_at: DateTime<Utc> = <DateTime<Utc>>::from(timestamp).trunc_subsecs(0);
 		let book = UpdateBook {
 			book_id: book_id.into_inner(),
 			title: package.metadata.title.as_deref(),
 			author: package.metadata.creator.as_deref(),
+			identifier: package.metadata.identifier.as_deref(),
+			fingerprint: &fingerprint,
 			modified_at,
 		};
 		self.records.update_book(book)?;
+		self.records
+			.record_book_metadata(book_id, &book_metadata(&package.metadata))?;
+		if self.tag_subjects {
+			self.records
+				.record_book_tags(book_id, &package.metadata.subjects)?;
+		}
+
+		let subjects = package.metadata.subjects.join("\n");
+		let body = if self.index_content {
+			Some(extract_book_text(archive, &package))
+		} else {
+			None
+		};
+		self.records.record_book_index(IndexBook {
+			book_id: book_id.into_inner(),
+			title: package.metadata.title.as_deref(),
+			author: package.metadata.creator.as_deref(),
+			publisher: package.metadata.publisher.as_deref(),
+			subjects: Some(&subjects)
+				.filter(|s| !s.is_empty())
+				.map(String::as_str),
+			body: body.as_deref(),
+		})?;
+
+		let previous = self.records.fetch_book_spine(book_id)?;
+		let spine = diff_spine(&previous, &package.spine, Utc::now());
+		let added = spine.len().saturating_sub(previous.len());
+		if added > 0 && !previous.is_empty() {
+			log::info!("Book {book_id} grew by {added} spine items");
+		}
+		self.records.record_book_spine(book_id, &spine)?;
 		self.bell.book_updated(book_id);
 
 		if let Some(cover) = package
@@ -355,6 +466,7 @@ impl<B: LibraryBell + Sized + Send> Wrangler for LibraryScribe<B> {
 
 			self.discovery_ticket = Some(ticket);
 			self.stale_books.clear();
+			self.new_books.clear();
 
 			WranglerResult::Handled
 		} else {
@@ -397,6 +509,152 @@ impl<B: LibraryBell + Sized + Send> Wrangler for LibraryScribe<B> {
 	}
 }
 
+/// Fingerprint archive content from zip central directory.
+///
+/// FNV-1a over name, crc32 and size of each entry, avoids decompressing.
+fn fingerprint<R: io::Read + io::Seek>(
+	archive: &mut ZipArchive<R>,
+) -> Result<String, zip::result::ZipError> {
+	const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+	const FNV_PRIME: u64 = 0x100000001b3;
+
+	let mut hash = FNV_OFFSET;
+	for i in 0..archive.len() {
+		let file = archive.by_index_raw(i)?;
+		let crc32 = file.crc32().to_le_bytes();
+		let size = file.size().to_le_bytes();
+		let bytes = file.name().as_bytes().iter().chain(&crc32).chain(&size);
+		for b in bytes {
+			hash ^= *b as u64;
+			hash = hash.wrapping_mul(FNV_PRIME);
+		}
+	}
+	Ok(format!("{hash:016x}"))
+}
+
+fn book_metadata(metadata: &scribe_epub::Metadata) -> BookMetadata {
+	let creator = |c: &scribe_epub::Creator| Creator {
+		name: c.name.clone(),
+		role: c.role.clone(),
+		file_as: c.file_as.clone(),
+	};
+	BookMetadata {
+		description: metadata.description.clone(),
+		creators: metadata.creators.iter().map(creator).collect(),
+		contributors: metadata.contributors.iter().map(creator).collect(),
+		subjects: metadata.subjects.clone(),
+		series: metadata.series.as_ref().map(|s| Series {
+			name: Arc::new(s.name.clone()),
+			index: s.index,
+		}),
+	}
+}
+
+/// Text of spine documents for search index, capped at [`MAX_INDEX_BODY_BYTES`].
+fn extract_book_text<R: io::Read + io::Seek>(
+	archive: &mut ZipArchive<R>,
+	package: &Package,
+) -> String {
+	let mut text = String::new();
+	for resource in package
+		.spine
+		.iter()
+		.filter_map(|id| package.manifest.get(id))
+	{
+		let file = match archive.by_path(resource.as_path()) {
+			Ok(file) => file,
+			Err(e) => {
+				log::warn!("Failed to open {} for indexing: {e}", resource.href);
+				continue;
+			}
+		};
+		let reader = quick_xml::Reader::from_reader(io::BufReader::new(file));
+		if let Err(e) = extract_body_text(reader, &mut text) {
+			log::warn!("Failed to read {} for indexing: {e}", resource.href);
+		}
+		if text.len() >= MAX_INDEX_BODY_BYTES {
+			let mut end = MAX_INDEX_BODY_BYTES;
+			while !text.is_char_boundary(end) {
+				end -= 1;
+			}
+			text.truncate(end);
+			break;
+		}
+	}
+	text
+}
+
+/// Append text of xhtml body to `out`, skipping scripts and styles.
+///
+/// Text read before an error is kept.
+fn extract_body_text<R: BufRead>(
+	mut reader: quick_xml::Reader<R>,
+	out: &mut String,
+) -> Result<(), quick_xml::Error> {
+	use quick_xml::events::Event;
+
+	reader.config_mut().check_end_names = false;
+
+	let mut buf = Vec::new();
+	let mut in_body = false;
+	let mut skip = 0u32;
+	loop {
+		match reader.read_event_into(&mut buf)? {
+			Event::Start(e) => match e.local_name().as_ref() {
+				b"body" => in_body = true,
+				b"script" | b"style" => skip += 1,
+				_ => {}
+			},
+			Event::End(e) => match e.local_name().as_ref() {
+				b"body" => in_body = false,
+				b"script" | b"style" => skip = skip.saturating_sub(1),
+				// Keep words of adjacent blocks apart
+				_ if in_body => out.push(' '),
+				_ => {}
+			},
+			Event::Empty(_) if in_body => out.push(' '),
+			Event::Text(t) if in_body && skip == 0 => out.push_str(&t.decode()?),
+			Event::CData(t) if in_body && skip == 0 => out.push_str(&t.decode()?),
+			Event::GeneralRef(r) if in_body && skip == 0 => {
+				if let Some(c) = r.resolve_char_ref()? {
+					out.push(c);
+				} else if let Some(s) = resolve_html5_entity(&r.decode()?) {
+					out.push_str(s);
+				}
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+	out.push('\n');
+	Ok(())
+}
+
+/// Carry over when each spine item was first seen.
+///
+/// Items not present in previous scan are stamped with `now`, unless there
+/// was no previous scan at all, in which case the whole spine is considered
+/// known since forever.
+fn diff_spine(previous: &[SpineItem], spine: &[ResourceId], now: DateTime<Utc>) -> Vec<SpineItem> {
+	let known: BTreeMap<&str, DateTime<Utc>> = previous
+		.iter()
+		.map(|item| (item.idref.as_str(), item.added_at))
+		.collect();
+	let added_at = if previous.is_empty() {
+		DateTime::<Utc>::UNIX_EPOCH
+	} else {
+		now
+	};
+	spine
+		.iter()
+		.map(|idref| SpineItem {
+			idref: idref.to_string(),
+			added_at: known.get(&**idref).copied().unwrap_or(added_at),
+		})
+		.collect()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum CreateThumbnailError {
 	#[error("at {1}: {0}")]
@@ -447,3 +705,45 @@ fn create_thumbnail(path: &Path, bytes: &[u8]) -> Result<(), CreateThumbnailErro
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+	use std::io::Write;
+
+	use zip::ZipArchive;
+	use zip::ZipWriter;
+	use zip::write::SimpleFileOptions;
+
+	use crate::library::fingerprint;
+
+	fn archive(files: &[(&str, &str)]) -> ZipArchive<io::Cursor<Vec<u8>>> {
+		let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+		for (name, content) in files {
+			zip.start_file(*name, SimpleFileOptions::default())
+				.expect("Start file failed");
+			zip.write_all(content.as_bytes()).expect("Write failed");
+		}
+		let cursor = zip.finish().expect("Finish failed");
+		ZipArchive::new(io::Cursor::new(cursor.into_inner())).expect("Open failed")
+	}
+
+	#[test]
+	fn test_fingerprint() {
+		let files = [("mimetype", "application/epub+zip"), ("a.xhtml", "Chapter")];
+		let first = fingerprint(&mut archive(&files)).expect("Fingerprint failed");
+		let again = fingerprint(&mut archive(&files)).expect("Fingerprint failed");
+		assert_eq!(first, again, "Same content missmatch");
+
+		let changed = [
+			("mimetype", "application/epub+zip"),
+			("a.xhtml", "Chapter 1"),
+		];
+		let other = fingerprint(&mut archive(&changed)).expect("Fingerprint failed");
+		assert_ne!(first, other, "Changed content missmatch");
+
+		let renamed = [("mimetype", "application/epub+zip"), ("b.xhtml", "Chapter")];
+		let other = fingerprint(&mut archive(&renamed)).expect("Fingerprint failed");
+		assert_ne!(first, other, "Renamed content missmatch");
+	}
+}
@@ -1,4 +1,6 @@
 use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::TimeDelta;
 use chrono::Utc;
 use chrono::serde::ts_seconds;
 use chrono::serde::ts_seconds_option;
@@ -15,6 +17,7 @@ use serde_rusqlite::from_rows;
 use serde_rusqlite::to_params_named;
 
 use std::collections::BTreeMap;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -22,7 +25,26 @@ use std::sync::OnceLock;
 
 use crate::Book;
 use crate::BookId;
+use crate::BookMetadata;
+use crate::BookReadingTime;
+use crate::Bookmark;
+use crate::Collection;
+use crate::CollectionId;
+use crate::Creator;
+use crate::DayReading;
+use crate::Highlight;
+use crate::HighlightId;
 use crate::Location;
+use crate::ReadingPace;
+use crate::ReadingSession;
+use crate::Series;
+use crate::SessionId;
+use crate::SpineChars;
+use crate::SpinePages;
+use crate::TextPosition;
+
+/// Characters read in book before its own sessions are used for pace
+const MIN_PACE_CHARS: u64 = 20_000;
 
 const MIGRATIONS_SLICE: &[M<'_>] = &[
 	M::up(
@@ -72,6 +94,181 @@ const MIGRATIONS_SLICE: &[M<'_>] = &[
 		) strict;
 		",
 	),
+	M::up(
+		"create table book_spine_items (
+			book_id integer not null,
+			spine integer not null,
+			idref text not null,
+			added_at integer not null,
+			primary key (book_id, spine),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;",
+	),
+	M::up(
+		"alter table books add column identifier text;
+		alter table books add column fingerprint text;
+		create index books_identity on books (identifier, fingerprint);
+		",
+	),
+	M::up(
+		"create virtual table books_fts using fts5 (
+			title,
+			author,
+			publisher,
+			subjects,
+			body,
+			tokenize = 'unicode61 remove_diacritics 2'
+		);
+		insert into books_fts (rowid, title, author)
+			select id, title, author from books;
+		create trigger books_fts_delete after delete on books begin
+			delete from books_fts where rowid = old.id;
+		end;
+		",
+	),
+	M::up(
+		"alter table books add column description text;
+		create table book_creators (
+			book_id integer not null,
+			contributor integer not null check (contributor in (0, 1)),
+			position integer not null,
+			name text not null,
+			role text,
+			file_as text,
+			primary key (book_id, contributor, position),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		create table book_subjects (
+			book_id integer not null,
+			subject text not null,
+			primary key (book_id, subject),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		create table book_series (
+			book_id integer primary key,
+			name text not null,
+			series_index real,
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		create index book_series_order on book_series (name, series_index);
+		",
+	),
+	M::up(
+		"create table collections (
+			id integer primary key,
+			name text not null unique collate nocase,
+			tag integer not null check (tag in (0, 1)),
+			added_at integer not null
+		) strict;
+		create table book_collections (
+			book_id integer not null,
+			collection_id integer not null,
+			tagged integer not null check (tagged in (0, 1)),
+			added_at integer not null,
+			primary key (book_id, collection_id),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade,
+			foreign key (collection_id) references collections(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		create index book_collections_collection on book_collections (collection_id, book_id);
+		",
+	),
+	M::up(
+		"create table bookmarks (
+			book_id integer not null,
+			spine integer not null,
+			element integer not null,
+			chapter text,
+			excerpt text,
+			created_at integer not null,
+			primary key (book_id, spine, element),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;",
+	),
+	M::up(
+		"create table highlights (
+			id integer primary key,
+			book_id integer not null,
+			spine integer not null,
+			start_element integer not null,
+			start_offset integer not null,
+			end_element integer not null,
+			end_offset integer not null,
+			chapter text,
+			text text not null,
+			note text,
+			created_at integer not null,
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		create index highlights_book on highlights (book_id, spine, start_element, start_offset);
+		",
+	),
+	M::up(
+		"create table reading_sessions (
+			id integer primary key,
+			book_id integer not null,
+			started_at integer not null,
+			ended_at integer not null,
+			pages integer not null,
+			start_spine integer not null,
+			start_element integer not null,
+			end_spine integer not null,
+			end_element integer not null,
+			start_percent integer not null,
+			end_percent integer not null,
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		create index reading_sessions_book on reading_sessions (book_id, started_at);
+		create index reading_sessions_started on reading_sessions (started_at);
+		",
+	),
+	M::up(
+		"create table book_spine_chars (
+			book_id integer not null,
+			spine integer not null,
+			crc32 integer not null,
+			chars integer not null,
+			primary key (book_id, spine),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		alter table reading_sessions add column chars integer not null default 0;
+		",
+	),
+	M::up(
+		"create table book_pages (
+			book_id integer not null,
+			layout text not null,
+			spine integer not null,
+			crc32 integer not null,
+			page integer not null,
+			start_element integer not null,
+			end_element integer not null,
+			primary key (book_id, layout, spine, page),
+			foreign key (book_id) references books(id)
+				on update cascade
+				on delete cascade
+		) strict;
+		",
+	),
 ];
 const MIGRATIONS: Migrations<'_> = Migrations::from_slice(MIGRATIONS_SLICE);
 
@@ -130,6 +327,10 @@ struct SecretBook {
 	spine: Option<u32>,
 	element: Option<u32>,
 	percent_read: Option<u32>,
+	new_spines: u32,
+	first_unread_spine: Option<u32>,
+	series: Option<String>,
+	series_index: Option<f64>,
 }
 
 impl From<SecretBook> for Book {
@@ -146,15 +347,30 @@ impl From<SecretBook> for Book {
 			spine: value.spine,
 			element: value.element.map(U26F6::from_bits),
 			percent_read: value.percent_read,
+			new_spines: value.new_spines,
+			first_unread_spine: value.first_unread_spine,
+			series: value.series.map(|name| Series {
+				name: Arc::new(name),
+				index: value.series_index,
+			}),
 		}
 	}
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpineItem {
+	pub idref: String,
+	#[serde(with = "ts_seconds")]
+	pub added_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UpdateBook<'a> {
 	pub book_id: i64,
 	pub title: Option<&'a str>,
 	pub author: Option<&'a str>,
+	pub identifier: Option<&'a str>,
+	pub fingerprint: &'a str,
 	#[serde(with = "ts_seconds")]
 	pub modified_at: DateTime<Utc>,
 }
@@ -171,6 +387,263 @@ pub struct InsertBook {
 	pub added_at: DateTime<Utc>,
 }
 
+/// Searchable text of book, `body` only when content is indexed
+#[derive(Debug, Serialize)]
+pub struct IndexBook<'a> {
+	pub book_id: i64,
+	pub title: Option<&'a str>,
+	pub author: Option<&'a str>,
+	pub publisher: Option<&'a str>,
+	pub subjects: Option<&'a str>,
+	pub body: Option<&'a str>,
+}
+
+/// Order of books in library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShelfOrder {
+	/// Last modified first
+	Modified,
+	/// Last added first
+	Added,
+	/// Last opened first
+	Opened,
+	/// Shelved by author sort name
+	Author,
+	/// Shelved by series, in series order
+	Series,
+	/// Shelved by first letter of title, leading articles ignored
+	Title,
+}
+
+impl ShelfOrder {
+	/// Shelf name and order by clause for `shelved` of [`RecordKeeperAssistant::fetch_shelved_books`]
+	fn clauses(self) -> (&'static str, &'static str) {
+		match self {
+			ShelfOrder::Modified => ("null", "sh.modified_at desc"),
+			ShelfOrder::Added => ("null", "sh.added_at desc"),
+			ShelfOrder::Opened => ("null", "sh.opened_at is null, sh.opened_at desc"),
+			ShelfOrder::Author => (
+				"sh.author",
+				"sh.author is null, sh.author collate nocase, sh.title collate nocase",
+			),
+			ShelfOrder::Series => (
+				"sh.series",
+				"sh.series is null,
+				sh.series collate nocase,
+				sh.series_index is null,
+				sh.series_index,
+				sh.title collate nocase",
+			),
+			ShelfOrder::Title => (
+				"upper(substr(sh.title, 1, 1))",
+				"sh.title is null, sh.title collate nocase",
+			),
+		}
+	}
+}
+
+/// Book in library order, books of same shelf are adjacent
+#[derive(Debug, Clone)]
+pub struct ShelvedBook {
+	pub id: BookId,
+	/// Shelf name, `None` when order has no shelves or book is missing shelf value
+	pub shelf: Option<Arc<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretShelvedBook {
+	id: i64,
+	shelf: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretBookmark {
+	spine: u32,
+	element: u32,
+	chapter: Option<String>,
+	excerpt: Option<String>,
+	#[serde(with = "ts_seconds")]
+	created_at: DateTime<Utc>,
+}
+
+impl From<SecretBookmark> for Bookmark {
+	fn from(value: SecretBookmark) -> Self {
+		Bookmark {
+			location: Location {
+				spine: value.spine,
+				element: U26F6::from_bits(value.element),
+			},
+			chapter: value.chapter.map(Arc::new),
+			excerpt: value.excerpt,
+			created_at: value.created_at,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct InsertBookmark<'a> {
+	book_id: i64,
+	spine: u32,
+	element: u32,
+	chapter: Option<&'a str>,
+	excerpt: Option<&'a str>,
+	#[serde(with = "ts_seconds")]
+	created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretHighlight {
+	id: i64,
+	spine: u32,
+	start_element: u32,
+	start_offset: u32,
+	end_element: u32,
+	end_offset: u32,
+	chapter: Option<String>,
+	text: String,
+	note: Option<String>,
+	#[serde(with = "ts_seconds")]
+	created_at: DateTime<Utc>,
+}
+
+impl From<SecretHighlight> for Highlight {
+	fn from(value: SecretHighlight) -> Self {
+		Highlight {
+			id: HighlightId(value.id),
+			spine: value.spine,
+			start: TextPosition {
+				element: value.start_element,
+				offset: value.start_offset,
+			},
+			end: TextPosition {
+				element: value.end_element,
+				offset: value.end_offset,
+			},
+			chapter: value.chapter.map(Arc::new),
+			text: value.text,
+			note: value.note,
+			created_at: value.created_at,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct InsertReadingSession {
+	book_id: i64,
+	#[serde(with = "ts_seconds")]
+	started_at: DateTime<Utc>,
+	#[serde(with = "ts_seconds")]
+	ended_at: DateTime<Utc>,
+	pages: u32,
+	chars: u64,
+	start_spine: u32,
+	start_element: u32,
+	end_spine: u32,
+	end_element: u32,
+	start_percent: u32,
+	end_percent: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateReadingSession {
+	id: i64,
+	#[serde(with = "ts_seconds")]
+	ended_at: DateTime<Utc>,
+	pages: u32,
+	chars: u64,
+	end_spine: u32,
+	end_element: u32,
+	end_percent: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretBookReadingTime {
+	id: i64,
+	title: Option<String>,
+	seconds: i64,
+	pages: u32,
+	sessions: u32,
+	#[serde(with = "ts_seconds")]
+	last_read_at: DateTime<Utc>,
+}
+
+impl From<SecretBookReadingTime> for BookReadingTime {
+	fn from(value: SecretBookReadingTime) -> Self {
+		BookReadingTime {
+			id: BookId(value.id),
+			title: value.title.map(Arc::new),
+			time: TimeDelta::seconds(value.seconds),
+			pages: value.pages,
+			sessions: value.sessions,
+			last_read_at: value.last_read_at,
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretDayReading {
+	day: NaiveDate,
+	pages: u32,
+	seconds: i64,
+}
+
+impl From<SecretDayReading> for DayReading {
+	fn from(value: SecretDayReading) -> Self {
+		DayReading {
+			day: value.day,
+			pages: value.pages,
+			time: TimeDelta::seconds(value.seconds),
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretPace {
+	seconds: i64,
+	chars: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretSpineChars {
+	spine: u32,
+	crc32: u32,
+	chars: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretBookPage {
+	spine: u32,
+	crc32: u32,
+	start_element: u32,
+	end_element: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretCollection {
+	id: i64,
+	name: String,
+	tag: bool,
+	books: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretCreator {
+	contributor: bool,
+	name: String,
+	role: Option<String>,
+	file_as: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct InsertCreator<'a> {
+	book_id: i64,
+	contributor: bool,
+	position: u32,
+	name: &'a str,
+	role: Option<&'a str>,
+	file_as: Option<&'a str>,
+}
+
 #[derive(Debug, Serialize)]
 struct InsertThumbnail<'a> {
 	pub book_id: i64,
@@ -189,6 +662,15 @@ struct InsertBookState {
 	pub percent_read: u32,
 }
 
+#[derive(Debug, Serialize)]
+struct InsertSpineItem<'a> {
+	pub book_id: i64,
+	pub spine: u32,
+	pub idref: &'a str,
+	#[serde(with = "ts_seconds")]
+	pub added_at: DateTime<Utc>,
+}
+
 pub struct RecordKeeperAssistant {
 	conn: rusqlite::Connection,
 }
@@ -233,9 +715,22 @@ impl RecordKeeperAssistant {
 				bs.opened_at,
 				bs.spine,
 				bs.element,
-				bs.percent_read
+				bs.percent_read,
+				(select count(*)
+					from book_spine_items si
+					where si.book_id = bo.id
+						and si.added_at > bs.opened_at
+				) as new_spines,
+				(select min(si.spine)
+					from book_spine_items si
+					where si.book_id = bo.id
+						and si.added_at > bs.opened_at
+				) as first_unread_spine,
+				se.name as series,
+				se.series_index
 			from books bo
 			left join book_reading_state bs on bs.book_id = bo.id
+			left join book_series se on se.book_id = bo.id
 			where bo.exist = true
 				and id = ?1;
 			",
@@ -258,9 +753,22 @@ impl RecordKeeperAssistant {
 				bs.opened_at,
 				bs.spine,
 				bs.element,
-				bs.percent_read
+				bs.percent_read,
+				(select count(*)
+					from book_spine_items si
+					where si.book_id = bo.id
+						and si.added_at > bs.opened_at
+				) as new_spines,
+				(select min(si.spine)
+					from book_spine_items si
+					where si.book_id = bo.id
+						and si.added_at > bs.opened_at
+				) as first_unread_spine,
+				se.name as series,
+				se.series_index
 			from books bo
 			left join book_reading_state bs on bs.book_id = bo.id
+			left join book_series se on se.book_id = bo.id
 			where bo.exist = true
 			",
 		)?;
@@ -304,6 +812,46 @@ impl RecordKeeperAssistant {
 		Ok(())
 	}
 
+	/// Find book no longer in library with same identity.
+	pub fn fetch_unexist_book(
+		&self,
+		identifier: &str,
+		fingerprint: &str,
+	) -> Result<Option<BookId>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				bo.id
+			from books bo
+			where bo.exist = false
+				and bo.identifier = ?1
+				and bo.fingerprint = ?2
+			order by bo.modified_at desc
+			limit 1
+			",
+		)?;
+		let id = stmt
+			.query_one([identifier, fingerprint], |row| row.get(0))
+			.optional()?;
+		Ok(id.map(BookId))
+	}
+
+	/// Move path of book `from` to unexisting book `id`, removing `from`.
+	pub fn repoint_book(&mut self, id: BookId, from: BookId) -> Result<(), RecordKeeperError> {
+		let tx = self.conn.transaction()?;
+		let path: String = tx.query_row(
+			"select path from books where id = ?1",
+			[from.into_inner()],
+			|row| row.get(0),
+		)?;
+		tx.execute("delete from books where id = ?1", [from.into_inner()])?;
+		tx.execute(
+			"update books set path = ?1, exist = true where id = ?2",
+			(path, id.into_inner()),
+		)?;
+		tx.commit()?;
+		Ok(())
+	}
+
 	pub fn fetch_thumbnail(
 		&self,
 		id: super::BookId,
@@ -330,6 +878,8 @@ impl RecordKeeperAssistant {
 			"update books set
 				title = :title,
 				author = :author,
+				identifier = :identifier,
+				fingerprint = :fingerprint,
 				modified_at = :modified_at,
 				exist = true
 			where id = :book_id;
@@ -339,6 +889,44 @@ impl RecordKeeperAssistant {
 		Ok(())
 	}
 
+	/// Replace search index entry of book
+	pub fn record_book_index(&mut self, book: IndexBook) -> Result<(), RecordKeeperError> {
+		let tx = self.conn.transaction()?;
+		tx.execute("delete from books_fts where rowid = ?1", [book.book_id])?;
+		tx.execute(
+			"insert into books_fts (rowid, title, author, publisher, subjects, body)
+				values (:book_id, :title, :author, :publisher, :subjects, :body)
+			",
+			to_params_named(book)?.to_slice().as_slice(),
+		)?;
+		tx.commit()?;
+		Ok(())
+	}
+
+	/// Books matching all words of query, best match first.
+	///
+	/// Words match as prefixes, case and diacritics are ignored.
+	pub fn search_books(&self, query: &str, limit: u32) -> Result<Vec<BookId>, RecordKeeperError> {
+		let Some(query) = fts_query(query) else {
+			return Ok(Vec::new());
+		};
+		let mut stmt = self.conn.prepare(
+			"select
+				fts.rowid
+			from books_fts fts
+			join books bo on bo.id = fts.rowid
+			where books_fts match ?1
+				and bo.exist = true
+			order by bm25(books_fts, 10.0, 5.0, 2.0, 2.0, 1.0)
+			limit ?2
+			",
+		)?;
+		let ids = stmt
+			.query_map((query, limit), |row| row.get(0).map(BookId))?
+			.collect::<Result<_, _>>()?;
+		Ok(ids)
+	}
+
 	pub fn record_thumbnail(
 		&mut self,
 		id: super::BookId,
@@ -362,6 +950,404 @@ impl RecordKeeperAssistant {
 		Ok(())
 	}
 
+	pub fn fetch_shelved_books(
+		&self,
+		order: ShelfOrder,
+	) -> Result<Vec<ShelvedBook>, RecordKeeperError> {
+		let (shelf, order_by) = order.clauses();
+		let mut stmt = self.conn.prepare(&format!(
+			"with shelved as (
+				select
+					bo.id,
+					bo.modified_at,
+					bo.added_at,
+					bs.opened_at,
+					coalesce(
+						(select coalesce(bc.file_as, bc.name)
+							from book_creators bc
+							where bc.book_id = bo.id
+								and bc.contributor = false
+							order by bc.role is not 'aut', bc.position
+							limit 1
+						),
+						bo.author
+					) as author,
+					case
+						when bo.title like 'the %' then substr(bo.title, 5)
+						when bo.title like 'an %' then substr(bo.title, 4)
+						when bo.title like 'a %' then substr(bo.title, 3)
+						else bo.title
+					end as title,
+					se.name as series,
+					se.series_index
+				from books bo
+				left join book_reading_state bs on bs.book_id = bo.id
+				left join book_series se on se.book_id = bo.id
+				where bo.exist = true
+			)
+			select
+				sh.id,
+				{shelf} as shelf
+			from shelved sh
+			order by {order_by}
+			"
+		))?;
+		Ok(from_rows::<SecretShelvedBook>(stmt.query([])?)
+			.map(|b| {
+				b.map(|b| ShelvedBook {
+					id: BookId(b.id),
+					shelf: b.shelf.map(Arc::new),
+				})
+			})
+			.collect::<Result<_, _>>()?)
+	}
+
+	pub fn fetch_book_metadata(&self, id: BookId) -> Result<BookMetadata, RecordKeeperError> {
+		let description = self.conn.query_row(
+			"select description from books where id = ?1",
+			[id.into_inner()],
+			|row| row.get(0),
+		)?;
+
+		let mut stmt = self.conn.prepare(
+			"select
+				bc.contributor,
+				bc.name,
+				bc.role,
+				bc.file_as
+			from book_creators bc
+			where bc.book_id = ?1
+			order by bc.contributor, bc.position
+			",
+		)?;
+		let mut creators = Vec::new();
+		let mut contributors = Vec::new();
+		for creator in from_rows::<SecretCreator>(stmt.query([id.into_inner()])?) {
+			let creator = creator?;
+			let list = if creator.contributor {
+				&mut contributors
+			} else {
+				&mut creators
+			};
+			list.push(Creator {
+				name: creator.name,
+				role: creator.role,
+				file_as: creator.file_as,
+			});
+		}
+
+		let mut stmt = self
+			.conn
+			.prepare("select subject from book_subjects where book_id = ?1 order by subject")?;
+		let subjects = stmt
+			.query_map([id.into_inner()], |row| row.get(0))?
+			.collect::<Result<_, _>>()?;
+
+		let series = self
+			.conn
+			.query_row(
+				"select name, series_index from book_series where book_id = ?1",
+				[id.into_inner()],
+				|row| {
+					Ok(Series {
+						name: Arc::new(row.get(0)?),
+						index: row.get(1)?,
+					})
+				},
+			)
+			.optional()?;
+
+		Ok(BookMetadata {
+			description,
+			creators,
+			contributors,
+			subjects,
+			series,
+		})
+	}
+
+	/// Replace extended metadata of book
+	pub fn record_book_metadata(
+		&mut self,
+		id: BookId,
+		metadata: &BookMetadata,
+	) -> Result<(), RecordKeeperError> {
+		let tx = self.conn.transaction()?;
+		tx.execute(
+			"update books set description = ?1 where id = ?2",
+			(metadata.description.as_deref(), id.into_inner()),
+		)?;
+		tx.execute(
+			"delete from book_creators where book_id = ?1",
+			[id.into_inner()],
+		)?;
+		tx.execute(
+			"delete from book_subjects where book_id = ?1",
+			[id.into_inner()],
+		)?;
+		tx.execute(
+			"delete from book_series where book_id = ?1",
+			[id.into_inner()],
+		)?;
+
+		let mut insert_stmt = tx.prepare(
+			"insert into book_creators (book_id, contributor, position, name, role, file_as)
+				values (:book_id, :contributor, :position, :name, :role, :file_as)
+			",
+		)?;
+		let lists = [(false, &metadata.creators), (true, &metadata.contributors)];
+		for (contributor, list) in lists {
+			for (position, creator) in list.iter().enumerate() {
+				let creator = InsertCreator {
+					book_id: id.into_inner(),
+					contributor,
+					position: position as u32,
+					name: &creator.name,
+					role: creator.role.as_deref(),
+					file_as: creator.file_as.as_deref(),
+				};
+				insert_stmt.execute(to_params_named(creator)?.to_slice().as_slice())?;
+			}
+		}
+		drop(insert_stmt);
+
+		let mut insert_stmt =
+			tx.prepare("insert or ignore into book_subjects (book_id, subject) values (?1, ?2)")?;
+		for subject in &metadata.subjects {
+			insert_stmt.execute((id.into_inner(), subject))?;
+		}
+		drop(insert_stmt);
+
+		if let Some(series) = &metadata.series {
+			tx.execute(
+				"insert into book_series (book_id, name, series_index) values (?1, ?2, ?3)",
+				(id.into_inner(), series.name.as_str(), series.index),
+			)?;
+		}
+		tx.commit()?;
+		Ok(())
+	}
+
+	/// Collections by name, with number of existing books
+	pub fn fetch_collections(&self) -> Result<Vec<Collection>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				co.id,
+				co.name,
+				co.tag,
+				(select count(*)
+					from book_collections bc
+					join books bo on bo.id = bc.book_id
+					where bc.collection_id = co.id
+						and bo.exist = true
+				) as books
+			from collections co
+			order by co.name collate nocase
+			",
+		)?;
+		Ok(from_rows::<SecretCollection>(stmt.query([])?)
+			.map(|c| {
+				c.map(|c| Collection {
+					id: CollectionId(c.id),
+					name: Arc::new(c.name),
+					tag: c.tag,
+					books: c.books,
+				})
+			})
+			.collect::<Result<_, _>>()?)
+	}
+
+	/// Create collection, or return existing collection with same name.
+	///
+	/// Existing tag becomes a user collection.
+	pub fn create_collection(&mut self, name: &str) -> Result<CollectionId, RecordKeeperError> {
+		let id = self.conn.query_row(
+			"insert into collections (name, tag, added_at)
+				values (?1, false, ?2)
+			on conflict (name)
+			do update set
+				tag = false
+			returning collections.id",
+			(name, Utc::now().timestamp()),
+			|row| row.get(0),
+		)?;
+		Ok(CollectionId(id))
+	}
+
+	pub fn rename_collection(
+		&mut self,
+		id: CollectionId,
+		name: &str,
+	) -> Result<(), RecordKeeperError> {
+		self.conn.execute(
+			"update collections set name = ?1 where id = ?2",
+			(name, id.into_inner()),
+		)?;
+		Ok(())
+	}
+
+	pub fn delete_collection(&mut self, id: CollectionId) -> Result<(), RecordKeeperError> {
+		self.conn
+			.execute("delete from collections where id = ?1", [id.into_inner()])?;
+		Ok(())
+	}
+
+	pub fn fetch_collection_books(
+		&self,
+		id: CollectionId,
+	) -> Result<Vec<BookId>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				bc.book_id
+			from book_collections bc
+			where bc.collection_id = ?1
+			",
+		)?;
+		let ids = stmt
+			.query_map([id.into_inner()], |row| row.get(0).map(BookId))?
+			.collect::<Result<_, _>>()?;
+		Ok(ids)
+	}
+
+	pub fn fetch_book_collections(
+		&self,
+		id: BookId,
+	) -> Result<Vec<CollectionId>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				bc.collection_id
+			from book_collections bc
+			where bc.book_id = ?1
+			",
+		)?;
+		let ids = stmt
+			.query_map([id.into_inner()], |row| row.get(0).map(CollectionId))?
+			.collect::<Result<_, _>>()?;
+		Ok(ids)
+	}
+
+	/// Add book to collection, book is kept when tags are replaced
+	pub fn add_book_to_collection(
+		&mut self,
+		id: BookId,
+		collection: CollectionId,
+	) -> Result<(), RecordKeeperError> {
+		self.conn.execute(
+			"insert into book_collections (book_id, collection_id, tagged, added_at)
+				values (?1, ?2, false, ?3)
+			on conflict (book_id, collection_id)
+			do update set
+				tagged = false
+			",
+			(
+				id.into_inner(),
+				collection.into_inner(),
+				Utc::now().timestamp(),
+			),
+		)?;
+		Ok(())
+	}
+
+	pub fn remove_book_from_collection(
+		&mut self,
+		id: BookId,
+		collection: CollectionId,
+	) -> Result<(), RecordKeeperError> {
+		self.conn.execute(
+			"delete from book_collections where book_id = ?1 and collection_id = ?2",
+			(id.into_inner(), collection.into_inner()),
+		)?;
+		Ok(())
+	}
+
+	/// Replace tags of book with subjects.
+	///
+	/// Tags are created as needed and removed when no book has them.
+	pub fn record_book_tags(
+		&mut self,
+		id: BookId,
+		subjects: &[String],
+	) -> Result<(), RecordKeeperError> {
+		let now = Utc::now().timestamp();
+		let tx = self.conn.transaction()?;
+		tx.execute(
+			"delete from book_collections where book_id = ?1 and tagged = true",
+			[id.into_inner()],
+		)?;
+		let mut tag_stmt = tx.prepare(
+			"insert into collections (name, tag, added_at)
+				values (?1, true, ?2)
+			on conflict (name)
+			do update set
+				name = name
+			returning collections.id",
+		)?;
+		let mut insert_stmt = tx.prepare(
+			"insert or ignore into book_collections (book_id, collection_id, tagged, added_at)
+				values (?1, ?2, true, ?3)
+			",
+		)?;
+		for subject in subjects {
+			let collection: i64 = tag_stmt.query_one((subject, now), |row| row.get(0))?;
+			insert_stmt.execute((id.into_inner(), collection, now))?;
+		}
+		drop(tag_stmt);
+		drop(insert_stmt);
+		tx.execute(
+			"delete from collections
+			where tag = true
+				and not exists (
+					select 1 from book_collections bc where bc.collection_id = collections.id
+				)
+			",
+			[],
+		)?;
+		tx.commit()?;
+		Ok(())
+	}
+
+	pub fn fetch_book_spine(&self, id: BookId) -> Result<Vec<SpineItem>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				si.idref,
+				si.added_at
+			from book_spine_items si
+			where si.book_id = ?1
+			order by si.spine
+			",
+		)?;
+		Ok(from_rows::<SpineItem>(stmt.query([id.into_inner()])?).collect::<Result<_, _>>()?)
+	}
+
+	pub fn record_book_spine(
+		&mut self,
+		id: BookId,
+		items: &[SpineItem],
+	) -> Result<(), RecordKeeperError> {
+		let tx = self.conn.transaction()?;
+		tx.execute(
+			"delete from book_spine_items where book_id = ?1",
+			[id.into_inner()],
+		)?;
+		let mut insert_stmt = tx.prepare(
+			"insert into book_spine_items (book_id, spine, idref, added_at)
+				values (:book_id, :spine, :idref, :added_at)
+			",
+		)?;
+		for (spine, item) in items.iter().enumerate() {
+			let item = InsertSpineItem {
+				book_id: id.into_inner(),
+				spine: spine as u32,
+				idref: &item.idref,
+				added_at: item.added_at,
+			};
+			insert_stmt.execute(to_params_named(item)?.to_slice().as_slice())?;
+		}
+		drop(insert_stmt);
+		tx.commit()?;
+		Ok(())
+	}
+
 	pub fn record_book_state(
 		&self,
 		id: super::BookId,
@@ -390,6 +1376,408 @@ impl RecordKeeperAssistant {
 		Ok(())
 	}
 
+	/// Bookmarks of book in reading order
+	pub fn fetch_bookmarks(&self, id: BookId) -> Result<Vec<Bookmark>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				bm.spine,
+				bm.element,
+				bm.chapter,
+				bm.excerpt,
+				bm.created_at
+			from bookmarks bm
+			where bm.book_id = ?1
+			order by bm.spine, bm.element
+			",
+		)?;
+		Ok(from_rows::<SecretBookmark>(stmt.query([id.into_inner()])?)
+			.map(|b| b.map(Bookmark::from))
+			.collect::<Result<_, _>>()?)
+	}
+
+	pub fn record_bookmark(
+		&mut self,
+		id: BookId,
+		bookmark: &Bookmark,
+	) -> Result<(), RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"insert into bookmarks (book_id, spine, element, chapter, excerpt, created_at)
+				values (:book_id, :spine, :element, :chapter, :excerpt, :created_at)
+			on conflict (book_id, spine, element)
+			do update set
+				chapter = :chapter,
+				excerpt = :excerpt,
+				created_at = :created_at;
+			",
+		)?;
+		let bookmark = InsertBookmark {
+			book_id: id.into_inner(),
+			spine: bookmark.location.spine,
+			element: bookmark.location.element.to_bits(),
+			chapter: bookmark.chapter.as_deref().map(String::as_str),
+			excerpt: bookmark.excerpt.as_deref(),
+			created_at: bookmark.created_at,
+		};
+		stmt.execute(to_params_named(bookmark)?.to_slice().as_slice())?;
+		Ok(())
+	}
+
+	pub fn delete_bookmark(&mut self, id: BookId, loc: Location) -> Result<(), RecordKeeperError> {
+		self.conn.execute(
+			"delete from bookmarks where book_id = ?1 and spine = ?2 and element = ?3",
+			(id.into_inner(), loc.spine, loc.element.to_bits()),
+		)?;
+		Ok(())
+	}
+
+	/// Highlights of book in reading order
+	pub fn fetch_highlights(&self, id: BookId) -> Result<Vec<Highlight>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				hl.id,
+				hl.spine,
+				hl.start_element,
+				hl.start_offset,
+				hl.end_element,
+				hl.end_offset,
+				hl.chapter,
+				hl.text,
+				hl.note,
+				hl.created_at
+			from highlights hl
+			where hl.book_id = ?1
+			order by hl.spine, hl.start_element, hl.start_offset
+			",
+		)?;
+		Ok(from_rows::<SecretHighlight>(stmt.query([id.into_inner()])?)
+			.map(|h| h.map(Highlight::from))
+			.collect::<Result<_, _>>()?)
+	}
+
+	pub fn insert_highlight(
+		&mut self,
+		id: BookId,
+		spine: u32,
+		range: Range<TextPosition>,
+		chapter: Option<&str>,
+		text: &str,
+	) -> Result<HighlightId, RecordKeeperError> {
+		let id = self.conn.query_row(
+			"insert into highlights (
+				book_id,
+				spine,
+				start_element,
+				start_offset,
+				end_element,
+				end_offset,
+				chapter,
+				text,
+				created_at
+			)
+			values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+			returning highlights.id",
+			(
+				id.into_inner(),
+				spine,
+				range.start.element,
+				range.start.offset,
+				range.end.element,
+				range.end.offset,
+				chapter,
+				text,
+				Utc::now().timestamp(),
+			),
+			|row| row.get(0),
+		)?;
+		Ok(HighlightId(id))
+	}
+
+	/// Set note of highlight, blank note is removed
+	pub fn record_highlight_note(
+		&mut self,
+		id: HighlightId,
+		note: &str,
+	) -> Result<(), RecordKeeperError> {
+		let note = note.trim();
+		self.conn.execute(
+			"update highlights set note = ?1 where id = ?2",
+			((!note.is_empty()).then_some(note), id.into_inner()),
+		)?;
+		Ok(())
+	}
+
+	pub fn delete_highlight(&mut self, id: HighlightId) -> Result<(), RecordKeeperError> {
+		self.conn
+			.execute("delete from highlights where id = ?1", [id.into_inner()])?;
+		Ok(())
+	}
+
+	pub fn insert_reading_session(
+		&self,
+		id: BookId,
+		session: &ReadingSession,
+	) -> Result<SessionId, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"insert into reading_sessions (
+				book_id,
+				started_at,
+				ended_at,
+				pages,
+				chars,
+				start_spine,
+				start_element,
+				end_spine,
+				end_element,
+				start_percent,
+				end_percent
+			)
+			values (
+				:book_id,
+				:started_at,
+				:ended_at,
+				:pages,
+				:chars,
+				:start_spine,
+				:start_element,
+				:end_spine,
+				:end_element,
+				:start_percent,
+				:end_percent
+			)
+			returning reading_sessions.id",
+		)?;
+		let session = InsertReadingSession {
+			book_id: id.into_inner(),
+			started_at: session.started_at,
+			ended_at: session.ended_at,
+			pages: session.pages,
+			chars: session.chars,
+			start_spine: session.start.spine,
+			start_element: session.start.element.to_bits(),
+			end_spine: session.end.spine,
+			end_element: session.end.element.to_bits(),
+			start_percent: session.start_percent,
+			end_percent: session.end_percent,
+		};
+		let params = to_params_named(session)?;
+		let id = stmt.query_one(params.to_slice().as_slice(), |row| row.get(0))?;
+		Ok(SessionId(id))
+	}
+
+	/// Update end of session
+	pub fn record_reading_session(
+		&self,
+		id: SessionId,
+		session: &ReadingSession,
+	) -> Result<(), RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"update reading_sessions set
+				ended_at = :ended_at,
+				pages = :pages,
+				chars = :chars,
+				end_spine = :end_spine,
+				end_element = :end_element,
+				end_percent = :end_percent
+			where id = :id
+			",
+		)?;
+		let session = UpdateReadingSession {
+			id: id.into_inner(),
+			ended_at: session.ended_at,
+			pages: session.pages,
+			chars: session.chars,
+			end_spine: session.end.spine,
+			end_element: session.end.element.to_bits(),
+			end_percent: session.end_percent,
+		};
+		stmt.execute(to_params_named(session)?.to_slice().as_slice())?;
+		Ok(())
+	}
+
+	/// Time spent reading each book, last read first
+	pub fn fetch_reading_time(&self) -> Result<Vec<BookReadingTime>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				rs.book_id as id,
+				bo.title,
+				sum(rs.ended_at - rs.started_at) as seconds,
+				sum(rs.pages) as pages,
+				count(*) as sessions,
+				max(rs.ended_at) as last_read_at
+			from reading_sessions rs
+			join books bo on bo.id = rs.book_id
+			group by rs.book_id
+			order by last_read_at desc
+			",
+		)?;
+		Ok(from_rows::<SecretBookReadingTime>(stmt.query([])?)
+			.map(|t| t.map(BookReadingTime::from))
+			.collect::<Result<_, _>>()?)
+	}
+
+	/// Reading per local day since `since`, days without reading are left out
+	pub fn fetch_reading_days(
+		&self,
+		since: DateTime<Utc>,
+	) -> Result<Vec<DayReading>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				date(rs.started_at, 'unixepoch', 'localtime') as day,
+				sum(rs.pages) as pages,
+				sum(rs.ended_at - rs.started_at) as seconds
+			from reading_sessions rs
+			where rs.started_at >= ?1
+			group by day
+			order by day
+			",
+		)?;
+		Ok(
+			from_rows::<SecretDayReading>(stmt.query([since.timestamp()])?)
+				.map(|d| d.map(DayReading::from))
+				.collect::<Result<_, _>>()?,
+		)
+	}
+
+	/// Reading pace of book, from sessions of all books until book has enough characters read
+	///
+	/// `None` without any characters read.
+	pub fn fetch_reading_pace(&self, id: BookId) -> Result<Option<ReadingPace>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select
+				coalesce(sum(rs.ended_at - rs.started_at), 0) as seconds,
+				coalesce(sum(rs.chars), 0) as chars
+			from reading_sessions rs
+			where rs.chars > 0
+				and (?1 is null or rs.book_id = ?1)
+			",
+		)?;
+		let mut fetch_pace = |id: Option<i64>| -> Result<SecretPace, RecordKeeperError> {
+			Ok(stmt.query_one([id], |row| Ok(from_row::<SecretPace>(row)))??)
+		};
+		let mut pace = fetch_pace(Some(id.into_inner()))?;
+		if pace.chars < MIN_PACE_CHARS {
+			pace = fetch_pace(None)?;
+		}
+
+		Ok((pace.chars > 0).then(|| ReadingPace {
+			seconds_per_char: pace.seconds as f64 / pace.chars as f64,
+		}))
+	}
+
+	/// Visible characters of spine items of book, in spine order
+	pub fn fetch_spine_chars(&self, id: BookId) -> Result<Vec<SpineChars>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select spine, crc32, chars
+			from book_spine_chars
+			where book_id = ?1
+			order by spine
+			",
+		)?;
+		Ok(
+			from_rows::<SecretSpineChars>(stmt.query([id.into_inner()])?)
+				.map(|c| {
+					c.map(|c| SpineChars {
+						spine: c.spine,
+						crc32: c.crc32,
+						chars: c.chars,
+					})
+				})
+				.collect::<Result<_, _>>()?,
+		)
+	}
+
+	/// Replace visible characters of spine items of book
+	pub fn record_spine_chars(
+		&mut self,
+		id: BookId,
+		spines: &[SpineChars],
+	) -> Result<(), RecordKeeperError> {
+		let tx = self.conn.transaction()?;
+		tx.execute(
+			"delete from book_spine_chars where book_id = ?1",
+			[id.into_inner()],
+		)?;
+		{
+			let mut stmt = tx.prepare(
+				"insert into book_spine_chars (book_id, spine, crc32, chars)
+				values (?1, ?2, ?3, ?4)
+				",
+			)?;
+			for spine in spines {
+				stmt.execute((id.into_inner(), spine.spine, spine.crc32, spine.chars))?;
+			}
+		}
+		tx.commit()?;
+		Ok(())
+	}
+
+	/// Page boundaries of spine items of book in layout
+	pub fn fetch_book_pages(
+		&self,
+		id: BookId,
+		layout: &str,
+	) -> Result<Vec<SpinePages>, RecordKeeperError> {
+		let mut stmt = self.conn.prepare(
+			"select spine, crc32, start_element, end_element
+			from book_pages
+			where book_id = ?1 and layout = ?2
+			order by spine, page
+			",
+		)?;
+		let mut spines: Vec<SpinePages> = Vec::new();
+		for page in from_rows::<SecretBookPage>(stmt.query((id.into_inner(), layout))?) {
+			let page = page?;
+			let range = U26F6::from_bits(page.start_element)..U26F6::from_bits(page.end_element);
+			match spines.last_mut() {
+				Some(spine) if spine.spine == page.spine => spine.pages.push(range),
+				_ => spines.push(SpinePages {
+					spine: page.spine,
+					crc32: page.crc32,
+					pages: vec![range],
+				}),
+			}
+		}
+		Ok(spines)
+	}
+
+	/// Replace page boundaries of spine item of book
+	///
+	/// Pages of other layouts of book are removed, only latest layout is kept.
+	pub fn record_spine_pages(
+		&mut self,
+		id: BookId,
+		layout: &str,
+		spine: &SpinePages,
+	) -> Result<(), RecordKeeperError> {
+		let tx = self.conn.transaction()?;
+		tx.execute(
+			"delete from book_pages
+			where book_id = ?1 and (layout != ?2 or spine = ?3)
+			",
+			(id.into_inner(), layout, spine.spine),
+		)?;
+		{
+			let mut stmt = tx.prepare(
+				"insert into book_pages (book_id, layout, spine, crc32, page, start_element, end_element)
+				values (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+				",
+			)?;
+			for (page, range) in spine.pages.iter().enumerate() {
+				stmt.execute((
+					id.into_inner(),
+					layout,
+					spine.spine,
+					spine.crc32,
+					page as u32,
+					range.start.to_bits(),
+					range.end.to_bits(),
+				))?;
+			}
+		}
+		tx.commit()?;
+		Ok(())
+	}
+
 	pub fn record_view_state<T: Serialize>(
 		&self,
 		key: &'static str,
@@ -432,3 +1820,105 @@ impl RecordKeeperAssistant {
 		Ok(value)
 	}
 }
+
+/// Quote words of user input as FTS5 prefix terms, `None` without words
+fn fts_query(input: &str) -> Option<String> {
+	let terms = input
+		.split_whitespace()
+		.map(|word| word.replace('"', ""))
+		.filter(|word| !word.is_empty())
+		.map(|word| format!("\"{word}\"*"))
+		.collect::<Vec<_>>();
+	(!terms.is_empty()).then(|| terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::Utc;
+	use fixed::types::U26F6;
+
+	use std::path::PathBuf;
+
+	use crate::Location;
+	use crate::records::InsertBook;
+	use crate::records::RecordKeeper;
+	use crate::records::UpdateBook;
+
+	fn insert_book(path: &str) -> InsertBook {
+		InsertBook {
+			path: PathBuf::from(path),
+			title: Some("Tale".to_string()),
+			author: None,
+			size: 1024,
+			modified_at: Utc::now(),
+			added_at: Utc::now(),
+		}
+	}
+
+	#[test]
+	fn test_repoint_book() {
+		let data_path =
+			std::env::temp_dir().join(format!("scribe-records-test-{}", std::process::id()));
+		std::fs::create_dir_all(&data_path).expect("Create data dir failed");
+		let keeper = RecordKeeper::new(&data_path);
+		let mut assistant = keeper.assistant().expect("Open records failed");
+
+		let old_id = assistant
+			.upsert_book(insert_book("books/tale.epub"))
+			.expect("Insert book failed");
+		assistant
+			.update_book(UpdateBook {
+				book_id: old_id.into_inner(),
+				title: Some("Tale"),
+				author: None,
+				identifier: Some("urn:uuid:tale"),
+				fingerprint: "0123456789abcdef",
+				modified_at: Utc::now(),
+			})
+			.expect("Update book failed");
+		let loc = Location {
+			spine: 3,
+			element: U26F6::from_num(12),
+		};
+		assistant
+			.record_book_state(old_id, loc, 40)
+			.expect("Record state failed");
+		assistant
+			.unexist_books(&[old_id])
+			.expect("Unexist book failed");
+
+		let new_id = assistant
+			.upsert_book(insert_book("moved/tale.epub"))
+			.expect("Insert book failed");
+		assert_ne!(old_id, new_id, "Book id missmatch");
+
+		let found = assistant
+			.fetch_unexist_book("urn:uuid:tale", "fedcba9876543210")
+			.expect("Fetch unexist failed");
+		assert_eq!(found, None, "Other fingerprint missmatch");
+		let found = assistant
+			.fetch_unexist_book("urn:uuid:tale", "0123456789abcdef")
+			.expect("Fetch unexist failed");
+		assert_eq!(found, Some(old_id), "Unexist book missmatch");
+
+		assistant
+			.repoint_book(old_id, new_id)
+			.expect("Repoint book failed");
+		let book = assistant.fetch_book(old_id).expect("Fetch book failed");
+		assert_eq!(book.id, old_id, "Book id missmatch");
+		assert_eq!(
+			book.path,
+			PathBuf::from("moved/tale.epub"),
+			"Path missmatch"
+		);
+		assert_eq!(book.percent_read, Some(40), "Progress missmatch");
+		assert_eq!(book.location(), loc, "Location missmatch");
+		assert!(
+			assistant.fetch_book(new_id).is_err(),
+			"Removed book missmatch"
+		);
+
+		drop(assistant);
+		std::fs::remove_dir_all(&data_path).expect("Remove data dir failed");
+	}
+}
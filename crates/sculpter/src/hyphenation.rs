@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) const SOFT_HYPHEN: char = '\u{ad}';
+
+/// Liang hyphenator using TeX hyphenation patterns
+#[derive(Debug)]
+pub struct Hyphenator {
+	patterns: HashMap<Vec<char>, Vec<u8>>,
+	exceptions: HashMap<String, Vec<usize>>,
+	max_pattern_len: usize,
+	left_min: usize,
+	right_min: usize,
+}
+
+impl Default for Hyphenator {
+	fn default() -> Self {
+		Self {
+			patterns: HashMap::new(),
+			exceptions: HashMap::new(),
+			max_pattern_len: 0,
+			left_min: 2,
+			right_min: 2,
+		}
+	}
+}
+
+impl Hyphenator {
+	/// Add patterns and exceptions from source
+	///
+	/// Accepts TeX files with `\patterns{}` and `\hyphenation{}` as well as
+	/// whitespace separated lists as in hyph-utf8 `.pat.txt` and `.hyp.txt`.
+	/// Words with explicit `-` are treated as exceptions.
+	pub fn add(&mut self, source: &str) {
+		for line in source.lines() {
+			let line = line.split('%').next().unwrap_or_default();
+			for token in line.split_whitespace() {
+				let token = token
+					.trim_start_matches("\\patterns{")
+					.trim_start_matches("\\hyphenation{")
+					.trim_matches(['{', '}']);
+				if token.is_empty() || token.starts_with('\\') {
+					continue;
+				}
+				if token.contains('-') {
+					self.add_exception(token);
+				} else {
+					self.add_pattern(token);
+				}
+			}
+		}
+	}
+
+	fn add_pattern(&mut self, pattern: &str) {
+		let mut letters = Vec::new();
+		let mut values = vec![0];
+		for c in pattern.chars() {
+			if let Some(d) = c.to_digit(10) {
+				*values.last_mut().expect("Values never empty") = d as u8;
+			} else {
+				letters.push(c);
+				values.push(0);
+			}
+		}
+		self.max_pattern_len = self.max_pattern_len.max(letters.len());
+		self.patterns.insert(letters, values);
+	}
+
+	fn add_exception(&mut self, exception: &str) {
+		let mut word = String::new();
+		let mut points = Vec::new();
+		for c in exception.chars() {
+			if c == '-' {
+				points.push(word.chars().count());
+			} else {
+				word.extend(c.to_lowercase());
+			}
+		}
+		self.exceptions.insert(word, points);
+	}
+
+	/// Byte offsets in word where a hyphen may be inserted before
+	pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+		let chars = word.char_indices().collect::<Vec<_>>();
+		let len = chars.len();
+		if len < self.left_min + self.right_min {
+			return Vec::new();
+		}
+		let lower = chars
+			.iter()
+			.map(|(_, c)| c.to_lowercase().next().unwrap_or(*c))
+			.collect::<Vec<_>>();
+
+		if let Some(points) = self.exceptions.get(&lower.iter().collect::<String>()) {
+			return points
+				.iter()
+				.filter_map(|i| chars.get(*i).map(|(offset, _)| *offset))
+				.collect();
+		}
+
+		let mut dotted = Vec::with_capacity(len + 2);
+		dotted.push('.');
+		dotted.extend_from_slice(&lower);
+		dotted.push('.');
+
+		let mut values = vec![0u8; dotted.len() + 1];
+		for start in 0..dotted.len() {
+			let max_end = (start + self.max_pattern_len).min(dotted.len());
+			for end in start + 1..=max_end {
+				if let Some(pattern) = self.patterns.get(&dotted[start..end]) {
+					for (i, v) in pattern.iter().enumerate() {
+						values[start + i] = values[start + i].max(*v);
+					}
+				}
+			}
+		}
+
+		// Value before word char i is at i + 1 due to leading dot
+		(self.left_min..=len - self.right_min)
+			.filter(|i| values[i + 1] % 2 == 1)
+			.map(|i| chars[i].0)
+			.collect()
+	}
+}
+
+/// Hyphenators by language
+#[derive(Debug, Clone, Default)]
+pub struct SculpterHyphenation(Arc<BTreeMap<String, Hyphenator>>);
+
+impl SculpterHyphenation {
+	/// Find hyphenator by exact language tag, or by primary language subtag
+	pub fn find(&self, lang: &str) -> Option<&Hyphenator> {
+		let lang = normalize_lang(lang);
+		let primary = lang.split('-').next().unwrap_or_default();
+		self.0
+			.get(&lang)
+			.or_else(|| self.0.get(primary))
+			.or_else(|| {
+				self.0
+					.iter()
+					.find(|(key, _)| key.split('-').next() == Some(primary))
+					.map(|(_, h)| h)
+			})
+	}
+}
+
+#[derive(Default)]
+pub struct SculpterHyphenationBuilder {
+	hyphenators: BTreeMap<String, Hyphenator>,
+}
+
+impl SculpterHyphenationBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add patterns for language, merges with already added patterns
+	pub fn add_patterns(mut self, lang: &str, source: &str) -> Self {
+		self.hyphenators
+			.entry(normalize_lang(lang))
+			.or_default()
+			.add(source);
+		self
+	}
+
+	pub fn build(self) -> SculpterHyphenation {
+		SculpterHyphenation(Arc::new(self.hyphenators))
+	}
+}
+
+fn normalize_lang(lang: &str) -> String {
+	lang.trim().replace('_', "-").to_lowercase()
+}
+
+/// Byte offsets in text where a hyphen may be inserted before
+///
+/// Words with soft hyphens are left as is, these are handled when shaping.
+pub(crate) fn hyphen_points(text: &str, hyphenator: &Hyphenator) -> Vec<usize> {
+	let mut points = Vec::new();
+	let mut word_start = None;
+	for (i, c) in text
+		.char_indices()
+		.chain(std::iter::once((text.len(), ' ')))
+	{
+		let is_word = c.is_alphabetic() || c == SOFT_HYPHEN;
+		match (word_start, is_word) {
+			(None, true) => word_start = Some(i),
+			(Some(start), false) => {
+				let word = &text[start..i];
+				if !word.contains(SOFT_HYPHEN) {
+					points.extend(hyphenator.hyphenate(word).into_iter().map(|p| start + p));
+				}
+				word_start = None;
+			}
+			_ => {}
+		}
+	}
+	points
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::hyphenation::Hyphenator;
+	use crate::hyphenation::SculpterHyphenationBuilder;
+	use crate::hyphenation::hyphen_points;
+
+	const LIANG_PATTERNS: &str = r#"
+% Patterns from Liang's thesis
+\patterns{
+hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n
+}
+\hyphenation{
+ta-ble
+}
+"#;
+
+	fn split(word: &str, points: &[usize]) -> String {
+		let mut out = String::new();
+		let mut last = 0;
+		for p in points {
+			out.push_str(&word[last..*p]);
+			out.push('-');
+			last = *p;
+		}
+		out.push_str(&word[last..]);
+		out
+	}
+
+	#[test]
+	fn test_hyphenation_liang_patterns() {
+		let mut hyphenator = Hyphenator::default();
+		hyphenator.add(LIANG_PATTERNS);
+
+		let word = "hyphenation";
+		let points = hyphenator.hyphenate(word);
+		assert_eq!(
+			split(word, &points),
+			"hy-phen-ation",
+			"Hyphenation missmatch"
+		);
+
+		let word = "Hyphenation";
+		let points = hyphenator.hyphenate(word);
+		assert_eq!(split(word, &points), "Hy-phen-ation", "Case missmatch");
+	}
+
+	#[test]
+	fn test_hyphenation_exceptions_and_min() {
+		let mut hyphenator = Hyphenator::default();
+		hyphenator.add(LIANG_PATTERNS);
+
+		let points = hyphenator.hyphenate("table");
+		assert_eq!(split("table", &points), "ta-ble", "Exception missmatch");
+		assert!(
+			hyphenator.hyphenate("on").is_empty(),
+			"Short word hyphenated"
+		);
+	}
+
+	#[test]
+	fn test_hyphenation_find_language() {
+		let hyphenation = SculpterHyphenationBuilder::new()
+			.add_patterns("de-1996", "1na")
+			.add_patterns("sv", "1na")
+			.build();
+
+		assert!(hyphenation.find("de-DE").is_some(), "Missing de");
+		assert!(hyphenation.find("sv_SE").is_some(), "Missing sv");
+		assert!(hyphenation.find("en").is_none(), "Unexpected en");
+	}
+
+	#[test]
+	fn test_hyphenation_points_in_text() {
+		let mut hyphenator = Hyphenator::default();
+		hyphenator.add(LIANG_PATTERNS);
+
+		let text = "The hyphenation, and hy\u{ad}phenation.";
+		let points = hyphen_points(text, &hyphenator);
+		assert_eq!(points, vec![6, 10], "Points missmatch");
+	}
+}
@@ -10,7 +10,12 @@ use fixed::types::I26F6;
 pub use crate::fonts::SculpterFontErrors;
 pub use crate::fonts::SculpterFonts;
 pub use crate::fonts::SculpterFontsBuilder;
+pub use crate::hyphenation::Hyphenator;
+pub use crate::hyphenation::SculpterHyphenation;
+pub use crate::hyphenation::SculpterHyphenationBuilder;
+use crate::hyphenation::hyphen_points;
 use crate::lines::StyledLines;
+use crate::lines::total_fit_breaks;
 pub use crate::printer::AtlasImage;
 pub use crate::printer::AtlasVersion;
 use crate::printer::SculpterPrinter;
@@ -19,6 +24,7 @@ use crate::shaper::SculptureShaper;
 use crate::shaper::ShapeFaceRef;
 
 mod fonts;
+mod hyphenation;
 mod lines;
 mod printer;
 mod shaper;
@@ -147,18 +153,32 @@ pub struct FontStyle<'a> {
 	pub line_height_em: Fixed,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineBreaking {
+	/// Break line at first glyph not fitting
+	#[default]
+	Greedy,
+	/// Minimize raggedness over whole paragraph, Knuth-Plass style
+	TotalFit,
+}
+
 #[derive(Debug)]
 pub struct SculpterOptions {
 	/// Optimize atlas usage by masking out sub pixel bits
 	///
 	/// Only fraction bits are used.
 	pub atlas_sub_pixel_mask: I26F6,
+	pub line_breaking: LineBreaking,
+	/// Hyphenation patterns by language
+	pub hyphenation: SculpterHyphenation,
 }
 
 impl Default for SculpterOptions {
 	fn default() -> Self {
 		Self {
 			atlas_sub_pixel_mask: I26F6::from_bits(!0),
+			line_breaking: LineBreaking::Greedy,
+			hyphenation: SculpterHyphenation::default(),
 		}
 	}
 }
@@ -182,6 +202,7 @@ pub fn create_sculpter<'a>(
 	let mut printer = SculpterPrinter::new([8192; 2]);
 
 	let mut faces = Vec::with_capacity(font_options.len());
+	let mut hyphen_advances = Vec::new();
 	for option in font_options {
 		let font = fonts
 			.find_font(option)
@@ -203,6 +224,7 @@ pub fn create_sculpter<'a>(
 		);
 		let printer_ref = printer.add(printer_font);
 		debug_assert_eq!(shaper_ref, printer_ref, "Missmatched face ref");
+		hyphen_advances.push(printer.hyphen_advance(printer_ref));
 
 		let hash = {
 			let mut s = DefaultHasher::new();
@@ -229,6 +251,7 @@ pub fn create_sculpter<'a>(
 		);
 		let printer_ref = printer.add(printer_font);
 		debug_assert_eq!(shaper_ref, printer_ref, "Missmatched face ref");
+		hyphen_advances.push(printer.hyphen_advance(printer_ref));
 	}
 
 	Ok(Sculpter {
@@ -237,6 +260,7 @@ pub fn create_sculpter<'a>(
 		printer,
 		glyphs: Vec::new(),
 		styles: Vec::new(),
+		hyphen_advances,
 		options,
 	})
 }
@@ -245,15 +269,51 @@ pub fn create_sculpter<'a>(
 pub struct SculpterInput<'a> {
 	pub style: FontStyle<'a>,
 	pub input: &'a str,
+	/// Language tag used for hyphenation
+	pub lang: Option<&'a str>,
+	/// Report rectangles of input as `TextSpan` when rendered
+	pub span: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+	#[default]
+	Left,
+	Center,
+	Right,
+	/// Stretch word gaps to fill all but last line
+	Justify,
+}
+
+/// Block level style applied on line layout
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlockStyle {
+	/// First line indent in px
+	pub text_indent: Fixed,
+	pub text_align: TextAlign,
 }
 
 #[derive(Debug, Clone)]
 pub struct SculpterHandle {
+	text_start: usize,
 	glyphs_start: usize,
 	glyphs_end: usize,
+	block: BlockStyle,
 }
 
 impl SculpterHandle {
+	pub fn with_block_style(self, block: BlockStyle) -> Self {
+		Self { block, ..self }
+	}
+
+	fn first_line_indent(&self) -> I26F6 {
+		if self.glyphs_start == self.text_start {
+			self.block.text_indent
+		} else {
+			I26F6::ZERO
+		}
+	}
+
 	pub fn is_empty(&self) -> bool {
 		self.glyph_range().is_empty()
 	}
@@ -269,6 +329,11 @@ struct Style {
 	font_size: I26F6,
 	line_height_em: I26F6,
 	end_index: usize,
+	span: Option<u32>,
+	/// Index of input in shaped block
+	input: u32,
+	/// Bytes trimmed from start of input
+	input_offset: u32,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -288,6 +353,8 @@ pub struct Sculpter<'font> {
 	printer: SculpterPrinter<'font>,
 	glyphs: Vec<GlyphPlan>,
 	styles: Vec<Style>,
+	/// Advance of hyphen by face, in em
+	hyphen_advances: Vec<I26F6>,
 	options: SculpterOptions,
 }
 
@@ -298,13 +365,23 @@ impl Sculpter<'_> {
 	) -> Result<SculpterHandle, SculpterShapeError> {
 		let len = inputs.len();
 		let glyphs_start = self.glyphs.len();
-		for (i, SculpterInput { style, input }) in inputs.enumerate() {
-			let input = if i == 0 {
-				input.trim_start()
+		for (
+			i,
+			SculpterInput {
+				style,
+				input,
+				lang,
+				span,
+			},
+		) in inputs.enumerate()
+		{
+			let (input, input_offset) = if i == 0 {
+				let trimmed = input.trim_start();
+				(trimmed, input.len() - trimmed.len())
 			} else if i + 1 == len {
-				input.trim_end()
+				(input.trim_end(), 0)
 			} else {
-				input
+				(input, 0)
 			};
 			if input.is_empty() {
 				continue;
@@ -327,24 +404,71 @@ impl Sculpter<'_> {
 				})
 				.ok_or(SculpterShapeError::FaceNotFound)?;
 
-			self.shaper.shape(face_ref, input, &mut self.glyphs)?;
+			let hyphens = lang
+				.and_then(|lang| self.options.hyphenation.find(lang))
+				.map(|hyphenator| hyphen_points(input, hyphenator))
+				.unwrap_or_default();
+			self.shaper
+				.shape(face_ref, input, &hyphens, &mut self.glyphs)?;
 
 			self.styles.push(Style {
 				face_ref,
 				font_size,
 				line_height_em,
 				end_index: self.glyphs.len(),
+				span,
+				input: i as u32,
+				input_offset: input_offset as u32,
 			});
 		}
 		let glyphs_end = self.glyphs.len();
 
 		Ok(SculpterHandle {
+			text_start: glyphs_start,
 			glyphs_start,
 			glyphs_end,
+			block: BlockStyle::default(),
 		})
 	}
 }
 
+/// Lines of text in handle, broken as set by `line_breaking`
+fn styled_lines<'a>(
+	glyphs: &'a [GlyphPlan],
+	styles: &'a [Style],
+	hyphen_advances: &'a [I26F6],
+	line_breaking: LineBreaking,
+	handle: &SculpterHandle,
+	max_line_width: I26F6,
+) -> StyledLines<'a> {
+	let lines = StyledLines::new(
+		handle.glyphs_start,
+		styles,
+		&glyphs[handle.glyph_range()],
+		max_line_width,
+	)
+	.with_first_line_indent(handle.first_line_indent() * PT_PER_PX)
+	.with_hyphen_advances(hyphen_advances);
+	match line_breaking {
+		LineBreaking::Greedy => lines,
+		LineBreaking::TotalFit => {
+			// Break whole paragraph so lines stay same when resumed on next page
+			let breaks = total_fit_breaks(
+				handle.text_start,
+				styles,
+				hyphen_advances,
+				&glyphs[handle.text_start..handle.glyphs_end],
+				max_line_width,
+				handle.block.text_indent * PT_PER_PX,
+			);
+			match breaks {
+				Some(breaks) => lines.with_breaks(breaks),
+				None => lines,
+			}
+		}
+	}
+}
+
 #[derive(Debug)]
 pub struct MeasureResult {
 	pub height: I26F6,
@@ -362,10 +486,12 @@ impl Sculpter<'_> {
 
 		let mut measure_height = I26F6::ZERO;
 		let mut lines = 0;
-		let lines_iter = StyledLines::new(
-			handle.glyphs_start,
+		let lines_iter = styled_lines(
+			&self.glyphs,
 			&self.styles,
-			&self.glyphs[handle.glyph_range()],
+			&self.hyphen_advances,
+			self.options.line_breaking,
+			handle,
 			I26F6::from_num(width_px) * PT_PER_PX,
 		);
 		for line in lines_iter {
@@ -402,10 +528,31 @@ pub struct DisplayGlyph {
 	pub uv_dim: [u32; 2],
 }
 
+/// Rectangle of spanned input on a single line
+#[derive(Debug, Clone)]
+pub struct TextSpan {
+	pub span: u32,
+	pub pos: [f32; 2],
+	pub dim: [f32; 2],
+}
+
+/// Rectangle of printed cluster with source in shaped inputs
+#[derive(Debug, Clone)]
+pub struct TextCluster {
+	/// Index of input passed to `shape`
+	pub input: u32,
+	/// Byte offset of cluster in input
+	pub offset: u32,
+	pub pos: [f32; 2],
+	pub dim: [f32; 2],
+}
+
 #[derive(Debug)]
 pub struct TextBlock {
 	pub block_height: I26F6,
 	pub glyphs: Vec<DisplayGlyph>,
+	pub spans: Vec<TextSpan>,
+	pub clusters: Vec<TextCluster>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -430,13 +577,22 @@ impl Sculpter<'_> {
 	) -> Result<TextBlock, SculpterPrinterError> {
 		let empty_line_height = empty_line_height_px.round();
 
-		let mut output = Vec::new();
+		let mut output = TextBlock {
+			block_height: I26F6::ZERO,
+			glyphs: Vec::new(),
+			spans: Vec::new(),
+			clusters: Vec::new(),
+		};
 		let mut block_height = I26F6::ZERO;
-		let lines_iter = StyledLines::new(
-			handle.glyphs_start,
+		let mut indent = handle.first_line_indent();
+		let max_line_width = I26F6::from_num(width_px) * PT_PER_PX;
+		let lines_iter = styled_lines(
+			&self.glyphs,
 			&self.styles,
-			&self.glyphs[handle.glyph_range()],
-			I26F6::from_num(width_px) * PT_PER_PX,
+			&self.hyphen_advances,
+			self.options.line_breaking,
+			handle,
+			max_line_width,
 		);
 		for line in lines_iter {
 			if block_height + empty_line_height > height_px {
@@ -446,6 +602,7 @@ impl Sculpter<'_> {
 				log::info!("empty line, end {}", line.end());
 				handle.glyphs_start = line.end();
 				block_height += empty_line_height;
+				indent = I26F6::ZERO;
 				continue;
 			}
 
@@ -460,12 +617,28 @@ impl Sculpter<'_> {
 			}
 			block_height += font_height;
 
-			let x_origin = I26F6::ZERO;
+			let hyphen_width = line.hyphen_style().map_or(I26F6::ZERO, |style| {
+				self.hyphen_advances[style.face_ref.0 as usize] * style.font_size
+			});
+			let (x_origin, word_spacing) = line.alignment(
+				handle.block.text_align,
+				max_line_width,
+				indent,
+				hyphen_width,
+				line.end() >= handle.glyphs_end,
+			);
 			let y_origin = block_height;
+			indent = I26F6::ZERO;
 
 			handle.glyphs_start = line.end();
-			self.printer
-				.print_line(x_origin, y_origin, line, &mut output, &self.options)?;
+			self.printer.print_line(
+				x_origin,
+				y_origin,
+				word_spacing,
+				line,
+				&mut output,
+				&self.options,
+			)?;
 
 			let line_space = font_height * (line_style.line_height_em - I26F6::ONE);
 			if block_height + line_space > height_px {
@@ -475,10 +648,8 @@ impl Sculpter<'_> {
 			block_height = (block_height + line_space).round();
 		}
 
-		Ok(TextBlock {
-			block_height,
-			glyphs: output,
-		})
+		output.block_height = block_height;
+		Ok(output)
 	}
 }
 
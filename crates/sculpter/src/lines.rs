@@ -1,6 +1,8 @@
 use fixed::types::I26F6;
 
+use crate::PX_PER_PT;
 use crate::Style;
+use crate::TextAlign;
 use crate::shaper::BreakpointType;
 use crate::shaper::GlyphPlan;
 
@@ -64,9 +66,66 @@ impl<'a> StyledGlyphs<'a> {
 		}
 	}
 
+	/// Count of whitespace runs followed by more glyphs on line
+	pub(crate) fn word_gaps(&self) -> usize {
+		self.glyphs[self.cursor..]
+			.windows(2)
+			.filter(|w| w[0].br.is_whitespace() && !w[1].br.is_whitespace())
+			.count()
+	}
+
+	/// Offset of line start and extra space per word gap, in px
+	///
+	/// Widths are in pt and `indent` in px, `hyphen_width` is the hyphen printed
+	/// after the line. Justified lines are stretched unless they are the last
+	/// of the block or end in a forced break.
+	pub(crate) fn alignment(
+		&self,
+		align: TextAlign,
+		max_line_width: I26F6,
+		indent: I26F6,
+		hyphen_width: I26F6,
+		last: bool,
+	) -> (I26F6, I26F6) {
+		let line_rem = (max_line_width - self.width() - hyphen_width) * PX_PER_PT - indent;
+		let x_origin = indent
+			+ match align {
+				TextAlign::Left | TextAlign::Justify => I26F6::ZERO,
+				TextAlign::Center => (line_rem / 2).max(I26F6::ZERO),
+				TextAlign::Right => line_rem.max(I26F6::ZERO),
+			};
+		let forced_break = self
+			.glyphs
+			.last()
+			.is_some_and(|g| matches!(g.br, BreakpointType::Newline));
+		let word_gaps = self.word_gaps();
+		let word_spacing = if align == TextAlign::Justify && !last && !forced_break && word_gaps > 0
+		{
+			line_rem.max(I26F6::ZERO) / I26F6::from_num(word_gaps)
+		} else {
+			I26F6::ZERO
+		};
+		(x_origin, word_spacing)
+	}
+
 	pub(crate) fn end(&self) -> usize {
 		self.offset + self.cursor + self.glyphs.len()
 	}
+
+	/// Width of line in pt, excluding trailing whitespace
+	pub(crate) fn width(&self) -> I26F6 {
+		let trailing = self
+			.glyphs
+			.iter()
+			.rev()
+			.take_while(|g| g.br.is_whitespace())
+			.count();
+		let mut width = I26F6::ZERO;
+		for (s, g) in self.clone().take(self.len() - trailing) {
+			width += g.pos.x_advance * s.font_size;
+		}
+		width
+	}
 }
 
 impl<'a> ExactSizeIterator for StyledGlyphs<'a> {
@@ -91,9 +150,24 @@ impl<'a> Iterator for StyledGlyphs<'a> {
 	}
 }
 
+/// Width of hyphen printed after line ending at absolute glyph index `end`, in pt
+fn hyphen_width(styles: &[Style], hyphen_advances: &[I26F6], end: usize) -> I26F6 {
+	styles
+		.iter()
+		.find(|s| s.end_index > end)
+		.or(styles.last())
+		.and_then(|s| Some(*hyphen_advances.get(s.face_ref.0 as usize)? * s.font_size))
+		.unwrap_or(I26F6::ZERO)
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct StyledLines<'a> {
 	max_line_width: I26F6,
+	first_line_indent: I26F6,
+	/// Advance of hyphen by face, in em
+	hyphen_advances: &'a [I26F6],
+	/// Precomputed line ends, absolute glyph index
+	breaks: Option<Vec<usize>>,
 	styles: &'a [Style],
 	glyphs: &'a [GlyphPlan],
 	offset: usize,
@@ -109,12 +183,39 @@ impl<'a> StyledLines<'a> {
 	) -> Self {
 		Self {
 			max_line_width,
+			first_line_indent: I26F6::ZERO,
+			hyphen_advances: &[],
+			breaks: None,
 			styles,
 			glyphs,
 			offset,
 			cursor: 0,
 		}
 	}
+
+	/// Reduce width of first line by indent, in pt
+	pub(crate) fn with_first_line_indent(self, first_line_indent: I26F6) -> Self {
+		Self {
+			first_line_indent,
+			..self
+		}
+	}
+
+	/// Fit hyphen after lines broken at hyphenation points, advances by face in em
+	pub(crate) fn with_hyphen_advances(self, hyphen_advances: &'a [I26F6]) -> Self {
+		Self {
+			hyphen_advances,
+			..self
+		}
+	}
+
+	/// Break lines at precomputed line ends instead of first fit
+	pub(crate) fn with_breaks(self, breaks: Vec<usize>) -> Self {
+		Self {
+			breaks: Some(breaks),
+			..self
+		}
+	}
 }
 
 impl<'a> Iterator for StyledLines<'a> {
@@ -125,6 +226,27 @@ impl<'a> Iterator for StyledLines<'a> {
 			return None;
 		}
 
+		if let Some(breaks) = &self.breaks {
+			let start = self.offset + self.cursor;
+			let end = breaks
+				.iter()
+				.find(|b| **b > start)
+				.map_or(self.glyphs.len(), |b| {
+					(b - self.offset).min(self.glyphs.len())
+				});
+			let used = self.cursor;
+			self.cursor = end;
+			let hyphen = end > used && matches!(self.glyphs[end - 1].br, BreakpointType::Hyphen);
+			return Some(
+				StyledGlyphs::new(start, &self.glyphs[used..end], self.styles).with_hyphen(hyphen),
+			);
+		}
+
+		let max_line_width = if self.cursor == 0 {
+			self.max_line_width - self.first_line_indent
+		} else {
+			self.max_line_width
+		};
 		let mut idx = self.cursor;
 		let mut segment_width = I26F6::ZERO;
 		let mut last_line_break = None;
@@ -135,22 +257,34 @@ impl<'a> Iterator for StyledLines<'a> {
 		);
 		for (s, g) in &mut iter {
 			let width = g.pos.x_advance * s.font_size;
-			if segment_width + width > self.max_line_width {
+			if segment_width + width > max_line_width {
 				let used = self.cursor;
 				self.cursor = last_line_break.unwrap_or(idx);
+				let hyphen = match last_line_break {
+					Some(end) => matches!(self.glyphs[end - 1].br, BreakpointType::Hyphen),
+					None => matches!(g.br, BreakpointType::No | BreakpointType::Hyphen),
+				};
 				return Some(
 					StyledGlyphs::new(
 						self.offset + used,
 						&self.glyphs[used..self.cursor],
 						self.styles,
 					)
-					.with_hyphen(last_line_break.is_none() && matches!(g.br, BreakpointType::No)),
+					.with_hyphen(hyphen),
 				);
 			}
 
 			idx += 1;
 			segment_width += width;
-			if !matches!(g.br, BreakpointType::No) {
+			let fits = match g.br {
+				BreakpointType::No => false,
+				BreakpointType::Hyphen => {
+					let hyphen = hyphen_width(self.styles, self.hyphen_advances, self.offset + idx);
+					segment_width + hyphen <= max_line_width
+				}
+				_ => true,
+			};
+			if fits {
 				last_line_break = Some(idx);
 			}
 		}
@@ -165,14 +299,139 @@ impl<'a> Iterator for StyledLines<'a> {
 	}
 }
 
+/// Glue stretch relative to natural whitespace width
+const GLUE_STRETCH: f32 = 0.5;
+/// Demerits added per line, favours fewer lines
+const LINE_PENALTY: f32 = 10.;
+const MAX_BADNESS: f32 = 10000.;
+/// Penalty for breaking at hyphenation point
+const HYPHEN_PENALTY: f32 = 50.;
+
+/// Total-fit paragraph breaking over box/glue/penalty model
+///
+/// Glyphs are boxes, whitespace glyphs are glue with no shrink, hyphenation
+/// points are penalties adding the width of the hyphen, and paragraph end is
+/// a forced break with infinite stretch. Returns absolute line end
+/// indices, or `None` if some box does not fit on a line.
+pub(crate) fn total_fit_breaks(
+	offset: usize,
+	styles: &[Style],
+	hyphen_advances: &[I26F6],
+	glyphs: &[GlyphPlan],
+	max_line_width: I26F6,
+	first_line_indent: I26F6,
+) -> Option<Vec<usize>> {
+	let len = glyphs.len();
+	if len == 0 {
+		return Some(Vec::new());
+	}
+
+	// Prefix sums of natural width and stretch
+	let mut width = Vec::with_capacity(len + 1);
+	let mut stretch = Vec::with_capacity(len + 1);
+	width.push(0f32);
+	stretch.push(0f32);
+	for (s, g) in StyledGlyphs::new(offset, glyphs, styles) {
+		let w = (g.pos.x_advance * s.font_size).to_num::<f32>();
+		let is_glue = g.br.is_whitespace();
+		width.push(width.last().unwrap() + w);
+		stretch.push(stretch.last().unwrap() + if is_glue { w * GLUE_STRETCH } else { 0. });
+	}
+
+	// Legal breaks are after glue not followed by more glue, after hyphenation
+	// points, and paragraph end
+	let mut candidates = vec![0];
+	candidates.extend((1..len).filter(|&i| match glyphs[i - 1].br {
+		BreakpointType::Hyphen => true,
+		br => br.is_whitespace() && !glyphs[i].br.is_whitespace(),
+	}));
+	candidates.push(len);
+
+	// Glue at end of line is discarded
+	let content_end = |b: usize| {
+		let mut end = b;
+		while end > 0 && glyphs[end - 1].br.is_whitespace() {
+			end -= 1;
+		}
+		end
+	};
+
+	let max_line_width = max_line_width.to_num::<f32>();
+	let first_line_width = max_line_width - first_line_indent.to_num::<f32>();
+
+	let mut demerits = vec![f32::INFINITY; candidates.len()];
+	let mut previous = vec![0; candidates.len()];
+	demerits[0] = 0.;
+	for j in 1..candidates.len() {
+		let b = candidates[j];
+		let end = content_end(b);
+		let is_last = b == len;
+		let (penalty, hyphen) = if !is_last && matches!(glyphs[b - 1].br, BreakpointType::Hyphen) {
+			let hyphen = hyphen_width(styles, hyphen_advances, offset + b);
+			(HYPHEN_PENALTY.powi(2), hyphen.to_num::<f32>())
+		} else {
+			(0., 0.)
+		};
+		for i in (0..j).rev() {
+			let a = candidates[i];
+			let line_width = if a == 0 {
+				first_line_width
+			} else {
+				max_line_width
+			};
+			let natural = width[end.max(a)] - width[a] + hyphen;
+			if natural > line_width {
+				// Earlier starts only get wider
+				break;
+			}
+			if demerits[i].is_infinite() {
+				continue;
+			}
+			let badness = if is_last {
+				0.
+			} else {
+				let line_stretch = stretch[end.max(a)] - stretch[a];
+				let slack = line_width - natural;
+				if line_stretch > 0. {
+					(100. * (slack / line_stretch).powi(3)).min(MAX_BADNESS)
+				} else if slack > 0. {
+					MAX_BADNESS
+				} else {
+					0.
+				}
+			};
+			let d = demerits[i] + (LINE_PENALTY + badness).powi(2) + penalty;
+			if d < demerits[j] {
+				demerits[j] = d;
+				previous[j] = i;
+			}
+		}
+	}
+
+	let last = candidates.len() - 1;
+	if demerits[last].is_infinite() {
+		return None;
+	}
+	let mut breaks = Vec::new();
+	let mut j = last;
+	while j > 0 {
+		breaks.push(offset + candidates[j]);
+		j = previous[j];
+	}
+	breaks.reverse();
+	Some(breaks)
+}
+
 #[cfg(test)]
 mod tests {
 	use fixed::types::I26F6;
 
 	use crate::PX_PER_PT;
 	use crate::Style;
+	use crate::TextAlign;
 	use crate::lines::StyledGlyphs;
 	use crate::lines::StyledLines;
+	use crate::lines::total_fit_breaks;
 	use crate::shaper::BreakpointType;
 	use crate::shaper::GlyphPlan;
 	use crate::shaper::GlyphPosition;
@@ -183,6 +442,9 @@ mod tests {
 			font_size: I26F6::ONE,
 			line_height_em: I26F6::ONE,
 			end_index,
+			span: None,
+			input: 0,
+			input_offset: 0,
 		}
 	}
 
@@ -197,6 +459,7 @@ mod tests {
 				y_offset: I26F6::ZERO,
 			},
 			br,
+			cluster: 0,
 		}
 	}
 
@@ -307,4 +570,259 @@ mod tests {
 		let lines = StyledLines::new(0, &styles, &glyphs, 20 * PX_PER_PT);
 		assert_eq!(lines.count(), 1);
 	}
+
+	#[test]
+	fn test_lines_first_line_indent() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = vec![
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 4, BreakpointType::No),
+		];
+
+		let lines = StyledLines::new(0, &styles, &glyphs, 4 * PX_PER_PT)
+			.with_first_line_indent(2 * PX_PER_PT)
+			.map(|line| line.len())
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![2, 3], "Line lengths missmatch");
+	}
+
+	#[test]
+	fn test_styled_glyphs_width_excludes_trailing_space() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = vec![
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::Wordbreak),
+		];
+		let sg = StyledGlyphs::new(0, &glyphs, &styles);
+
+		assert_eq!(sg.width(), 2, "Width missmatch");
+	}
+
+	#[test]
+	fn test_styled_glyphs_word_gaps() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = vec![
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::Newline),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 4, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 5, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 6, BreakpointType::Wordbreak),
+		];
+		let sg = StyledGlyphs::new(0, &glyphs, &styles);
+
+		assert_eq!(sg.word_gaps(), 2, "Word gaps missmatch");
+	}
+
+	#[test]
+	fn test_lines_total_fit_balances_lines() {
+		let styles = vec![mock_style(usize::MAX)];
+		// Words "a b c d ee f gggg" with single glyph spaces
+		let glyphs = [1, 1, 1, 1, 2, 1, 4]
+			.into_iter()
+			.enumerate()
+			.flat_map(|(i, word)| {
+				let space = (i > 0).then(|| mock_glyph(I26F6::ONE, 0, BreakpointType::Wordbreak));
+				space
+					.into_iter()
+					.chain((0..word).map(|_| mock_glyph(I26F6::ONE, 1, BreakpointType::No)))
+			})
+			.collect::<Vec<_>>();
+		let max_line_width = I26F6::from_num(8);
+
+		let greedy = StyledLines::new(0, &styles, &glyphs, max_line_width)
+			.map(|line| line.width().to_num::<u32>())
+			.collect::<Vec<_>>();
+		assert_eq!(greedy, vec![7, 4, 4], "Greedy line widths missmatch");
+
+		let breaks = total_fit_breaks(0, &styles, &[], &glyphs, max_line_width, I26F6::ZERO)
+			.expect("Expected feasible breaks");
+		assert_eq!(breaks, vec![6, 13, 17], "Breaks missmatch");
+		let total_fit = StyledLines::new(0, &styles, &glyphs, max_line_width)
+			.with_breaks(breaks)
+			.map(|line| line.width().to_num::<u32>())
+			.collect::<Vec<_>>();
+		assert_eq!(total_fit, vec![5, 6, 4], "Total fit line widths missmatch");
+	}
+
+	#[test]
+	fn test_lines_total_fit_infeasible() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = vec![
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+		];
+
+		let breaks = total_fit_breaks(0, &styles, &[], &glyphs, I26F6::from_num(2), I26F6::ZERO);
+		assert!(breaks.is_none(), "Expected no feasible breaks");
+	}
+
+	#[test]
+	fn test_lines_total_fit_resume_offset() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = [
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 4, BreakpointType::No),
+		];
+		let breaks = vec![2, 4, 5];
+
+		// Resume from second line as when continuing on next page
+		let lines = StyledLines::new(2, &styles, &glyphs[2..], I26F6::from_num(2))
+			.with_breaks(breaks)
+			.map(|line| line.len())
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![2, 1], "Line lengths missmatch");
+	}
+
+	#[test]
+	fn test_lines_break_at_hyphenation_point() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = [
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Hyphen),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::No),
+		];
+
+		let lines = StyledLines::new(0, &styles, &glyphs, I26F6::from_num(3))
+			.map(|line| (line.len(), line.hyphen_style().is_some()))
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![(2, true), (2, false)], "Lines missmatch");
+	}
+
+	#[test]
+	fn test_lines_total_fit_hyphenation_point() {
+		let styles = vec![mock_style(usize::MAX)];
+		let glyphs = [
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Hyphen),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::No),
+		];
+
+		let breaks = total_fit_breaks(0, &styles, &[], &glyphs, I26F6::from_num(3), I26F6::ZERO)
+			.expect("Expected feasible breaks");
+		assert_eq!(breaks, vec![2, 4], "Breaks missmatch");
+
+		let lines = StyledLines::new(0, &styles, &glyphs, I26F6::from_num(3))
+			.with_breaks(breaks)
+			.map(|line| line.hyphen_style().is_some())
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![true, false], "Hyphen missmatch");
+	}
+
+	#[test]
+	fn test_lines_hyphen_fills_line() {
+		let styles = vec![mock_style(usize::MAX)];
+		let hyphen_advances = [I26F6::ONE];
+		// "a bc-def", hyphen is as wide as a glyph
+		let glyphs = [
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::Hyphen),
+			mock_glyph(I26F6::ONE, 4, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 5, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 6, BreakpointType::No),
+		];
+		let lines = |max_line_width: I26F6| {
+			StyledLines::new(0, &styles, &glyphs, max_line_width)
+				.with_hyphen_advances(&hyphen_advances)
+				.map(|line| (line.len(), line.hyphen_style().is_some()))
+				.collect::<Vec<_>>()
+		};
+		let breaks = |max_line_width: I26F6| {
+			total_fit_breaks(
+				0,
+				&styles,
+				&hyphen_advances,
+				&glyphs,
+				max_line_width,
+				I26F6::ZERO,
+			)
+		};
+
+		// Hyphen exactly fills line
+		let max_line_width = I26F6::from_num(5);
+		assert_eq!(breaks(max_line_width), Some(vec![4, 7]), "Breaks missmatch");
+		assert_eq!(
+			lines(max_line_width),
+			vec![(4, true), (3, false)],
+			"Lines missmatch"
+		);
+
+		// Hyphen does not fit
+		let max_line_width = I26F6::from_num(4.5);
+		assert_eq!(
+			breaks(max_line_width),
+			Some(vec![2, 4, 7]),
+			"Overflowing breaks missmatch"
+		);
+		assert_eq!(
+			lines(max_line_width),
+			vec![(2, false), (2, true), (3, false)],
+			"Overflowing lines missmatch"
+		);
+	}
+
+	#[test]
+	fn test_line_alignment_justify() {
+		let styles = vec![mock_style(usize::MAX)];
+		let max_line_width = I26F6::from_num(10);
+		let hyphen_width = I26F6::ONE;
+		// Words of one glyph, last word hyphenated
+		let glyphs = [
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 4, BreakpointType::Hyphen),
+		];
+		let line = StyledGlyphs::new(0, &glyphs, &styles).with_hyphen(true);
+		let line_rem = (max_line_width - I26F6::from_num(5) - hyphen_width) * PX_PER_PT;
+
+		let (x_origin, word_spacing) = line.alignment(
+			TextAlign::Justify,
+			max_line_width,
+			I26F6::ZERO,
+			hyphen_width,
+			false,
+		);
+		assert_eq!(x_origin, I26F6::ZERO, "Origin missmatch");
+		assert_eq!(word_spacing, line_rem / 2, "Hyphen not fitted in line");
+		let (x_origin, _) = line.alignment(
+			TextAlign::Right,
+			max_line_width,
+			I26F6::ZERO,
+			hyphen_width,
+			false,
+		);
+		assert_eq!(x_origin, line_rem, "Hyphen not fitted in line");
+
+		// Line ended by forced break keeps natural spacing
+		let glyphs = [
+			mock_glyph(I26F6::ONE, 0, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 1, BreakpointType::Wordbreak),
+			mock_glyph(I26F6::ONE, 2, BreakpointType::No),
+			mock_glyph(I26F6::ONE, 3, BreakpointType::Newline),
+		];
+		let line = StyledGlyphs::new(0, &glyphs, &styles);
+		let (_, word_spacing) = line.alignment(
+			TextAlign::Justify,
+			max_line_width,
+			I26F6::ZERO,
+			I26F6::ZERO,
+			false,
+		);
+		assert_eq!(word_spacing, I26F6::ZERO, "Forced break line stretched");
+	}
 }
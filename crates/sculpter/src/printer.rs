@@ -18,6 +18,9 @@ use crate::DisplayGlyph;
 use crate::PX_PER_PT;
 use crate::SculpterOptions;
 use crate::SculpterPrinterError;
+use crate::TextBlock;
+use crate::TextCluster;
+use crate::TextSpan;
 use crate::lines::StyledGlyphs;
 use crate::shaper::GlyphPlan;
 use crate::shaper::ShapeFaceRef;
@@ -139,18 +142,40 @@ impl<'a> SculpterPrinter<'a> {
 		face_ref
 	}
 
+	/// Advance of hyphen printed after a line in face, in em
+	pub(crate) fn hyphen_advance(&self, face_ref: ShapeFaceRef) -> I26F6 {
+		let font = &self.fonts[face_ref.0 as usize];
+		let units_per_em = font.font.units_per_em().unwrap_or(1000.);
+		I26F6::from_num(font.font.h_advance_unscaled(font.hyphen_glyph_id) / units_per_em)
+	}
+
 	pub(crate) fn print_line(
 		&mut self,
 		x_origin: I26F6,
 		y_origin: I26F6,
+		word_spacing: I26F6,
 		styled_glyphs: StyledGlyphs<'_>,
-		glyphs: &mut Vec<DisplayGlyph>,
+		output: &mut TextBlock,
 		options: &SculpterOptions,
 	) -> Result<(), SculpterPrinterError> {
+		let TextBlock {
+			glyphs,
+			spans,
+			clusters,
+			..
+		} = output;
 		let hyphen_style = styled_glyphs.hyphen_style();
+		let line_spans = spans.len();
 
 		let mut x_pos = x_origin;
+		let mut after_whitespace = false;
 		for (style, glyph) in styled_glyphs {
+			// Extra space is added once per word gap, before next word
+			if after_whitespace && !glyph.br.is_whitespace() {
+				x_pos += word_spacing;
+			}
+			after_whitespace = glyph.br.is_whitespace();
+
 			if matches!(glyph.br, crate::shaper::BreakpointType::Newline) {
 				// Ignore newline characters included in print
 				continue;
@@ -161,6 +186,30 @@ impl<'a> SculpterPrinter<'a> {
 			let y_offset = glyph.pos.y_offset * style.font_size * PX_PER_PT;
 
 			let font_size = style.font_size * PX_PER_PT;
+			let top = (y_origin - font_size).to_num::<f32>();
+			let bottom = y_origin.to_num::<f32>();
+			let left = x_pos.to_num::<f32>();
+			let right = (x_pos + x_advance).to_num::<f32>();
+			clusters.push(TextCluster {
+				input: style.input,
+				offset: style.input_offset + glyph.cluster,
+				pos: [left, top],
+				dim: [right - left, bottom - top],
+			});
+			if let Some(span) = style.span {
+				match spans[line_spans..].last_mut() {
+					Some(s) if s.span == span => {
+						let s_bottom = s.pos[1] + s.dim[1];
+						s.pos[1] = s.pos[1].min(top);
+						s.dim = [right - s.pos[0], s_bottom.max(bottom) - s.pos[1]];
+					}
+					_ => spans.push(TextSpan {
+						span,
+						pos: [left, top],
+						dim: [right - left, bottom - top],
+					}),
+				}
+			}
 			let sub_pixel = (x_pos + x_offset).frac();
 			let key = GlyphKey::from_glyph(glyph, font_size, sub_pixel, options);
 			let entry = if let Some(entry) = self.glyph_map.get(&key) {
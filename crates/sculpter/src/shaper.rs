@@ -4,6 +4,7 @@ use fixed::types::I26F6;
 
 use crate::SculpterShapeError;
 use crate::Variation;
+use crate::hyphenation::SOFT_HYPHEN;
 
 #[derive(Debug)]
 pub(crate) struct GlyphPosition {
@@ -32,6 +33,14 @@ pub(crate) enum BreakpointType {
 	No,
 	Newline,
 	Wordbreak,
+	/// Hyphen may be inserted after glyph
+	Hyphen,
+}
+
+impl BreakpointType {
+	pub(crate) fn is_whitespace(&self) -> bool {
+		matches!(self, BreakpointType::Newline | BreakpointType::Wordbreak)
+	}
 }
 
 pub struct GlyphPlan {
@@ -39,6 +48,8 @@ pub struct GlyphPlan {
 	pub(crate) glyph_id: u16,
 	pub(crate) pos: GlyphPosition,
 	pub(crate) br: BreakpointType,
+	/// Byte offset of source cluster in shaped input
+	pub(crate) cluster: u32,
 }
 
 impl std::fmt::Debug for GlyphPlan {
@@ -103,6 +114,7 @@ impl<'font> SculptureShaper<'font> {
 		&mut self,
 		face_ref: ShapeFaceRef,
 		input: &str,
+		hyphens: &[usize],
 		glyphs: &mut Vec<GlyphPlan>,
 	) -> Result<usize, SculpterShapeError> {
 		let mut buffer = self.buffer.take().unwrap_or_default();
@@ -144,7 +156,7 @@ impl<'font> SculptureShaper<'font> {
 				.next()
 				.expect("Failed to get original char");
 
-			if info.glyph_id == 0 && !c.is_whitespace() {
+			if info.glyph_id == 0 && !c.is_whitespace() && c != SOFT_HYPHEN {
 				invalid.insert(info.cluster, idx);
 			}
 
@@ -152,6 +164,8 @@ impl<'font> SculptureShaper<'font> {
 				BreakpointType::Newline
 			} else if c.is_whitespace() {
 				BreakpointType::Wordbreak
+			} else if c == SOFT_HYPHEN {
+				BreakpointType::Hyphen
 			} else {
 				BreakpointType::No
 			};
@@ -161,9 +175,22 @@ impl<'font> SculptureShaper<'font> {
 				glyph_id: info.glyph_id as u16,
 				pos: GlyphPosition::from(pos, face.em_per_unit),
 				br,
+				cluster: info.cluster,
 			});
 		}
 
+		// Mark glyphs followed by a hyphenation point
+		let infos = shaped.glyph_infos();
+		for (idx, next) in infos.iter().skip(1).enumerate() {
+			let glyph = &mut glyphs[glyphs_start + idx];
+			if matches!(glyph.br, BreakpointType::No)
+				&& infos[idx].cluster != next.cluster
+				&& hyphens.binary_search(&(next.cluster as usize)).is_ok()
+			{
+				glyph.br = BreakpointType::Hyphen;
+			}
+		}
+
 		self.buffer.replace(shaped.clear());
 
 		self.shape_fallback(input, &mut glyphs[glyphs_start..], invalid)?;
@@ -229,6 +256,7 @@ impl<'font> SculptureShaper<'font> {
 						glyph_id: info.glyph_id as u16,
 						pos: GlyphPosition::from(pos, face.em_per_unit),
 						br: glyphs[idx].br,
+						cluster: glyphs[idx].cluster,
 					};
 				}
 			}
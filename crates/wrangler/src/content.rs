@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::io;
 use std::io::Read;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -18,6 +19,7 @@ use crate::WranglerResult;
 use crate::WranglerSystem;
 
 type ContentResult = Result<(Vec<u8>, SystemTime), io::Error>;
+type SaveResult = Result<DocumentId, io::Error>;
 
 #[derive(Debug)]
 pub(crate) enum State<T> {
@@ -26,15 +28,21 @@ pub(crate) enum State<T> {
 	Complete(Option<T>),
 }
 
-type FileContentStates = BTreeMap<Ticket, State<ContentResult>>;
+type TicketStates<T> = BTreeMap<Ticket, State<T>>;
+type FileContentStates = TicketStates<ContentResult>;
+type FileSaveStates = TicketStates<SaveResult>;
 
-pub struct FileContentFuture {
-	states: Arc<Mutex<FileContentStates>>,
+/// Result of a wrangler command, completed by wrangler thread
+pub struct TicketFuture<T> {
+	states: Arc<Mutex<TicketStates<T>>>,
 	ticket: Ticket,
 }
 
-impl Future for FileContentFuture {
-	type Output = ContentResult;
+pub type FileContentFuture = TicketFuture<ContentResult>;
+pub type FileSaveFuture = TicketFuture<SaveResult>;
+
+impl<T> Future for TicketFuture<T> {
+	type Output = T;
 
 	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
 		let mut states = self.states.lock().unwrap();
@@ -67,14 +75,28 @@ impl Future for FileContentFuture {
 	}
 }
 
+fn complete<T>(states: &Mutex<TicketStates<T>>, ticket: Ticket, result: T) {
+	let mut states = states.lock().unwrap();
+	let state = states
+		.get_mut(&ticket)
+		.expect("future completed by other thread");
+	match std::mem::replace(state, State::Complete(Some(result))) {
+		State::Incomplete => {}
+		State::Waiting(waker) => waker.wake(),
+		State::Complete(_) => unreachable!("future already completed"),
+	}
+}
+
 pub struct ContentWrangler {
 	states: Arc<Mutex<FileContentStates>>,
+	saves: Arc<Mutex<FileSaveStates>>,
 }
 
 #[derive(Clone)]
 pub struct ContentWranglerAssistant {
 	system: WranglerSystem,
 	states: Arc<Mutex<FileContentStates>>,
+	saves: Arc<Mutex<FileSaveStates>>,
 }
 
 impl ContentWrangler {
@@ -84,14 +106,17 @@ impl ContentWrangler {
 	/// Returns `ContentWranglerAssistant` which can be cheaply cloned.
 	pub fn create(system: WranglerSystem) -> ContentWranglerAssistant {
 		let states = Arc::new(Mutex::new(BTreeMap::new()));
+		let saves = Arc::new(Mutex::new(BTreeMap::new()));
 
 		system.register(Box::new(ContentWrangler {
 			states: states.clone(),
+			saves: saves.clone(),
 		}));
 
 		ContentWranglerAssistant {
 			system: system.clone(),
 			states: states.clone(),
+			saves: saves.clone(),
 		}
 	}
 }
@@ -109,6 +134,21 @@ impl ContentWranglerAssistant {
 			ticket,
 		}
 	}
+
+	/// Save text document at path relative to document tree, replacing existing document
+	pub fn save(&self, path: PathBuf, content: String) -> FileSaveFuture {
+		let ticket = Ticket::take();
+
+		let mut saves = self.saves.lock().unwrap();
+		saves.insert(ticket, State::Incomplete);
+		self.system
+			.send(WranglerCommand::Write(ticket, path, content));
+
+		FileSaveFuture {
+			states: self.saves.clone(),
+			ticket,
+		}
+	}
 }
 
 impl Wrangler for ContentWrangler {
@@ -124,15 +164,26 @@ impl Wrangler for ContentWrangler {
 				Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
 			};
 
-			let mut states = self.states.lock().unwrap();
-			let state = states
-				.get_mut(&ticket)
-				.expect("future completed by other thread");
-			match std::mem::replace(state, State::Complete(Some(result))) {
-				State::Incomplete => {}
-				State::Waiting(waker) => waker.wake(),
-				State::Complete(_) => unreachable!("future already completed"),
-			}
+			complete(&self.states, ticket, result);
+
+			WranglerResult::Handled
+		} else {
+			WranglerResult::SomebodyElsesProblem
+		}
+	}
+
+	fn written(
+		&mut self,
+		ticket: Ticket,
+		result: &Result<DocumentId, io::Error>,
+	) -> WranglerResult {
+		let is_mine = self.saves.lock().unwrap().contains_key(&ticket);
+		if is_mine {
+			let result = match result {
+				Ok(document) => Ok(document.clone()),
+				Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+			};
+			complete(&self.saves, ticket, result);
 
 			WranglerResult::Handled
 		} else {
@@ -6,6 +6,7 @@ use std::fs::File;
 use std::io;
 use std::os::unix::fs::MetadataExt;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::AtomicU64;
@@ -82,6 +83,8 @@ pub enum WranglerCommand {
 	SetTree(DocumentTree),
 	Document(Ticket, DocumentId),
 	ExploreTree(Ticket),
+	/// Write text document at path relative to tree, creating missing folders
+	Write(Ticket, PathBuf, String),
 	Shutdown,
 }
 
@@ -150,6 +153,15 @@ pub trait Wrangler: Send {
 		let _ = (ticket, discovery);
 		WranglerResult::SomebodyElsesProblem
 	}
+
+	fn written(
+		&mut self,
+		ticket: Ticket,
+		result: &Result<DocumentId, io::Error>,
+	) -> WranglerResult {
+		let _ = (ticket, result);
+		WranglerResult::SomebodyElsesProblem
+	}
 }
 
 #[derive(Clone)]
@@ -188,6 +200,16 @@ pub fn create_wrangler(document_tree: DocumentTree) -> (WranglerSystem, JoinHand
 		Ok((file, size, timestamp))
 	}
 
+	fn write_file(root: &Path, path: &Path, content: &str) -> Result<(), io::Error> {
+		let path = expand_tilde_owned(root)
+			.map_err(|err| io::Error::other(err.to_string()))?
+			.join(path);
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		fs::write(path, content)
+	}
+
 	let (sender, receiver) = channel();
 	let wranglers = Arc::new(Mutex::new(Vec::new()));
 
@@ -307,6 +329,21 @@ pub fn create_wrangler(document_tree: DocumentTree) -> (WranglerSystem, JoinHand
 						"Unexpected discovery end result"
 					);
 				}
+				WranglerCommand::Write(ticket, path, content) => {
+					let result = write_file(document_tree.path(), &path, &content)
+						.map(|_| DocumentId::new(path.to_string_lossy().into()));
+					let mut handled = false;
+					for wrangler in &mut *wranglers.lock().unwrap() {
+						let result = wrangler.written(ticket, &result);
+						if matches!(result, WranglerResult::Handled) {
+							handled = true;
+							break;
+						}
+					}
+					if !handled {
+						log::warn!("Unhandled write ticket {ticket}");
+					}
+				}
 				WranglerCommand::Shutdown => {
 					log::info!("Wrangler shutdown");
 					return;